@@ -0,0 +1,186 @@
+//! The derive macro for `fractional_index::FractionallyOrdered`. Use it
+//! through the `derive` feature of `fractional_index`.
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields, Member, Type};
+
+/// Implements `FractionallyOrdered`, `PartialEq`, `Eq`, `PartialOrd` and
+/// `Ord` for a struct ordered by the field marked `#[order]`, or by its
+/// only `FractionalIndex` field, and adds the constructors `new_before`,
+/// `new_after` and `new_between`.
+#[proc_macro_derive(FractionallyOrdered, attributes(order))]
+pub fn derive_fractionally_ordered(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "FractionallyOrdered can only be derived for structs.",
+            ))
+        }
+    };
+    let order = order_field(fields)?;
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let krate = quote!(::fractional_index);
+    let index = member(fields, order);
+
+    // The anchors of the constructors use mixed-site hygiene, so that they
+    // can't collide with the parameters named after fields.
+    let anchor = format_ident!("anchor", span = Span::mixed_site());
+    let lower = format_ident!("lower", span = Span::mixed_site());
+    let upper = format_ident!("upper", span = Span::mixed_site());
+    let position = format_ident!("position", span = Span::mixed_site());
+
+    let mut params = Vec::new();
+    let mut inits = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let member = member(fields, i);
+        if i == order {
+            inits.push(quote!(#member: #position));
+        } else {
+            let param = match &field.ident {
+                Some(ident) => ident.clone(),
+                None => format_ident!("field{}", i),
+            };
+            let ty = &field.ty;
+            params.push(quote!(#param: #ty));
+            inits.push(quote!(#member: #param));
+        }
+    }
+
+    Ok(quote! {
+        impl #impl_generics #krate::FractionallyOrdered for #name #ty_generics #where_clause {
+            fn index(&self) -> &#krate::FractionalIndex {
+                &self.#index
+            }
+
+            fn index_mut(&mut self) -> &mut #krate::FractionalIndex {
+                &mut self.#index
+            }
+        }
+
+        impl #impl_generics ::core::cmp::PartialEq for #name #ty_generics #where_clause {
+            fn eq(&self, other: &Self) -> bool {
+                self.#index == other.#index
+            }
+        }
+
+        impl #impl_generics ::core::cmp::Eq for #name #ty_generics #where_clause {}
+
+        impl #impl_generics ::core::cmp::PartialOrd for #name #ty_generics #where_clause {
+            fn partial_cmp(&self, other: &Self) -> ::core::option::Option<::core::cmp::Ordering> {
+                ::core::option::Option::Some(::core::cmp::Ord::cmp(self, other))
+            }
+        }
+
+        impl #impl_generics ::core::cmp::Ord for #name #ty_generics #where_clause {
+            fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                ::core::cmp::Ord::cmp(&self.#index, &other.#index)
+            }
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Constructs a value ordered before `anchor`, or the first
+            /// value of a list if `anchor` is None.
+            #vis fn new_before(#anchor: ::core::option::Option<&Self>, #(#params),*) -> Self {
+                let #position = match #anchor {
+                    ::core::option::Option::Some(#anchor) => {
+                        #krate::FractionalIndex::new_before(&#anchor.#index)
+                    }
+                    ::core::option::Option::None => #krate::FractionalIndex::default(),
+                };
+                Self { #(#inits),* }
+            }
+
+            /// Constructs a value ordered after `anchor`, or the first
+            /// value of a list if `anchor` is None.
+            #vis fn new_after(#anchor: ::core::option::Option<&Self>, #(#params),*) -> Self {
+                let #position = match #anchor {
+                    ::core::option::Option::Some(#anchor) => {
+                        #krate::FractionalIndex::new_after(&#anchor.#index)
+                    }
+                    ::core::option::Option::None => #krate::FractionalIndex::default(),
+                };
+                Self { #(#inits),* }
+            }
+
+            /// Constructs a value ordered between `lower` and `upper`, or
+            /// returns None if `lower` is not less than `upper`.
+            #vis fn new_between(
+                #lower: &Self,
+                #upper: &Self,
+                #(#params),*
+            ) -> ::core::option::Option<Self> {
+                let #position =
+                    #krate::FractionalIndex::new_between(&#lower.#index, &#upper.#index)?;
+                ::core::option::Option::Some(Self { #(#inits),* })
+            }
+        }
+    })
+}
+
+/// Returns the position of the field that orders the struct.
+fn order_field(fields: &Fields) -> Result<usize, Error> {
+    let marked: Vec<usize> = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| field.attrs.iter().any(|attr| attr.path().is_ident("order")))
+        .map(|(i, _)| i)
+        .collect();
+    match marked[..] {
+        [i] => return Ok(i),
+        [_, second, ..] => {
+            let field = fields.iter().nth(second).expect("The field exists.");
+            return Err(Error::new(
+                field.span(),
+                "Only one field can be marked #[order].",
+            ));
+        }
+        [] => {}
+    }
+
+    let indexes: Vec<usize> = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| is_fractional_index(&field.ty))
+        .map(|(i, _)| i)
+        .collect();
+    match indexes[..] {
+        [i] => Ok(i),
+        _ => Err(Error::new(
+            fields.span(),
+            "Mark the field that orders the struct with #[order].",
+        )),
+    }
+}
+
+fn is_fractional_index(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "FractionalIndex"),
+        _ => false,
+    }
+}
+
+fn member(fields: &Fields, i: usize) -> Member {
+    let field = fields.iter().nth(i).expect("The field exists.");
+    match &field.ident {
+        Some(ident) => Member::Named(ident.clone()),
+        None => Member::Unnamed(i.into()),
+    }
+}
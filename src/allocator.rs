@@ -0,0 +1,235 @@
+//! Fractional indexes whose bytes are stored in a custom allocator, such as
+//! a bump arena.
+//!
+//! [FractionalIndexIn] is generic over the `Allocator` trait from the
+//! [allocator-api2](https://docs.rs/allocator-api2) crate, which mirrors the
+//! unstable `allocator_api` of the standard library. On stable Rust, it
+//! works with allocators that implement the `allocator-api2` trait (e.g.
+//! `&bumpalo::Bump` with bumpalo's `allocator-api2` feature). On nightly,
+//! enabling the `nightly` feature of `allocator-api2` makes it re-export the
+//! standard library's trait, so any `core::alloc::Allocator` can be used.
+//!
+//! Keys generated from a [FractionalIndexIn] are allocated with a clone of
+//! its allocator, and have the same bytes as the equivalent
+//! [FractionalIndex].
+//!
+//! ```rust
+//! use bumpalo::Bump;
+//! use fractional_index::{allocator::FractionalIndexIn, FractionalIndex};
+//!
+//! let arena = Bump::new();
+//! let a = FractionalIndexIn::default_in(&arena);
+//! let b = FractionalIndexIn::new_after(&a);
+//! let c = FractionalIndexIn::new_between(&a, &b).unwrap();
+//! assert!(a < c && c < b);
+//!
+//! let c2 = FractionalIndex::new_between(&a.to_index(), &b.to_index()).unwrap();
+//! assert_eq!(c.as_bytes(), c2.as_bytes());
+//! ```
+use crate::{
+    assertions::check_index,
+    format::{ByteFormat, Derived, Side},
+    fract_index::TERMINATOR,
+    instrument, FractionalIndex,
+};
+use allocator_api2::{
+    alloc::{Allocator, Global},
+    vec::Vec,
+};
+use std::{cmp::Ordering, fmt, ops::Deref};
+
+/// A [FractionalIndex] whose bytes are allocated by `A`.
+pub struct FractionalIndexIn<A: Allocator = Global>(Vec<u8, A>);
+
+impl<A: Allocator> FractionalIndexIn<A> {
+    /// Constructs the equivalent of [FractionalIndex::default] in the given
+    /// allocator.
+    pub fn default_in(alloc: A) -> Self {
+        let mut bytes = Vec::with_capacity_in(1, alloc);
+        bytes.push(TERMINATOR);
+        FractionalIndexIn(bytes)
+    }
+
+    /// Copies a [FractionalIndex] into the given allocator.
+    pub fn from_index_in(index: &FractionalIndex, alloc: A) -> Self {
+        let mut bytes = Vec::with_capacity_in(index.as_bytes().len(), alloc);
+        bytes.extend_from_slice(index.as_bytes());
+        FractionalIndexIn(bytes)
+    }
+
+    /// Copies this index into a [FractionalIndex] on the global heap.
+    pub fn to_index(&self) -> FractionalIndex {
+        FractionalIndex::from_bytes_unchecked(self.as_bytes().to_vec())
+    }
+
+    /// Returns the byte representation of this index, which is the same as
+    /// that of the equivalent [FractionalIndex].
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the allocator that holds this index.
+    pub fn allocator(&self) -> &A {
+        self.0.allocator()
+    }
+
+    fn from_derived(derived: Derived, source: &[u8], alloc: A) -> Self {
+        let (prefix, last) = derived.parts(source);
+        let mut bytes = Vec::with_capacity_in(derived.len() + 1, alloc);
+        bytes.extend_from_slice(prefix);
+        bytes.extend(last);
+        bytes.push(TERMINATOR);
+        FractionalIndexIn(bytes)
+    }
+}
+
+impl<A: Allocator + Clone> FractionalIndexIn<A> {
+    /// Constructs an index that compares before the given one, in the same
+    /// allocator. See [FractionalIndex::new_before].
+    pub fn new_before(index: &Self) -> Self {
+        let bytes = index.as_bytes();
+        let derived = ByteFormat::DEFAULT.derive_before(bytes);
+        let result = Self::from_derived(derived, bytes, index.allocator().clone());
+        check_index(&result.0, None, Some(bytes));
+        instrument::generated("new_before", &result.0, None, Some(bytes));
+        result
+    }
+
+    /// Constructs an index that compares after the given one, in the same
+    /// allocator. See [FractionalIndex::new_after].
+    pub fn new_after(index: &Self) -> Self {
+        let bytes = index.as_bytes();
+        let derived = ByteFormat::DEFAULT.derive_after(bytes);
+        let result = Self::from_derived(derived, bytes, index.allocator().clone());
+        check_index(&result.0, Some(bytes), None);
+        instrument::generated("new_after", &result.0, Some(bytes), None);
+        result
+    }
+
+    /// Constructs an index that compares between the given two, in the
+    /// allocator of `left`. Returns None if they are not in order and
+    /// distinct. See [FractionalIndex::new_between].
+    pub fn new_between(left: &Self, right: &Self) -> Option<Self> {
+        let (left_bytes, right_bytes) = (left.as_bytes(), right.as_bytes());
+        let (side, derived) = ByteFormat::DEFAULT.derive_between(left_bytes, right_bytes)?;
+        let source = match side {
+            Side::Left => left_bytes,
+            Side::Right => right_bytes,
+        };
+        let result = Self::from_derived(derived, source, left.allocator().clone());
+        check_index(&result.0, Some(left_bytes), Some(right_bytes));
+        instrument::generated(
+            "new_between",
+            &result.0,
+            Some(left_bytes),
+            Some(right_bytes),
+        );
+        Some(result)
+    }
+}
+
+impl<A: Allocator + Clone> Clone for FractionalIndexIn<A> {
+    fn clone(&self) -> Self {
+        FractionalIndexIn(self.0.clone())
+    }
+}
+
+impl<A: Allocator> fmt::Debug for FractionalIndexIn<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FractionalIndexIn")
+            .field(&self.as_bytes())
+            .finish()
+    }
+}
+
+impl<A: Allocator, B: Allocator> PartialEq<FractionalIndexIn<B>> for FractionalIndexIn<A> {
+    fn eq(&self, other: &FractionalIndexIn<B>) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<A: Allocator> Eq for FractionalIndexIn<A> {}
+
+impl<A: Allocator, B: Allocator> PartialOrd<FractionalIndexIn<B>> for FractionalIndexIn<A> {
+    fn partial_cmp(&self, other: &FractionalIndexIn<B>) -> Option<Ordering> {
+        Some(self.as_bytes().cmp(other.as_bytes()))
+    }
+}
+
+impl<A: Allocator> Ord for FractionalIndexIn<A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl<A: Allocator> PartialEq<FractionalIndex> for FractionalIndexIn<A> {
+    fn eq(&self, other: &FractionalIndex) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<A: Allocator> PartialOrd<FractionalIndex> for FractionalIndexIn<A> {
+    fn partial_cmp(&self, other: &FractionalIndex) -> Option<Ordering> {
+        Some(self.as_bytes().cmp(other.as_bytes()))
+    }
+}
+
+impl<A: Allocator> Deref for FractionalIndexIn<A> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_bytes()
+    }
+}
+
+impl Default for FractionalIndexIn {
+    fn default() -> Self {
+        FractionalIndexIn::default_in(Global)
+    }
+}
+
+impl From<&FractionalIndex> for FractionalIndexIn {
+    fn from(index: &FractionalIndex) -> Self {
+        FractionalIndexIn::from_index_in(index, Global)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bumpalo::Bump;
+
+    #[test]
+    fn matches_fractional_index() {
+        let arena = Bump::new();
+        let mut keys = vec![FractionalIndexIn::default_in(&arena)];
+        let mut expected = vec![FractionalIndex::default()];
+        for i in 0..100 {
+            let (key, index) = match i % 3 {
+                0 => (
+                    FractionalIndexIn::new_before(&keys[0]),
+                    FractionalIndex::new_before(&expected[0]),
+                ),
+                1 => (
+                    FractionalIndexIn::new_after(keys.last().unwrap()),
+                    FractionalIndex::new_after(expected.last().unwrap()),
+                ),
+                _ => (
+                    FractionalIndexIn::new_between(&keys[0], &keys[1]).unwrap(),
+                    FractionalIndex::new_between(&expected[0], &expected[1]).unwrap(),
+                ),
+            };
+            assert_eq!(key, index);
+            keys.push(key);
+            keys.sort();
+            expected.push(index);
+            expected.sort();
+        }
+        assert!(arena.allocated_bytes() > 0);
+
+        let heap: FractionalIndexIn = (&expected[5]).into();
+        assert_eq!(heap, keys[5]);
+        assert_eq!(keys[5].to_index(), expected[5]);
+        assert!(FractionalIndexIn::new_between(&keys[1], &keys[0]).is_none());
+    }
+}
@@ -0,0 +1,274 @@
+//! String encodings of [FractionalIndex] over a caller-supplied alphabet.
+//!
+//! [FractionalIndex::to_string] always produces lowercase hex. An
+//! [Alphabet] generalizes this to any sorted set of ASCII characters, e.g.
+//! to exclude ambiguous characters or to match the character set of an
+//! existing system.
+//!
+//! Each byte is encoded as a fixed number of digits in the alphabet's base,
+//! so the lexicographic order of the encoded strings matches the order of
+//! the indexes.
+//!
+//! ```rust
+//! use fractional_index::{alphabet::Alphabet, FractionalIndex};
+//!
+//! // Crockford's base 32, which omits I, L, O and U.
+//! let alphabet = Alphabet::new("0123456789ABCDEFGHJKMNPQRSTVWXYZ").unwrap();
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//! assert_eq!(alphabet.encode(&a), "40");
+//! assert!(alphabet.encode(&a) < alphabet.encode(&b));
+//! assert_eq!(alphabet.decode(&alphabet.encode(&b)).unwrap(), b);
+//! ```
+use crate::{DecodeError, FractionalIndex};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+const INVALID: u8 = u8::MAX;
+
+/// A sorted set of ASCII characters used to encode a [FractionalIndex] as a
+/// string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alphabet {
+    chars: Vec<u8>,
+    /// Maps an ASCII character to its digit value, or [INVALID].
+    digits: [u8; 128],
+    /// The number of characters used to encode each byte.
+    width: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AlphabetError {
+    TooShort,
+    NonAscii,
+    NotSorted,
+}
+
+impl Display for AlphabetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlphabetError::TooShort => write!(f, "An alphabet must have at least two characters."),
+            AlphabetError::NonAscii => write!(f, "An alphabet must only contain ASCII characters."),
+            AlphabetError::NotSorted => write!(
+                f,
+                "An alphabet's characters must be distinct and in ascending order."
+            ),
+        }
+    }
+}
+
+impl Error for AlphabetError {}
+
+impl Alphabet {
+    /// Constructs an alphabet from a string of distinct ASCII characters in
+    /// ascending order.
+    pub fn new(chars: &str) -> Result<Self, AlphabetError> {
+        let chars = chars.as_bytes();
+        if chars.len() < 2 {
+            return Err(AlphabetError::TooShort);
+        }
+        if !chars.is_ascii() {
+            return Err(AlphabetError::NonAscii);
+        }
+        if chars.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(AlphabetError::NotSorted);
+        }
+
+        let mut digits = [INVALID; 128];
+        for (value, c) in chars.iter().enumerate() {
+            digits[*c as usize] = value as u8;
+        }
+
+        let mut width = 1;
+        let mut capacity = chars.len();
+        while capacity < 256 {
+            capacity *= chars.len();
+            width += 1;
+        }
+
+        Ok(Alphabet {
+            chars: chars.to_vec(),
+            digits,
+            width,
+        })
+    }
+
+    /// The lowercase hexadecimal alphabet used by [FractionalIndex::to_string].
+    pub fn hex() -> Self {
+        Alphabet::new("0123456789abcdef").unwrap()
+    }
+
+    /// The 62 ASCII letters and digits, in ASCII order.
+    pub fn base62() -> Self {
+        Alphabet::new("0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz").unwrap()
+    }
+
+    /// Returns the characters of this alphabet.
+    pub fn chars(&self) -> &str {
+        std::str::from_utf8(&self.chars).expect("Alphabets only contain ASCII.")
+    }
+
+    /// Returns the number of characters used to encode each byte.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Encodes a [FractionalIndex] as a string in this alphabet.
+    pub fn encode(&self, index: &FractionalIndex) -> String {
+        self.encode_bytes(index.as_bytes())
+    }
+
+    fn encode_bytes(&self, bytes: &[u8]) -> String {
+        let base = self.chars.len();
+        let mut out = vec![0; bytes.len() * self.width];
+        for (byte, digits) in bytes.iter().zip(out.chunks_exact_mut(self.width)) {
+            let mut value = *byte as usize;
+            for digit in digits.iter_mut().rev() {
+                *digit = self.chars[value % base];
+                value /= base;
+            }
+        }
+        String::from_utf8(out).expect("Alphabets only contain ASCII.")
+    }
+
+    /// Decodes a string produced by [Alphabet::encode] with the same
+    /// alphabet.
+    pub fn decode(&self, s: &str) -> Result<FractionalIndex, DecodeError> {
+        if s.is_empty() {
+            return Err(DecodeError::EmptyString);
+        }
+        FractionalIndex::from_bytes(self.decode_bytes(s)?)
+    }
+
+    fn decode_bytes(&self, s: &str) -> Result<Vec<u8>, DecodeError> {
+        // Every byte before the first invalid one is ASCII, so the error
+        // position is always on a character boundary.
+        let invalid = |position: usize| DecodeError::InvalidChars {
+            character: s[position..].chars().next().unwrap_or_default(),
+            position,
+        };
+
+        let chars = s.as_bytes();
+        let base = self.chars.len();
+        let mut bytes = Vec::with_capacity(chars.len() / self.width);
+        for (i, digits) in chars.chunks(self.width).enumerate() {
+            let start = i * self.width;
+            let mut value: usize = 0;
+            for (j, c) in digits.iter().enumerate() {
+                let digit = self.digits.get(*c as usize).copied().unwrap_or(INVALID);
+                if digit == INVALID {
+                    return Err(invalid(start + j));
+                }
+                value = value * base + digit as usize;
+            }
+            if digits.len() < self.width {
+                return Err(DecodeError::InvalidLength);
+            }
+            if value > u8::MAX as usize {
+                // Every digit is valid, but the group encodes more than a
+                // byte.
+                return Err(DecodeError::DigitOutOfRange);
+            }
+            bytes.push(value as u8);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> Vec<FractionalIndex> {
+        let mut keys = vec![FractionalIndex::default()];
+        for i in 0..200 {
+            let last = keys.last().unwrap();
+            let next = if i % 3 == 0 {
+                FractionalIndex::new_after(last)
+            } else {
+                FractionalIndex::new_between(&keys[0], last).unwrap()
+            };
+            keys.push(next);
+        }
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn invalid_alphabets() {
+        assert_eq!(Alphabet::new("a"), Err(AlphabetError::TooShort));
+        assert_eq!(Alphabet::new("ab\u{e9}"), Err(AlphabetError::NonAscii));
+        assert_eq!(Alphabet::new("ba"), Err(AlphabetError::NotSorted));
+        assert_eq!(Alphabet::new("aab"), Err(AlphabetError::NotSorted));
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(Alphabet::new("01").unwrap().width(), 8);
+        assert_eq!(Alphabet::new("012").unwrap().width(), 6);
+        assert_eq!(Alphabet::hex().width(), 2);
+        assert_eq!(Alphabet::base62().width(), 2);
+    }
+
+    #[test]
+    fn hex_matches_to_string() {
+        for key in keys() {
+            assert_eq!(Alphabet::hex().encode(&key), key.to_string());
+        }
+    }
+
+    #[test]
+    fn preserves_order_and_roundtrips() {
+        let keys = keys();
+        for alphabet in [
+            Alphabet::new("01").unwrap(),
+            Alphabet::new("-_~").unwrap(),
+            Alphabet::hex(),
+            Alphabet::base62(),
+        ] {
+            let encoded: Vec<String> = keys.iter().map(|k| alphabet.encode(k)).collect();
+            for pair in encoded.windows(2) {
+                assert!(pair[0] < pair[1]);
+            }
+            for (key, s) in keys.iter().zip(&encoded) {
+                assert_eq!(&alphabet.decode(s).unwrap(), key);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_errors() {
+        let alphabet = Alphabet::base62();
+        assert!(matches!(alphabet.decode(""), Err(DecodeError::EmptyString)));
+        assert!(matches!(
+            alphabet.decode("2"),
+            Err(DecodeError::InvalidLength)
+        ));
+        assert_eq!(
+            alphabet.decode("2-"),
+            Err(DecodeError::InvalidChars {
+                character: '-',
+                position: 1
+            })
+        );
+        assert_eq!(
+            alphabet.decode("22\u{e9}"),
+            Err(DecodeError::InvalidChars {
+                character: '\u{e9}',
+                position: 2
+            })
+        );
+        // 62 * 62 digits can represent values that don't fit in a byte.
+        assert_eq!(alphabet.decode("22zz"), Err(DecodeError::DigitOutOfRange));
+        let decimal = Alphabet::new("0123456789").unwrap();
+        assert_eq!(decimal.decode("128"), Ok(FractionalIndex::default()));
+        assert_eq!(decimal.decode("256"), Err(DecodeError::DigitOutOfRange));
+        assert_eq!(
+            alphabet.decode("20"),
+            Err(DecodeError::MissingTerminator { found: Some(124) })
+        );
+    }
+}
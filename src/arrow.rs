@@ -0,0 +1,241 @@
+//! Conversion of [FractionalIndex] columns to and from Arrow arrays,
+//! enabled by the `arrow` feature.
+//!
+//! A column of keys is stored as a [BinaryArray] of the keys' bytes. Binary
+//! values compare byte by byte, so sorting the column with Arrow's kernels
+//! (or in any engine that reads it) gives the same order as the keys,
+//! without converting each row to a [FractionalIndex].
+//!
+//! With the `parquet` feature, [write_parquet] and [read_parquet] store a
+//! column of keys in a Parquet file.
+//!
+//! ```rust
+//! use fractional_index::{arrow, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//!
+//! let array = arrow::to_array(&[a.clone(), b.clone()]);
+//! assert_eq!(array.value(1), b.as_bytes());
+//! assert_eq!(arrow::from_array(&array).unwrap(), vec![a.clone(), b]);
+//!
+//! let nullable = arrow::to_nullable_array(vec![None, Some(&a)]);
+//! assert_eq!(arrow::from_nullable_array(&nullable).unwrap(), vec![None, Some(a)]);
+//! ```
+use crate::{DecodeError, FractionalIndex};
+use arrow_array::{Array, BinaryArray, GenericBinaryArray, OffsetSizeTrait};
+use std::fmt::{self, Display};
+
+/// The error returned when an array holds a value that is not a key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ColumnError {
+    /// The value at `row` is null, in a column that should not have nulls.
+    Null { row: usize },
+    /// The value at `row` is not a valid key.
+    Invalid { row: usize, error: DecodeError },
+}
+
+impl Display for ColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnError::Null { row } => {
+                write!(f, "The fractional index at row {} is null.", row)
+            }
+            ColumnError::Invalid { row, error } => {
+                write!(
+                    f,
+                    "The fractional index at row {} is invalid: {}",
+                    row, error
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ColumnError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ColumnError::Null { .. } => None,
+            ColumnError::Invalid { error, .. } => Some(error),
+        }
+    }
+}
+
+/// Builds a [BinaryArray] of the bytes of each key.
+pub fn to_array<'a, I>(keys: I) -> BinaryArray
+where
+    I: IntoIterator<Item = &'a FractionalIndex>,
+{
+    keys.into_iter().map(|key| Some(key.as_bytes())).collect()
+}
+
+/// Builds a [BinaryArray] of the bytes of each key, with a null for each
+/// missing key.
+pub fn to_nullable_array<'a, I>(keys: I) -> BinaryArray
+where
+    I: IntoIterator<Item = Option<&'a FractionalIndex>>,
+{
+    keys.into_iter()
+        .map(|key| key.map(FractionalIndex::as_bytes))
+        .collect()
+}
+
+/// Decodes each value of a binary array as a key. Returns an error if a
+/// value is null or not a valid key.
+pub fn from_array<O: OffsetSizeTrait>(
+    array: &GenericBinaryArray<O>,
+) -> Result<Vec<FractionalIndex>, ColumnError> {
+    decode_keys(array, 0)
+}
+
+/// Decodes each value of a binary array as a key, or None if it is null.
+/// Returns an error if a value is not a valid key.
+pub fn from_nullable_array<O: OffsetSizeTrait>(
+    array: &GenericBinaryArray<O>,
+) -> Result<Vec<Option<FractionalIndex>>, ColumnError> {
+    (0..array.len()).map(|row| decode(array, row, 0)).collect()
+}
+
+/// Decodes each value of a binary array as a key, numbering rows in errors
+/// from `first_row`.
+fn decode_keys<O: OffsetSizeTrait>(
+    array: &GenericBinaryArray<O>,
+    first_row: usize,
+) -> Result<Vec<FractionalIndex>, ColumnError> {
+    (0..array.len())
+        .map(|i| decode(array, i, first_row)?.ok_or(ColumnError::Null { row: first_row + i }))
+        .collect()
+}
+
+fn decode<O: OffsetSizeTrait>(
+    array: &GenericBinaryArray<O>,
+    i: usize,
+    first_row: usize,
+) -> Result<Option<FractionalIndex>, ColumnError> {
+    if array.is_null(i) {
+        return Ok(None);
+    }
+    FractionalIndex::from_bytes(array.value(i).to_vec())
+        .map(Some)
+        .map_err(|error| ColumnError::Invalid {
+            row: first_row + i,
+            error,
+        })
+}
+
+#[cfg(feature = "parquet")]
+pub use self::parquet_io::{read_parquet, write_parquet};
+
+#[cfg(feature = "parquet")]
+mod parquet_io {
+    use super::{decode_keys, to_array};
+    use crate::FractionalIndex;
+    use arrow_array::{ArrayRef, BinaryArray, RecordBatch};
+    use parquet::{
+        arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter, ProjectionMask},
+        errors::ParquetError,
+        file::reader::ChunkReader,
+    };
+    use std::{io::Write, sync::Arc};
+
+    /// Writes the keys to `writer` as a Parquet file with a single binary
+    /// column named `column`, and returns the writer.
+    pub fn write_parquet<'a, W, I>(writer: W, column: &str, keys: I) -> Result<W, ParquetError>
+    where
+        W: Write + Send,
+        I: IntoIterator<Item = &'a FractionalIndex>,
+    {
+        let array: ArrayRef = Arc::new(to_array(keys));
+        let batch = RecordBatch::try_from_iter(vec![(column, array)])?;
+        let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+        writer.write(&batch)?;
+        writer.into_inner()
+    }
+
+    /// Reads the keys in the binary column named `column` of a Parquet
+    /// file, e.g. one written by [write_parquet]. A value that is null or
+    /// not a key is returned as a [ParquetError::External] holding a
+    /// [ColumnError](super::ColumnError).
+    pub fn read_parquet<R>(reader: R, column: &str) -> Result<Vec<FractionalIndex>, ParquetError>
+    where
+        R: ChunkReader + 'static,
+    {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(reader)?;
+        let mask = ProjectionMask::columns(builder.parquet_schema(), vec![column]);
+        let mut keys = Vec::new();
+        for batch in builder.with_projection(mask).build()? {
+            let batch = batch?;
+            let array = batch
+                .column_by_name(column)
+                .and_then(|array| array.as_any().downcast_ref::<BinaryArray>())
+                .ok_or_else(|| {
+                    ParquetError::General(format!("No binary column named {}.", column))
+                })?;
+            let decoded = decode_keys(array, keys.len())
+                .map_err(|err| ParquetError::External(Box::new(err)))?;
+            keys.extend(decoded);
+        }
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::LargeBinaryArray;
+
+    #[test]
+    fn round_trip() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_between(&a, &b).unwrap();
+        let keys = vec![b, a, c];
+
+        let array = to_array(&keys);
+        assert_eq!(array.len(), 3);
+        assert_eq!(from_array(&array).unwrap(), keys);
+
+        // The byte order of the values is the order of the keys.
+        let mut values: Vec<&[u8]> = array.iter().map(Option::unwrap).collect();
+        values.sort();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(
+            values,
+            sorted.iter().map(|key| key.as_bytes()).collect::<Vec<_>>()
+        );
+
+        let large: LargeBinaryArray = keys.iter().map(|key| Some(key.as_bytes())).collect();
+        assert_eq!(from_array(&large).unwrap(), keys);
+    }
+
+    #[test]
+    fn invalid_values() {
+        let a = FractionalIndex::default();
+        let nullable = to_nullable_array(vec![Some(&a), None]);
+        assert_eq!(from_array(&nullable), Err(ColumnError::Null { row: 1 }));
+
+        let invalid = BinaryArray::from_vec(vec![a.as_bytes(), &[129]]);
+        assert_eq!(
+            from_nullable_array(&invalid),
+            Err(ColumnError::Invalid {
+                row: 1,
+                error: DecodeError::MissingTerminator { found: Some(129) }
+            })
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet() {
+        let mut keys = vec![FractionalIndex::default()];
+        for _ in 0..100 {
+            let last = keys.last().unwrap();
+            keys.push(FractionalIndex::new_after(last));
+        }
+        let file = write_parquet(Vec::new(), "position", &keys).unwrap();
+        let file = bytes::Bytes::from(file);
+        assert_eq!(read_parquet(file.clone(), "position").unwrap(), keys);
+        assert!(read_parquet(file, "missing").is_err());
+    }
+}
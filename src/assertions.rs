@@ -0,0 +1,51 @@
+//! Invariant checks enabled by the `assertions` feature in debug builds.
+
+/// Checks that `index` is terminated and lies strictly between the given
+/// optional bounds, panicking with the offending bytes otherwise.
+///
+/// This is a no-op unless the `assertions` feature is enabled and debug
+/// assertions are on.
+#[inline]
+pub(crate) fn check_index(index: &[u8], lower: Option<&[u8]>, upper: Option<&[u8]>) {
+    #[cfg(all(feature = "assertions", debug_assertions))]
+    {
+        let terminator = crate::format::ByteFormat::DEFAULT.terminator();
+        assert!(
+            index.last() == Some(&terminator),
+            "fractional_index: constructed an index without a terminator: {:?}",
+            index
+        );
+        let in_order =
+            lower.is_none_or(|lower| lower < index) && upper.is_none_or(|upper| index < upper);
+        assert!(
+            in_order,
+            "fractional_index: constructed index {:?} is not strictly between lower bound {:?} and upper bound {:?}",
+            index, lower, upper
+        );
+    }
+    #[cfg(not(all(feature = "assertions", debug_assertions)))]
+    let _ = (index, lower, upper);
+}
+
+#[cfg(all(test, feature = "assertions", debug_assertions))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid() {
+        check_index(&[128], None, None);
+        check_index(&[128], Some(&[127, 128]), Some(&[129, 128]));
+    }
+
+    #[test]
+    #[should_panic(expected = "without a terminator")]
+    fn unterminated() {
+        check_index(&[127], None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "not strictly between")]
+    fn out_of_order() {
+        check_index(&[129, 128], Some(&[127, 128]), Some(&[129, 128]));
+    }
+}
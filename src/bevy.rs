@@ -0,0 +1,233 @@
+//! Ordering of Bevy entities by [FractionalIndex], enabled by the `bevy`
+//! feature.
+//!
+//! [FractionalOrder] is a component holding the position of an entity, e.g.
+//! a layer in an editor or an item in an inventory. It is [Ord], so a query
+//! can be iterated in order with Bevy's `sort`, or collected with [sorted].
+//! [move_before], [move_after], [move_to_front] and [move_to_back] move an
+//! entity relative to the others in a query, by changing only its own key.
+//!
+//! ```rust
+//! use bevy_ecs::{prelude::*, system::SystemState};
+//! use fractional_index::bevy::{self, FractionalOrder};
+//!
+//! let mut world = World::new();
+//! let first = world.spawn(FractionalOrder::default()).id();
+//! let back = bevy::new_back(world.query::<&FractionalOrder>().iter(&world));
+//! let second = world.spawn(back).id();
+//!
+//! let mut state: SystemState<Query<(Entity, &mut FractionalOrder)>> = SystemState::new(&mut world);
+//! let mut query = state.get_mut(&mut world);
+//! assert_eq!(bevy::sorted(&query), vec![first, second]);
+//!
+//! bevy::move_before(&mut query, second, first).unwrap();
+//! assert_eq!(bevy::sorted(&query), vec![second, first]);
+//! ```
+use crate::FractionalIndex;
+use bevy_ecs::{component::Component, entity::Entity, query::QueryFilter, system::Query};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The position of an entity among the entities it is ordered with.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FractionalOrder(pub FractionalIndex);
+
+impl From<FractionalIndex> for FractionalOrder {
+    fn from(index: FractionalIndex) -> Self {
+        FractionalOrder(index)
+    }
+}
+
+impl From<FractionalOrder> for FractionalIndex {
+    fn from(order: FractionalOrder) -> Self {
+        order.0
+    }
+}
+
+/// Returns the entities in order, e.g. from `&query` for a
+/// `Query<(Entity, &FractionalOrder)>`. Entities with equal keys are
+/// ordered by [Entity].
+pub fn sorted<'a, I>(entities: I) -> Vec<Entity>
+where
+    I: IntoIterator<Item = (Entity, &'a FractionalOrder)>,
+{
+    let mut entities: Vec<(&FractionalOrder, Entity)> = entities
+        .into_iter()
+        .map(|(entity, order)| (order, entity))
+        .collect();
+    entities.sort();
+    entities.into_iter().map(|(_, entity)| entity).collect()
+}
+
+/// Returns a key before every given key, for spawning an entity at the
+/// front.
+pub fn new_front<'a, I>(orders: I) -> FractionalOrder
+where
+    I: IntoIterator<Item = &'a FractionalOrder>,
+{
+    let first = orders.into_iter().min();
+    FractionalOrder(FractionalIndex::new(None, first.map(|order| &order.0)).unwrap())
+}
+
+/// Returns a key after every given key, for spawning an entity at the
+/// back.
+pub fn new_back<'a, I>(orders: I) -> FractionalOrder
+where
+    I: IntoIterator<Item = &'a FractionalOrder>,
+{
+    let last = orders.into_iter().max();
+    FractionalOrder(FractionalIndex::new(last.map(|order| &order.0), None).unwrap())
+}
+
+/// Moves `entity` immediately before `anchor`, and returns its new key.
+/// Returns None, leaving the keys unchanged, if either entity is not in the
+/// query.
+///
+/// If `anchor` is `entity` itself, its key is returned unchanged.
+pub fn move_before<F: QueryFilter>(
+    query: &mut Query<(Entity, &mut FractionalOrder), F>,
+    entity: Entity,
+    anchor: Entity,
+) -> Option<FractionalIndex> {
+    let upper = query.get(anchor).ok()?.1 .0.clone();
+    if anchor == entity {
+        return Some(upper);
+    }
+    let lower = others(query, entity)
+        .into_iter()
+        .filter(|key| *key < &upper)
+        .max()
+        .cloned();
+    place(query, entity, lower.as_ref(), Some(&upper))
+}
+
+/// Moves `entity` immediately after `anchor`, and returns its new key.
+/// Returns None, leaving the keys unchanged, if either entity is not in the
+/// query.
+///
+/// If `anchor` is `entity` itself, its key is returned unchanged.
+pub fn move_after<F: QueryFilter>(
+    query: &mut Query<(Entity, &mut FractionalOrder), F>,
+    entity: Entity,
+    anchor: Entity,
+) -> Option<FractionalIndex> {
+    let lower = query.get(anchor).ok()?.1 .0.clone();
+    if anchor == entity {
+        return Some(lower);
+    }
+    let upper = others(query, entity)
+        .into_iter()
+        .filter(|key| *key > &lower)
+        .min()
+        .cloned();
+    place(query, entity, Some(&lower), upper.as_ref())
+}
+
+/// Moves `entity` before every other entity in the query, and returns its
+/// new key. Returns None if it is not in the query.
+pub fn move_to_front<F: QueryFilter>(
+    query: &mut Query<(Entity, &mut FractionalOrder), F>,
+    entity: Entity,
+) -> Option<FractionalIndex> {
+    let upper = others(query, entity).into_iter().min().cloned();
+    place(query, entity, None, upper.as_ref())
+}
+
+/// Moves `entity` after every other entity in the query, and returns its
+/// new key. Returns None if it is not in the query.
+pub fn move_to_back<F: QueryFilter>(
+    query: &mut Query<(Entity, &mut FractionalOrder), F>,
+    entity: Entity,
+) -> Option<FractionalIndex> {
+    let lower = others(query, entity).into_iter().max().cloned();
+    place(query, entity, lower.as_ref(), None)
+}
+
+/// Returns the keys of the entities in the query other than `entity`.
+fn others<'a, F: QueryFilter>(
+    query: &'a Query<(Entity, &mut FractionalOrder), F>,
+    entity: Entity,
+) -> Vec<&'a FractionalIndex> {
+    query
+        .iter()
+        .filter(|(other, _)| *other != entity)
+        .map(|(_, order)| &order.0)
+        .collect()
+}
+
+/// Sets the key of `entity` to a new key between `lower` and `upper`.
+fn place<F: QueryFilter>(
+    query: &mut Query<(Entity, &mut FractionalOrder), F>,
+    entity: Entity,
+    lower: Option<&FractionalIndex>,
+    upper: Option<&FractionalIndex>,
+) -> Option<FractionalIndex> {
+    let (_, mut order) = query.get_mut(entity).ok()?;
+    let index = FractionalIndex::new(lower, upper).expect("Neighbors are always in order.");
+    order.0 = index.clone();
+    Some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::{query::With, system::SystemState, world::World};
+
+    #[derive(Component)]
+    struct Layer;
+
+    #[test]
+    fn reorder_entities() {
+        let mut world = World::new();
+        let mut entities = Vec::new();
+        for _ in 0..4 {
+            let order = new_back(world.query::<&FractionalOrder>().iter(&world));
+            entities.push(world.spawn((order, Layer)).id());
+        }
+        let front = new_front(world.query::<&FractionalOrder>().iter(&world));
+        let unlayered = world.spawn(front).id();
+        let [a, b, c, d] = [entities[0], entities[1], entities[2], entities[3]];
+
+        let mut state: SystemState<Query<(Entity, &mut FractionalOrder), With<Layer>>> =
+            SystemState::new(&mut world);
+        let mut query = state.get_mut(&mut world);
+        assert_eq!(sorted(&query), vec![a, b, c, d]);
+
+        move_after(&mut query, a, c).unwrap();
+        assert_eq!(sorted(&query), vec![b, c, a, d]);
+        move_before(&mut query, d, b).unwrap();
+        assert_eq!(sorted(&query), vec![d, b, c, a]);
+        move_to_back(&mut query, d).unwrap();
+        assert_eq!(sorted(&query), vec![b, c, a, d]);
+        move_to_front(&mut query, a).unwrap();
+        assert_eq!(sorted(&query), vec![a, b, c, d]);
+
+        // Moving next to itself leaves the key unchanged.
+        let key = query.get(b).unwrap().1 .0.clone();
+        assert_eq!(move_before(&mut query, b, b), Some(key));
+
+        // Entities outside the query are not moved.
+        assert_eq!(move_after(&mut query, unlayered, a), None);
+        assert_eq!(move_after(&mut query, a, unlayered), None);
+        assert_eq!(sorted(&query), vec![a, b, c, d]);
+    }
+
+    #[test]
+    fn equal_keys() {
+        let mut world = World::new();
+        let a = world.spawn(FractionalOrder::default()).id();
+        let b = world.spawn(FractionalOrder::default()).id();
+        let c = world.spawn(FractionalOrder::default()).id();
+
+        let mut state: SystemState<Query<(Entity, &mut FractionalOrder)>> =
+            SystemState::new(&mut world);
+        let mut query = state.get_mut(&mut world);
+        move_before(&mut query, c, a).unwrap();
+        let key = |query: &Query<(Entity, &mut FractionalOrder)>, entity| {
+            query.get(entity).unwrap().1 .0.clone()
+        };
+        assert!(key(&query, c) < key(&query, a));
+        assert_eq!(key(&query, a), key(&query, b));
+    }
+}
@@ -0,0 +1,192 @@
+//! A borrowed form of [FractionalIndex], for reading keys without
+//! allocating.
+//!
+//! [FractionalIndexRef] holds its bytes as a [Cow]. With the `serde`
+//! feature, it deserializes from the same representation as
+//! [FractionalIndex], borrowing the bytes from the input when the format
+//! provides them (as bincode and postcard do when deserializing from a
+//! slice) and copying them otherwise (as with JSON).
+//!
+//! ```rust
+//! use fractional_index::{borrowed::FractionalIndexRef, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//!
+//! let json = serde_json::to_string(&vec![a.clone(), b.clone()]).unwrap();
+//! let keys: Vec<FractionalIndexRef> = serde_json::from_str(&json).unwrap();
+//! assert_eq!(keys[0], a);
+//! assert_eq!(keys[1].to_index(), b);
+//! ```
+use crate::{DecodeError, FractionalIndex};
+use std::{borrow::Cow, cmp::Ordering, ops::Deref};
+
+/// A [FractionalIndex] whose bytes may be borrowed.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FractionalIndexRef<'a>(Cow<'a, [u8]>);
+
+impl<'a> FractionalIndexRef<'a> {
+    /// Constructs a [FractionalIndexRef] that borrows the given bytes.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        FractionalIndex::validate(bytes)?;
+        Ok(FractionalIndexRef(Cow::Borrowed(bytes)))
+    }
+
+    /// Returns the byte representation of this index.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns true if the bytes of this index are borrowed.
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.0, Cow::Borrowed(_))
+    }
+
+    /// Copies this index into a [FractionalIndex].
+    pub fn to_index(&self) -> FractionalIndex {
+        FractionalIndex::from_bytes_unchecked(self.0.to_vec())
+    }
+
+    /// Converts this index into a [FractionalIndex], copying its bytes only
+    /// if they are borrowed.
+    pub fn into_index(self) -> FractionalIndex {
+        FractionalIndex::from_bytes_unchecked(self.0.into_owned())
+    }
+}
+
+impl<'a> From<&'a FractionalIndex> for FractionalIndexRef<'a> {
+    fn from(index: &'a FractionalIndex) -> Self {
+        FractionalIndexRef(Cow::Borrowed(index.as_bytes()))
+    }
+}
+
+impl From<FractionalIndexRef<'_>> for FractionalIndex {
+    fn from(index: FractionalIndexRef<'_>) -> Self {
+        index.into_index()
+    }
+}
+
+impl PartialEq<FractionalIndex> for FractionalIndexRef<'_> {
+    fn eq(&self, other: &FractionalIndex) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialOrd<FractionalIndex> for FractionalIndexRef<'_> {
+    fn partial_cmp(&self, other: &FractionalIndex) -> Option<Ordering> {
+        Some(self.as_bytes().cmp(other.as_bytes()))
+    }
+}
+
+impl Deref for FractionalIndexRef<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::*;
+    use crate::limits::collect_seq;
+    use serde::{
+        de::{self, SeqAccess, Visitor},
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+    use std::fmt;
+
+    /// The newtype name used by the derived impls of [FractionalIndex].
+    const NAME: &str = "FractionalIndex";
+
+    impl Serialize for FractionalIndexRef<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            // Matches the derived impl of FractionalIndex, which serializes
+            // its bytes as a sequence.
+            serializer.serialize_newtype_struct(NAME, self.as_bytes())
+        }
+    }
+
+    struct IndexVisitor;
+
+    impl<'de> Visitor<'de> for IndexVisitor {
+        type Value = FractionalIndexRef<'de>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("the bytes of a fractional index")
+        }
+
+        fn visit_newtype_struct<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_bytes(IndexVisitor)
+        }
+
+        fn visit_borrowed_bytes<E: de::Error>(self, bytes: &'de [u8]) -> Result<Self::Value, E> {
+            FractionalIndexRef::from_bytes(bytes).map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+            self.visit_byte_buf(bytes.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+            FractionalIndex::validate(&bytes).map_err(E::custom)?;
+            Ok(FractionalIndexRef(Cow::Owned(bytes)))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+            self.visit_byte_buf(collect_seq(seq)?)
+        }
+    }
+
+    impl<'de: 'a, 'a> Deserialize<'de> for FractionalIndexRef<'a> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_newtype_struct(NAME, IndexVisitor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_bytes() {
+        let bytes = [127, 128];
+        let index = FractionalIndexRef::from_bytes(&bytes).unwrap();
+        assert!(index.is_borrowed());
+        assert_eq!(
+            index,
+            FractionalIndex::new_before(&FractionalIndex::default())
+        );
+        assert_eq!(
+            FractionalIndexRef::from_bytes(&[127]),
+            Err(DecodeError::MissingTerminator { found: Some(127) })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn borrowed_deserialize() {
+        let mut keys = vec![FractionalIndex::default()];
+        for _ in 0..10 {
+            keys.push(FractionalIndex::new_after(keys.last().unwrap()));
+        }
+
+        let encoded = bincode::serialize(&keys).unwrap();
+        let decoded: Vec<FractionalIndexRef> = bincode::deserialize(&encoded).unwrap();
+        assert!(decoded.iter().all(FractionalIndexRef::is_borrowed));
+        assert_eq!(decoded, keys);
+        assert_eq!(bincode::serialize(&decoded).unwrap(), encoded);
+
+        let json = serde_json::to_string(&keys).unwrap();
+        let decoded: Vec<FractionalIndexRef> = serde_json::from_str(&json).unwrap();
+        assert!(!decoded[0].is_borrowed());
+        assert_eq!(decoded, keys);
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+
+        assert!(serde_json::from_str::<FractionalIndexRef>("[1, 2]").is_err());
+    }
+}
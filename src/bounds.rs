@@ -0,0 +1,104 @@
+//! Helpers for building range bounds over [FractionalIndex]es, e.g. for
+//! paginating over a [BTreeMap](std::collections::BTreeMap) with
+//! [BTreeMap::range](std::collections::BTreeMap::range).
+//!
+//! Every helper returns a `(Bound<FractionalIndex>, Bound<FractionalIndex>)`
+//! tuple, which implements [RangeBounds](std::ops::RangeBounds).
+//!
+//! ```rust
+//! use fractional_index::{bounds, btree::BTreeMapExt, FractionalIndex};
+//! use std::collections::BTreeMap;
+//!
+//! let mut map = BTreeMap::new();
+//! let a = map.insert_back('a');
+//! map.insert_back('b');
+//! let c = map.insert_back('c');
+//!
+//! let between: String = map.range(bounds::between(&a, &c)).map(|(_, v)| v).collect();
+//! assert_eq!(between, "b");
+//!
+//! let page: String = map.range(bounds::after(&a)).map(|(_, v)| v).collect();
+//! assert_eq!(page, "bc");
+//! ```
+use crate::FractionalIndex;
+use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+/// A pair of bounds over [FractionalIndex]es, usable as a range.
+pub type IndexRange = (Bound<FractionalIndex>, Bound<FractionalIndex>);
+
+/// Every index. Both ends are the synthetic before-all and after-all
+/// bounds.
+pub fn all() -> IndexRange {
+    (Unbounded, Unbounded)
+}
+
+/// Every index that compares before `upper`.
+pub fn before(upper: &FractionalIndex) -> IndexRange {
+    (Unbounded, Excluded(upper.clone()))
+}
+
+/// Every index that compares before or equal to `upper`.
+pub fn at_or_before(upper: &FractionalIndex) -> IndexRange {
+    (Unbounded, Included(upper.clone()))
+}
+
+/// Every index that compares after `lower`.
+pub fn after(lower: &FractionalIndex) -> IndexRange {
+    (Excluded(lower.clone()), Unbounded)
+}
+
+/// Every index that compares after or equal to `lower`.
+pub fn at_or_after(lower: &FractionalIndex) -> IndexRange {
+    (Included(lower.clone()), Unbounded)
+}
+
+/// Every index strictly between `lower` and `upper`.
+///
+/// Note that [BTreeMap::range](std::collections::BTreeMap::range) panics if
+/// `lower == upper` for an exclusive range, or if `lower > upper`.
+pub fn between(lower: &FractionalIndex, upper: &FractionalIndex) -> IndexRange {
+    (Excluded(lower.clone()), Excluded(upper.clone()))
+}
+
+/// Every index between `lower` and `upper`, including both.
+pub fn between_inclusive(lower: &FractionalIndex, upper: &FractionalIndex) -> IndexRange {
+    (Included(lower.clone()), Included(upper.clone()))
+}
+
+/// Every index strictly between optional bounds, where a missing bound is
+/// treated as the synthetic before-all or after-all bound. This mirrors
+/// the arguments of [FractionalIndex::new].
+pub fn between_optional(
+    lower: Option<&FractionalIndex>,
+    upper: Option<&FractionalIndex>,
+) -> IndexRange {
+    (
+        lower.map_or(Unbounded, |lower| Excluded(lower.clone())),
+        upper.map_or(Unbounded, |upper| Excluded(upper.clone())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::btree::BTreeMapExt;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn ranges() {
+        let mut map = BTreeMap::new();
+        let keys: Vec<FractionalIndex> = "abcde".chars().map(|c| map.insert_back(c)).collect();
+        let collect = |range: IndexRange| -> String { map.range(range).map(|(_, v)| v).collect() };
+
+        assert_eq!(collect(all()), "abcde");
+        assert_eq!(collect(before(&keys[2])), "ab");
+        assert_eq!(collect(at_or_before(&keys[2])), "abc");
+        assert_eq!(collect(after(&keys[2])), "de");
+        assert_eq!(collect(at_or_after(&keys[2])), "cde");
+        assert_eq!(collect(between(&keys[1], &keys[3])), "c");
+        assert_eq!(collect(between_inclusive(&keys[1], &keys[3])), "bcd");
+        assert_eq!(collect(between_optional(None, Some(&keys[1]))), "a");
+        assert_eq!(collect(between_optional(Some(&keys[3]), None)), "e");
+        assert_eq!(collect(between_optional(None, None)), "abcde");
+    }
+}
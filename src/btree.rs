@@ -0,0 +1,112 @@
+//! Extension methods for using [FractionalIndex] as the key of a
+//! [BTreeMap].
+//!
+//! ```rust
+//! use fractional_index::{btree::BTreeMapExt, FractionalIndex};
+//! use std::collections::BTreeMap;
+//!
+//! let mut map: BTreeMap<FractionalIndex, &str> = BTreeMap::new();
+//! let b = map.insert_back("b");
+//! map.insert_front("a");
+//! map.insert_after(&b, "d");
+//! map.insert_after(&b, "c");
+//!
+//! let values: Vec<&str> = map.values().copied().collect();
+//! assert_eq!(values, vec!["a", "b", "c", "d"]);
+//! ```
+use crate::FractionalIndex;
+use std::collections::BTreeMap;
+use std::ops::Bound::{Excluded, Unbounded};
+
+/// Relative insertion into a [BTreeMap] keyed by [FractionalIndex].
+///
+/// Each method computes a new key from the neighbors already present in
+/// the map, inserts the value under that key, and returns the key.
+pub trait BTreeMapExt<V> {
+    /// Inserts a value immediately before `key`, i.e. between `key` and
+    /// the greatest key in the map that is less than it.
+    fn insert_before(&mut self, key: &FractionalIndex, value: V) -> FractionalIndex;
+
+    /// Inserts a value immediately after `key`, i.e. between `key` and
+    /// the least key in the map that is greater than it.
+    fn insert_after(&mut self, key: &FractionalIndex, value: V) -> FractionalIndex;
+
+    /// Inserts a value before every other entry in the map.
+    fn insert_front(&mut self, value: V) -> FractionalIndex;
+
+    /// Inserts a value after every other entry in the map.
+    fn insert_back(&mut self, value: V) -> FractionalIndex;
+}
+
+impl<V> BTreeMapExt<V> for BTreeMap<FractionalIndex, V> {
+    fn insert_before(&mut self, key: &FractionalIndex, value: V) -> FractionalIndex {
+        let lower = self.range(..key).next_back().map(|(k, _)| k);
+        let new_key = FractionalIndex::new(lower, Some(key))
+            .expect("Keys of a BTreeMap are always distinct and in order.");
+        self.insert(new_key.clone(), value);
+        new_key
+    }
+
+    fn insert_after(&mut self, key: &FractionalIndex, value: V) -> FractionalIndex {
+        let upper = self
+            .range::<FractionalIndex, _>((Excluded(key), Unbounded))
+            .next()
+            .map(|(k, _)| k);
+        let new_key = FractionalIndex::new(Some(key), upper)
+            .expect("Keys of a BTreeMap are always distinct and in order.");
+        self.insert(new_key.clone(), value);
+        new_key
+    }
+
+    fn insert_front(&mut self, value: V) -> FractionalIndex {
+        let new_key = match self.keys().next() {
+            Some(first) => FractionalIndex::new_before(first),
+            None => FractionalIndex::default(),
+        };
+        self.insert(new_key.clone(), value);
+        new_key
+    }
+
+    fn insert_back(&mut self, value: V) -> FractionalIndex {
+        let new_key = match self.keys().next_back() {
+            Some(last) => FractionalIndex::new_after(last),
+            None => FractionalIndex::default(),
+        };
+        self.insert(new_key.clone(), value);
+        new_key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_relative() {
+        let mut map = BTreeMap::new();
+        let c = map.insert_front('c');
+        let a = map.insert_before(&c, 'a');
+        map.insert_after(&a, 'b');
+        map.insert_back('e');
+        map.insert_after(&c, 'd');
+        map.insert_front('_');
+
+        let values: String = map.values().collect();
+        assert_eq!(values, "_abcde");
+    }
+
+    #[test]
+    fn insert_relative_to_missing_key() {
+        let mut map = BTreeMap::new();
+        let a = map.insert_back('a');
+        let c = map.insert_back('c');
+        let b = FractionalIndex::new_between(&a, &c).unwrap();
+
+        // The reference key does not have to be present in the map.
+        map.insert_after(&b, 'x');
+        map.insert_before(&b, 'y');
+
+        let values: String = map.values().collect();
+        assert_eq!(values, "ayxc");
+    }
+}
@@ -0,0 +1,171 @@
+//! A string encoding of [FractionalIndex]es with a checksum, for keys that
+//! travel through spreadsheets, URLs and support tooling.
+//!
+//! [encode] appends a `-` and the CRC-16 of the key's bytes (as four hex
+//! digits) to the string returned by [FractionalIndex::to_string], and
+//! [decode] verifies it, so a key that was truncated, mistyped or pasted
+//! incompletely is rejected rather than decoded as a different position.
+//!
+//! `-` sorts before every hex digit, so checksummed strings still compare in
+//! the same order as the keys they encode.
+//!
+//! With the `serde` feature, the module can be used with serde's `with`
+//! attribute, like [stringify](crate::stringify).
+//!
+//! ```rust
+//! use fractional_index::{checksum, DecodeError, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//!
+//! let encoded = checksum::encode(&b);
+//! assert_eq!(encoded, "8180-a42e");
+//! assert_eq!(checksum::decode(&encoded), Ok(b));
+//! assert!(checksum::encode(&a) < encoded);
+//!
+//! // A key that lost a byte fails the checksum.
+//! assert!(matches!(
+//!     checksum::decode("80-a42e"),
+//!     Err(DecodeError::ChecksumMismatch { .. })
+//! ));
+//! ```
+use crate::{
+    hex::{hex_to_bytes, InvalidChar},
+    limits::check_len,
+    DecodeError, FractionalIndex,
+};
+
+/// Separates the key from its checksum.
+pub const SEPARATOR: char = '-';
+
+/// Computes the CRC-16/CCITT-FALSE checksum of `bytes`.
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0xffff;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Encodes `index` as its hex string, followed by [SEPARATOR] and the
+/// checksum of its bytes.
+pub fn encode(index: &FractionalIndex) -> String {
+    format!(
+        "{}{}{:04x}",
+        index.to_string(),
+        SEPARATOR,
+        crc16(index.as_bytes())
+    )
+}
+
+/// Decodes a string returned by [encode], after verifying its checksum.
+///
+/// Returns [DecodeError::MissingChecksum] if the string has no checksum,
+/// and [DecodeError::ChecksumMismatch] if the checksum does not match the
+/// key.
+pub fn decode(s: &str) -> Result<FractionalIndex, DecodeError> {
+    if s.is_empty() {
+        return Err(DecodeError::EmptyString);
+    }
+    let (key, checksum) = s
+        .rsplit_once(SEPARATOR)
+        .ok_or(DecodeError::MissingChecksum)?;
+    if key.is_empty() {
+        return Err(DecodeError::EmptyString);
+    }
+    if key.len() % 2 != 0 || checksum.len() != 4 {
+        return Err(DecodeError::InvalidLength);
+    }
+    check_len(key.len() / 2)?;
+
+    let invalid_chars = |offset: usize| {
+        move |err: InvalidChar| DecodeError::InvalidChars {
+            character: err.character,
+            position: err.position + offset,
+        }
+    };
+    let bytes = hex_to_bytes(key).map_err(invalid_chars(0))?;
+    let checksum = hex_to_bytes(checksum).map_err(invalid_chars(key.len() + 1))?;
+    let found = u16::from_be_bytes([checksum[0], checksum[1]]);
+    let expected = crc16(&bytes);
+    if found != expected {
+        return Err(DecodeError::ChecksumMismatch { expected, found });
+    }
+    FractionalIndex::from_bytes(bytes)
+}
+
+#[cfg(feature = "serde")]
+pub fn serialize<S>(index: &FractionalIndex, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&encode(index))
+}
+
+#[cfg(feature = "serde")]
+pub fn deserialize<'de, D>(deserializer: D) -> Result<FractionalIndex, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+    decode(&s).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+        assert_eq!(crc16(b""), 0xffff);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let a = FractionalIndex::default();
+        let mut keys = FractionalIndex::new_before_many(&a, 20);
+        keys.push(a.clone());
+        keys.extend(FractionalIndex::new_after_many(&a, 20));
+        let encoded: Vec<String> = keys.iter().map(encode).collect();
+        for (key, encoded) in keys.iter().zip(&encoded) {
+            assert_eq!(decode(encoded).as_ref(), Ok(key));
+        }
+        assert!(encoded.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn corruption() {
+        let key = FractionalIndex::new_after(&FractionalIndex::default());
+        let encoded = encode(&key);
+        assert_eq!(decode("8180"), Err(DecodeError::MissingChecksum));
+        assert_eq!(decode(""), Err(DecodeError::EmptyString));
+        assert_eq!(decode("-a42e"), Err(DecodeError::EmptyString));
+        assert_eq!(decode("8180-a42"), Err(DecodeError::InvalidLength));
+        assert_eq!(decode("818-a42e"), Err(DecodeError::InvalidLength));
+        assert_eq!(
+            decode("8180-a42g"),
+            Err(DecodeError::InvalidChars {
+                character: 'g',
+                position: 8
+            })
+        );
+        // A mistyped digit is caught, wherever it is.
+        for i in (0..encoded.len()).filter(|&i| i != 4) {
+            let mut typo = encoded.clone().into_bytes();
+            typo[i] = if typo[i] == b'0' { b'1' } else { b'0' };
+            let typo = String::from_utf8(typo).unwrap();
+            assert!(matches!(
+                decode(&typo),
+                Err(DecodeError::ChecksumMismatch { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,478 @@
+//! A harness that simulates concurrent inserts into a shared list, for
+//! checking a key allocation strategy before it meets real traffic.
+//! Enabled by the `test-util` feature.
+//!
+//! A [Simulation] runs several actors against one server. Each actor
+//! inserts into its own (possibly stale) view of the list with
+//! [Strategy::propose] and sends the proposal to the server, which
+//! decides the authoritative key with [Strategy::accept] and broadcasts
+//! it back. Messages are delayed by a random latency, and the actors take
+//! turns in a random interleaving, both drawn from a seed so that any run
+//! can be reproduced.
+//!
+//! The [Report] counts colliding proposals and checks that the keys of
+//! the final order are unique, that every actor converged to it, and that
+//! every item is still between the neighbors it was inserted between.
+//!
+//! ```rust
+//! use fractional_index::concurrency::{Optimistic, Reconciling, Simulation};
+//!
+//! let simulation = Simulation {
+//!     actors: 4,
+//!     inserts_per_actor: 50,
+//!     max_latency: 5,
+//!     gaps: Some(1),
+//!     seed: 7,
+//! };
+//!
+//! // Accepting every proposal as is lets concurrent inserts into the
+//! // same gap end up with the same key.
+//! let report = simulation.run(&mut Optimistic);
+//! assert!(report.collisions > 0 && report.duplicate_keys > 0);
+//!
+//! let report = simulation.run(&mut Reconciling);
+//! assert!(report.is_ok(), "{:?}", report);
+//! ```
+use crate::{reconcile::reconcile, rng::SplitMix, FractionalIndex};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+/// The id of an inserted item: the actor that inserted it, and the number
+/// of items that actor inserted before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemId {
+    pub actor: usize,
+    pub seq: usize,
+}
+
+/// A view of the list, in order. Items with equal keys are ordered by id.
+pub type Order = BTreeSet<(FractionalIndex, ItemId)>;
+
+/// An insert received by the server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proposal {
+    pub item: ItemId,
+    /// The key the actor generated.
+    pub key: FractionalIndex,
+    /// The server's keys of the items the actor inserted between, or None
+    /// for an end of the list (or an item the server rejected).
+    pub lower: Option<FractionalIndex>,
+    pub upper: Option<FractionalIndex>,
+}
+
+/// A key allocation strategy under test.
+pub trait Strategy {
+    /// Returns the key with which `actor` inserts an item between the
+    /// given optional bounds of its own view.
+    fn propose(
+        &mut self,
+        actor: usize,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> FractionalIndex;
+
+    /// Returns the authoritative key for `proposal`, or None to reject
+    /// it. `order` is the server's order before the item is inserted.
+    fn accept(&mut self, proposal: &Proposal, order: &Order) -> Option<FractionalIndex>;
+}
+
+/// Proposes [FractionalIndex::new] and accepts every key as is. This is
+/// the baseline that shows what happens without conflict handling.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Optimistic;
+
+impl Strategy for Optimistic {
+    fn propose(
+        &mut self,
+        _actor: usize,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> FractionalIndex {
+        FractionalIndex::new(lower, upper).expect("Views are in order.")
+    }
+
+    fn accept(&mut self, proposal: &Proposal, _order: &Order) -> Option<FractionalIndex> {
+        Some(proposal.key.clone())
+    }
+}
+
+/// Proposes [FractionalIndex::new], and checks proposals with
+/// [reconcile](crate::reconcile::reconcile) against the bounds. A key
+/// that is taken is replaced by a key between it and the next key of the
+/// server's order.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Reconciling;
+
+impl Strategy for Reconciling {
+    fn propose(
+        &mut self,
+        _actor: usize,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> FractionalIndex {
+        FractionalIndex::new(lower, upper).expect("Views are in order.")
+    }
+
+    fn accept(&mut self, proposal: &Proposal, order: &Order) -> Option<FractionalIndex> {
+        let key = reconcile(
+            proposal.key.as_bytes(),
+            proposal.lower.as_ref(),
+            proposal.upper.as_ref(),
+        )
+        .ok()?
+        .into_key();
+        if !contains_key(order, &key) {
+            return Some(key);
+        }
+        let next = order.iter().map(|(key, _)| key).find(|other| **other > key);
+        FractionalIndex::new(Some(&key), next)
+    }
+}
+
+fn contains_key(order: &Order, key: &FractionalIndex) -> bool {
+    order
+        .range((key.clone(), ItemId { actor: 0, seq: 0 })..)
+        .next()
+        .is_some_and(|(other, _)| other == key)
+}
+
+/// The parameters of a simulation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Simulation {
+    /// The number of concurrent actors.
+    pub actors: usize,
+    /// The number of items each actor inserts.
+    pub inserts_per_actor: usize,
+    /// The maximum delay of a message, in turns. Each message is delayed
+    /// by a random number of turns up to this, but messages between the
+    /// same actor and the server arrive in the order they were sent.
+    pub max_latency: u64,
+    /// The number of gaps, counted from the front of an actor's view,
+    /// that its inserts are spread over, or None to use every gap. Small
+    /// values make actors contend for the same gaps.
+    pub gaps: Option<usize>,
+    /// The seed of the latencies and the interleaving of the actors.
+    pub seed: u64,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation {
+            actors: 4,
+            inserts_per_actor: 100,
+            max_latency: 10,
+            gaps: None,
+            seed: 0,
+        }
+    }
+}
+
+/// The outcome of a simulation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The number of proposals received by the server.
+    pub inserts: usize,
+    /// The number of proposals whose key was already taken when they
+    /// reached the server.
+    pub collisions: usize,
+    /// The number of proposals accepted with a different key.
+    pub adjusted: usize,
+    /// The number of proposals rejected.
+    pub rejected: usize,
+    /// The number of keys shared by more than one item in the final order.
+    pub duplicate_keys: usize,
+    /// The number of items that are not between the items they were
+    /// inserted between, in the final order.
+    pub order_violations: usize,
+    /// True if every actor's view equals the server's order at the end.
+    pub converged: bool,
+    /// The length of the longest key in the final order.
+    pub max_key_len: usize,
+}
+
+impl Report {
+    /// Returns true if the final keys are unique, every actor converged,
+    /// and the order respects the intent of every insert.
+    pub fn is_ok(&self) -> bool {
+        self.duplicate_keys == 0 && self.order_violations == 0 && self.converged
+    }
+}
+
+enum Message {
+    /// An insert sent to the server, with the items it was inserted
+    /// between.
+    Propose {
+        item: ItemId,
+        key: FractionalIndex,
+        lower: Option<ItemId>,
+        upper: Option<ItemId>,
+    },
+    /// The server's decision, sent to an actor.
+    Decide {
+        actor: usize,
+        item: ItemId,
+        key: Option<FractionalIndex>,
+    },
+}
+
+/// An ordered view of the list with the key of each item.
+#[derive(Default, PartialEq)]
+struct View {
+    order: Order,
+    keys: BTreeMap<ItemId, FractionalIndex>,
+}
+
+impl View {
+    fn set(&mut self, item: ItemId, key: Option<FractionalIndex>) {
+        if let Some(old) = self.keys.remove(&item) {
+            self.order.remove(&(old, item));
+        }
+        if let Some(key) = key {
+            self.order.insert((key.clone(), item));
+            self.keys.insert(item, key);
+        }
+    }
+}
+
+/// A queue of messages in order of delivery time, then of sending.
+struct Network {
+    rng: SplitMix,
+    max_latency: u64,
+    queue: BinaryHeap<Reverse<(u64, usize)>>,
+    messages: Vec<Option<Message>>,
+    /// The delivery time of the last message on each channel, which later
+    /// messages on the channel may not precede.
+    last: BTreeMap<(usize, bool), u64>,
+}
+
+impl Network {
+    fn send(&mut self, now: u64, actor: usize, to_server: bool, message: Message) {
+        let latency = self.rng.below(self.max_latency + 1);
+        let last = self.last.entry((actor, to_server)).or_default();
+        let time = (now + latency).max(*last);
+        *last = time;
+        self.queue.push(Reverse((time, self.messages.len())));
+        self.messages.push(Some(message));
+    }
+
+    fn receive(&mut self, now: u64) -> Option<Message> {
+        let Reverse((time, index)) = *self.queue.peek()?;
+        if time > now {
+            return None;
+        }
+        self.queue.pop();
+        self.messages[index].take()
+    }
+}
+
+impl Simulation {
+    /// Runs the simulation with `strategy` and checks the final order.
+    pub fn run<S: Strategy>(&self, strategy: &mut S) -> Report {
+        let mut rng = SplitMix::new(self.seed);
+        let mut network = Network {
+            rng: SplitMix::new(rng.next_u64()),
+            max_latency: self.max_latency,
+            queue: BinaryHeap::new(),
+            messages: Vec::new(),
+            last: BTreeMap::new(),
+        };
+        let mut server = View::default();
+        let mut views: Vec<View> = (0..self.actors).map(|_| View::default()).collect();
+        let mut remaining = vec![self.inserts_per_actor; self.actors];
+        let mut intents: Vec<(ItemId, Option<ItemId>, Option<ItemId>)> = Vec::new();
+        let mut report = Report::default();
+
+        let mut now = 0;
+        loop {
+            while let Some(message) = network.receive(now) {
+                match message {
+                    Message::Propose {
+                        item,
+                        key,
+                        lower,
+                        upper,
+                    } => {
+                        let bound = |item: Option<ItemId>| {
+                            item.and_then(|item| server.keys.get(&item).cloned())
+                        };
+                        let proposal = Proposal {
+                            item,
+                            lower: bound(lower),
+                            upper: bound(upper),
+                            key,
+                        };
+                        report.inserts += 1;
+                        if contains_key(&server.order, &proposal.key) {
+                            report.collisions += 1;
+                        }
+                        let decision = strategy.accept(&proposal, &server.order);
+                        match &decision {
+                            Some(key) if *key != proposal.key => report.adjusted += 1,
+                            Some(_) => {}
+                            None => report.rejected += 1,
+                        }
+                        if decision.is_some() {
+                            intents.push((item, lower, upper));
+                        }
+                        server.set(item, decision.clone());
+                        for actor in 0..self.actors {
+                            let key = decision.clone();
+                            network.send(now, actor, false, Message::Decide { actor, item, key });
+                        }
+                    }
+                    Message::Decide { actor, item, key } => views[actor].set(item, key),
+                }
+            }
+
+            let active: Vec<usize> = (0..self.actors)
+                .filter(|&actor| remaining[actor] > 0)
+                .collect();
+            if active.is_empty() {
+                if network.queue.is_empty() {
+                    break;
+                }
+                now += 1;
+                continue;
+            }
+
+            let actor = active[rng.below(active.len() as u64) as usize];
+            let view = &mut views[actor];
+            let items: Vec<(FractionalIndex, ItemId)> = view.order.iter().cloned().collect();
+            let gaps = self
+                .gaps
+                .map_or(items.len() + 1, |gaps| gaps.clamp(1, items.len() + 1));
+            let mut gap = rng.below(gaps as u64) as usize;
+            // A stale view may hold equal keys, which leave no room between
+            // them, so insert after them instead.
+            while gap > 0 && gap < items.len() && items[gap - 1].0 == items[gap].0 {
+                gap += 1;
+            }
+            let lower = gap.checked_sub(1).map(|i| &items[i]);
+            let upper = items.get(gap);
+            let key = strategy.propose(actor, lower.map(|(key, _)| key), upper.map(|(key, _)| key));
+
+            let item = ItemId {
+                actor,
+                seq: self.inserts_per_actor - remaining[actor],
+            };
+            remaining[actor] -= 1;
+            view.set(item, Some(key.clone()));
+            let message = Message::Propose {
+                item,
+                key,
+                lower: lower.map(|(_, item)| *item),
+                upper: upper.map(|(_, item)| *item),
+            };
+            network.send(now, actor, true, message);
+            now += 1;
+        }
+
+        let mut keys = server.order.iter().map(|(key, _)| key).peekable();
+        while let Some(key) = keys.next() {
+            if keys.peek() == Some(&key) {
+                report.duplicate_keys += 1;
+                while keys.next_if_eq(&key).is_some() {}
+            }
+        }
+        let position = |item: &Option<ItemId>| {
+            item.and_then(|item| server.keys.get(&item).map(|key| (key.clone(), item)))
+        };
+        for (item, lower, upper) in &intents {
+            let here = position(&Some(*item));
+            if position(lower).is_some_and(|lower| Some(lower) >= here)
+                || position(upper).is_some_and(|upper| Some(upper) <= here)
+            {
+                report.order_violations += 1;
+            }
+        }
+        report.converged = views.iter().all(|view| *view == server);
+        report.max_key_len = server
+            .order
+            .iter()
+            .map(|(key, _)| key.len())
+            .max()
+            .unwrap_or(0);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contention() {
+        for gaps in [Some(1), Some(3), None] {
+            for seed in 0..5 {
+                let simulation = Simulation {
+                    actors: 5,
+                    inserts_per_actor: 40,
+                    max_latency: 8,
+                    gaps,
+                    seed,
+                };
+                let report = simulation.run(&mut Reconciling);
+                assert_eq!(report.inserts, 200);
+                assert!(report.is_ok(), "{:?} {:?}", gaps, report);
+                assert_eq!(report, simulation.run(&mut Reconciling));
+            }
+        }
+    }
+
+    #[test]
+    fn optimistic_collides() {
+        let simulation = Simulation {
+            gaps: Some(1),
+            ..Simulation::default()
+        };
+        let report = simulation.run(&mut Optimistic);
+        assert!(report.collisions > 0);
+        assert!(report.duplicate_keys > 0);
+        assert!(report.converged);
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn without_latency() {
+        // Every message arrives before the next turn, so views are never
+        // stale and nothing collides.
+        let simulation = Simulation {
+            max_latency: 0,
+            ..Simulation::default()
+        };
+        let report = simulation.run(&mut Optimistic);
+        assert_eq!(report.collisions, 0);
+        assert!(report.is_ok());
+    }
+
+    /// Rejects every other proposal.
+    struct Picky(bool);
+
+    impl Strategy for Picky {
+        fn propose(
+            &mut self,
+            _actor: usize,
+            lower: Option<&FractionalIndex>,
+            upper: Option<&FractionalIndex>,
+        ) -> FractionalIndex {
+            FractionalIndex::new(lower, upper).unwrap()
+        }
+
+        fn accept(&mut self, proposal: &Proposal, order: &Order) -> Option<FractionalIndex> {
+            self.0 = !self.0;
+            if self.0 {
+                Reconciling.accept(proposal, order)
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn rejections() {
+        let simulation = Simulation::default();
+        let report = simulation.run(&mut Picky(false));
+        assert_eq!(report.rejected, report.inserts / 2);
+        assert!(report.is_ok(), "{:?}", report);
+    }
+}
@@ -0,0 +1,145 @@
+//! Serde helpers for reading and writing [FractionalIndex]es as CSV fields.
+//!
+//! The derived serde impls of [FractionalIndex] encode it as a sequence of
+//! bytes, which CSV cannot represent in a single field. These helpers
+//! encode it as the plain hex string returned by
+//! [FractionalIndex::to_string] instead. Its alphabet is `[0-9a-f]`, so a
+//! field never contains a delimiter, quote or line break and is never
+//! quoted, whatever the writer's settings. Hex strings also sort in the
+//! same order as the keys, so a sorted export stays sorted as text.
+//!
+//! Use [serialize] and [deserialize] with serde's `with` attribute, and
+//! [option] for columns that may be empty:
+//!
+//! ```rust
+//! use fractional_index::FractionalIndex;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Row {
+//!     id: u32,
+//!     #[serde(with = "fractional_index::csv")]
+//!     position: FractionalIndex,
+//!     #[serde(with = "fractional_index::csv::option")]
+//!     parent: Option<FractionalIndex>,
+//! }
+//!
+//! let a = FractionalIndex::default();
+//! let rows = vec![
+//!     Row { id: 1, position: a.clone(), parent: None },
+//!     Row { id: 2, position: FractionalIndex::new_after(&a), parent: Some(a) },
+//! ];
+//!
+//! let mut writer = csv::Writer::from_writer(Vec::new());
+//! for row in &rows {
+//!     writer.serialize(row).unwrap();
+//! }
+//! let data = writer.into_inner().unwrap();
+//! assert_eq!(data, b"id,position,parent\n1,80,\n2,8180,80\n");
+//!
+//! let mut reader = csv::Reader::from_reader(&data[..]);
+//! let decoded: Vec<Row> = reader.deserialize().collect::<Result<_, _>>().unwrap();
+//! assert_eq!(decoded, rows);
+//! ```
+use crate::FractionalIndex;
+use serde::{
+    de::{self, Visitor},
+    Deserializer, Serializer,
+};
+use std::{fmt, str};
+
+/// Serializes a [FractionalIndex] as its hex string.
+pub fn serialize<S>(index: &FractionalIndex, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&index.to_string())
+}
+
+/// Deserializes a [FractionalIndex] from its hex string, without copying
+/// the field.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<FractionalIndex, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(HexVisitor)
+}
+
+struct HexVisitor;
+
+impl Visitor<'_> for HexVisitor {
+    type Value = FractionalIndex;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a fractional index as a hex string")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        FractionalIndex::from_string(s).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        let s = str::from_utf8(bytes)
+            .map_err(|_| E::invalid_value(de::Unexpected::Bytes(bytes), &self))?;
+        self.visit_str(s)
+    }
+}
+
+/// Serde helpers for an `Option<FractionalIndex>`, written as an empty
+/// field when it is `None`.
+pub mod option {
+    use super::*;
+
+    /// Serializes `Some` index as its hex string and `None` as an empty
+    /// string.
+    pub fn serialize<S>(index: &Option<FractionalIndex>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match index {
+            Some(index) => super::serialize(index, serializer),
+            None => serializer.serialize_str(""),
+        }
+    }
+
+    /// Deserializes an empty field as `None` and anything else as a hex
+    /// string.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<FractionalIndex>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(OptionVisitor)
+    }
+
+    struct OptionVisitor;
+
+    impl Visitor<'_> for OptionVisitor {
+        type Value = Option<FractionalIndex>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a fractional index as a hex string, or an empty string")
+        }
+
+        fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+            match s {
+                "" => Ok(None),
+                s => HexVisitor.visit_str(s).map(Some),
+            }
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+            match bytes {
+                [] => Ok(None),
+                bytes => HexVisitor.visit_bytes(bytes).map(Some),
+            }
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,483 @@
+//! Opaque cursors for keyset pagination over ordered lists.
+//!
+//! A page of a list ordered by key ends at some item. A [Cursor] holds that
+//! item's key, and optionally its row id to break ties between rows with
+//! equal keys, and encodes them as a URL-safe string (unpadded base64url of
+//! a versioned binary form) to hand to clients, e.g. as a GraphQL
+//! connection's `endCursor`. The next page starts after the decoded key:
+//!
+//! ```sql
+//! SELECT * FROM item WHERE (position, id) > ($1, $2) ORDER BY position, id LIMIT 20
+//! ```
+//!
+//! ```rust
+//! use fractional_index::{cursor::Cursor, FractionalIndex};
+//!
+//! let key = FractionalIndex::new_after(&FractionalIndex::default());
+//! let cursor = Cursor::with_id(key.clone(), 42u64.to_be_bytes());
+//!
+//! let encoded = cursor.encode();
+//! assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+//!
+//! let decoded = Cursor::decode(&encoded).unwrap();
+//! assert_eq!(decoded.key, key);
+//! assert_eq!(decoded.id_u64(), Some(42));
+//! ```
+//!
+//! [after] and [before] turn a cursor into a [KeysetBound], which builds
+//! the comparison and ordering for the next or previous page:
+//!
+//! ```rust
+//! use fractional_index::{cursor::{after, before, Cursor}, sql::Dialect, FractionalIndex};
+//!
+//! let cursor = Cursor::with_id(FractionalIndex::default(), 7u64.to_be_bytes());
+//! let next = after(&cursor);
+//! assert_eq!(
+//!     next.to_sql(Dialect::Postgres, "position", Some("id"), 1),
+//!     r#"("position", "id") > ($1, $2)"#
+//! );
+//! assert_eq!(
+//!     next.order_by(Dialect::Postgres, "position", Some("id")),
+//!     r#""position" ASC, "id" ASC"#
+//! );
+//!
+//! let previous = before(&Cursor::new(FractionalIndex::default())).inclusive();
+//! assert_eq!(previous.to_sql(Dialect::Sqlite, "position", None, 1), r#""position" <= ?"#);
+//! ```
+use crate::{
+    bounds::{self, IndexRange},
+    sql::Dialect,
+    varint::{read_varint, write_varint},
+    DecodeError, FractionalIndex,
+};
+use std::cmp::Ordering;
+use std::convert::{TryFrom, TryInto};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// The version byte at the start of every encoded cursor.
+const VERSION: u8 = 1;
+
+const BASE64_URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A position in a list, as the key of an item and optionally its row id.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor {
+    pub key: FractionalIndex,
+    /// The id of the row, as bytes, to order rows with equal keys.
+    pub id: Option<Vec<u8>>,
+}
+
+impl Cursor {
+    /// Constructs a cursor at `key`, without a row id.
+    pub fn new(key: FractionalIndex) -> Self {
+        Cursor { key, id: None }
+    }
+
+    /// Constructs a cursor at `key` and the row with the given id.
+    pub fn with_id(key: FractionalIndex, id: impl Into<Vec<u8>>) -> Self {
+        Cursor {
+            key,
+            id: Some(id.into()),
+        }
+    }
+
+    /// Returns the row id as a big-endian `u64`, or None if there is no id
+    /// or it is not 8 bytes long.
+    pub fn id_u64(&self) -> Option<u64> {
+        let bytes = self.id.as_deref()?.try_into().ok()?;
+        Some(u64::from_be_bytes(bytes))
+    }
+
+    /// Returns the row id as a string, or None if there is no id or it is
+    /// not UTF-8.
+    pub fn id_str(&self) -> Option<&str> {
+        std::str::from_utf8(self.id.as_deref()?).ok()
+    }
+
+    /// Encodes the cursor as an opaque, URL-safe string.
+    pub fn encode(&self) -> String {
+        let key = self.key.as_bytes();
+        let mut bytes = Vec::with_capacity(3 + key.len() + self.id.as_ref().map_or(0, Vec::len));
+        bytes.push(VERSION);
+        write_varint(&mut bytes, key.len() as u64);
+        bytes.extend_from_slice(key);
+        if let Some(id) = &self.id {
+            bytes.push(1);
+            bytes.extend_from_slice(id);
+        } else {
+            bytes.push(0);
+        }
+        encode_base64(&bytes)
+    }
+
+    /// Decodes a cursor returned by [Cursor::encode].
+    pub fn decode(s: &str) -> Result<Self, DecodeError> {
+        let bytes = decode_base64(s)?;
+        let (&version, mut rest) = bytes.split_first().ok_or(DecodeError::Truncated)?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion { version });
+        }
+        let len = read_varint(&mut rest)?;
+        let len = usize::try_from(len).map_err(|_| DecodeError::InvalidLength)?;
+        if rest.len() < len {
+            return Err(DecodeError::Truncated);
+        }
+        let (key, rest) = rest.split_at(len);
+        let key = FractionalIndex::from_bytes(key.to_vec())?;
+        let id = match rest.split_first() {
+            Some((0, [])) => None,
+            Some((1, id)) => Some(id.to_vec()),
+            Some((&byte, _)) => {
+                return Err(DecodeError::UnexpectedByte {
+                    byte,
+                    position: bytes.len() - rest.len(),
+                })
+            }
+            None => return Err(DecodeError::Truncated),
+        };
+        Ok(Cursor { key, id })
+    }
+}
+
+impl Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.encode())
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = DecodeError;
+
+    fn from_str(s: &str) -> Result<Self, DecodeError> {
+        Cursor::decode(s)
+    }
+}
+
+/// Which side of a cursor a [KeysetBound] selects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Rows after the cursor, for the next page.
+    After,
+    /// Rows before the cursor, for the previous page.
+    Before,
+}
+
+/// A comparison boundary at a [Cursor], for keyset pagination.
+///
+/// Rows are compared by key, then by id if the cursor has one. The bound
+/// is exclusive unless [KeysetBound::inclusive] is called.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeysetBound {
+    pub cursor: Cursor,
+    pub direction: Direction,
+    pub inclusive: bool,
+}
+
+/// Returns the exclusive bound selecting the rows after `cursor`.
+pub fn after(cursor: &Cursor) -> KeysetBound {
+    KeysetBound {
+        cursor: cursor.clone(),
+        direction: Direction::After,
+        inclusive: false,
+    }
+}
+
+/// Returns the exclusive bound selecting the rows before `cursor`.
+pub fn before(cursor: &Cursor) -> KeysetBound {
+    KeysetBound {
+        cursor: cursor.clone(),
+        direction: Direction::Before,
+        inclusive: false,
+    }
+}
+
+impl KeysetBound {
+    /// Makes the bound include the row at the cursor.
+    pub fn inclusive(self) -> Self {
+        KeysetBound {
+            inclusive: true,
+            ..self
+        }
+    }
+
+    /// Makes the bound exclude the row at the cursor.
+    pub fn exclusive(self) -> Self {
+        KeysetBound {
+            inclusive: false,
+            ..self
+        }
+    }
+
+    fn operator(&self) -> &'static str {
+        match (self.direction, self.inclusive) {
+            (Direction::After, false) => ">",
+            (Direction::After, true) => ">=",
+            (Direction::Before, false) => "<",
+            (Direction::Before, true) => "<=",
+        }
+    }
+
+    /// Returns true if the row with the given key and id is within the
+    /// bound. The id is ignored if the cursor has none.
+    pub fn contains(&self, key: &FractionalIndex, id: &[u8]) -> bool {
+        let ordering = match &self.cursor.id {
+            Some(cursor_id) => (key, id).cmp(&(&self.cursor.key, cursor_id.as_slice())),
+            None => key.cmp(&self.cursor.key),
+        };
+        match (ordering, self.direction) {
+            (Ordering::Equal, _) => self.inclusive,
+            (Ordering::Greater, Direction::After) | (Ordering::Less, Direction::Before) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the range of keys within the bound, e.g. for
+    /// [BTreeMap::range](std::collections::BTreeMap::range). If the cursor
+    /// has an id, rows at the cursor's key are included, and should be
+    /// filtered with [KeysetBound::contains].
+    pub fn range(&self) -> IndexRange {
+        let inclusive = self.inclusive || self.cursor.id.is_some();
+        let key = &self.cursor.key;
+        match (self.direction, inclusive) {
+            (Direction::After, false) => bounds::after(key),
+            (Direction::After, true) => bounds::at_or_after(key),
+            (Direction::Before, false) => bounds::before(key),
+            (Direction::Before, true) => bounds::at_or_before(key),
+        }
+    }
+
+    /// Returns a SQL condition comparing `column`, and `id_column` if the
+    /// cursor has an id, to the bound. Parameters are numbered from
+    /// `first_param`: the key, then the id.
+    pub fn to_sql(
+        &self,
+        dialect: Dialect,
+        column: &str,
+        id_column: Option<&str>,
+        first_param: usize,
+    ) -> String {
+        match (&self.cursor.id, id_column) {
+            (Some(_), Some(id_column)) => format!(
+                "({}, {}) {} ({}, {})",
+                dialect.quote(column),
+                dialect.quote(id_column),
+                self.operator(),
+                dialect.placeholder(first_param),
+                dialect.placeholder(first_param + 1)
+            ),
+            _ => format!(
+                "{} {} {}",
+                dialect.quote(column),
+                self.operator(),
+                dialect.placeholder(first_param)
+            ),
+        }
+    }
+
+    /// Returns the SQL `ORDER BY` terms that list rows starting at the
+    /// bound: ascending after the cursor, and descending before it.
+    pub fn order_by(&self, dialect: Dialect, column: &str, id_column: Option<&str>) -> String {
+        let direction = match self.direction {
+            Direction::After => "ASC",
+            Direction::Before => "DESC",
+        };
+        let mut terms = format!("{} {}", dialect.quote(column), direction);
+        if let Some(id_column) = id_column {
+            terms.push_str(&format!(", {} {}", dialect.quote(id_column), direction));
+        }
+        terms
+    }
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(BASE64_URL[(n >> (18 - 6 * i)) as usize & 63] as char);
+        }
+    }
+    out
+}
+
+fn decode_base64(s: &str) -> Result<Vec<u8>, DecodeError> {
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let mut n = 0u32;
+    let mut bits = 0;
+    for (position, character) in s.char_indices() {
+        let value = BASE64_URL
+            .iter()
+            .position(|&c| char::from(c) == character)
+            .ok_or(DecodeError::InvalidChars {
+                character,
+                position,
+            })?;
+        n = n << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+    // A trailing group of 6 bits can't hold a byte.
+    if bits == 6 {
+        return Err(DecodeError::InvalidLength);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64() {
+        for (bytes, encoded) in [
+            (&b""[..], ""),
+            (b"f", "Zg"),
+            (b"fo", "Zm8"),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg"),
+            (&[0xfb, 0xff], "-_8"),
+        ] {
+            assert_eq!(encode_base64(bytes), encoded);
+            assert_eq!(decode_base64(encoded).unwrap(), bytes);
+        }
+        assert_eq!(decode_base64("Z"), Err(DecodeError::InvalidLength));
+        assert_eq!(
+            decode_base64("Zm+v"),
+            Err(DecodeError::InvalidChars {
+                character: '+',
+                position: 2
+            })
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let a = FractionalIndex::default();
+        let mut keys = FractionalIndex::new_after_many(&a, 50);
+        keys.push(a);
+        for key in keys {
+            for cursor in [
+                Cursor::new(key.clone()),
+                Cursor::with_id(key.clone(), ""),
+                Cursor::with_id(key.clone(), "row-7"),
+                Cursor::with_id(key.clone(), 7u64.to_be_bytes()),
+            ] {
+                let encoded = cursor.to_string();
+                assert_eq!(encoded.parse::<Cursor>().as_ref(), Ok(&cursor));
+            }
+        }
+    }
+
+    #[test]
+    fn ids() {
+        let key = FractionalIndex::default();
+        assert_eq!(Cursor::new(key.clone()).id_u64(), None);
+        assert_eq!(Cursor::with_id(key.clone(), "row").id_str(), Some("row"));
+        assert_eq!(Cursor::with_id(key.clone(), "row").id_u64(), None);
+        assert_eq!(
+            Cursor::with_id(key, u64::MAX.to_be_bytes()).id_u64(),
+            Some(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn keyset_bounds() {
+        let keys = FractionalIndex::new_after_many(&FractionalIndex::default(), 5);
+        let rows: Vec<(FractionalIndex, Vec<u8>)> = keys
+            .iter()
+            .flat_map(|key| (0..3u8).map(move |id| (key.clone(), vec![id])))
+            .collect();
+        let count = |bound: &KeysetBound| {
+            rows.iter()
+                .filter(|(key, id)| bound.contains(key, id))
+                .count()
+        };
+
+        let with_id = Cursor::with_id(keys[2].clone(), [1]);
+        assert_eq!(count(&after(&with_id)), 7);
+        assert_eq!(count(&after(&with_id).inclusive()), 8);
+        assert_eq!(count(&before(&with_id)), 7);
+        assert_eq!(count(&before(&with_id).inclusive().exclusive()), 7);
+
+        let key_only = Cursor::new(keys[2].clone());
+        assert_eq!(count(&after(&key_only)), 6);
+        assert_eq!(count(&after(&key_only).inclusive()), 9);
+        assert_eq!(count(&before(&key_only)), 6);
+
+        // Ranges select the same rows before filtering by id.
+        let map: std::collections::BTreeMap<FractionalIndex, ()> =
+            keys.iter().map(|key| (key.clone(), ())).collect();
+        for (bound, expected) in [
+            (after(&key_only), 2),
+            (after(&key_only).inclusive(), 3),
+            (before(&key_only), 2),
+            (after(&with_id), 3),
+            (before(&with_id), 3),
+        ] {
+            assert_eq!(map.range(bound.range()).count(), expected, "{:?}", bound);
+        }
+    }
+
+    #[test]
+    fn keyset_sql() {
+        let cursor = Cursor::with_id(FractionalIndex::default(), "x");
+        assert_eq!(
+            before(&cursor)
+                .inclusive()
+                .to_sql(Dialect::MySql, "position", Some("id"), 3),
+            "(`position`, `id`) <= (?, ?)"
+        );
+        assert_eq!(
+            before(&cursor).to_sql(Dialect::Postgres, "position", Some("id"), 3),
+            r#"("position", "id") < ($3, $4)"#
+        );
+        // Without an id column, only the key is compared.
+        assert_eq!(
+            after(&cursor).to_sql(Dialect::Postgres, "position", None, 1),
+            r#""position" > $1"#
+        );
+        assert_eq!(
+            before(&cursor).order_by(Dialect::Sqlite, "position", None),
+            r#""position" DESC"#
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let key = FractionalIndex::default();
+        let bytes = |bytes: &[u8]| Cursor::decode(&encode_base64(bytes));
+        assert_eq!(Cursor::decode(""), Err(DecodeError::Truncated));
+        assert_eq!(
+            bytes(&[2, 1, 128, 0]),
+            Err(DecodeError::UnsupportedVersion { version: 2 })
+        );
+        assert_eq!(bytes(&[1, 2, 128]), Err(DecodeError::Truncated));
+        assert_eq!(bytes(&[1, 1, 128]), Err(DecodeError::Truncated));
+        assert_eq!(
+            bytes(&[1, 1, 129, 0]),
+            Err(DecodeError::MissingTerminator { found: Some(129) })
+        );
+        assert_eq!(
+            bytes(&[1, 1, 128, 2]),
+            Err(DecodeError::UnexpectedByte {
+                byte: 2,
+                position: 3
+            })
+        );
+        assert_eq!(
+            bytes(&[1, 1, 128, 0, 5]),
+            Err(DecodeError::UnexpectedByte {
+                byte: 0,
+                position: 3
+            })
+        );
+        assert_eq!(bytes(&[1, 1, 128, 0]), Ok(Cursor::new(key)));
+    }
+}
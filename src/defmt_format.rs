@@ -0,0 +1,28 @@
+//! [defmt] support, for logging keys from `no_std` targets.
+//!
+//! Keys are formatted as the same hex string as
+//! [FractionalIndex::to_string](crate::FractionalIndex::to_string), one
+//! byte at a time, so formatting them needs neither an allocation nor
+//! `core::fmt`.
+
+use crate::fixed::FixedFractionalIndex;
+use defmt::{Format, Formatter};
+
+fn format_hex(f: Formatter<'_>, bytes: &[u8]) {
+    for byte in bytes {
+        defmt::write!(f, "{=u8:02x}", byte);
+    }
+}
+
+impl<const N: usize> Format for FixedFractionalIndex<N> {
+    fn format(&self, f: Formatter<'_>) {
+        format_hex(f, self.as_bytes())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Format for crate::FractionalIndex {
+    fn format(&self, f: Formatter<'_>) {
+        format_hex(f, self.as_bytes())
+    }
+}
@@ -0,0 +1,200 @@
+//! A [FractionalIndex] that sorts in descending order.
+//!
+//! [Desc] reverses the order of the key it wraps, both as a Rust value and
+//! in its encoded form, so a store that can only scan keys in ascending
+//! order (e.g. a key-value store, or an index without a `DESC` option)
+//! returns a list newest-first.
+//!
+//! The encoded form is the byte complement of the key, with an escape that
+//! keeps a key sorting after the keys that extend it: each complemented
+//! byte `0xff` is written as `ff 00`, and the key ends with `ff ff`.
+//!
+//! ```rust
+//! use fractional_index::{Desc, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//! assert!(Desc(b.clone()) < Desc(a.clone()));
+//!
+//! let (a, b) = (Desc(a), Desc(b));
+//! assert!(b.to_bytes() < a.to_bytes());
+//! assert!(b.to_string() < a.to_string());
+//! assert_eq!(Desc::from_bytes(&b.to_bytes()).unwrap(), b);
+//! ```
+use crate::{
+    hex::{bytes_to_hex, hex_to_bytes},
+    limits::check_len,
+    DecodeError, FractionalIndex,
+};
+use std::cmp::Ordering;
+
+const ESCAPE: u8 = 0xff;
+const ESCAPED: u8 = 0x00;
+const END: u8 = 0xff;
+
+/// A [FractionalIndex] whose order is reversed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Desc(pub FractionalIndex);
+
+impl Desc {
+    /// Returns the encoded form of the key, which sorts in the reverse
+    /// order of the key's bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let bytes = self.0.as_bytes();
+        let mut encoded = Vec::with_capacity(bytes.len() + 2);
+        for &byte in bytes {
+            let complement = !byte;
+            encoded.push(complement);
+            if complement == ESCAPE {
+                encoded.push(ESCAPED);
+            }
+        }
+        encoded.extend_from_slice(&[ESCAPE, END]);
+        encoded
+    }
+
+    /// Decodes a key from the encoded form returned by [Desc::to_bytes].
+    pub fn from_bytes(encoded: &[u8]) -> Result<Self, DecodeError> {
+        let mut bytes = Vec::with_capacity(encoded.len());
+        let mut iter = encoded.iter().copied().enumerate();
+        while let Some((_, byte)) = iter.next() {
+            if byte != ESCAPE {
+                bytes.push(!byte);
+                continue;
+            }
+            match iter.next() {
+                Some((_, ESCAPED)) => bytes.push(!ESCAPE),
+                Some((position, END)) => {
+                    if position + 1 != encoded.len() {
+                        return Err(DecodeError::UnexpectedByte {
+                            byte: encoded[position + 1],
+                            position: position + 1,
+                        });
+                    }
+                    return FractionalIndex::from_bytes(bytes).map(Desc);
+                }
+                Some((position, byte)) => {
+                    return Err(DecodeError::UnexpectedByte { byte, position })
+                }
+                None => return Err(DecodeError::Truncated),
+            }
+        }
+        Err(DecodeError::Truncated)
+    }
+
+    /// Returns the hex encoding of [Desc::to_bytes], which sorts in the
+    /// reverse order of the key.
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        bytes_to_hex(&self.to_bytes())
+    }
+
+    /// Decodes a key from the string returned by [Desc::to_string].
+    pub fn from_string(s: &str) -> Result<Self, DecodeError> {
+        if s.is_empty() {
+            return Err(DecodeError::EmptyString);
+        }
+        check_len(s.len() / 2)?;
+        let encoded = hex_to_bytes(s).map_err(|err| DecodeError::InvalidChars {
+            character: err.character,
+            position: err.position,
+        })?;
+        Desc::from_bytes(&encoded)
+    }
+
+    /// Returns the wrapped [FractionalIndex].
+    pub fn into_inner(self) -> FractionalIndex {
+        self.0
+    }
+}
+
+impl PartialOrd for Desc {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Desc {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.cmp(&self.0)
+    }
+}
+
+impl From<FractionalIndex> for Desc {
+    fn from(index: FractionalIndex) -> Self {
+        Desc(index)
+    }
+}
+
+impl From<Desc> for FractionalIndex {
+    fn from(desc: Desc) -> Self {
+        desc.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_order() {
+        // Keys that are prefixes of other keys, and keys with bytes whose
+        // complement must be escaped.
+        let mut keys: Vec<FractionalIndex> = vec![
+            vec![128],
+            vec![128, 128],
+            vec![128, 0, 128],
+            vec![128, 0, 0, 128],
+            vec![0, 128],
+            vec![255, 128],
+            vec![255, 255, 128],
+            vec![127, 255, 128],
+        ]
+        .into_iter()
+        .map(|bytes| FractionalIndex::from_bytes(bytes).unwrap())
+        .collect();
+        let mut key = FractionalIndex::default();
+        for _ in 0..50 {
+            key = FractionalIndex::new_before(&key);
+            keys.push(key.clone());
+        }
+        keys.sort();
+
+        let descs: Vec<Desc> = keys.iter().rev().cloned().map(Desc).collect();
+        for pair in descs.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].to_bytes() < pair[1].to_bytes());
+            assert!(pair[0].to_string() < pair[1].to_string());
+        }
+        for desc in &descs {
+            assert_eq!(&Desc::from_bytes(&desc.to_bytes()).unwrap(), desc);
+            assert_eq!(&Desc::from_string(&desc.to_string()).unwrap(), desc);
+        }
+    }
+
+    #[test]
+    fn invalid_encodings() {
+        assert_eq!(Desc::from_bytes(&[]), Err(DecodeError::Truncated));
+        assert_eq!(Desc::from_bytes(&[127]), Err(DecodeError::Truncated));
+        assert_eq!(Desc::from_bytes(&[127, 0xff]), Err(DecodeError::Truncated));
+        assert_eq!(
+            Desc::from_bytes(&[127, 0xff, 7]),
+            Err(DecodeError::UnexpectedByte {
+                byte: 7,
+                position: 2
+            })
+        );
+        assert_eq!(
+            Desc::from_bytes(&[127, 0xff, 0xff, 0]),
+            Err(DecodeError::UnexpectedByte {
+                byte: 0,
+                position: 3
+            })
+        );
+        assert_eq!(
+            Desc::from_bytes(&[126, 0xff, 0xff]),
+            Err(DecodeError::MissingTerminator { found: Some(129) })
+        );
+        assert_eq!(Desc::from_string(""), Err(DecodeError::EmptyString));
+    }
+}
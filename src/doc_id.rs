@@ -0,0 +1,163 @@
+//! An encoding of [FractionalIndex] that is safe to use as a document ID in
+//! databases that order documents by ID, like CouchDB and Firestore.
+//!
+//! Keys are encoded five bits at a time using the characters `0-9a-v`.
+//! These are alphanumeric and never start with an underscore, and their
+//! order is the same under ASCII (byte) ordering and under the Unicode
+//! collation used by some databases, so list order falls out of ID order.
+//!
+//! The encoding is 20% shorter than [FractionalIndex::to_string].
+//!
+//! ```rust
+//! use fractional_index::{doc_id, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//! assert_eq!(doc_id::encode(&a), "g0");
+//! assert!(doc_id::encode(&a) < doc_id::encode(&b));
+//! assert_eq!(doc_id::decode(&doc_id::encode(&b)).unwrap(), b);
+//! ```
+use crate::{DecodeError, FractionalIndex};
+
+const CHARS: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+
+fn digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'v' => Some(c - b'a' + 10),
+        _ => None,
+    }
+}
+
+/// Returns the number of characters used to encode `len` bytes.
+fn encoded_len(len: usize) -> usize {
+    (len * 8).div_ceil(5)
+}
+
+/// Encodes a [FractionalIndex] as a document ID.
+pub fn encode(index: &FractionalIndex) -> String {
+    let bytes = index.as_bytes();
+    let mut out = String::with_capacity(encoded_len(bytes.len()));
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | u16::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(CHARS[usize::from((buffer >> bits) & 0x1f)] as char);
+        }
+    }
+    if bits > 0 {
+        // Pad the final character with zero bits.
+        out.push(CHARS[usize::from((buffer << (5 - bits)) & 0x1f)] as char);
+    }
+    out
+}
+
+/// Decodes a document ID produced by [encode].
+pub fn decode(s: &str) -> Result<FractionalIndex, DecodeError> {
+    if s.is_empty() {
+        return Err(DecodeError::EmptyString);
+    }
+
+    let len = s.len() * 5 / 8;
+    if encoded_len(len) != s.len() {
+        return Err(DecodeError::InvalidLength);
+    }
+
+    let mut bytes = Vec::with_capacity(len);
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+    for (position, c) in s.bytes().enumerate() {
+        let value = digit(c).ok_or_else(|| DecodeError::InvalidChars {
+            // Every byte before `position` is ASCII.
+            character: s[position..].chars().next().unwrap_or_default(),
+            position,
+        })?;
+        buffer = (buffer << 5) | u16::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    // Padding must be zero, so that every index has exactly one encoding.
+    if buffer & ((1 << bits) - 1) != 0 {
+        let position = s.len() - 1;
+        return Err(DecodeError::InvalidChars {
+            character: s.as_bytes()[position] as char,
+            position,
+        });
+    }
+
+    FractionalIndex::from_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> Vec<FractionalIndex> {
+        let mut keys = vec![FractionalIndex::default()];
+        for i in 0..300 {
+            keys.sort();
+            keys.push(match i % 3 {
+                0 => FractionalIndex::new_after(keys.last().unwrap()),
+                1 => FractionalIndex::new_before(&keys[0]),
+                _ => FractionalIndex::new_between(&keys[0], &keys[1]).unwrap(),
+            });
+        }
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn preserves_order_and_roundtrips() {
+        let keys = keys();
+        let encoded: Vec<String> = keys.iter().map(encode).collect();
+        for pair in encoded.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+        for (key, s) in keys.iter().zip(&encoded) {
+            assert!(s
+                .bytes()
+                .all(|c| c.is_ascii_digit() || c.is_ascii_lowercase()));
+            assert_eq!(s.len(), encoded_len(key.len()));
+            assert_eq!(&decode(s).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert!(matches!(decode(""), Err(DecodeError::EmptyString)));
+        assert!(matches!(decode("g"), Err(DecodeError::InvalidLength)));
+        assert!(matches!(decode("g00"), Err(DecodeError::InvalidLength)));
+        assert_eq!(
+            decode("G0"),
+            Err(DecodeError::InvalidChars {
+                character: 'G',
+                position: 0
+            })
+        );
+        assert_eq!(
+            decode("0_"),
+            Err(DecodeError::InvalidChars {
+                character: '_',
+                position: 1
+            })
+        );
+        // Non-zero padding bits.
+        assert_eq!(
+            decode("g1"),
+            Err(DecodeError::InvalidChars {
+                character: '1',
+                position: 1
+            })
+        );
+        assert_eq!(
+            decode("00"),
+            Err(DecodeError::MissingTerminator { found: Some(0) })
+        );
+    }
+}
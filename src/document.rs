@@ -0,0 +1,331 @@
+//! Orderings of items across many named lists, such as the columns of a
+//! board or the sections of a document.
+//!
+//! [DocumentOrdering] keeps an [OrderedList] of item ids per list, and
+//! tracks which list each item is in. Moving an item between lists
+//! allocates a key in the destination list's keyspace, relative to the
+//! destination's items.
+//!
+//! ```rust
+//! use fractional_index::document::{DocumentOrdering, Placement};
+//!
+//! let mut board = DocumentOrdering::new();
+//! board.add_list("todo");
+//! board.add_list("done");
+//! board.insert(&"todo", "write", Placement::Back).unwrap();
+//! board.insert(&"todo", "test", Placement::Back).unwrap();
+//! board.insert(&"done", "plan", Placement::Back).unwrap();
+//!
+//! // Move "write" to the top of "done".
+//! board.move_item(&"write", &"done", Placement::Before(&"plan")).unwrap();
+//!
+//! let done: Vec<&str> = board.items(&"done").unwrap().copied().collect();
+//! assert_eq!(done, vec!["write", "plan"]);
+//! assert_eq!(board.location(&"write").map(|(list, _)| *list), Some("done"));
+//! ```
+use crate::{FractionalIndex, OrderedList};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+/// Where to place an item in a list, relative to its other items.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Placement<'a, Id> {
+    /// Before every other item.
+    Front,
+    /// After every other item.
+    Back,
+    /// Immediately before the given item, which must be in the list.
+    Before(&'a Id),
+    /// Immediately after the given item, which must be in the list.
+    After(&'a Id),
+}
+
+// Implemented by hand so that `Id` need not be `Copy`.
+impl<Id> Clone for Placement<'_, Id> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Id> Copy for Placement<'_, Id> {}
+
+impl<Id: Ord> Placement<'_, Id> {
+    /// Returns the key of the item the placement is relative to, looked up
+    /// in `locations`, a map from items to their list and key, or None for
+    /// [Placement::Front] and [Placement::Back]. Returns `not_in_list` if
+    /// the item is missing or `in_list` is false for its list.
+    pub(crate) fn anchor<L, E>(
+        self,
+        locations: &BTreeMap<Id, (L, FractionalIndex)>,
+        in_list: impl Fn(&L) -> bool,
+        not_in_list: E,
+    ) -> Result<Option<&FractionalIndex>, E> {
+        match self {
+            Placement::Front | Placement::Back => Ok(None),
+            Placement::Before(id) | Placement::After(id) => match locations.get(id) {
+                Some((list, key)) if in_list(list) => Ok(Some(key)),
+                _ => Err(not_in_list),
+            },
+        }
+    }
+
+    /// Inserts `id` into `list` at the placement, given the key returned by
+    /// [Self::anchor], and returns its key.
+    pub(crate) fn insert_into(
+        self,
+        list: &mut OrderedList<Id>,
+        anchor: Option<&FractionalIndex>,
+        id: Id,
+    ) -> FractionalIndex {
+        match (self, anchor) {
+            (Placement::Front, _) => list.push_front(id),
+            (Placement::Back, _) => list.push_back(id),
+            (Placement::Before(_), Some(anchor)) => list.insert_before(anchor, id),
+            (Placement::After(_), Some(anchor)) => list.insert_after(anchor, id),
+            _ => unreachable!("Anchored placements have an anchor."),
+        }
+    }
+}
+
+/// The error returned when a change to a [DocumentOrdering] is invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocumentError {
+    /// The document has no list with the given name.
+    UnknownList,
+    /// The item is not in the document.
+    UnknownItem,
+    /// The item is already in the document.
+    DuplicateItem,
+    /// The item used as an anchor is not in the destination list.
+    AnchorNotInList,
+}
+
+impl Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocumentError::UnknownList => write!(f, "The document has no list with this name."),
+            DocumentError::UnknownItem => write!(f, "The item is not in the document."),
+            DocumentError::DuplicateItem => write!(f, "The item is already in the document."),
+            DocumentError::AnchorNotInList => {
+                write!(f, "The anchor item is not in the destination list.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+/// Named lists of items, where each item is in at most one list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocumentOrdering<L, Id> {
+    lists: BTreeMap<L, OrderedList<Id>>,
+    /// The list and key of each item.
+    locations: BTreeMap<Id, (L, FractionalIndex)>,
+}
+
+impl<L, Id> Default for DocumentOrdering<L, Id> {
+    fn default() -> Self {
+        DocumentOrdering {
+            lists: BTreeMap::new(),
+            locations: BTreeMap::new(),
+        }
+    }
+}
+
+impl<L: Ord + Clone, Id: Ord + Clone> DocumentOrdering<L, Id> {
+    /// Constructs a document with no lists.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an empty list, returning false if a list with that name exists.
+    pub fn add_list(&mut self, name: L) -> bool {
+        if self.lists.contains_key(&name) {
+            return false;
+        }
+        self.lists.insert(name, OrderedList::new());
+        true
+    }
+
+    /// Removes a list and its items, returning them.
+    pub fn remove_list(&mut self, name: &L) -> Option<OrderedList<Id>> {
+        let list = self.lists.remove(name)?;
+        for id in list.values() {
+            self.locations.remove(id);
+        }
+        Some(list)
+    }
+
+    /// Returns the names of the lists, in order.
+    pub fn lists(&self) -> impl DoubleEndedIterator<Item = &L> + '_ {
+        self.lists.keys()
+    }
+
+    /// Returns the list with the given name.
+    pub fn list(&self, name: &L) -> Option<&OrderedList<Id>> {
+        self.lists.get(name)
+    }
+
+    /// Returns the items of a list, in order.
+    pub fn items(&self, name: &L) -> Option<impl DoubleEndedIterator<Item = &Id> + '_> {
+        self.lists.get(name).map(OrderedList::values)
+    }
+
+    /// Returns the list that contains an item and the item's key.
+    pub fn location(&self, id: &Id) -> Option<(&L, &FractionalIndex)> {
+        self.locations.get(id).map(|(list, key)| (list, key))
+    }
+
+    /// Returns the number of items across all lists.
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    /// Returns true if no list contains any items.
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    /// Returns the key of the anchor of `placement`, after checking that
+    /// the list exists and contains the anchor.
+    fn anchor(
+        &self,
+        name: &L,
+        placement: Placement<Id>,
+    ) -> Result<Option<&FractionalIndex>, DocumentError> {
+        if !self.lists.contains_key(name) {
+            return Err(DocumentError::UnknownList);
+        }
+        placement.anchor(
+            &self.locations,
+            |list| list == name,
+            DocumentError::AnchorNotInList,
+        )
+    }
+
+    /// Inserts an item that is not in the document, returning its key.
+    /// The placement must have been checked with [Self::anchor].
+    fn place(&mut self, name: &L, id: Id, placement: Placement<Id>) -> FractionalIndex {
+        let anchor = self
+            .anchor(name, placement)
+            .expect("The placement was checked.")
+            .cloned();
+        let list = self.lists.get_mut(name).expect("The list was checked.");
+        let key = placement.insert_into(list, anchor.as_ref(), id.clone());
+        self.locations.insert(id, (name.clone(), key.clone()));
+        key
+    }
+
+    /// Inserts a new item into a list, returning its key.
+    pub fn insert(
+        &mut self,
+        name: &L,
+        id: Id,
+        placement: Placement<Id>,
+    ) -> Result<FractionalIndex, DocumentError> {
+        if self.locations.contains_key(&id) {
+            return Err(DocumentError::DuplicateItem);
+        }
+        self.anchor(name, placement)?;
+        Ok(self.place(name, id, placement))
+    }
+
+    /// Moves an item to a position in the same or another list, returning
+    /// its new key. The document is unchanged if an error is returned.
+    ///
+    /// Placing an item before or after itself leaves it where it is.
+    pub fn move_item(
+        &mut self,
+        id: &Id,
+        name: &L,
+        placement: Placement<Id>,
+    ) -> Result<FractionalIndex, DocumentError> {
+        let (list, key) = self.locations.get(id).ok_or(DocumentError::UnknownItem)?;
+        if let Placement::Before(anchor) | Placement::After(anchor) = placement {
+            if anchor == id && list == name {
+                return Ok(key.clone());
+            }
+        }
+        self.anchor(name, placement)?;
+        self.remove(id);
+        Ok(self.place(name, id.clone(), placement))
+    }
+
+    /// Removes an item, returning the list it was in and its key.
+    pub fn remove(&mut self, id: &Id) -> Option<(L, FractionalIndex)> {
+        let (list, key) = self.locations.remove(id)?;
+        self.lists
+            .get_mut(&list)
+            .expect("Every item is in a list.")
+            .remove(&key);
+        Some((list, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(document: &DocumentOrdering<&str, u32>, name: &str) -> Vec<u32> {
+        document.items(&name).unwrap().copied().collect()
+    }
+
+    #[test]
+    fn moves_between_lists() {
+        let mut board = DocumentOrdering::new();
+        assert!(board.add_list("a"));
+        assert!(board.add_list("b"));
+        assert!(!board.add_list("a"));
+        for id in 0..5 {
+            board.insert(&"a", id, Placement::Back).unwrap();
+        }
+        board.insert(&"b", 10, Placement::Front).unwrap();
+
+        board.move_item(&2, &"b", Placement::After(&10)).unwrap();
+        board.move_item(&4, &"b", Placement::Before(&10)).unwrap();
+        board.move_item(&0, &"a", Placement::After(&3)).unwrap();
+        board.move_item(&1, &"a", Placement::Before(&1)).unwrap();
+        board.move_item(&3, &"b", Placement::Before(&2)).unwrap();
+        assert_eq!(items(&board, "a"), vec![1, 0]);
+        assert_eq!(items(&board, "b"), vec![4, 10, 3, 2]);
+        assert_eq!(board.len(), 6);
+
+        let (list, key) = board.location(&3).unwrap();
+        assert_eq!(*list, "b");
+        assert_eq!(board.list(&"b").unwrap().get(key), Some(&3));
+
+        assert_eq!(board.remove(&10).map(|(list, _)| list), Some("b"));
+        assert_eq!(items(&board, "b"), vec![4, 3, 2]);
+        assert_eq!(board.remove_list(&"b").unwrap().len(), 3);
+        assert_eq!(board.location(&4), None);
+        assert_eq!(board.lists().copied().collect::<Vec<_>>(), vec!["a"]);
+    }
+
+    #[test]
+    fn errors() {
+        let mut board = DocumentOrdering::new();
+        board.add_list("a");
+        board.add_list("b");
+        board.insert(&"a", 1, Placement::Back).unwrap();
+        board.insert(&"b", 2, Placement::Back).unwrap();
+
+        assert_eq!(
+            board.insert(&"c", 3, Placement::Back),
+            Err(DocumentError::UnknownList)
+        );
+        assert_eq!(
+            board.insert(&"b", 1, Placement::Back),
+            Err(DocumentError::DuplicateItem)
+        );
+        assert_eq!(
+            board.move_item(&3, &"a", Placement::Back),
+            Err(DocumentError::UnknownItem)
+        );
+        assert_eq!(
+            board.move_item(&1, &"a", Placement::After(&2)),
+            Err(DocumentError::AnchorNotInList)
+        );
+        assert_eq!(items(&board, "a"), vec![1]);
+        assert_eq!(items(&board, "b"), vec![2]);
+    }
+}
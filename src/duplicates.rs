@@ -0,0 +1,204 @@
+//! Deterministic resolution of duplicate [FractionalIndex]es.
+//!
+//! When two clients concurrently insert into the same gap, they may
+//! construct the same [FractionalIndex]. Items stored under equal indices
+//! have no defined order relative to each other, so a database may return
+//! them in a different order on each query.
+//!
+//! [resolve_duplicates] assigns a new index to all but one item in each
+//! group of duplicates, using a caller-supplied id to decide the order
+//! within the group. Every replica that runs it over the same entries
+//! arrives at the same result.
+//!
+//! ```rust
+//! use fractional_index::{duplicates::resolve_duplicates, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//! let entries = vec![(a.clone(), "x"), (b.clone(), "z"), (a.clone(), "y")];
+//!
+//! let rewrites = resolve_duplicates(&entries);
+//! assert_eq!(rewrites.len(), 1);
+//!
+//! let (id, new_index) = &rewrites[0];
+//! assert_eq!(*id, "y");
+//! assert!(a < *new_index && *new_index < b);
+//! ```
+//!
+//! Until duplicates are resolved, collections can still keep a consistent
+//! order by comparing the id whenever indices are equal, either with
+//! [by_index_then_id] or by storing [WithTiebreak] keys:
+//!
+//! ```rust
+//! use fractional_index::{duplicates::{by_index_then_id, WithTiebreak}, FractionalIndex};
+//! use std::collections::BTreeSet;
+//!
+//! let a = FractionalIndex::default();
+//! let mut items = vec![(a.clone(), "y", 2), (a.clone(), "x", 1)];
+//! items.sort_by(by_index_then_id(|(index, id, _)| (index, id)));
+//! assert_eq!(items[0].1, "x");
+//!
+//! let keys: BTreeSet<_> = [WithTiebreak::new(a.clone(), "y"), WithTiebreak::new(a, "x")]
+//!     .iter()
+//!     .cloned()
+//!     .collect();
+//! assert_eq!(keys.len(), 2);
+//! assert_eq!(keys.iter().next().unwrap().id, "x");
+//! ```
+use crate::{instrument, FractionalIndex};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// A [FractionalIndex] paired with an id that breaks ties between equal
+/// indices. Compares by index first and id second, so items with
+/// duplicate indices still have a total order.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WithTiebreak<Id> {
+    // The derived comparisons depend on the order of these fields.
+    pub index: FractionalIndex,
+    pub id: Id,
+}
+
+impl<Id> WithTiebreak<Id> {
+    /// Pairs `index` with the `id` that breaks ties.
+    pub fn new(index: FractionalIndex, id: Id) -> Self {
+        WithTiebreak { index, id }
+    }
+}
+
+/// Compares two `(index, id)` pairs by index, and by id if the indices are
+/// equal. This is the order of [WithTiebreak].
+pub fn cmp_with_tiebreak<Id: Ord>(
+    (index, id): (&FractionalIndex, &Id),
+    (other_index, other_id): (&FractionalIndex, &Id),
+) -> Ordering {
+    index.cmp(other_index).then_with(|| id.cmp(other_id))
+}
+
+/// Returns a comparator for `sort_by` and similar methods, which orders
+/// items by the index and id that `key` extracts from them, using
+/// [cmp_with_tiebreak].
+pub fn by_index_then_id<T, Id: Ord>(
+    key: impl Fn(&T) -> (&FractionalIndex, &Id),
+) -> impl Fn(&T, &T) -> Ordering {
+    move |a, b| cmp_with_tiebreak(key(a), key(b))
+}
+
+/// Finds groups of entries that share an index and assigns new indices to
+/// all but the first entry of each group, ordered by `id`.
+///
+/// The new indices are placed in the gap between the duplicated index and
+/// the next distinct index, so the order of the entries relative to the
+/// rest of the list is unchanged.
+///
+/// Returns the `(id, new_index)` pairs that need to be written back, in
+/// order. Entries that are not duplicated are never rewritten.
+pub fn resolve_duplicates<Id: Ord + Clone>(
+    entries: &[(FractionalIndex, Id)],
+) -> Vec<(Id, FractionalIndex)> {
+    let mut sorted: Vec<&(FractionalIndex, Id)> = entries.iter().collect();
+    sorted.sort_by(by_index_then_id(|entry: &&(FractionalIndex, Id)| {
+        (&entry.0, &entry.1)
+    }));
+
+    let mut rewrites = Vec::new();
+    let mut start = 0;
+    while start < sorted.len() {
+        let index = &sorted[start].0;
+        let end = start + sorted[start..].partition_point(|(i, _)| i == index);
+        let upper = sorted.get(end).map(|(i, _)| i);
+
+        let mut lower = index.clone();
+        for (_, id) in &sorted[start + 1..end] {
+            let new_index = FractionalIndex::new(Some(&lower), upper)
+                .expect("Distinct indices should be in order after sorting.");
+            rewrites.push((id.clone(), new_index.clone()));
+            lower = new_index;
+        }
+
+        start = end;
+    }
+
+    instrument::collisions("resolve_duplicates", rewrites.len());
+    rewrites
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiebreak_order() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let mut keys = vec![
+            WithTiebreak::new(b.clone(), 1),
+            WithTiebreak::new(a.clone(), 3),
+            WithTiebreak::new(a.clone(), 2),
+        ];
+        keys.sort();
+        let ids: Vec<i32> = keys.iter().map(|key| key.id).collect();
+        assert_eq!(ids, vec![2, 3, 1]);
+
+        for x in &keys {
+            for y in &keys {
+                assert_eq!(
+                    x.cmp(y),
+                    cmp_with_tiebreak((&x.index, &x.id), (&y.index, &y.id))
+                );
+                assert_eq!(x == y, x.cmp(y) == Ordering::Equal);
+            }
+        }
+    }
+
+    #[test]
+    fn no_duplicates() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        assert!(resolve_duplicates(&[(a, 1), (b, 2)]).is_empty());
+    }
+
+    #[test]
+    fn deterministic_regardless_of_input_order() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+
+        let entries = vec![
+            (b.clone(), 4),
+            (a.clone(), 3),
+            (a.clone(), 1),
+            (b.clone(), 5),
+            (a.clone(), 2),
+        ];
+        let rewrites = resolve_duplicates(&entries);
+
+        let mut reversed = entries.clone();
+        reversed.reverse();
+        assert_eq!(rewrites, resolve_duplicates(&reversed));
+
+        let ids: Vec<i32> = rewrites.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![2, 3, 5]);
+
+        // Apply the rewrites and check that the result is strictly ordered
+        // by (original index, id).
+        let mut resolved: Vec<(FractionalIndex, i32)> = entries
+            .iter()
+            .map(|(index, id)| {
+                let index = rewrites
+                    .iter()
+                    .find(|(i, _)| i == id)
+                    .map_or(index, |(_, new_index)| new_index);
+                (index.clone(), *id)
+            })
+            .collect();
+        resolved.sort();
+
+        let ids: Vec<i32> = resolved.iter().map(|(_, id)| *id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+        for pair in resolved.windows(2) {
+            assert!(pair[0].0 < pair[1].0);
+        }
+    }
+}
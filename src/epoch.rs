@@ -0,0 +1,188 @@
+//! Keys prefixed with an epoch, for rebalancing append-mostly lists
+//! without rewriting existing keys.
+//!
+//! Appending to a list with [FractionalIndex::new_after] grows keys slowly
+//! but without bound. An [EpochIndex] pairs a key with an epoch that
+//! compares first: after [EpochIndex::next_epoch], new keys are appended in
+//! the new epoch starting from the shortest key again, and still compare
+//! after every key of earlier epochs. Insertions between existing keys
+//! stay in the epoch of those keys.
+//!
+//! # Migration
+//!
+//! Existing rows are treated as epoch 0, so adopting epochs does not
+//! require rewriting them:
+//!
+//! 1. Add an epoch column that defaults to 0, e.g.
+//!    `ALTER TABLE item ADD COLUMN epoch smallint NOT NULL DEFAULT 0`.
+//! 2. Switch readers to `ORDER BY epoch, position`, which is equivalent
+//!    to ordering by the old column alone while every epoch is 0.
+//! 3. Switch writers to [EpochIndex]. To rebalance, start appending with
+//!    [EpochIndex::next_epoch] of the last key.
+//!
+//! Where keys must be stored in a single column (e.g. a key-value store),
+//! [EpochIndex::to_bytes] prefixes the key with the epoch byte, which
+//! preserves the same order.
+//!
+//! ```rust
+//! use fractional_index::epoch::EpochIndex;
+//!
+//! let mut last = EpochIndex::default();
+//! for _ in 0..1000 {
+//!     last = last.new_after();
+//! }
+//! let long = last.clone();
+//!
+//! let next = long.next_epoch().unwrap();
+//! assert!(long < next);
+//! assert!(next.index().len() < long.index().len());
+//! ```
+use crate::{DecodeError, FractionalIndex};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A [FractionalIndex] within an epoch. Indexes compare by epoch first.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EpochIndex {
+    epoch: u8,
+    index: FractionalIndex,
+}
+
+impl EpochIndex {
+    pub fn new(epoch: u8, index: FractionalIndex) -> Self {
+        EpochIndex { epoch, index }
+    }
+
+    pub fn epoch(&self) -> u8 {
+        self.epoch
+    }
+
+    pub fn index(&self) -> &FractionalIndex {
+        &self.index
+    }
+
+    /// Returns the default key of the next epoch, which compares after
+    /// every key of this and earlier epochs. Returns None if this is the
+    /// last epoch.
+    pub fn next_epoch(&self) -> Option<Self> {
+        Some(EpochIndex {
+            epoch: self.epoch.checked_add(1)?,
+            index: FractionalIndex::default(),
+        })
+    }
+
+    /// Constructs a key before this one, in the same epoch.
+    pub fn new_before(&self) -> Self {
+        EpochIndex {
+            epoch: self.epoch,
+            index: FractionalIndex::new_before(&self.index),
+        }
+    }
+
+    /// Constructs a key after this one, in the same epoch.
+    pub fn new_after(&self) -> Self {
+        EpochIndex {
+            epoch: self.epoch,
+            index: FractionalIndex::new_after(&self.index),
+        }
+    }
+
+    /// Constructs a key between the given two, which are assumed to be
+    /// provided in order and distinct. Returns None if either of these
+    /// assumptions does not hold.
+    ///
+    /// If the keys are in different epochs, the new key is placed after
+    /// `left` in its epoch.
+    pub fn new_between(left: &Self, right: &Self) -> Option<Self> {
+        if left.epoch < right.epoch {
+            Some(left.new_after())
+        } else if left.epoch == right.epoch {
+            Some(EpochIndex {
+                epoch: left.epoch,
+                index: FractionalIndex::new_between(&left.index, &right.index)?,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the epoch byte followed by the bytes of the index, which
+    /// compare in the same order as [EpochIndex].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.index.len() + 1);
+        bytes.push(self.epoch);
+        bytes.extend_from_slice(self.index.as_bytes());
+        bytes
+    }
+
+    /// Decodes the output of [EpochIndex::to_bytes].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let (&epoch, index) = bytes.split_first().ok_or(DecodeError::Truncated)?;
+        Ok(EpochIndex {
+            epoch,
+            index: FractionalIndex::from_bytes(index.to_vec())?,
+        })
+    }
+}
+
+impl From<FractionalIndex> for EpochIndex {
+    /// Treats an index without an epoch as epoch 0.
+    fn from(index: FractionalIndex) -> Self {
+        EpochIndex { epoch: 0, index }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordering() {
+        let a = EpochIndex::from(FractionalIndex::default());
+        let b = a.new_after();
+        let c = b.next_epoch().unwrap();
+        let d = c.new_after();
+
+        // Keys of a later epoch compare after longer keys of earlier ones.
+        assert!(a < b && b < c && c < d);
+        assert!(c.index() < b.index());
+
+        let between = EpochIndex::new_between(&b, &c).unwrap();
+        assert_eq!(between.epoch(), 0);
+        assert!(b < between && between < c);
+
+        let between = EpochIndex::new_between(&c, &d).unwrap();
+        assert_eq!(between.epoch(), 1);
+        assert!(c < between && between < d);
+
+        assert!(EpochIndex::new_between(&c, &b).is_none());
+        assert!(EpochIndex::new_between(&b, &b).is_none());
+        assert!(EpochIndex::new(255, FractionalIndex::default())
+            .next_epoch()
+            .is_none());
+    }
+
+    #[test]
+    fn bytes() {
+        let keys = [
+            EpochIndex::default().new_before(),
+            EpochIndex::default(),
+            EpochIndex::default().new_after().new_after(),
+            EpochIndex::new(1, FractionalIndex::default()).new_before(),
+            EpochIndex::new(1, FractionalIndex::default()),
+        ];
+        for pair in keys.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].to_bytes() < pair[1].to_bytes());
+        }
+        for key in &keys {
+            assert_eq!(&EpochIndex::from_bytes(&key.to_bytes()).unwrap(), key);
+        }
+        assert_eq!(EpochIndex::from_bytes(&[]), Err(DecodeError::Truncated));
+        assert_eq!(
+            EpochIndex::from_bytes(&[0]),
+            Err(DecodeError::MissingTerminator { found: None })
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use core::fmt::{self, Display};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    EmptyString,
+    /// The key does not end with the terminator. `found` is the byte it
+    /// ends with instead, or None if the key is empty.
+    MissingTerminator {
+        found: Option<u8>,
+    },
+    /// A string encoding contains a character that is not valid at the
+    /// given byte offset.
+    InvalidChars {
+        character: char,
+        position: usize,
+    },
+    /// A binary encoding contains a byte that is not valid at the given
+    /// offset.
+    UnexpectedByte {
+        byte: u8,
+        position: usize,
+    },
+    Truncated,
+    InvalidLength,
+    DigitOutOfRange,
+    /// The key is longer than the limit set with
+    /// [limits::with_max_len](crate::limits::with_max_len).
+    TooLong {
+        max: usize,
+    },
+    /// A batch encoding decodes to more than `max` bytes of keys in total.
+    BatchTooLarge {
+        max: usize,
+    },
+    /// A versioned encoding has a version this crate cannot decode.
+    UnsupportedVersion {
+        version: u8,
+    },
+    /// A checksummed string encoding has no checksum.
+    MissingChecksum,
+    /// The checksum of a checksummed string encoding does not match the
+    /// key, e.g. because the key was truncated or mistyped.
+    ChecksumMismatch {
+        expected: u16,
+        found: u16,
+    },
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::EmptyString => write!(
+                f,
+                "Attempted to decode an empty string as a fractional index."
+            ),
+            DecodeError::MissingTerminator { found: Some(byte) } => write!(
+                f,
+                "Attempted to decode a corrupt fractional index (missing terminator, found trailing byte {:#04x}).",
+                byte
+            ),
+            DecodeError::MissingTerminator { found: None } => write!(
+                f,
+                "Attempted to decode a corrupt fractional index (missing terminator, no bytes)."
+            ),
+            DecodeError::InvalidChars {
+                character,
+                position,
+            } => write!(
+                f,
+                "Attempted to decode a corrupt fractional index (invalid character {:?} at byte offset {}).",
+                character, position
+            ),
+            DecodeError::UnexpectedByte { byte, position } => write!(
+                f,
+                "Attempted to decode a corrupt fractional index encoding (unexpected byte {:#04x} at offset {}).",
+                byte, position
+            ),
+            DecodeError::Truncated => write!(
+                f,
+                "Attempted to decode a truncated fractional index encoding."
+            ),
+            DecodeError::InvalidLength => write!(
+                f,
+                "Attempted to decode a corrupt fractional index encoding (invalid length)."
+            ),
+            DecodeError::DigitOutOfRange => write!(
+                f,
+                "Attempted to decode a corrupt fractional index (byte outside of the digit range)."
+            ),
+            DecodeError::TooLong { max } => write!(
+                f,
+                "Attempted to decode a fractional index longer than the limit of {} bytes.",
+                max
+            ),
+            DecodeError::BatchTooLarge { max } => write!(
+                f,
+                "Attempted to decode a batch of fractional indexes larger than the limit of {} bytes.",
+                max
+            ),
+            DecodeError::UnsupportedVersion { version } => write!(
+                f,
+                "Attempted to decode a fractional index encoding of unsupported version {}.",
+                version
+            ),
+            DecodeError::MissingChecksum => write!(
+                f,
+                "Attempted to decode a checksummed fractional index without a checksum."
+            ),
+            DecodeError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "Attempted to decode a corrupt fractional index (checksum {:04x} does not match {:04x}).",
+                found, expected
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
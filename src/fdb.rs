@@ -0,0 +1,246 @@
+//! Encoding of [FractionalIndex] as a FoundationDB tuple layer element.
+//!
+//! Keys are encoded as tuple byte strings (type code `0x01`), whose packed
+//! form sorts in the same order as the index. The encoding is implemented
+//! directly, so it can be mixed with keys packed by any FoundationDB
+//! binding's tuple layer without depending on one.
+//!
+//! [ListSubspace] builds `(list, index)` keys under a prefix, so that a
+//! range read over [ListSubspace::range] returns a list's entries in order.
+//!
+//! ```rust
+//! use fractional_index::{fdb::ListSubspace, FractionalIndex};
+//!
+//! let lists = ListSubspace::new(b"app/lists/");
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//!
+//! let (begin, end) = lists.range(b"todo");
+//! let key_a = lists.key(b"todo", &a);
+//! let key_b = lists.key(b"todo", &b);
+//! assert!(begin < key_a && key_a < key_b && key_b < end);
+//! assert_eq!(lists.unpack(&key_b).unwrap(), (b"todo".to_vec(), b));
+//! ```
+use crate::{DecodeError, FractionalIndex};
+
+/// The tuple layer type code for byte strings.
+const BYTES_CODE: u8 = 0x01;
+
+/// Appends `bytes` as a tuple layer byte string.
+fn pack_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    out.push(BYTES_CODE);
+    for &byte in bytes {
+        out.push(byte);
+        if byte == 0x00 {
+            out.push(0xff);
+        }
+    }
+    out.push(0x00);
+}
+
+/// Reads a tuple layer byte string from the front of `input`, returning it
+/// and the remaining input.
+fn unpack_bytes(input: &[u8]) -> Result<(Vec<u8>, &[u8]), DecodeError> {
+    match input.first() {
+        Some(&BYTES_CODE) => {}
+        Some(&byte) => return Err(DecodeError::UnexpectedByte { byte, position: 0 }),
+        None => return Err(DecodeError::Truncated),
+    }
+
+    let mut bytes = Vec::new();
+    let mut i = 1;
+    loop {
+        match input.get(i) {
+            None => return Err(DecodeError::Truncated),
+            Some(0x00) if input.get(i + 1) == Some(&0xff) => {
+                bytes.push(0x00);
+                i += 2;
+            }
+            Some(0x00) => return Ok((bytes, &input[i + 1..])),
+            Some(&byte) => {
+                bytes.push(byte);
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Appends the tuple layer encoding of `index` to `out`.
+pub fn pack_into(index: &FractionalIndex, out: &mut Vec<u8>) {
+    pack_bytes(index.as_bytes(), out);
+}
+
+/// Returns the tuple layer encoding of `index`, as a one-element tuple.
+pub fn pack(index: &FractionalIndex) -> Vec<u8> {
+    let mut out = Vec::new();
+    pack_into(index, &mut out);
+    out
+}
+
+/// Reads a [FractionalIndex] from the front of a packed tuple, returning it
+/// and the remaining elements.
+pub fn unpack(input: &[u8]) -> Result<(FractionalIndex, &[u8]), DecodeError> {
+    let (bytes, rest) = unpack_bytes(input)?;
+    Ok((FractionalIndex::from_bytes(bytes)?, rest))
+}
+
+/// Keys of the form `prefix + pack((list, index))`, for storing ordered
+/// lists.
+///
+/// The prefix is typically the raw bytes of a FoundationDB subspace or
+/// directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListSubspace {
+    prefix: Vec<u8>,
+}
+
+impl ListSubspace {
+    /// Constructs a subspace under the given raw key prefix.
+    pub fn new(prefix: &[u8]) -> Self {
+        ListSubspace {
+            prefix: prefix.to_vec(),
+        }
+    }
+
+    /// Returns the raw key prefix of this subspace.
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    fn list_prefix(&self, list: &[u8]) -> Vec<u8> {
+        let mut key = self.prefix.clone();
+        pack_bytes(list, &mut key);
+        key
+    }
+
+    /// Returns the key for the entry at `index` in `list`.
+    pub fn key(&self, list: &[u8], index: &FractionalIndex) -> Vec<u8> {
+        let mut key = self.list_prefix(list);
+        pack_into(index, &mut key);
+        key
+    }
+
+    /// Returns the range `[begin, end)` containing every entry of `list`,
+    /// following the tuple layer's convention for subspace ranges.
+    pub fn range(&self, list: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let prefix = self.list_prefix(list);
+        let mut begin = prefix.clone();
+        begin.push(0x00);
+        let mut end = prefix;
+        end.push(0xff);
+        (begin, end)
+    }
+
+    /// Decodes a key produced by [ListSubspace::key] into its list and
+    /// index.
+    pub fn unpack(&self, key: &[u8]) -> Result<(Vec<u8>, FractionalIndex), DecodeError> {
+        if let Some(position) = self.prefix.iter().zip(key).position(|(a, b)| a != b) {
+            return Err(DecodeError::UnexpectedByte {
+                byte: key[position],
+                position,
+            });
+        }
+        if key.len() < self.prefix.len() {
+            return Err(DecodeError::Truncated);
+        }
+
+        // Report positions relative to the whole key.
+        let offset = |consumed: usize| {
+            move |err| match err {
+                DecodeError::UnexpectedByte { byte, position } => DecodeError::UnexpectedByte {
+                    byte,
+                    position: position + consumed,
+                },
+                err => err,
+            }
+        };
+        let rest = &key[self.prefix.len()..];
+        let (list, rest) = unpack_bytes(rest).map_err(offset(self.prefix.len()))?;
+        let (index, rest) = unpack(rest).map_err(offset(key.len() - rest.len()))?;
+        if !rest.is_empty() {
+            return Err(DecodeError::InvalidLength);
+        }
+        Ok((list, index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packing() {
+        let index = FractionalIndex::from_bytes(vec![0, 1, 0xff, 128]).unwrap();
+        let packed = pack(&index);
+        assert_eq!(packed, vec![0x01, 0x00, 0xff, 0x01, 0xff, 128, 0x00]);
+        let (unpacked, rest) = unpack(&packed).unwrap();
+        assert_eq!(unpacked, index);
+        assert!(rest.is_empty());
+
+        assert!(matches!(unpack(&[]), Err(DecodeError::Truncated)));
+        assert!(matches!(unpack(&[0x01, 128]), Err(DecodeError::Truncated)));
+        assert_eq!(
+            unpack(&[0x02, 128, 0]),
+            Err(DecodeError::UnexpectedByte {
+                byte: 0x02,
+                position: 0
+            })
+        );
+        assert_eq!(
+            unpack(&[0x01, 1, 0]),
+            Err(DecodeError::MissingTerminator { found: Some(1) })
+        );
+
+        let lists = ListSubspace::new(b"ab");
+        assert_eq!(
+            lists.unpack(b"ac"),
+            Err(DecodeError::UnexpectedByte {
+                byte: b'c',
+                position: 1
+            })
+        );
+        assert_eq!(lists.unpack(b"a"), Err(DecodeError::Truncated));
+        assert_eq!(
+            lists.unpack(b"ab\x01x\x00\x05"),
+            Err(DecodeError::UnexpectedByte {
+                byte: 0x05,
+                position: 5
+            })
+        );
+    }
+
+    #[test]
+    fn preserves_order() {
+        let mut keys = vec![
+            FractionalIndex::from_bytes(vec![0, 128]).unwrap(),
+            FractionalIndex::from_bytes(vec![0, 0, 128]).unwrap(),
+            FractionalIndex::default(),
+        ];
+        for i in 0..200 {
+            keys.sort();
+            keys.push(match i % 3 {
+                0 => FractionalIndex::new_after(keys.last().unwrap()),
+                1 => FractionalIndex::new_before(&keys[0]),
+                _ => FractionalIndex::new_between(&keys[0], &keys[1]).unwrap(),
+            });
+        }
+        keys.sort();
+
+        let lists = ListSubspace::new(b"\x15\x07");
+        let (begin, end) = lists.range(b"a\x00b");
+        let other = lists.range(b"a");
+        for pair in keys.windows(2) {
+            assert!(pack(&pair[0]) < pack(&pair[1]));
+            let (a, b) = (
+                lists.key(b"a\x00b", &pair[0]),
+                lists.key(b"a\x00b", &pair[1]),
+            );
+            assert!(begin < a && a < b && b < end);
+            assert!(!(other.0 <= a && a < other.1));
+            assert_eq!(
+                lists.unpack(&a).unwrap(),
+                (b"a\x00b".to_vec(), pair[0].clone())
+            );
+        }
+    }
+}
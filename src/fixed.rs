@@ -0,0 +1,335 @@
+//! A fixed-capacity fractional index that never allocates.
+//!
+//! [FixedFractionalIndex] stores its bytes in an inline array, so it is
+//! available without the `std` feature (in `no_std` environments without
+//! `alloc`). Since keys can grow, every generation method is fallible and
+//! returns [CapacityError] if the result does not fit.
+//!
+//! [FixedFractionalIndex] uses the same byte representation as
+//! [FractionalIndex](crate::FractionalIndex), and generates the same keys.
+//!
+//! ```rust
+//! use fractional_index::fixed::FixedFractionalIndex;
+//!
+//! let a = FixedFractionalIndex::<8>::default();
+//! let b = FixedFractionalIndex::new_after(&a).unwrap();
+//! let c = FixedFractionalIndex::new_between(&a, &b).unwrap().unwrap();
+//! assert!(a < c && c < b);
+//! assert_eq!(c.as_bytes(), &[129, 127, 128]);
+//! ```
+use crate::{
+    assertions::check_index,
+    format::{ByteFormat, Derived, Side},
+    instrument, DecodeError,
+};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+const TERMINATOR: u8 = ByteFormat::DEFAULT.terminator();
+
+/// The error returned when a generated key would not fit in a
+/// [FixedFractionalIndex].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapacityError {
+    /// The number of bytes required to store the key.
+    pub required: usize,
+    /// The capacity of the [FixedFractionalIndex].
+    pub capacity: usize,
+}
+
+impl Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "A fractional index of {} bytes does not fit in a capacity of {} bytes.",
+            self.required, self.capacity
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CapacityError {}
+
+/// A fractional index stored inline in an array of `N` bytes.
+///
+/// Comparison, equality and hashing consider only the bytes of the key,
+/// not the unused capacity.
+#[derive(Clone, Copy)]
+pub struct FixedFractionalIndex<const N: usize> {
+    len: usize,
+    bytes: [u8; N],
+}
+
+impl<const N: usize> Default for FixedFractionalIndex<N> {
+    /// Constructs the default index.
+    ///
+    /// Panics if `N == 0`.
+    fn default() -> Self {
+        let mut bytes = [0; N];
+        bytes[0] = TERMINATOR;
+        FixedFractionalIndex { len: 1, bytes }
+    }
+}
+
+impl<const N: usize> FixedFractionalIndex<N> {
+    fn from_derived(source: &[u8], derived: Derived) -> Result<Self, CapacityError> {
+        let required = derived.len() + 1;
+        if required > N {
+            return Err(CapacityError {
+                required,
+                capacity: N,
+            });
+        }
+
+        let (prefix, last) = derived.parts(source);
+        let mut bytes = [0; N];
+        bytes[..prefix.len()].copy_from_slice(prefix);
+        if let Some(last) = last {
+            bytes[prefix.len()] = last;
+        }
+        bytes[required - 1] = TERMINATOR;
+        Ok(FixedFractionalIndex {
+            len: required,
+            bytes,
+        })
+    }
+
+    /// Constructs a [FixedFractionalIndex] from its byte representation,
+    /// which must be terminated and fit within the capacity.
+    pub fn from_bytes(source: &[u8]) -> Result<Self, DecodeError> {
+        if source.last() != Some(&TERMINATOR) {
+            return Err(DecodeError::MissingTerminator {
+                found: source.last().copied(),
+            });
+        }
+        if source.len() > N {
+            return Err(DecodeError::InvalidLength);
+        }
+
+        let mut bytes = [0; N];
+        bytes[..source.len()].copy_from_slice(source);
+        Ok(FixedFractionalIndex {
+            len: source.len(),
+            bytes,
+        })
+    }
+
+    /// Returns the byte representation of this index.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Returns the number of bytes this index can hold.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Constructs a new index that compares before the given one.
+    pub fn new_before(index: &Self) -> Result<Self, CapacityError> {
+        let bytes = index.as_bytes();
+        let new = Self::from_derived(bytes, ByteFormat::DEFAULT.derive_before(bytes))?;
+        check_index(new.as_bytes(), None, Some(bytes));
+        instrument::generated("new_before", new.as_bytes(), None, Some(bytes));
+        Ok(new)
+    }
+
+    /// Constructs a new index that compares after the given one.
+    pub fn new_after(index: &Self) -> Result<Self, CapacityError> {
+        let bytes = index.as_bytes();
+        let new = Self::from_derived(bytes, ByteFormat::DEFAULT.derive_after(bytes))?;
+        check_index(new.as_bytes(), Some(bytes), None);
+        instrument::generated("new_after", new.as_bytes(), Some(bytes), None);
+        Ok(new)
+    }
+
+    /// Constructs a new index that compares between the given two, which
+    /// are assumed to be provided in order and distinct. Returns `Ok(None)`
+    /// if either of these assumptions does not hold.
+    pub fn new_between(left: &Self, right: &Self) -> Result<Option<Self>, CapacityError> {
+        let (left, right) = (left.as_bytes(), right.as_bytes());
+        let new = match ByteFormat::DEFAULT.derive_between(left, right) {
+            Some((Side::Left, derived)) => Self::from_derived(left, derived)?,
+            Some((Side::Right, derived)) => Self::from_derived(right, derived)?,
+            None => return Ok(None),
+        };
+        check_index(new.as_bytes(), Some(left), Some(right));
+        instrument::generated("new_between", new.as_bytes(), Some(left), Some(right));
+        Ok(Some(new))
+    }
+
+    /// Constructs a new index based on optional lower and upper bounds,
+    /// like [FractionalIndex::new](crate::FractionalIndex::new).
+    pub fn new(lower: Option<&Self>, upper: Option<&Self>) -> Result<Option<Self>, CapacityError> {
+        match (lower, upper) {
+            (Some(lower), Some(upper)) => Self::new_between(lower, upper),
+            (Some(lower), None) => Self::new_after(lower).map(Some),
+            (None, Some(upper)) => Self::new_before(upper).map(Some),
+            (None, None) => Ok(Some(Self::default())),
+        }
+    }
+}
+
+impl<const N: usize> Deref for FixedFractionalIndex<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_bytes()
+    }
+}
+
+impl<const N: usize> PartialEq for FixedFractionalIndex<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl<const N: usize> Eq for FixedFractionalIndex<N> {}
+
+impl<const N: usize> PartialOrd for FixedFractionalIndex<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const N: usize> Ord for FixedFractionalIndex<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl<const N: usize> Hash for FixedFractionalIndex<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state)
+    }
+}
+
+impl<const N: usize> fmt::Debug for FixedFractionalIndex<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FixedFractionalIndex")
+            .field(&self.as_bytes())
+            .finish()
+    }
+}
+
+/// Formats the index as the same hex string as
+/// [FractionalIndex::to_string](crate::FractionalIndex::to_string).
+impl<const N: usize> Display for FixedFractionalIndex<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.as_bytes() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const N: usize> From<FixedFractionalIndex<N>> for crate::FractionalIndex {
+    fn from(index: FixedFractionalIndex<N>) -> Self {
+        crate::FractionalIndex::from_bytes(index.as_bytes().to_vec())
+            .expect("A FixedFractionalIndex is always terminated.")
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const N: usize> core::convert::TryFrom<&crate::FractionalIndex> for FixedFractionalIndex<N> {
+    type Error = DecodeError;
+
+    fn try_from(index: &crate::FractionalIndex) -> Result<Self, Self::Error> {
+        Self::from_bytes(index.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Fixed = FixedFractionalIndex<4>;
+
+    #[test]
+    fn generation() {
+        let a = Fixed::default();
+        assert_eq!(a.as_bytes(), &[128]);
+
+        let b = Fixed::new_before(&a).unwrap();
+        assert_eq!(b.as_bytes(), &[127, 128]);
+
+        let c = Fixed::new_after(&a).unwrap();
+        assert_eq!(c.as_bytes(), &[129, 128]);
+
+        let d = Fixed::new_between(&b, &a).unwrap().unwrap();
+        assert_eq!(d.as_bytes(), &[127, 129, 128]);
+
+        assert_eq!(Fixed::new_between(&a, &b), Ok(None));
+        assert_eq!(Fixed::new_between(&a, &a), Ok(None));
+    }
+
+    #[test]
+    fn capacity_exceeded() {
+        let mut lower = Fixed::default();
+        let upper = Fixed::new_after(&lower).unwrap();
+        let err = loop {
+            match Fixed::new_between(&lower, &upper) {
+                Ok(Some(mid)) => lower = mid,
+                Ok(None) => unreachable!(),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(
+            err,
+            CapacityError {
+                required: 5,
+                capacity: 4
+            }
+        );
+    }
+
+    #[test]
+    fn comparison_ignores_spare_capacity() {
+        let a = Fixed::from_bytes(&[127, 128]).unwrap();
+        let b = Fixed::new_before(&Fixed::default()).unwrap();
+        assert_eq!(a, b);
+        assert!(Fixed::from_bytes(&[1, 2, 3, 4, 128]).is_err());
+        assert!(Fixed::from_bytes(&[1, 2]).is_err());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn matches_fractional_index() {
+        use crate::FractionalIndex;
+        use std::convert::TryFrom;
+
+        let mut fixed = vec![FixedFractionalIndex::<16>::default()];
+        let mut dynamic = vec![FractionalIndex::default()];
+        for i in 0..100 {
+            let (f, d) = match i % 3 {
+                0 => (
+                    FixedFractionalIndex::new_after(fixed.last().unwrap()).unwrap(),
+                    FractionalIndex::new_after(dynamic.last().unwrap()),
+                ),
+                1 => (
+                    FixedFractionalIndex::new_before(&fixed[0]).unwrap(),
+                    FractionalIndex::new_before(&dynamic[0]),
+                ),
+                _ => (
+                    FixedFractionalIndex::new_between(&fixed[0], fixed.last().unwrap())
+                        .unwrap()
+                        .unwrap(),
+                    FractionalIndex::new_between(&dynamic[0], dynamic.last().unwrap()).unwrap(),
+                ),
+            };
+            assert_eq!(f.as_bytes(), d.as_bytes());
+            assert_eq!(f.to_string(), d.to_string());
+            assert_eq!(FractionalIndex::from(f), d);
+            assert_eq!(FixedFractionalIndex::<16>::try_from(&d).unwrap(), f);
+
+            // Keep the smallest index at the front.
+            let position = if i % 3 == 1 { 0 } else { fixed.len() };
+            fixed.insert(position, f);
+            dynamic.insert(position, d);
+        }
+    }
+}
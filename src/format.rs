@@ -0,0 +1,411 @@
+//! Key generation over a configurable byte format.
+//!
+//! A [FractionalIndex] is a byte string that ends with a terminator byte
+//! (128), where every byte may take any value. [ByteFormat] generalizes this
+//! to an arbitrary terminator and range of allowed digit bytes, so that raw
+//! byte keys can be generated to interoperate with an existing system that
+//! uses a different convention.
+//!
+//! Keys generated by a [ByteFormat] other than [ByteFormat::DEFAULT] are
+//! plain byte vectors, since they do not satisfy the invariants of a
+//! [FractionalIndex].
+//!
+//! ```rust
+//! use fractional_index::format::ByteFormat;
+//!
+//! // Printable ASCII digits, with 'P' as the terminator.
+//! let format = ByteFormat::new(b'P', b'!', b'~').unwrap();
+//!
+//! let a = format.default_key();
+//! let b = format.new_after(&a).unwrap();
+//! let c = format.new_between(&a, &b).unwrap().unwrap();
+//! assert!(a < c && c < b);
+//! assert!(c.iter().all(|byte| (b'!'..=b'~').contains(byte)));
+//! ```
+use crate::DecodeError;
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+use core::fmt::{self, Display};
+
+/// The terminator byte and range of digit bytes used to generate keys.
+///
+/// Keys are compared lexicographically. Every key ends with the terminator,
+/// and every byte of a key is within `min_digit..=max_digit`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ByteFormat {
+    terminator: u8,
+    min_digit: u8,
+    max_digit: u8,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FormatError {
+    /// The terminator must be strictly between the smallest and largest
+    /// digit, so that there is always room to generate a key before or
+    /// after any other key.
+    TerminatorOutOfRange,
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::TerminatorOutOfRange => write!(
+                f,
+                "The terminator must be strictly between the smallest and largest digit."
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FormatError {}
+
+impl ByteFormat {
+    /// The format used by [FractionalIndex](crate::FractionalIndex).
+    pub const DEFAULT: ByteFormat = ByteFormat {
+        terminator: 0b1000_0000, // =128
+        min_digit: u8::MIN,
+        max_digit: u8::MAX,
+    };
+
+    /// Constructs a format, validating that
+    /// `min_digit < terminator < max_digit`.
+    pub fn new(terminator: u8, min_digit: u8, max_digit: u8) -> Result<Self, FormatError> {
+        if !(min_digit < terminator && terminator < max_digit) {
+            return Err(FormatError::TerminatorOutOfRange);
+        }
+        Ok(ByteFormat {
+            terminator,
+            min_digit,
+            max_digit,
+        })
+    }
+
+    pub const fn terminator(&self) -> u8 {
+        self.terminator
+    }
+
+    pub const fn min_digit(&self) -> u8 {
+        self.min_digit
+    }
+
+    pub const fn max_digit(&self) -> u8 {
+        self.max_digit
+    }
+
+    /// Returns the key consisting of only the terminator, which is the
+    /// equivalent of [FractionalIndex::default](crate::FractionalIndex::default).
+    #[cfg(feature = "alloc")]
+    pub fn default_key(&self) -> Vec<u8> {
+        vec![self.terminator]
+    }
+
+    /// Checks that a key is terminated and that every byte is a valid digit.
+    pub fn validate(&self, key: &[u8]) -> Result<(), DecodeError> {
+        if key.last() != Some(&self.terminator) {
+            return Err(DecodeError::MissingTerminator {
+                found: key.last().copied(),
+            });
+        }
+        if key
+            .iter()
+            .any(|byte| *byte < self.min_digit || *byte > self.max_digit)
+        {
+            return Err(DecodeError::DigitOutOfRange);
+        }
+        Ok(())
+    }
+
+    /// Constructs a key that compares before the given one.
+    #[cfg(feature = "alloc")]
+    pub fn new_before(&self, key: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        self.validate(key)?;
+        Ok(self.terminate(self.before(key)))
+    }
+
+    /// Constructs a key that compares after the given one.
+    #[cfg(feature = "alloc")]
+    pub fn new_after(&self, key: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        self.validate(key)?;
+        Ok(self.terminate(self.after(key)))
+    }
+
+    /// Constructs a key that compares between the given two, which are
+    /// assumed to be provided in order and distinct. Returns `Ok(None)` if
+    /// either of these assumptions does not hold.
+    #[cfg(feature = "alloc")]
+    pub fn new_between(&self, left: &[u8], right: &[u8]) -> Result<Option<Vec<u8>>, DecodeError> {
+        self.validate(left)?;
+        self.validate(right)?;
+        Ok(self.between(left, right).map(|bytes| self.terminate(bytes)))
+    }
+
+    #[cfg(feature = "alloc")]
+    fn terminate(&self, mut bytes: Vec<u8>) -> Vec<u8> {
+        bytes.push(self.terminator);
+        bytes
+    }
+
+    /// Returns the unterminated bytes of a key that compares before the
+    /// given (terminated) key.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn before(&self, bytes: &[u8]) -> Vec<u8> {
+        self.derive_before(bytes).to_vec(bytes)
+    }
+
+    /// Returns the unterminated bytes of a key that compares after the
+    /// given (terminated) key.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn after(&self, bytes: &[u8]) -> Vec<u8> {
+        self.derive_after(bytes).to_vec(bytes)
+    }
+
+    /// Returns the unterminated bytes of a key that compares between the
+    /// given (terminated) keys, or None if they are not in order and
+    /// distinct.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn between(&self, left: &[u8], right: &[u8]) -> Option<Vec<u8>> {
+        self.derive_between(left, right)
+            .map(|(side, derived)| match side {
+                Side::Left => derived.to_vec(left),
+                Side::Right => derived.to_vec(right),
+            })
+    }
+
+    /// Describes a key that compares before the given (terminated) key.
+    pub(crate) fn derive_before(&self, bytes: &[u8]) -> Derived {
+        for (i, &byte) in bytes.iter().enumerate() {
+            if byte > self.terminator {
+                // If we encounter a byte greater than the terminator, we can
+                // create a byte string that comes lexicographically before
+                // it (after appending the terminator to both strings) by
+                // truncating the string just before this byte.
+                return Derived::truncate(i);
+            }
+            if byte > self.min_digit {
+                // If we encounter a byte greater than the smallest digit, we
+                // can create a byte string that comes lexicographically
+                // before it by decrementing that byte and truncating the
+                // string there.
+                return Derived::adjust(i + 1, -1);
+            }
+        }
+
+        panic!("We should never reach the end of a properly-terminated fractional index without finding a byte greater than the smallest digit.")
+    }
+
+    /// Describes a key that compares after the given (terminated) key.
+    pub(crate) fn derive_after(&self, bytes: &[u8]) -> Derived {
+        for (i, &byte) in bytes.iter().enumerate() {
+            if byte < self.terminator {
+                // If we encounter a byte less than the terminator, we can
+                // create a byte string that comes lexicographically after
+                // it (after appending the terminator to both strings) by
+                // truncating the string just before this byte.
+                return Derived::truncate(i);
+            }
+            if byte < self.max_digit {
+                // If we encounter a byte less than the largest digit, we can
+                // create a byte string that comes lexicographically after it
+                // by incrementing that byte and truncating the string there.
+                return Derived::adjust(i + 1, 1);
+            }
+        }
+
+        panic!("We should never reach the end of a properly-terminated fractional index without finding a byte less than the largest digit.")
+    }
+
+    /// Describes a key that compares between the given (terminated) keys,
+    /// or returns None if they are not in order and distinct.
+    pub(crate) fn derive_between(&self, left: &[u8], right: &[u8]) -> Option<(Side, Derived)> {
+        let shorter_len = core::cmp::min(left.len(), right.len()) - 1;
+        for i in 0..shorter_len {
+            if left[i] > right[i] {
+                // We return None if right is greater than left.
+                return None;
+            }
+
+            if right[i] - left[i] > 1 {
+                let delta = (right[i] - left[i]) / 2;
+                return Some((Side::Left, Derived::adjust(i + 1, delta as i16)));
+            }
+
+            if right[i] - left[i] == 1 {
+                // Keep left up to and including this byte, and construct a
+                // suffix that comes after the rest of left.
+                let suffix = self.derive_after(&left[i + 1..]);
+                return Some((Side::Left, suffix.offset(i + 1)));
+            }
+        }
+
+        #[allow(clippy::comparison_chain)]
+        if left.len() < right.len() {
+            if right[shorter_len] < self.terminator {
+                // Right side is less than the left side.
+                return None;
+            }
+
+            let suffix = self.derive_before(&right[shorter_len + 1..]);
+            Some((Side::Right, suffix.offset(shorter_len + 1)))
+        } else if left.len() > right.len() {
+            if left[shorter_len] >= self.terminator {
+                // Left side is greater than the right side.
+                return None;
+            }
+
+            let suffix = self.derive_after(&left[shorter_len + 1..]);
+            Some((Side::Left, suffix.offset(shorter_len + 1)))
+        } else {
+            // They are equal.
+            None
+        }
+    }
+}
+
+/// Which of the two keys passed to [ByteFormat::derive_between] a
+/// [Derived] key refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Side {
+    Left,
+    Right,
+}
+
+/// Describes a new (unterminated) key in terms of an existing key: a prefix
+/// of the existing key, with the last byte of the prefix adjusted by
+/// `delta`.
+///
+/// Every key constructed by [ByteFormat] can be described this way, which
+/// allows keys to be generated without allocating.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Derived {
+    len: usize,
+    delta: i16,
+}
+
+impl Derived {
+    fn truncate(len: usize) -> Self {
+        Derived { len, delta: 0 }
+    }
+
+    fn adjust(len: usize, delta: i16) -> Self {
+        Derived { len, delta }
+    }
+
+    /// Shifts a key derived from a suffix so that it is relative to the
+    /// whole key, keeping the first `prefix_len` bytes unchanged.
+    fn offset(self, prefix_len: usize) -> Self {
+        Derived {
+            len: self.len + prefix_len,
+            delta: self.delta,
+        }
+    }
+
+    /// The length of the derived key, not including the terminator.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the unchanged prefix of `source`, followed by the adjusted
+    /// byte (if any).
+    pub(crate) fn parts<'a>(&self, source: &'a [u8]) -> (&'a [u8], Option<u8>) {
+        if self.delta == 0 {
+            (&source[..self.len], None)
+        } else {
+            let last = (source[self.len - 1] as i16 + self.delta) as u8;
+            (&source[..self.len - 1], Some(last))
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    pub(crate) fn to_vec(self, source: &[u8]) -> Vec<u8> {
+        let (prefix, last) = self.parts(source);
+        let mut bytes = Vec::with_capacity(self.len + 1);
+        bytes.extend_from_slice(prefix);
+        bytes.extend(last);
+        bytes
+    }
+}
+
+impl Default for ByteFormat {
+    fn default() -> Self {
+        ByteFormat::DEFAULT
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_formats() {
+        assert_eq!(
+            ByteFormat::new(0, 0, 255),
+            Err(FormatError::TerminatorOutOfRange)
+        );
+        assert_eq!(
+            ByteFormat::new(255, 0, 255),
+            Err(FormatError::TerminatorOutOfRange)
+        );
+        assert_eq!(
+            ByteFormat::new(10, 20, 30),
+            Err(FormatError::TerminatorOutOfRange)
+        );
+        assert!(ByteFormat::new(1, 0, 2).is_ok());
+    }
+
+    #[test]
+    fn validate() {
+        let format = ByteFormat::new(b'5', b'0', b'9').unwrap();
+        assert!(format.validate(b"125").is_ok());
+        assert!(matches!(
+            format.validate(b"12"),
+            Err(DecodeError::MissingTerminator { found: Some(b'2') })
+        ));
+        assert!(matches!(
+            format.validate(b"1a5"),
+            Err(DecodeError::DigitOutOfRange)
+        ));
+        assert!(matches!(
+            format.new_after(b"1a5"),
+            Err(DecodeError::DigitOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn narrow_format() {
+        // A decimal format with '5' as the terminator.
+        let format = ByteFormat::new(b'5', b'0', b'9').unwrap();
+
+        let mut keys = vec![format.default_key()];
+        for _ in 0..20 {
+            let before = format.new_before(&keys[0]).unwrap();
+            keys.insert(0, before);
+            let after = format.new_after(keys.last().unwrap()).unwrap();
+            keys.push(after);
+        }
+
+        for _ in 0..5 {
+            let mut new_keys = Vec::new();
+            for pair in keys.windows(2) {
+                let mid = format.new_between(&pair[0], &pair[1]).unwrap().unwrap();
+                assert!(pair[0] < mid && mid < pair[1]);
+                format.validate(&mid).unwrap();
+                new_keys.push(pair[0].clone());
+                new_keys.push(mid);
+            }
+            new_keys.push(keys.last().unwrap().clone());
+            keys = new_keys;
+        }
+
+        for pair in keys.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+    }
+
+    #[test]
+    fn between_out_of_order() {
+        let format = ByteFormat::new(b'5', b'0', b'9').unwrap();
+        assert_eq!(format.new_between(b"85", b"05").unwrap(), None);
+        assert_eq!(format.new_between(b"85", b"85").unwrap(), None);
+    }
+}
@@ -1,15 +1,29 @@
-use crate::hex::{bytes_to_hex, hex_to_bytes};
-use std::{
-    convert::TryFrom,
-    error::Error,
-    fmt::{self, Display},
-    ops::Deref,
+use crate::{
+    assertions::check_index,
+    format::{ByteFormat, Derived, Side},
+    hex::{bytes_to_hex, check_hex_digits, eq_hex, hex_to_bytes},
+    instrument, spacing, DecodeError,
 };
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{cmp::Ordering, convert::TryFrom, fmt, ops::Deref, str::FromStr};
+
+#[cfg(feature = "std")]
+use crate::limits::check_len;
 
 #[cfg(feature = "serde")]
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 
-pub(crate) const TERMINATOR: u8 = 0b1000_0000; // =128
+/// Without `std` there is no per-thread limit, so every length is accepted.
+#[cfg(not(feature = "std"))]
+fn check_len(_len: usize) -> Result<(), DecodeError> {
+    Ok(())
+}
+
+pub(crate) const TERMINATOR: u8 = ByteFormat::DEFAULT.terminator(); // =128
 
 /// A [FractionalIndex] is an opaque data type that is only useful for
 /// comparing to another [FractionalIndex].
@@ -21,102 +35,116 @@ pub(crate) const TERMINATOR: u8 = 0b1000_0000; // =128
 /// Because of this, it is useful as an index in a sorted data structure
 /// (like a [BTreeMap](std::collections::BTreeMap)) or for merging concurrent
 /// modifications to a shared list data structure.
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct FractionalIndex(Vec<u8>);
-
-impl Default for FractionalIndex {
-    fn default() -> Self {
-        FractionalIndex(vec![TERMINATOR])
-    }
-}
-
-fn new_before(bytes: &[u8]) -> Vec<u8> {
-    for i in 0..bytes.len() {
-        if bytes[i] > TERMINATOR {
-            // If we encounter a byte greater than TERMINATOR, we can
-            // create a byte string that comes lexicographically before
-            // it (after appending the terminator to both strings) by
-            // truncating the string just before this byte.
-            return bytes[0..i].into();
-        }
-        if bytes[i] > u8::MIN {
-            // If we encounter a byte greater than 0, we can create a
-            // byte string that comes lexicographically before it by
-            // decrementing that byte and truncating the string there.
-            let mut bytes: Vec<u8> = bytes[0..=i].into();
-            bytes[i] -= 1;
-            return bytes;
-        }
-    }
-
-    panic!("We should never reach the end of a properly-terminated fractional index without finding a byte greater than 0.")
-}
-
-fn new_after(bytes: &[u8]) -> Vec<u8> {
-    for i in 0..bytes.len() {
-        if bytes[i] < TERMINATOR {
-            // If we encounter a byte less than TERMINATOR, we can
-            // create a byte string that comes lexicographically after
-            // it (after appending the terminator to both strings) by
-            // truncating the string just before this byte.
-            return bytes[0..i].into();
-        }
-        if bytes[i] < u8::MAX {
-            // If we encounter a byte less than 255, we can create a
-            // byte string that comes lexicographically after it by
-            // incrementing that byte and truncating the string there.
-            let mut bytes: Vec<u8> = bytes[0..=i].into();
-            bytes[i] += 1;
-            return bytes;
+///
+/// The bytes are stored in a boxed slice, so a [FractionalIndex] is two
+/// words wide and holds no spare capacity.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FractionalIndex(Box<[u8]>);
+
+impl fmt::Debug for FractionalIndex {
+    /// The alternate form (`{:#?}`) also shows the approximate position of
+    /// the key between the smallest and largest possible keys, and its
+    /// depth, i.e. its number of bytes before the terminator.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return f.debug_tuple("FractionalIndex").field(&self.0).finish();
         }
+        // The bytes as a base-256 fraction; later bytes don't change the
+        // rounded percentage.
+        let position: f64 = self
+            .0
+            .iter()
+            .take(8)
+            .rev()
+            .fold(0.0, |fraction, byte| (fraction + *byte as f64) / 256.0);
+        f.debug_struct("FractionalIndex")
+            .field("hex", &self.to_string())
+            .field("position", &format_args!("{:.1}%", position * 100.0))
+            .field("depth", &(self.0.len() - 1))
+            .finish()
     }
-
-    panic!("We should never reach the end of a properly-terminated fractional index without finding a byte less than 255.")
-}
-
-#[derive(Debug)]
-pub enum DecodeError {
-    EmptyString,
-    MissingTerminator,
-    InvalidChars,
 }
 
-impl Display for DecodeError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            DecodeError::EmptyString => write!(
-                f,
-                "Attempted to decode an empty string as a fractional index."
-            ),
-            DecodeError::MissingTerminator => write!(
-                f,
-                "Attempted to decode a corrupt fractional index (missing terminator)."
-            ),
-            DecodeError::InvalidChars => write!(
-                f,
-                "Attempted to decode a corrupt fractional index (invalid characters)."
-            ),
-        }
+impl Default for FractionalIndex {
+    fn default() -> Self {
+        FractionalIndex(Box::new([TERMINATOR]))
     }
 }
 
-impl Error for DecodeError {}
-
 impl FractionalIndex {
     /// Constructs a FractionalIndex from a byte vec, which DOES NOT include
     /// the terminating byte.
     fn from_vec_unterminated(mut bytes: Vec<u8>) -> Self {
         bytes.push(TERMINATOR);
-        FractionalIndex(bytes)
+        FractionalIndex(bytes.into_boxed_slice())
     }
 
     /// Constructs a FractionalIndex from a byte vec.
+    ///
+    /// Returns an error if the bytes are longer than the limit set with
+    /// [limits::with_max_len](crate::limits::with_max_len).
     pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, DecodeError> {
+        FractionalIndex::validate(&bytes)?;
+        Ok(FractionalIndex(bytes.into_boxed_slice()))
+    }
+
+    /// Constructs a FractionalIndex from a nullable byte vec, treating None
+    /// as [FractionalIndex::default].
+    #[deprecated(
+        since = "2.1.0",
+        note = "Use OrDefault, or decode nullable values as Option<FractionalIndex>"
+    )]
+    pub fn from_optional_bytes(bytes: Option<Vec<u8>>) -> Result<Self, DecodeError> {
+        match bytes {
+            Some(bytes) => FractionalIndex::from_bytes(bytes),
+            None => Ok(FractionalIndex::default()),
+        }
+    }
+
+    /// Checks that `bytes` is within the length limit and ends with the
+    /// terminator.
+    pub(crate) fn validate(bytes: &[u8]) -> Result<(), DecodeError> {
+        check_len(bytes.len())?;
+        FractionalIndex::check_terminator(bytes)
+    }
+
+    /// Checks that `bytes` ends with the terminator.
+    pub(crate) fn check_terminator(bytes: &[u8]) -> Result<(), DecodeError> {
         if bytes.last() != Some(&TERMINATOR) {
-            return Err(DecodeError::MissingTerminator);
+            return Err(DecodeError::MissingTerminator {
+                found: bytes.last().copied(),
+            });
         }
-        Ok(FractionalIndex(bytes))
+        Ok(())
+    }
+
+    /// Constructs a FractionalIndex from a byte vec without checking that
+    /// it is terminated, for hot paths that read back bytes previously
+    /// returned by [FractionalIndex::as_bytes].
+    ///
+    /// This is not `unsafe`, but passing bytes that did not come from a
+    /// valid [FractionalIndex] may cause later operations on the result to
+    /// panic or produce indexes that compare incorrectly. The check is
+    /// still performed in debug builds.
+    pub fn from_bytes_unchecked(bytes: Vec<u8>) -> Self {
+        debug_assert_eq!(
+            bytes.last(),
+            Some(&TERMINATOR),
+            "Attempted to construct a fractional index without a terminator."
+        );
+        FractionalIndex(bytes.into_boxed_slice())
+    }
+
+    /// Returns true if the bytes of this FractionalIndex are the canonical
+    /// encoding of its position, without changing them.
+    ///
+    /// Every byte string that ends with the terminator encodes a distinct
+    /// position, so this is only false for an index that does not end with
+    /// the terminator, which [FractionalIndex::from_bytes_unchecked] does
+    /// not reject in release builds.
+    pub fn is_canonical(&self) -> bool {
+        FractionalIndex::check_terminator(&self.0).is_ok()
     }
 
     /// Returns the byte representation of this FractionalIndex.
@@ -124,6 +152,36 @@ impl FractionalIndex {
         &self.0
     }
 
+    /// Consumes this FractionalIndex, returning its byte representation.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0.into_vec()
+    }
+
+    /// Returns the digits of this FractionalIndex, i.e. its bytes without
+    /// the terminator, for algorithms that work on keys as numbers in base
+    /// 256. The default key has no digits.
+    ///
+    /// Keys compare like their digits, except that a key compares before
+    /// the keys whose digits extend its own with a digit below the
+    /// terminator (128), and after those that extend them with one above.
+    pub fn to_digits(&self) -> Vec<u8> {
+        let (_, digits) = self.0.split_last().expect("Keys are never empty.");
+        digits.to_vec()
+    }
+
+    /// Constructs a FractionalIndex from the digits returned by
+    /// [FractionalIndex::to_digits]. Every sequence of digits is a valid
+    /// key.
+    ///
+    /// Returns an error if the key would be longer than the limit set with
+    /// [limits::with_max_len](crate::limits::with_max_len).
+    pub fn from_digits(digits: &[u8]) -> Result<Self, DecodeError> {
+        let mut bytes = Vec::with_capacity(digits.len() + 1);
+        bytes.extend_from_slice(digits);
+        bytes.push(TERMINATOR);
+        FractionalIndex::from_bytes(bytes)
+    }
+
     /// Returns a string representation of this FractionalIndex.
     /// The string representation maintains the lexicographic ordering
     /// of the [FractionalIndex].
@@ -132,18 +190,75 @@ impl FractionalIndex {
         bytes_to_hex(&self.0)
     }
 
+    /// Returns an object that displays this FractionalIndex as its bytes in
+    /// decimal, separated by dots and without the terminator (e.g. `129.127`
+    /// for `817f80`), for logs and support tickets. The default key, which
+    /// has only the terminator, is displayed as `root`.
+    ///
+    /// Unlike [FractionalIndex::to_string], this form does not sort in the
+    /// same order as the keys.
+    pub fn display_path(&self) -> DisplayPath<'_> {
+        DisplayPath(&self.0)
+    }
+
+    /// Returns this FractionalIndex as its bytes in decimal, separated by
+    /// dots and without the terminator (e.g. `129.127` for `817f80`), for
+    /// config files and tools where keys are edited by hand. The default
+    /// key is the empty string.
+    ///
+    /// Like [FractionalIndex::display_path], this form does not sort in the
+    /// same order as the keys.
+    pub fn to_path_string(&self) -> String {
+        let (_, digits) = self.0.split_last().expect("Keys are never empty.");
+        let labels: Vec<String> = digits.iter().map(u8::to_string).collect();
+        labels.join(".")
+    }
+
+    /// Parses a path returned by [FractionalIndex::to_path_string]. Every
+    /// path of numbers from 0 to 255 is a valid key.
+    pub fn from_path_string(s: &str) -> Result<Self, DecodeError> {
+        let mut bytes = Vec::with_capacity(s.len() / 2 + 1);
+        if !s.is_empty() {
+            let mut position = 0;
+            for label in s.split('.') {
+                if let Some((offset, character)) = label
+                    .char_indices()
+                    .find(|(_, character)| !character.is_ascii_digit())
+                {
+                    return Err(DecodeError::InvalidChars {
+                        character,
+                        position: position + offset,
+                    });
+                }
+                if label.is_empty() {
+                    return Err(DecodeError::InvalidLength);
+                }
+                let byte = label
+                    .bytes()
+                    .try_fold(0u8, |value, digit| {
+                        value.checked_mul(10)?.checked_add(digit - b'0')
+                    })
+                    .ok_or(DecodeError::DigitOutOfRange)?;
+                bytes.push(byte);
+                position += label.len() + 1;
+            }
+        }
+        bytes.push(TERMINATOR);
+        FractionalIndex::from_bytes(bytes)
+    }
+
     /// Constructs a [FractionalIndex] from a string previously returned
     /// by [FractionalIndex::to_string].
     pub fn from_string(s: &str) -> Result<Self, DecodeError> {
         if s.is_empty() {
             return Err(DecodeError::EmptyString);
         }
+        check_len(s.len() / 2)?;
 
-        let bytes = hex_to_bytes(s).map_err(|_| DecodeError::InvalidChars)?;
-
-        if bytes.last() != Some(&TERMINATOR) {
-            return Err(DecodeError::MissingTerminator);
-        }
+        let bytes = hex_to_bytes(s).map_err(|err| DecodeError::InvalidChars {
+            character: err.character,
+            position: err.position,
+        })?;
 
         FractionalIndex::from_bytes(bytes)
     }
@@ -151,13 +266,19 @@ impl FractionalIndex {
     /// Construct a new [FractionalIndex] that compares as before
     /// the given one.
     pub fn new_before(FractionalIndex(bytes): &FractionalIndex) -> FractionalIndex {
-        FractionalIndex::from_vec_unterminated(new_before(bytes))
+        let index = FractionalIndex::from_vec_unterminated(ByteFormat::DEFAULT.before(bytes));
+        check_index(&index.0, None, Some(bytes));
+        instrument::generated("new_before", &index.0, None, Some(bytes));
+        index
     }
 
     /// Construct a new [FractionalIndex] that compares as after
     /// the given one.
     pub fn new_after(FractionalIndex(bytes): &FractionalIndex) -> FractionalIndex {
-        FractionalIndex::from_vec_unterminated(new_after(bytes))
+        let index = FractionalIndex::from_vec_unterminated(ByteFormat::DEFAULT.after(bytes));
+        check_index(&index.0, Some(bytes), None);
+        instrument::generated("new_after", &index.0, Some(bytes), None);
+        index
     }
 
     /// Construct a new [FractionalIndex] based on a given optional lower
@@ -196,58 +317,145 @@ impl FractionalIndex {
         FractionalIndex(left): &FractionalIndex,
         FractionalIndex(right): &FractionalIndex,
     ) -> Option<FractionalIndex> {
-        let shorter_len = std::cmp::min(left.len(), right.len()) - 1;
-        for i in 0..shorter_len {
-            if left[i] < right[i] - 1 {
-                let mut bytes: Vec<u8> = left[0..=i].into();
-                bytes[i] += (right[i] - left[i]) / 2;
-                return Some(FractionalIndex::from_vec_unterminated(bytes));
-            }
+        let index = ByteFormat::DEFAULT
+            .between(left, right)
+            .map(FractionalIndex::from_vec_unterminated)?;
+        check_index(&index.0, Some(left), Some(right));
+        instrument::generated("new_between", &index.0, Some(left), Some(right));
+        Some(index)
+    }
 
-            if left[i] == right[i] - 1 {
-                let (prefix, suffix) = left.split_at(i + 1);
-                let mut bytes = Vec::with_capacity(suffix.len() + prefix.len() + 1);
-                bytes.extend_from_slice(prefix);
-                bytes.extend_from_slice(&new_after(suffix));
-                return Some(FractionalIndex::from_vec_unterminated(bytes));
-            }
+    /// Appends the bytes of a key that compares as before the given one to
+    /// `out`, like [FractionalIndex::new_before] but without allocating
+    /// when `out` has spare capacity.
+    ///
+    /// The appended bytes are valid input to
+    /// [FractionalIndex::from_bytes], so `out` can be cleared and reused
+    /// for each key, or hold many keys back to back (e.g. in an arena),
+    /// each spanning from the previous length of `out` to its new length.
+    pub fn new_before_into(FractionalIndex(bytes): &FractionalIndex, out: &mut Vec<u8>) {
+        let start = out.len();
+        write_derived(ByteFormat::DEFAULT.derive_before(bytes), bytes, out);
+        check_index(&out[start..], None, Some(bytes));
+        instrument::generated("new_before_into", &out[start..], None, Some(bytes));
+    }
 
-            if left[i] > right[i] {
-                // We return None if right is greater than left.
-                return None;
-            }
-        }
+    /// Appends the bytes of a key that compares as after the given one to
+    /// `out`, like [FractionalIndex::new_after]. See
+    /// [FractionalIndex::new_before_into].
+    pub fn new_after_into(FractionalIndex(bytes): &FractionalIndex, out: &mut Vec<u8>) {
+        let start = out.len();
+        write_derived(ByteFormat::DEFAULT.derive_after(bytes), bytes, out);
+        check_index(&out[start..], Some(bytes), None);
+        instrument::generated("new_after_into", &out[start..], Some(bytes), None);
+    }
 
-        #[allow(clippy::comparison_chain)]
-        if left.len() < right.len() {
-            let (prefix, suffix) = right.split_at(shorter_len + 1);
-            if prefix.last().unwrap() < &TERMINATOR {
-                // Right side is less than the left side.
-                return None;
-            }
+    /// Appends the bytes of a key that compares as between the given two to
+    /// `out`, like [FractionalIndex::new_between]. See
+    /// [FractionalIndex::new_before_into].
+    ///
+    /// Returns false, leaving `out` unchanged, if the keys are not in order
+    /// and distinct.
+    pub fn new_between_into(
+        FractionalIndex(left): &FractionalIndex,
+        FractionalIndex(right): &FractionalIndex,
+        out: &mut Vec<u8>,
+    ) -> bool {
+        let Some((side, derived)) = ByteFormat::DEFAULT.derive_between(left, right) else {
+            return false;
+        };
+        let source = match side {
+            Side::Left => left,
+            Side::Right => right,
+        };
+        let start = out.len();
+        write_derived(derived, source, out);
+        check_index(&out[start..], Some(left), Some(right));
+        instrument::generated("new_between_into", &out[start..], Some(left), Some(right));
+        true
+    }
 
-            let new_suffix = new_before(suffix);
-            let mut bytes = Vec::with_capacity(new_suffix.len() + prefix.len() + 1);
-            bytes.extend_from_slice(prefix);
-            bytes.extend_from_slice(&new_suffix);
-            Some(FractionalIndex::from_vec_unterminated(bytes))
-        } else if left.len() > right.len() {
-            let (prefix, suffix) = left.split_at(shorter_len + 1);
+    /// Constructs `n` keys in ascending order that all compare as before
+    /// the given one.
+    ///
+    /// Unlike a chain of [FractionalIndex::new_before] calls, whose keys
+    /// grow by a byte every few calls, the keys are evenly spaced and their
+    /// length grows logarithmically with `n`.
+    pub fn new_before_many(upper: &FractionalIndex, n: usize) -> Vec<FractionalIndex> {
+        FractionalIndex::spaced(None, Some(upper), n).expect("A single bound is always in order.")
+    }
 
-            if prefix.last().unwrap() >= &TERMINATOR {
-                // Left side is greater than the right side.
+    /// Constructs `n` keys in ascending order that all compare as after
+    /// the given one.
+    ///
+    /// Unlike a chain of [FractionalIndex::new_after] calls, whose keys
+    /// grow by a byte every few calls, the keys are evenly spaced and their
+    /// length grows logarithmically with `n`.
+    pub fn new_after_many(lower: &FractionalIndex, n: usize) -> Vec<FractionalIndex> {
+        FractionalIndex::spaced(Some(lower), None, n).expect("A single bound is always in order.")
+    }
+
+    /// Returns the key for slot `i` of `n` evenly spaced slots, without
+    /// computing the keys of the other slots.
+    ///
+    /// The keys of slots `0..n` are in ascending order, so a long list can
+    /// be keyed lazily as its items are materialized. Panics if `i >= n`.
+    pub fn at_fraction(i: usize, n: usize) -> FractionalIndex {
+        FractionalIndex::at_fraction_between(None, None, i, n)
+            .expect("Missing bounds are always in order.")
+    }
+
+    /// Returns the key for slot `i` of `n` evenly spaced slots between the
+    /// given optional bounds, like [FractionalIndex::at_fraction].
+    ///
+    /// Returns None if the bounds are not in order or are equal. Panics if
+    /// `i >= n`.
+    pub fn at_fraction_between(
+        lower_bound: Option<&FractionalIndex>,
+        upper_bound: Option<&FractionalIndex>,
+        i: usize,
+        n: usize,
+    ) -> Option<FractionalIndex> {
+        assert!(i < n, "Slot (is {}) should be < n (is {}).", i, n);
+        if let (Some(lower), Some(upper)) = (lower_bound, upper_bound) {
+            if lower >= upper {
                 return None;
             }
+        }
+        let key = spacing::nth(
+            lower_bound.map(FractionalIndex::as_bytes),
+            upper_bound.map(FractionalIndex::as_bytes),
+            i,
+            n,
+        );
+        Some(FractionalIndex(key.into_boxed_slice()))
+    }
 
-            let new_suffix = new_after(suffix);
-            let mut bytes = Vec::with_capacity(new_suffix.len() + prefix.len() + 1);
-            bytes.extend_from_slice(prefix);
-            bytes.extend_from_slice(&new_suffix);
-            Some(FractionalIndex::from_vec_unterminated(bytes))
-        } else {
-            // They are equal.
-            None
+    /// Constructs `n` keys in ascending order between the given optional
+    /// bounds, like [FractionalIndex::new]. The keys are evenly spaced, and
+    /// their length grows logarithmically with `n`.
+    ///
+    /// Returns None if the bounds are not in order or are equal.
+    pub(crate) fn spaced(
+        lower_bound: Option<&FractionalIndex>,
+        upper_bound: Option<&FractionalIndex>,
+        n: usize,
+    ) -> Option<Vec<FractionalIndex>> {
+        if let (Some(lower), Some(upper)) = (lower_bound, upper_bound) {
+            if lower >= upper {
+                return None;
+            }
         }
+        let keys = spacing::spaced(
+            lower_bound.map(FractionalIndex::as_bytes),
+            upper_bound.map(FractionalIndex::as_bytes),
+            n,
+        );
+        Some(
+            keys.into_iter()
+                .map(|key| FractionalIndex(key.into_boxed_slice()))
+                .collect(),
+        )
     }
 }
 
@@ -259,17 +467,83 @@ impl TryFrom<Vec<u8>> for FractionalIndex {
     }
 }
 
+/// Parses the hex representation returned by [FractionalIndex::to_string].
+impl FromStr for FractionalIndex {
+    type Err = DecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FractionalIndex::from_string(s)
+    }
+}
+
+/// Decodes a nullable column, treating NULL as [FractionalIndex::default].
+///
+/// Prefer [OrDefault], or decode nullable columns as
+/// `Option<FractionalIndex>` (with the `sqlx` feature), which make this
+/// behavior explicit. Trait impls cannot carry `#[deprecated]`, so this
+/// impl delegates to the deprecated [FractionalIndex::from_optional_bytes]
+/// and will be removed with it in the next major version.
 impl TryFrom<Option<Vec<u8>>> for FractionalIndex {
     type Error = DecodeError;
 
+    fn try_from(bytes: Option<Vec<u8>>) -> Result<Self, Self::Error> {
+        #[allow(deprecated)]
+        FractionalIndex::from_optional_bytes(bytes)
+    }
+}
+
+/// A [FractionalIndex] decoded from a nullable value, where a missing value
+/// is replaced by [FractionalIndex::default].
+///
+/// This is an explicit opt-in for columns where NULL is meant to stand for
+/// the default index, e.g. with `#[sqlx(try_from = "Option<Vec<u8>>")]`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OrDefault(pub FractionalIndex);
+
+impl TryFrom<Option<Vec<u8>>> for OrDefault {
+    type Error = DecodeError;
+
     fn try_from(bytes: Option<Vec<u8>>) -> Result<Self, Self::Error> {
         match bytes {
-            Some(bytes) => FractionalIndex::from_bytes(bytes),
-            None => Ok(FractionalIndex::default()),
+            Some(bytes) => FractionalIndex::from_bytes(bytes).map(OrDefault),
+            None => Ok(OrDefault::default()),
         }
     }
 }
 
+impl From<OrDefault> for FractionalIndex {
+    fn from(OrDefault(index): OrDefault) -> Self {
+        index
+    }
+}
+
+impl Deref for OrDefault {
+    type Target = FractionalIndex;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Displays a [FractionalIndex] as a path of decimal bytes, returned by
+/// [FractionalIndex::display_path].
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayPath<'a>(&'a [u8]);
+
+impl fmt::Display for DisplayPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (_, digits) = self.0.split_last().expect("Keys are never empty.");
+        let Some((first, rest)) = digits.split_first() else {
+            return f.write_str("root");
+        };
+        write!(f, "{}", first)?;
+        for byte in rest {
+            write!(f, ".{}", byte)?;
+        }
+        Ok(())
+    }
+}
+
 impl Deref for FractionalIndex {
     type Target = [u8];
 
@@ -278,10 +552,303 @@ impl Deref for FractionalIndex {
     }
 }
 
+/// Compares the bytes of the index, as returned by
+/// [FractionalIndex::as_bytes], with a raw stored representation.
+impl PartialEq<[u8]> for FractionalIndex {
+    fn eq(&self, other: &[u8]) -> bool {
+        *self.0 == *other
+    }
+}
+
+impl PartialEq<&[u8]> for FractionalIndex {
+    fn eq(&self, other: &&[u8]) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialOrd<[u8]> for FractionalIndex {
+    fn partial_cmp(&self, other: &[u8]) -> Option<Ordering> {
+        Some((*self.0).cmp(other))
+    }
+}
+
+impl PartialOrd<&[u8]> for FractionalIndex {
+    fn partial_cmp(&self, other: &&[u8]) -> Option<Ordering> {
+        Some((*self.0).cmp(*other))
+    }
+}
+
+/// Compares the index with a hex string, as returned by
+/// [FractionalIndex::to_string], without decoding it. Strings that are not
+/// in that form (e.g. uppercase hex) are never equal to an index.
+impl PartialEq<str> for FractionalIndex {
+    fn eq(&self, other: &str) -> bool {
+        eq_hex(&self.0, other)
+    }
+}
+
+impl PartialEq<&str> for FractionalIndex {
+    fn eq(&self, other: &&str) -> bool {
+        eq_hex(&self.0, other)
+    }
+}
+
+/// Deserializes the representation of the derived [Serialize] impl,
+/// validating the key and enforcing the length limit.
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for FractionalIndex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IndexVisitor;
+
+        impl<'de> de::Visitor<'de> for IndexVisitor {
+            type Value = FractionalIndex;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("the bytes of a fractional index")
+            }
+
+            fn visit_newtype_struct<D: Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<Self::Value, D::Error> {
+                deserializer.deserialize_seq(IndexVisitor)
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                self.visit_byte_buf(crate::limits::collect_seq(seq)?)
+            }
+
+            fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+                self.visit_byte_buf(bytes.to_vec())
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+                FractionalIndex::from_bytes(bytes).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct("FractionalIndex", IndexVisitor)
+    }
+}
+
+/// Appends the key described by `derived`, followed by the terminator, to
+/// `out`.
+fn write_derived(derived: Derived, source: &[u8], out: &mut Vec<u8>) {
+    let (prefix, last) = derived.parts(source);
+    out.reserve(derived.len() + 1);
+    out.extend_from_slice(prefix);
+    out.extend(last);
+    out.push(TERMINATOR);
+}
+
+/// Compares two keys encoded by [FractionalIndex::to_string], without
+/// decoding them, e.g. to route requests by key. Returns the error that
+/// [FractionalIndex::from_string] returns if either is not a valid key.
+pub fn cmp_hex(a: &str, b: &str) -> Result<Ordering, DecodeError> {
+    // Each byte is two lowercase digits, so the strings are in the same
+    // order as the bytes.
+    Ok(check_hex(a)?.cmp(check_hex(b)?))
+}
+
+/// Checks `s` as [FractionalIndex::from_string] does, and returns the
+/// digits that encode the bytes of the key.
+fn check_hex(s: &str) -> Result<&str, DecodeError> {
+    if s.is_empty() {
+        return Err(DecodeError::EmptyString);
+    }
+    check_len(s.len() / 2)?;
+    let digits = check_hex_digits(s).map_err(|err| DecodeError::InvalidChars {
+        character: err.character,
+        position: err.position,
+    })?;
+    let last =
+        hex_to_bytes(&digits[digits.len().saturating_sub(2)..]).expect("The digits were checked.");
+    FractionalIndex::check_terminator(&last)?;
+    Ok(digits)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn canonical() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_between(&a, &b).unwrap();
+        assert!(a.is_canonical() && b.is_canonical() && c.is_canonical());
+        assert!(FractionalIndex::from_bytes(vec![128, 128])
+            .unwrap()
+            .is_canonical());
+        // Bypasses the check of `from_bytes_unchecked` in debug builds.
+        assert!(!FractionalIndex(Box::new([129])).is_canonical());
+        assert!(!FractionalIndex(Box::new([])).is_canonical());
+    }
+
+    #[test]
+    fn write_into() {
+        let mut keys = vec![FractionalIndex::default()];
+        let mut out = Vec::new();
+        for i in 0..30 {
+            let key = &keys[keys.len() / 2];
+            let expected = match i % 3 {
+                0 => {
+                    FractionalIndex::new_before_into(key, &mut out);
+                    FractionalIndex::new_before(key)
+                }
+                1 => {
+                    FractionalIndex::new_after_into(key, &mut out);
+                    FractionalIndex::new_after(key)
+                }
+                _ => {
+                    let lower = FractionalIndex::new_before(key);
+                    assert!(FractionalIndex::new_between_into(&lower, key, &mut out));
+                    FractionalIndex::new_between(&lower, key).unwrap()
+                }
+            };
+            assert_eq!(out, expected.as_bytes());
+            out.clear();
+            keys.push(expected);
+            keys.sort();
+        }
+
+        // Keys are appended after the existing contents of the buffer.
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let mut arena = a.as_bytes().to_vec();
+        assert!(FractionalIndex::new_between_into(&a, &b, &mut arena));
+        assert_eq!(&arena[..1], a.as_bytes());
+        let c = FractionalIndex::from_bytes(arena[1..].to_vec()).unwrap();
+        assert!(a < c && c < b);
+        assert!(!FractionalIndex::new_between_into(&b, &a, &mut arena));
+        assert_eq!(arena.len(), 1 + c.len());
+    }
+
+    #[test]
+    fn debug() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_between(&a, &b).unwrap();
+        assert_eq!(format!("{:?}", c), "FractionalIndex([129, 127, 128])");
+        assert_eq!(
+            format!("{:#?}", a),
+            "FractionalIndex {\n    hex: \"80\",\n    position: 50.0%,\n    depth: 0,\n}"
+        );
+        assert_eq!(
+            format!("{:#?}", c),
+            "FractionalIndex {\n    hex: \"817f80\",\n    position: 50.6%,\n    depth: 2,\n}"
+        );
+        let low = (0..20).fold(a, |key, _| FractionalIndex::new_before(&key));
+        assert!(format!("{:#?}", low).contains("position: 42.4%"));
+    }
+
+    #[test]
+    fn display_path() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_between(&a, &b).unwrap();
+        assert_eq!(a.display_path().to_string(), "root");
+        assert_eq!(b.display_path().to_string(), "129");
+        assert_eq!(c.display_path().to_string(), "129.127");
+        assert_eq!(format!("key {}", c.display_path()), "key 129.127");
+    }
+
+    #[test]
+    fn digits() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_between(&a, &b).unwrap();
+        assert_eq!(a.to_digits(), Vec::<u8>::new());
+        assert_eq!(c.to_digits(), vec![129, 127]);
+        for key in FractionalIndex::spaced(None, None, 300).unwrap() {
+            assert_eq!(FractionalIndex::from_digits(&key.to_digits()), Ok(key));
+        }
+        assert_eq!(FractionalIndex::from_digits(&[]), Ok(a));
+        assert_eq!(
+            FractionalIndex::from_digits(&[0, 255]).unwrap().as_bytes(),
+            &[0, 255, 128]
+        );
+    }
+
+    #[test]
+    fn path_string() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_between(&a, &b).unwrap();
+        assert_eq!(a.to_path_string(), "");
+        assert_eq!(c.to_path_string(), "129.127");
+        for key in FractionalIndex::spaced(None, None, 300).unwrap() {
+            assert_eq!(
+                FractionalIndex::from_path_string(&key.to_path_string()),
+                Ok(key)
+            );
+        }
+        assert_eq!(
+            FractionalIndex::from_path_string("129.127.3")
+                .unwrap()
+                .as_bytes(),
+            &[129, 127, 3, 128]
+        );
+        assert_eq!(
+            FractionalIndex::from_path_string("007"),
+            Ok(FractionalIndex::from_bytes(vec![7, 128]).unwrap())
+        );
+
+        assert_eq!(
+            FractionalIndex::from_path_string("129.x"),
+            Err(DecodeError::InvalidChars {
+                character: 'x',
+                position: 4
+            })
+        );
+        assert_eq!(
+            FractionalIndex::from_path_string("129..3"),
+            Err(DecodeError::InvalidLength)
+        );
+        assert_eq!(
+            FractionalIndex::from_path_string("129."),
+            Err(DecodeError::InvalidLength)
+        );
+        assert_eq!(
+            FractionalIndex::from_path_string("256"),
+            Err(DecodeError::DigitOutOfRange)
+        );
+    }
+
+    #[test]
+    fn compare_hex() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_between(&a, &b).unwrap();
+        let d = FractionalIndex::new_before(&a);
+        let keys = [a, b, c, d];
+        for x in &keys {
+            for y in &keys {
+                assert_eq!(cmp_hex(&x.to_string(), &y.to_string()), Ok(x.cmp(y)));
+            }
+        }
+
+        // Invalid keys fail as they do in `from_string`.
+        let valid = keys[0].to_string();
+        for invalid in ["", "8", "81", "7f", "zz80", "80\u{e9}", "80G0"] {
+            let error = FractionalIndex::from_string(invalid).unwrap_err();
+            assert_eq!(cmp_hex(invalid, &valid), Err(error.clone()));
+            assert_eq!(cmp_hex(&valid, invalid), Err(error));
+        }
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(
+            std::mem::size_of::<FractionalIndex>(),
+            2 * std::mem::size_of::<usize>()
+        );
+        assert_eq!(
+            std::mem::size_of::<Option<FractionalIndex>>(),
+            2 * std::mem::size_of::<usize>()
+        );
+    }
+
     #[test]
     fn new_before_simple() {
         let mut i = FractionalIndex::default();
@@ -438,6 +1005,11 @@ mod tests {
 
         assert_eq!(FractionalIndex::new_between(&a, &a), None);
         assert_eq!(FractionalIndex::new_between(&b, &a), None);
+
+        // The first differing byte of the right side is zero.
+        let left = FractionalIndex::from_vec_unterminated(vec![5]);
+        let right = FractionalIndex::from_vec_unterminated(vec![0]);
+        assert_eq!(FractionalIndex::new_between(&left, &right), None);
     }
 
     #[test]
@@ -482,6 +1054,174 @@ mod tests {
     }
 
     #[test]
+    fn spaced() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        for (lower, upper) in [
+            (None, None),
+            (Some(&a), None),
+            (None, Some(&a)),
+            (Some(&a), Some(&b)),
+        ] {
+            for n in [0, 1, 2, 3, 100, 1000] {
+                let keys = FractionalIndex::spaced(lower, upper, n).unwrap();
+                assert_eq!(keys.len(), n);
+                for pair in keys.windows(2) {
+                    assert!(pair[0] < pair[1]);
+                }
+                if let (Some(lower), Some(first)) = (lower, keys.first()) {
+                    assert!(lower < first);
+                }
+                if let (Some(upper), Some(last)) = (upper, keys.last()) {
+                    assert!(last < upper);
+                }
+                assert!(keys.iter().all(|key| key.len() <= 4));
+            }
+        }
+        assert!(FractionalIndex::spaced(Some(&b), Some(&a), 3).is_none());
+    }
+
+    #[test]
+    fn new_many() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        for n in [0, 1, 2, 255, 256, 10_000] {
+            let before = FractionalIndex::new_before_many(&a, n);
+            let after = FractionalIndex::new_after_many(&b, n);
+            assert_eq!(before.len(), n);
+            assert_eq!(after.len(), n);
+            let keys: Vec<&FractionalIndex> = before
+                .iter()
+                .chain([&a, &b].iter().copied())
+                .chain(after.iter())
+                .collect();
+            for pair in keys.windows(2) {
+                assert!(pair[0] < pair[1]);
+            }
+            assert!(keys.iter().all(|key| key.len() <= 4));
+        }
+
+        // There is room to insert around a block.
+        let block = FractionalIndex::new_after_many(&a, 3);
+        assert!(FractionalIndex::new_between(&a, &block[0]).is_some());
+        assert!(FractionalIndex::new_after(&block[2]) > block[2]);
+    }
+
+    #[test]
+    fn at_fraction() {
+        let keys = FractionalIndex::spaced(None, None, 1000).unwrap();
+        assert_eq!(FractionalIndex::at_fraction(0, 1000), keys[0]);
+        assert_eq!(FractionalIndex::at_fraction(617, 1000), keys[617]);
+        assert_eq!(
+            FractionalIndex::at_fraction(0, 1),
+            FractionalIndex::default()
+        );
+
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let keys = FractionalIndex::spaced(Some(&a), Some(&b), 10).unwrap();
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(
+                FractionalIndex::at_fraction_between(Some(&a), Some(&b), i, 10).as_ref(),
+                Some(key)
+            );
+        }
+        assert!(FractionalIndex::at_fraction_between(Some(&b), Some(&a), 0, 10).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Slot (is 3) should be < n (is 3).")]
+    fn at_fraction_out_of_range() {
+        FractionalIndex::at_fraction(3, 3);
+    }
+
+    #[test]
+    fn compare_raw() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let bytes: &[u8] = &[129, 128];
+
+        assert_eq!(b, bytes);
+        assert!(b == *bytes);
+        assert!(a != bytes);
+        assert!(a < bytes);
+        assert!(b <= *bytes);
+        assert!(b > [129][..]);
+
+        assert_eq!(a, "80");
+        assert_eq!(b, *"8180");
+        assert!(b != "8180 ");
+        assert!(a != "8");
+        let stored = [b.to_string()];
+        assert!(stored.iter().any(|hex| b == hex.as_str()));
+    }
+
+    #[test]
+    fn from_optional_bytes() {
+        let key = FractionalIndex::new_after(&FractionalIndex::default());
+        assert_eq!(
+            FractionalIndex::from_optional_bytes(Some(key.as_bytes().to_vec())),
+            Ok(key.clone())
+        );
+        assert_eq!(
+            FractionalIndex::from_optional_bytes(None),
+            Ok(FractionalIndex::default())
+        );
+        assert_eq!(
+            FractionalIndex::try_from(Some(key.as_bytes().to_vec())),
+            Ok(key)
+        );
+        assert_eq!(
+            FractionalIndex::try_from(Some(vec![129])),
+            Err(DecodeError::MissingTerminator { found: Some(129) })
+        );
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(
+            FractionalIndex::from_string(""),
+            Err(DecodeError::EmptyString)
+        );
+        assert_eq!(
+            FractionalIndex::from_string("817g80"),
+            Err(DecodeError::InvalidChars {
+                character: 'g',
+                position: 3
+            })
+        );
+        assert_eq!(
+            FractionalIndex::from_string("817f"),
+            Err(DecodeError::MissingTerminator { found: Some(0x7f) })
+        );
+        assert_eq!(
+            FractionalIndex::from_bytes(vec![]),
+            Err(DecodeError::MissingTerminator { found: None })
+        );
+        assert_eq!(
+            FractionalIndex::from_string("817g80")
+                .unwrap_err()
+                .to_string(),
+            "Attempted to decode a corrupt fractional index (invalid character 'g' at byte offset 3)."
+        );
+    }
+
+    #[test]
+    fn from_bytes_unchecked() {
+        let i = FractionalIndex::new_after(&FractionalIndex::default());
+        let bytes = i.as_bytes().to_vec();
+        assert_eq!(FractionalIndex::from_bytes_unchecked(bytes), i);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn from_bytes_unchecked_debug_assertion() {
+        FractionalIndex::from_bytes_unchecked(vec![1, 2, 3]);
+    }
+
+    #[test]
+    #[allow(clippy::op_ref)]
     fn test_fractional_index() {
         let mut indices: Vec<FractionalIndex> = Vec::new();
 
@@ -0,0 +1,189 @@
+//! A compact binary encoding for batches of [FractionalIndex]es.
+//!
+//! Adjacent keys in a sorted list tend to share long prefixes, especially
+//! in deep lists where many keys were created by repeatedly inserting into
+//! the same gap. This encoding stores each key as the length of the prefix
+//! it shares with the previous key, followed by the remaining bytes
+//! (“front coding”).
+//!
+//! Keys do not need to be sorted to be encoded, but sorted keys compress
+//! best. Decoding preserves the order in which the keys were encoded.
+//!
+//! ```rust
+//! use fractional_index::{front_coding, FractionalIndex};
+//!
+//! let mut keys = vec![FractionalIndex::default()];
+//! for _ in 0..100 {
+//!     let next = FractionalIndex::new_after(keys.last().unwrap());
+//!     keys.push(next);
+//! }
+//!
+//! let encoded = front_coding::encode(&keys);
+//! assert_eq!(front_coding::decode(&encoded).unwrap(), keys);
+//! ```
+use crate::{
+    varint::{read_varint, write_varint},
+    DecodeError, FractionalIndex,
+};
+
+/// Encodes a slice of [FractionalIndex]es, sharing common prefixes between
+/// adjacent keys.
+pub fn encode(keys: &[FractionalIndex]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut previous: &[u8] = &[];
+    for key in keys {
+        let key = key.as_bytes();
+        let shared = previous
+            .iter()
+            .zip(key.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let suffix = &key[shared..];
+
+        write_varint(&mut out, shared as u64);
+        write_varint(&mut out, suffix.len() as u64);
+        out.extend_from_slice(suffix);
+        previous = key;
+    }
+    out
+}
+
+/// The largest ratio of the total length of the decoded keys to the length
+/// of the input accepted by [decode].
+///
+/// A record takes at least two bytes, so this admits every batch of keys
+/// shorter than 128 bytes, however much they share.
+pub const MAX_EXPANSION: usize = 64;
+
+/// Decodes keys previously encoded with [encode].
+///
+/// A record can repeat a long prefix of the previous key in a couple of
+/// bytes, so to bound the memory a corrupt or malicious input can use,
+/// this returns [DecodeError::BatchTooLarge] if the keys would total more
+/// than [MAX_EXPANSION] times the length of the input. Use
+/// [decode_with_limit] to set a different limit.
+pub fn decode(input: &[u8]) -> Result<Vec<FractionalIndex>, DecodeError> {
+    decode_with_limit(input, input.len().saturating_mul(MAX_EXPANSION))
+}
+
+/// Decodes keys previously encoded with [encode], returning
+/// [DecodeError::BatchTooLarge] if they total more than `max_size` bytes.
+pub fn decode_with_limit(
+    mut input: &[u8],
+    max_size: usize,
+) -> Result<Vec<FractionalIndex>, DecodeError> {
+    let mut keys: Vec<FractionalIndex> = Vec::new();
+    let mut size = 0usize;
+    while !input.is_empty() {
+        let shared = read_varint(&mut input)? as usize;
+        let suffix_len = read_varint(&mut input)? as usize;
+
+        let previous = keys.last().map_or(&[][..], |key| key.as_bytes());
+        if shared > previous.len() {
+            return Err(DecodeError::InvalidLength);
+        }
+        if suffix_len > input.len() {
+            return Err(DecodeError::Truncated);
+        }
+        size = size.saturating_add(shared + suffix_len);
+        if size > max_size {
+            return Err(DecodeError::BatchTooLarge { max: max_size });
+        }
+
+        let (suffix, rest) = input.split_at(suffix_len);
+        let mut bytes = Vec::with_capacity(shared + suffix_len);
+        bytes.extend_from_slice(&previous[..shared]);
+        bytes.extend_from_slice(suffix);
+        keys.push(FractionalIndex::from_bytes(bytes)?);
+        input = rest;
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deep_keys() -> Vec<FractionalIndex> {
+        // Repeatedly inserting into the middle of the most recently split
+        // gap makes the keys grow, with each new key sharing a long prefix
+        // with its neighbors.
+        let mut lower = FractionalIndex::default();
+        let mut upper = FractionalIndex::new_after(&lower);
+        let mut keys = vec![lower.clone(), upper.clone()];
+        for i in 0..200 {
+            let mid = FractionalIndex::new_between(&lower, &upper).unwrap();
+            keys.push(mid.clone());
+            if i % 2 == 0 {
+                lower = mid;
+            } else {
+                upper = mid;
+            }
+        }
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn roundtrip() {
+        assert!(decode(&encode(&[])).unwrap().is_empty());
+
+        let keys = deep_keys();
+        assert_eq!(decode(&encode(&keys)).unwrap(), keys);
+
+        let mut unsorted = keys.clone();
+        unsorted.reverse();
+        assert_eq!(decode(&encode(&unsorted)).unwrap(), unsorted);
+    }
+
+    #[test]
+    fn compresses_shared_prefixes() {
+        let keys = deep_keys();
+        let raw_len: usize = keys.iter().map(|k| k.len()).sum();
+        assert!(encode(&keys).len() * 4 < raw_len);
+    }
+
+    #[test]
+    fn decode_errors() {
+        let keys = deep_keys();
+        let encoded = encode(&keys[..3]);
+
+        assert!(matches!(
+            decode(&encoded[..encoded.len() - 1]),
+            Err(DecodeError::Truncated)
+        ));
+        // The first key cannot share a prefix with anything.
+        assert!(matches!(decode(&[1, 0]), Err(DecodeError::InvalidLength)));
+        assert!(matches!(
+            decode(&[0, 1, 3]),
+            Err(DecodeError::MissingTerminator { found: Some(3) })
+        ));
+    }
+
+    #[test]
+    fn decode_limit() {
+        // One long key, followed by records that repeat all of it in three
+        // bytes each.
+        let mut long = vec![129; 300];
+        long.push(128);
+        let mut encoded = encode(&[FractionalIndex::from_bytes(long).unwrap()]);
+        for _ in 0..1000 {
+            write_varint(&mut encoded, 301);
+            write_varint(&mut encoded, 0);
+        }
+        let max = encoded.len() * MAX_EXPANSION;
+        assert!(matches!(decode(&encoded), Err(DecodeError::BatchTooLarge { max: m }) if m == max));
+
+        assert_eq!(decode_with_limit(&encoded, 1001 * 301).unwrap().len(), 1001);
+        assert!(matches!(
+            decode_with_limit(&encoded, 1000 * 301),
+            Err(DecodeError::BatchTooLarge { max: 301000 })
+        ));
+
+        let encoded = encode(&deep_keys());
+        assert_eq!(
+            decode_with_limit(&encoded, 0),
+            Err(DecodeError::BatchTooLarge { max: 0 })
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Measurement of the gaps between keys, for deciding where to insert.
+//!
+//! A key `d1 .. dk` can be read as the base-256 fraction `0.d1 .. dk`, and
+//! keys compare in the same order as these fractions. The width of the gap
+//! between two keys is the difference of their fractions, and the keyspace
+//! is the interval from 0 to 1.
+//!
+//! ```rust
+//! use fractional_index::{gaps::find_largest_gap, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//! let c = FractionalIndex::new_between(&a, &b).unwrap();
+//!
+//! // The widest gap is before `a`, which covers half the keyspace.
+//! let keys = vec![a.clone(), c, b];
+//! let (position, key) = find_largest_gap(&keys).unwrap();
+//! assert_eq!(position, 0);
+//! assert!(key < a);
+//! ```
+//!
+//! [capacity_between] estimates how many more keys fit in a gap before
+//! keys must get longer:
+//!
+//! ```rust
+//! use fractional_index::{gaps::capacity_between, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//! assert_eq!(capacity_between(&a, &b), 1);
+//! assert_eq!(capacity_between(&b, &a), 0);
+//! ```
+use crate::{ordered_list::UnsortedError, spacing, FractionalIndex};
+
+/// Returns the fraction of a key as big-endian digits with a leading
+/// integer digit, padded to `len` fractional digits. A missing key stands
+/// for 0 if `upper` is false and 1 otherwise.
+fn fraction(key: Option<&FractionalIndex>, upper: bool, len: usize) -> Vec<u8> {
+    let mut digits = vec![0; len + 1];
+    match key {
+        Some(key) => digits[1..=key.len()].copy_from_slice(key.as_bytes()),
+        None if upper => digits[0] = 1,
+        None => {}
+    }
+    digits
+}
+
+/// Finds the widest gap between consecutive keys (including the gaps before
+/// the first key and after the last), and returns its position and a key
+/// near its middle, so that inserting the key at the position keeps `keys`
+/// sorted.
+///
+/// `keys` must be strictly ascending. Ties are broken in favor of the
+/// first gap. For an empty slice, this returns position 0 and
+/// [FractionalIndex::default].
+pub fn find_largest_gap(
+    keys: &[FractionalIndex],
+) -> Result<(usize, FractionalIndex), UnsortedError> {
+    if let Some(position) = keys.windows(2).position(|pair| pair[0] >= pair[1]) {
+        return Err(UnsortedError {
+            position: position + 1,
+        });
+    }
+
+    let len = keys.iter().map(|key| key.len()).max().unwrap_or(0);
+    let bound = |position: usize, upper: bool| {
+        let key = if upper {
+            keys.get(position)
+        } else {
+            position.checked_sub(1).map(|i| &keys[i])
+        };
+        fraction(key, upper, len)
+    };
+    let mut best: Option<(usize, Vec<u8>)> = None;
+    for position in 0..=keys.len() {
+        let width = spacing::difference(&bound(position, false), &bound(position, true));
+        if best.as_ref().is_none_or(|(_, best)| width > *best) {
+            best = Some((position, width));
+        }
+    }
+
+    let (position, _) = best.expect("There is at least one gap.");
+    let lower = position.checked_sub(1).map(|i| &keys[i]);
+    let key = FractionalIndex::spaced(lower, keys.get(position), 1)
+        .and_then(|keys| keys.into_iter().next())
+        .expect("The keys were checked to be strictly ascending.");
+    Ok((position, key))
+}
+
+/// Returns the number of keys strictly between `left` and `right` that are
+/// no longer than the longer of the two, i.e. how many keys can be
+/// inserted into the gap before new keys must be longer than its bounds.
+/// Saturates at `u128::MAX`, and is 0 if the keys are not in order.
+///
+/// This is the capacity with ideal placement, such as with evenly spaced
+/// keys. Repeatedly inserting at the same point of the gap (e.g. always
+/// after `left`) halves the remaining room each time, so lengths start to
+/// grow after about `log2` of the capacity insertions.
+pub fn capacity_between(left: &FractionalIndex, right: &FractionalIndex) -> u128 {
+    if left >= right {
+        return 0;
+    }
+    spacing::capacity(
+        left.as_bytes(),
+        right.as_bytes(),
+        left.len().max(right.len()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn largest_gap() {
+        assert_eq!(find_largest_gap(&[]), Ok((0, FractionalIndex::default())));
+
+        // Evenly spaced keys leave the widest gaps at the ends.
+        let keys = FractionalIndex::spaced(None, None, 4).unwrap();
+        assert_eq!(find_largest_gap(&keys).unwrap().0, 1);
+
+        // Appending shrinks the gap after the last key, so the gap before
+        // the first one is widest.
+        let mut keys = vec![FractionalIndex::default()];
+        for _ in 0..10 {
+            keys.push(FractionalIndex::new_after(keys.last().unwrap()));
+        }
+        let (position, key) = find_largest_gap(&keys).unwrap();
+        assert_eq!(position, 0);
+        assert!(key < keys[0]);
+
+        // A dense cluster between two distant keys leaves the rest wide.
+        let a = FractionalIndex::spaced(None, None, 3).unwrap();
+        let mut keys = vec![a[0].clone(), a[2].clone()];
+        for _ in 0..5 {
+            let key = FractionalIndex::new_between(&keys[0], &keys[1]).unwrap();
+            keys.insert(1, key);
+        }
+        let (position, key) = find_largest_gap(&keys).unwrap();
+        assert_eq!(position, keys.len() - 1);
+        assert!(keys[position - 1] < key && key < keys[position]);
+
+        assert_eq!(
+            find_largest_gap(&[a[1].clone(), a[0].clone()]),
+            Err(UnsortedError { position: 1 })
+        );
+    }
+
+    #[test]
+    fn capacity() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_between(&a, &b).unwrap();
+        assert_eq!(capacity_between(&a, &a), 0);
+
+        // Keys of every length up to the longer bound count, so the gap
+        // between 40 80 and c0 80 holds 41 80 ..= bf 80 and 80.
+        let keys = FractionalIndex::spaced(None, None, 2).unwrap();
+        assert_eq!(keys[0].as_bytes(), &[64, 128]);
+        assert_eq!(capacity_between(&keys[0], &keys[1]), 128);
+
+        // No key of at most three bytes is between 81 7f 80 and 81 80.
+        assert_eq!(capacity_between(&c, &b), 0);
+    }
+}
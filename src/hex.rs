@@ -1,4 +1,5 @@
-use std::{error::Error, fmt::Display};
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Display};
 
 const HEX_CHARS: &[u8] = b"0123456789abcdef";
 
@@ -17,38 +18,102 @@ pub fn bytes_to_hex(bytes: &[u8]) -> String {
     s
 }
 
+/// Returns true if `hex` is the (lowercase) hex encoding of `bytes`, without
+/// allocating.
+pub fn eq_hex(bytes: &[u8], hex: &str) -> bool {
+    hex.len() == bytes.len() * 2
+        && bytes
+            .iter()
+            .zip(hex.as_bytes().chunks(2))
+            .all(|(byte, digits)| {
+                digits[0] == HEX_CHARS[(byte >> 4) as usize]
+                    && digits[1] == HEX_CHARS[(byte & 0xf) as usize]
+            })
+}
+
 pub fn hex_to_bytes(hex: &str) -> Result<Vec<u8>, InvalidChar> {
+    let digits = hex.as_bytes();
     let mut bytes = Vec::with_capacity(hex.len() / 2);
     for i in 0..hex.len() / 2 {
-        bytes.push(hex_to_byte(&hex[i * 2..i * 2 + 2])?);
+        let high = hex_digit(hex, digits, i * 2)?;
+        let low = hex_digit(hex, digits, i * 2 + 1)?;
+        bytes.push(high << 4 | low);
     }
     Ok(bytes)
 }
 
+/// Checks the digits that [hex_to_bytes] decodes, without allocating, and
+/// returns them.
+pub fn check_hex_digits(hex: &str) -> Result<&str, InvalidChar> {
+    let digits = hex.as_bytes();
+    let len = hex.len() / 2 * 2;
+    for position in 0..len {
+        hex_digit(hex, digits, position)?;
+    }
+    Ok(&hex[..len])
+}
+
 #[derive(Debug)]
-pub struct InvalidChar(char);
+pub struct InvalidChar {
+    pub character: char,
+    /// The byte offset of the character in the string.
+    pub position: usize,
+}
 
 impl Display for InvalidChar {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Invalid hex character: {}", self.0)
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid hex character: {} at byte offset {}",
+            self.character, self.position
+        )
     }
 }
 
-impl Error for InvalidChar {
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidChar {
     fn description(&self) -> &str {
         "Invalid hex character"
     }
 }
 
-pub fn hex_to_byte(hex: &str) -> Result<u8, InvalidChar> {
-    let mut byte = 0;
-    for c in hex.chars() {
-        byte <<= 4;
-        match c {
-            '0'..='9' => byte += c as u8 - b'0',
-            'a'..='f' => byte += c as u8 - b'a' + 10,
-            _ => return Err(InvalidChar(c)),
+fn hex_digit(hex: &str, digits: &[u8], position: usize) -> Result<u8, InvalidChar> {
+    match digits[position] {
+        c @ b'0'..=b'9' => Ok(c - b'0'),
+        c @ b'a'..=b'f' => Ok(c - b'a' + 10),
+        _ => {
+            // Every byte before `position` is an ASCII digit, so `position`
+            // is on a character boundary.
+            let character = hex[position..].chars().next().unwrap_or_default();
+            Err(InvalidChar {
+                character,
+                position,
+            })
         }
     }
-    Ok(byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_chars() {
+        assert_eq!(hex_to_bytes("00ff80").unwrap(), vec![0, 255, 128]);
+
+        let err = hex_to_bytes("80Ff").unwrap_err();
+        assert_eq!((err.character, err.position), ('F', 2));
+
+        let err = hex_to_bytes("8\u{e9}0").unwrap_err();
+        assert_eq!((err.character, err.position), ('\u{e9}', 1));
+    }
+
+    #[test]
+    fn eq_hex_matches_encoding() {
+        assert!(eq_hex(&[0, 255, 128], "00ff80"));
+        assert!(eq_hex(&[], ""));
+        assert!(!eq_hex(&[0, 255, 128], "00FF80"));
+        assert!(!eq_hex(&[0, 255], "00ff80"));
+        assert!(!eq_hex(&[0, 255, 128], "00ff8"));
+    }
 }
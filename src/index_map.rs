@@ -0,0 +1,368 @@
+//! A hash map whose entries are kept in the order of a [FractionalIndex],
+//! enabled by the `indexmap` feature.
+//!
+//! [FractionalIndexMap] wraps an [IndexMap] from each key to its
+//! [FractionalIndex] and value, and keeps the entries physically sorted by
+//! index. This gives UI models both lookup by key and list order (and
+//! positional access) in one structure, with relative insertion methods
+//! that compute the index of a new entry from its neighbors.
+//!
+//! ```rust
+//! use fractional_index::index_map::FractionalIndexMap;
+//!
+//! let mut rows = FractionalIndexMap::new();
+//! rows.push_back("b", "second");
+//! rows.push_front("a", "first");
+//! rows.insert_after(&"b", "c", "third").unwrap();
+//!
+//! assert_eq!(rows.keys().copied().collect::<Vec<_>>(), vec!["a", "b", "c"]);
+//! assert_eq!(rows.get(&"c"), Some(&"third"));
+//! assert_eq!(rows.position_of(&"c"), Some(2));
+//!
+//! // Relative insertion of an existing key moves it.
+//! rows.insert_before(&"a", "c", "third").unwrap();
+//! assert_eq!(rows.keys().copied().collect::<Vec<_>>(), vec!["c", "a", "b"]);
+//! ```
+use crate::FractionalIndex;
+use indexmap::IndexMap;
+use std::borrow::Borrow;
+use std::fmt::{self, Display};
+use std::hash::Hash;
+
+/// The error returned by [FractionalIndexMap::insert] when the index is
+/// already used by a different key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexOccupied;
+
+impl Display for IndexOccupied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The index is already used by a different key.")
+    }
+}
+
+impl std::error::Error for IndexOccupied {}
+
+/// A map from keys to values, iterated in ascending order of a
+/// [FractionalIndex] stored with each entry.
+#[derive(Clone, Debug)]
+pub struct FractionalIndexMap<K, V> {
+    entries: IndexMap<K, (FractionalIndex, V)>,
+}
+
+impl<K, V> Default for FractionalIndexMap<K, V> {
+    fn default() -> Self {
+        FractionalIndexMap {
+            entries: IndexMap::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq, V: PartialEq> PartialEq for FractionalIndexMap<K, V> {
+    /// Maps are equal if they have the same entries in the same order.
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Hash + Eq, V: Eq> Eq for FractionalIndexMap<K, V> {}
+
+impl<K: Hash + Eq, V> FractionalIndexMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.entries.contains_key(key)
+    }
+
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.entries.get(key).map(|(_, value)| value)
+    }
+
+    pub fn get_mut<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+    {
+        self.entries.get_mut(key).map(|(_, value)| value)
+    }
+
+    /// Returns the fractional index of `key`.
+    pub fn index_of<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&FractionalIndex>
+    where
+        K: Borrow<Q>,
+    {
+        self.entries.get(key).map(|(index, _)| index)
+    }
+
+    /// Returns the position of `key` in the order, from 0.
+    pub fn position_of<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+    {
+        self.entries.get_index_of(key)
+    }
+
+    /// Returns the entry at `position` in the order.
+    pub fn get_position(&self, position: usize) -> Option<(&K, &FractionalIndex, &V)> {
+        self.entries
+            .get_index(position)
+            .map(|(key, (index, value))| (key, index, value))
+    }
+
+    /// Iterates over the entries in order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &FractionalIndex, &V)> {
+        self.entries
+            .iter()
+            .map(|(key, (index, value))| (key, index, value))
+    }
+
+    /// Iterates over the keys in order.
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> {
+        self.entries.keys()
+    }
+
+    /// Iterates over the values in order.
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> {
+        self.entries.values().map(|(_, value)| value)
+    }
+
+    /// Returns the underlying map, whose entries are sorted by index.
+    pub fn as_index_map(&self) -> &IndexMap<K, (FractionalIndex, V)> {
+        &self.entries
+    }
+
+    pub fn into_index_map(self) -> IndexMap<K, (FractionalIndex, V)> {
+        self.entries
+    }
+
+    /// Removes `key`, returning its index and value. The order of the
+    /// other entries is preserved.
+    pub fn remove<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<(FractionalIndex, V)>
+    where
+        K: Borrow<Q>,
+    {
+        self.entries.shift_remove(key)
+    }
+
+    /// Inserts `key` with the given index, or moves it there if it is
+    /// already in the map. Returns the previous value of the key, or an
+    /// error if a different key has an equal index.
+    pub fn insert(
+        &mut self,
+        key: K,
+        index: FractionalIndex,
+        value: V,
+    ) -> Result<Option<V>, IndexOccupied> {
+        let position = self.entries.partition_point(|_, (i, _)| *i < index);
+        if let Some((other, (i, _))) = self.entries.get_index(position) {
+            if *i == index && *other != key {
+                return Err(IndexOccupied);
+            }
+        }
+        let previous = self.entries.shift_remove_full(&key);
+        let position = match &previous {
+            Some((removed, _, _)) if *removed < position => position - 1,
+            _ => position,
+        };
+        self.entries.shift_insert(position, key, (index, value));
+        Ok(previous.map(|(_, _, (_, value))| value))
+    }
+
+    /// Inserts or moves `key` to the position `position`, between the
+    /// entries currently on either side of it, and returns its new index.
+    fn place(&mut self, position: usize, key: K, value: V) -> FractionalIndex {
+        let lower = position
+            .checked_sub(1)
+            .and_then(|i| self.entries.get_index(i))
+            .map(|(_, (index, _))| index);
+        let upper = self
+            .entries
+            .get_index(position)
+            .map(|(_, (index, _))| index);
+        let index =
+            FractionalIndex::new(lower, upper).expect("Entries are always distinct and in order.");
+        self.entries
+            .shift_insert(position, key, (index.clone(), value));
+        index
+    }
+
+    /// Inserts `key` before every other entry, or moves it there.
+    pub fn push_front(&mut self, key: K, value: V) -> FractionalIndex {
+        self.entries.shift_remove(&key);
+        self.place(0, key, value)
+    }
+
+    /// Inserts `key` after every other entry, or moves it there.
+    pub fn push_back(&mut self, key: K, value: V) -> FractionalIndex {
+        self.entries.shift_remove(&key);
+        self.place(self.len(), key, value)
+    }
+
+    /// Inserts `key` immediately before `anchor`, or moves it there, and
+    /// returns its new index. Returns None, leaving the map unchanged, if
+    /// `anchor` is not in the map.
+    ///
+    /// If `anchor` is `key` itself, only the value is replaced.
+    pub fn insert_before<Q: Hash + Eq + ?Sized>(
+        &mut self,
+        anchor: &Q,
+        key: K,
+        value: V,
+    ) -> Option<FractionalIndex>
+    where
+        K: Borrow<Q>,
+    {
+        self.insert_relative(anchor, key, value, 0)
+    }
+
+    /// Inserts `key` immediately after `anchor`, or moves it there, and
+    /// returns its new index. Returns None, leaving the map unchanged, if
+    /// `anchor` is not in the map.
+    ///
+    /// If `anchor` is `key` itself, only the value is replaced.
+    pub fn insert_after<Q: Hash + Eq + ?Sized>(
+        &mut self,
+        anchor: &Q,
+        key: K,
+        value: V,
+    ) -> Option<FractionalIndex>
+    where
+        K: Borrow<Q>,
+    {
+        self.insert_relative(anchor, key, value, 1)
+    }
+
+    /// Places `key` at the position of `anchor`, plus `offset`.
+    fn insert_relative<Q: Hash + Eq + ?Sized>(
+        &mut self,
+        anchor: &Q,
+        key: K,
+        value: V,
+        offset: usize,
+    ) -> Option<FractionalIndex>
+    where
+        K: Borrow<Q>,
+    {
+        if key.borrow() == anchor {
+            let (index, current) = self.entries.get_mut(anchor)?;
+            *current = value;
+            return Some(index.clone());
+        }
+        if !self.entries.contains_key(anchor) {
+            return None;
+        }
+        self.entries.shift_remove(&key);
+        let position = self.entries.get_index_of(anchor)? + offset;
+        Some(self.place(position, key, value))
+    }
+}
+
+impl<K: Hash + Eq, V> Extend<(K, V)> for FractionalIndexMap<K, V> {
+    /// Appends each entry with [FractionalIndexMap::push_back].
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.push_back(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V> std::iter::FromIterator<(K, V)> for FractionalIndexMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = FractionalIndexMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(map: &FractionalIndexMap<char, u32>) -> String {
+        map.keys().collect()
+    }
+
+    fn assert_sorted(map: &FractionalIndexMap<char, u32>) {
+        let indexes: Vec<&FractionalIndex> = map.iter().map(|(_, index, _)| index).collect();
+        assert!(indexes.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn relative_insertion() {
+        let mut map = FractionalIndexMap::new();
+        map.push_back('b', 2);
+        map.push_back('d', 4);
+        map.push_front('a', 1);
+        map.insert_after(&'b', 'c', 3).unwrap();
+        map.insert_before(&'a', 'z', 0).unwrap();
+        assert_eq!(keys(&map), "zabcd");
+        assert_sorted(&map);
+        assert_eq!(
+            map.get_position(1).map(|(key, _, value)| (*key, *value)),
+            Some(('a', 1))
+        );
+
+        assert_eq!(map.insert_after(&'x', 'y', 0), None);
+        assert_eq!(keys(&map), "zabcd");
+
+        // Moves keep one entry per key.
+        map.insert_after(&'d', 'z', 26).unwrap();
+        map.insert_before(&'b', 'd', 40).unwrap();
+        map.push_front('c', 30);
+        assert_eq!(keys(&map), "cadbz");
+        assert_eq!(map.get(&'d'), Some(&40));
+        assert_sorted(&map);
+
+        let index = map.index_of(&'b').cloned();
+        assert_eq!(map.insert_after(&'b', 'b', 20), index);
+        assert_eq!(map.get(&'b'), Some(&20));
+        assert_eq!(map.remove(&'a').map(|(_, value)| value), Some(1));
+        assert_eq!(keys(&map), "cdbz");
+        assert_eq!(map.len(), 4);
+    }
+
+    #[test]
+    fn insert_with_index() {
+        let indexes = FractionalIndex::new_after_many(&FractionalIndex::default(), 4);
+        let mut map = FractionalIndexMap::new();
+        assert_eq!(map.insert('c', indexes[2].clone(), 3), Ok(None));
+        assert_eq!(map.insert('a', indexes[0].clone(), 1), Ok(None));
+        assert_eq!(map.insert('d', indexes[3].clone(), 4), Ok(None));
+        assert_eq!(map.insert('b', indexes[1].clone(), 2), Ok(None));
+        assert_eq!(keys(&map), "abcd");
+
+        assert_eq!(map.insert('e', indexes[1].clone(), 5), Err(IndexOccupied));
+        assert_eq!(map.insert('b', indexes[1].clone(), 20), Ok(Some(2)));
+        // Move "a" to the end.
+        let last = FractionalIndex::new_after(&indexes[3]);
+        assert_eq!(map.insert('a', last, 10), Ok(Some(1)));
+        assert_eq!(keys(&map), "bcda");
+        assert_sorted(&map);
+    }
+
+    #[test]
+    fn collect() {
+        let map: FractionalIndexMap<char, u32> = "abc".chars().zip(1..).collect();
+        assert_eq!(keys(&map), "abc");
+        assert_eq!(map.values().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_sorted(&map);
+        let mut other = map.clone();
+        assert_eq!(other, map);
+        other.push_front('c', 3);
+        assert_ne!(other, map);
+    }
+}
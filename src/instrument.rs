@@ -0,0 +1,75 @@
+//! Telemetry for generated keys, enabled by the `tracing` and `metrics`
+//! features.
+
+/// Records that `operation` generated `index` between the given optional
+/// bounds, with the length of the key and whether it is longer than both
+/// bounds.
+///
+/// With the `tracing` feature, emits a `trace` event with target
+/// `fractional_index`. The event is recorded in the caller's current span,
+/// so long keys can be traced back to the request that generated them.
+///
+/// With the `metrics` feature, records the length in the
+/// `fractional_index_key_length` histogram, labeled with the operation.
+#[inline]
+pub(crate) fn generated(
+    operation: &'static str,
+    index: &[u8],
+    lower: Option<&[u8]>,
+    upper: Option<&[u8]>,
+) {
+    #[cfg(feature = "tracing")]
+    {
+        let bound_len = lower
+            .map_or(0, <[u8]>::len)
+            .max(upper.map_or(0, <[u8]>::len));
+        tracing::trace!(
+            target: "fractional_index",
+            operation,
+            len = index.len(),
+            grew = index.len() > bound_len,
+            "generated key"
+        );
+    }
+    #[cfg(feature = "metrics")]
+    metrics::histogram!("fractional_index_key_length", "operation" => operation)
+        .record(index.len() as f64);
+    #[cfg(not(feature = "tracing"))]
+    let _ = (lower, upper);
+    #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+    let _ = (operation, index);
+}
+
+/// Records that `source` found `count` keys that collided with another
+/// key and had to be replaced.
+///
+/// With the `metrics` feature, increments the
+/// `fractional_index_collisions` counter, labeled with the source.
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn collisions(source: &'static str, count: usize) {
+    #[cfg(feature = "metrics")]
+    if count > 0 {
+        metrics::counter!("fractional_index_collisions", "source" => source)
+            .increment(count as u64);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (source, count);
+}
+
+/// Records that a rebalance was planned that rewrites `rows` keys.
+///
+/// With the `metrics` feature, increments the
+/// `fractional_index_rebalances` counter if any key is rewritten, and the
+/// `fractional_index_rebalanced_keys` counter by the number of keys.
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn rebalanced(rows: usize) {
+    #[cfg(feature = "metrics")]
+    if rows > 0 {
+        metrics::counter!("fractional_index_rebalances").increment(1);
+        metrics::counter!("fractional_index_rebalanced_keys").increment(rows as u64);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = rows;
+}
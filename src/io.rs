@@ -0,0 +1,685 @@
+//! Streaming binary encoding of [FractionalIndex]es over
+//! [Read](std::io::Read) and [Write](std::io::Write), for spill files and
+//! replication streams that are too large to buffer.
+//!
+//! A stream starts with a single version byte, followed by each key as a
+//! varint length prefix and the key's bytes. With the `tokio` feature,
+//! [AsyncIndexWriter] and [AsyncIndexReader] provide the same encoding over
+//! tokio's `AsyncWrite` and `AsyncRead`.
+//!
+//! [HexLineWriter] and [HexLineReader] (and their async equivalents) instead
+//! write one key per line in the hex encoding of
+//! [FractionalIndex::to_string], which is convenient for piping keys
+//! between command-line tools and scripts.
+//!
+//! ```rust
+//! use fractional_index::{io::{IndexReader, IndexWriter}, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//!
+//! let mut writer = IndexWriter::new(Vec::new()).unwrap();
+//! writer.write(&a).unwrap();
+//! writer.write(&b).unwrap();
+//! let buffer = writer.into_inner();
+//!
+//! let reader = IndexReader::new(&buffer[..]).unwrap();
+//! let keys: Vec<FractionalIndex> = reader.collect::<Result<_, _>>().unwrap();
+//! assert_eq!(keys, vec![a, b]);
+//! ```
+use crate::{
+    limits::{self, check_len_against, MAX_PREALLOCATION},
+    varint::write_varint,
+    DecodeError, FractionalIndex,
+};
+use std::{
+    convert::TryFrom,
+    io::{self, BufRead, Read, Write},
+};
+
+/// The version byte written at the start of every stream.
+pub const VERSION: u8 = 1;
+
+fn invalid_data(err: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn check_version(version: u8) -> io::Result<()> {
+    if version != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unsupported fractional index stream version {}.", version),
+        ));
+    }
+    Ok(())
+}
+
+fn encode_record(buffer: &mut Vec<u8>, index: &FractionalIndex) {
+    buffer.clear();
+    write_varint(buffer, index.len() as u64);
+    buffer.extend_from_slice(index.as_bytes());
+}
+
+/// Accumulates the bytes of a varint length prefix. Returns the decoded
+/// length once the final byte has been pushed.
+#[derive(Default)]
+struct LengthDecoder {
+    value: u64,
+    shift: u32,
+}
+
+impl LengthDecoder {
+    fn push(&mut self, byte: u8) -> Result<Option<usize>, DecodeError> {
+        if self.shift >= 64 {
+            return Err(DecodeError::InvalidLength);
+        }
+        self.value |= u64::from(byte & 0x7f) << self.shift;
+        self.shift += 7;
+        if byte & 0x80 == 0 {
+            let len = usize::try_from(self.value).map_err(|_| DecodeError::InvalidLength)?;
+            return Ok(Some(len));
+        }
+        Ok(None)
+    }
+}
+
+/// Decodes the bytes of a key read from a stream, given the length its
+/// prefix declared, which was already checked against the reader's limit.
+/// Fewer bytes than that means the stream ended partway through the key.
+fn decode_record(bytes: Vec<u8>, len: usize) -> io::Result<Option<FractionalIndex>> {
+    if bytes.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    FractionalIndex::check_terminator(&bytes).map_err(invalid_data)?;
+    Ok(Some(FractionalIndex::from_bytes_unchecked(bytes)))
+}
+
+/// Writes a stream of [FractionalIndex]es to an underlying writer.
+pub struct IndexWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> IndexWriter<W> {
+    /// Constructs a writer, writing the stream header immediately.
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(&[VERSION])?;
+        Ok(IndexWriter {
+            inner,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Writes a single [FractionalIndex] to the stream.
+    pub fn write(&mut self, index: &FractionalIndex) -> io::Result<()> {
+        encode_record(&mut self.buffer, index);
+        self.inner.write_all(&self.buffer)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads a stream of [FractionalIndex]es written by [IndexWriter].
+///
+/// Yields one result per key, and ends at the end of the underlying
+/// reader. A stream that ends partway through a key yields an error of
+/// kind [UnexpectedEof](io::ErrorKind::UnexpectedEof).
+///
+/// Length prefixes are read one byte at a time, so unbuffered readers
+/// should be wrapped in a [BufReader](std::io::BufReader).
+///
+/// Keys are checked against the [limits::max_len] in effect when the
+/// reader is constructed, unless it is changed with
+/// [IndexReader::set_max_len].
+pub struct IndexReader<R: Read> {
+    inner: R,
+    max_len: Option<usize>,
+}
+
+impl<R: Read> IndexReader<R> {
+    /// Constructs a reader, reading and validating the stream header
+    /// immediately.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut version = [0];
+        inner.read_exact(&mut version)?;
+        check_version(version[0])?;
+        Ok(IndexReader {
+            inner,
+            max_len: limits::max_len(),
+        })
+    }
+
+    /// Sets the maximum length in bytes of the keys read, or removes the
+    /// limit.
+    pub fn set_max_len(&mut self, max_len: Option<usize>) {
+        self.max_len = max_len;
+    }
+
+    /// Reads the next [FractionalIndex], or returns None at the end of the
+    /// stream.
+    pub fn read(&mut self) -> io::Result<Option<FractionalIndex>> {
+        let mut length = LengthDecoder::default();
+        let mut first = true;
+        let len = loop {
+            let mut byte = [0];
+            if self.inner.read(&mut byte)? == 0 {
+                if first {
+                    return Ok(None);
+                }
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            first = false;
+            if let Some(len) = length.push(byte[0]).map_err(invalid_data)? {
+                break len;
+            }
+        };
+        check_len_against(len, self.max_len).map_err(invalid_data)?;
+
+        // The bytes are read as they arrive rather than into a buffer of
+        // `len` bytes, so a corrupt prefix fails at the end of the stream
+        // instead of allocating it.
+        let mut bytes = Vec::with_capacity(len.min(MAX_PREALLOCATION));
+        (&mut self.inner).take(len as u64).read_to_end(&mut bytes)?;
+        decode_record(bytes, len)
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Iterator for IndexReader<R> {
+    type Item = io::Result<FractionalIndex>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+/// Writes [FractionalIndex]es as newline-delimited hex strings.
+pub struct HexLineWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> HexLineWriter<W> {
+    /// Constructs a writer. Unlike [IndexWriter], no header is written.
+    pub fn new(inner: W) -> Self {
+        HexLineWriter { inner }
+    }
+
+    /// Writes a single [FractionalIndex], followed by a newline.
+    pub fn write(&mut self, index: &FractionalIndex) -> io::Result<()> {
+        writeln!(self.inner, "{}", index.to_string())
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// The most bytes read for a single line, which is enough for the hex
+/// encoding of a key of `max_len` bytes and a `\r\n` line ending.
+fn line_limit(max_len: Option<usize>) -> u64 {
+    max_len.map_or(u64::MAX, |max| {
+        (max as u64).saturating_mul(2).saturating_add(2)
+    })
+}
+
+/// Parses a line read by [HexLineReader], or returns None if it is blank.
+/// `read` is the number of bytes read for the line, so that a line cut
+/// short at [line_limit] is reported as too long.
+fn decode_line(
+    line: &str,
+    read: usize,
+    max_len: Option<usize>,
+) -> io::Result<Option<FractionalIndex>> {
+    if let Some(max) = max_len {
+        if read as u64 >= line_limit(max_len) && !line.ends_with('\n') {
+            return Err(invalid_data(DecodeError::TooLong { max }));
+        }
+    }
+    let line = line.trim_end_matches(&['\n', '\r'][..]);
+    if line.is_empty() {
+        return Ok(None);
+    }
+    limits::with_max_len(max_len, || FractionalIndex::from_string(line))
+        .map(Some)
+        .map_err(invalid_data)
+}
+
+/// Reads newline-delimited hex strings written by [HexLineWriter].
+///
+/// Both `\n` and `\r\n` line endings are accepted, and blank lines are
+/// skipped.
+///
+/// Keys are checked against the [limits::max_len] in effect when the
+/// reader is constructed, unless it is changed with
+/// [HexLineReader::set_max_len]. Lines are read no further than the limit
+/// allows, so an overlong line fails without being buffered.
+pub struct HexLineReader<R: BufRead> {
+    inner: R,
+    line: String,
+    max_len: Option<usize>,
+}
+
+impl<R: BufRead> HexLineReader<R> {
+    /// Constructs a reader.
+    pub fn new(inner: R) -> Self {
+        HexLineReader {
+            inner,
+            line: String::new(),
+            max_len: limits::max_len(),
+        }
+    }
+
+    /// Sets the maximum length in bytes of the keys read, or removes the
+    /// limit.
+    pub fn set_max_len(&mut self, max_len: Option<usize>) {
+        self.max_len = max_len;
+    }
+
+    /// Reads the next [FractionalIndex], or returns None at the end of the
+    /// input.
+    pub fn read(&mut self) -> io::Result<Option<FractionalIndex>> {
+        loop {
+            self.line.clear();
+            let read = (&mut self.inner)
+                .take(line_limit(self.max_len))
+                .read_line(&mut self.line)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            if let Some(index) = decode_line(&self.line, read, self.max_len)? {
+                return Ok(Some(index));
+            }
+        }
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: BufRead> Iterator for HexLineReader<R> {
+    type Item = io::Result<FractionalIndex>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub use self::tokio_io::{
+    AsyncHexLineReader, AsyncHexLineWriter, AsyncIndexReader, AsyncIndexWriter,
+};
+
+#[cfg(feature = "tokio")]
+mod tokio_io {
+    use super::*;
+    use tokio::io::{
+        AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+    };
+
+    /// Writes a stream of [FractionalIndex]es to an underlying
+    /// [AsyncWrite]. The async equivalent of [IndexWriter].
+    pub struct AsyncIndexWriter<W: AsyncWrite + Unpin> {
+        inner: W,
+        buffer: Vec<u8>,
+    }
+
+    impl<W: AsyncWrite + Unpin> AsyncIndexWriter<W> {
+        /// Constructs a writer, writing the stream header immediately.
+        pub async fn new(mut inner: W) -> io::Result<Self> {
+            inner.write_all(&[VERSION]).await?;
+            Ok(AsyncIndexWriter {
+                inner,
+                buffer: Vec::new(),
+            })
+        }
+
+        /// Writes a single [FractionalIndex] to the stream.
+        pub async fn write(&mut self, index: &FractionalIndex) -> io::Result<()> {
+            encode_record(&mut self.buffer, index);
+            self.inner.write_all(&self.buffer).await
+        }
+
+        /// Flushes the underlying writer.
+        pub async fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush().await
+        }
+
+        /// Returns the underlying writer.
+        pub fn into_inner(self) -> W {
+            self.inner
+        }
+    }
+
+    /// Reads a stream of [FractionalIndex]es from an underlying
+    /// [AsyncRead]. The async equivalent of [IndexReader].
+    ///
+    /// A future can be polled on any thread, so set the limit on the length
+    /// of keys with [AsyncIndexReader::set_max_len] rather than with
+    /// [limits::with_max_len].
+    pub struct AsyncIndexReader<R: AsyncRead + Unpin> {
+        inner: R,
+        max_len: Option<usize>,
+    }
+
+    impl<R: AsyncRead + Unpin> AsyncIndexReader<R> {
+        /// Constructs a reader, reading and validating the stream header
+        /// immediately.
+        pub async fn new(mut inner: R) -> io::Result<Self> {
+            let version = inner.read_u8().await?;
+            check_version(version)?;
+            Ok(AsyncIndexReader {
+                inner,
+                max_len: limits::max_len(),
+            })
+        }
+
+        /// Sets the maximum length in bytes of the keys read, or removes
+        /// the limit.
+        pub fn set_max_len(&mut self, max_len: Option<usize>) {
+            self.max_len = max_len;
+        }
+
+        /// Reads the next [FractionalIndex], or returns None at the end of
+        /// the stream.
+        pub async fn read(&mut self) -> io::Result<Option<FractionalIndex>> {
+            let mut length = LengthDecoder::default();
+            let mut first = true;
+            let len = loop {
+                let mut byte = [0];
+                if self.inner.read(&mut byte).await? == 0 {
+                    if first {
+                        return Ok(None);
+                    }
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                first = false;
+                if let Some(len) = length.push(byte[0]).map_err(invalid_data)? {
+                    break len;
+                }
+            };
+            check_len_against(len, self.max_len).map_err(invalid_data)?;
+
+            let mut bytes = Vec::with_capacity(len.min(MAX_PREALLOCATION));
+            (&mut self.inner)
+                .take(len as u64)
+                .read_to_end(&mut bytes)
+                .await?;
+            decode_record(bytes, len)
+        }
+
+        /// Returns the underlying reader.
+        pub fn into_inner(self) -> R {
+            self.inner
+        }
+    }
+
+    /// Writes [FractionalIndex]es as newline-delimited hex strings to an
+    /// [AsyncWrite]. The async equivalent of [HexLineWriter].
+    pub struct AsyncHexLineWriter<W: AsyncWrite + Unpin> {
+        inner: W,
+    }
+
+    impl<W: AsyncWrite + Unpin> AsyncHexLineWriter<W> {
+        /// Constructs a writer.
+        pub fn new(inner: W) -> Self {
+            AsyncHexLineWriter { inner }
+        }
+
+        /// Writes a single [FractionalIndex], followed by a newline.
+        pub async fn write(&mut self, index: &FractionalIndex) -> io::Result<()> {
+            let mut line = index.to_string();
+            line.push('\n');
+            self.inner.write_all(line.as_bytes()).await
+        }
+
+        /// Flushes the underlying writer.
+        pub async fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush().await
+        }
+
+        /// Returns the underlying writer.
+        pub fn into_inner(self) -> W {
+            self.inner
+        }
+    }
+
+    /// Reads newline-delimited hex strings from an [AsyncBufRead]. The
+    /// async equivalent of [HexLineReader].
+    ///
+    /// As with [AsyncIndexReader], set the limit on the length of keys with
+    /// [AsyncHexLineReader::set_max_len] rather than with
+    /// [limits::with_max_len].
+    pub struct AsyncHexLineReader<R: AsyncBufRead + Unpin> {
+        inner: R,
+        line: String,
+        max_len: Option<usize>,
+    }
+
+    impl<R: AsyncBufRead + Unpin> AsyncHexLineReader<R> {
+        /// Constructs a reader.
+        pub fn new(inner: R) -> Self {
+            AsyncHexLineReader {
+                inner,
+                line: String::new(),
+                max_len: limits::max_len(),
+            }
+        }
+
+        /// Sets the maximum length in bytes of the keys read, or removes
+        /// the limit.
+        pub fn set_max_len(&mut self, max_len: Option<usize>) {
+            self.max_len = max_len;
+        }
+
+        /// Reads the next [FractionalIndex], or returns None at the end of
+        /// the input.
+        pub async fn read(&mut self) -> io::Result<Option<FractionalIndex>> {
+            loop {
+                self.line.clear();
+                let read = (&mut self.inner)
+                    .take(line_limit(self.max_len))
+                    .read_line(&mut self.line)
+                    .await?;
+                if read == 0 {
+                    return Ok(None);
+                }
+                if let Some(index) = decode_line(&self.line, read, self.max_len)? {
+                    return Ok(Some(index));
+                }
+            }
+        }
+
+        /// Returns the underlying reader.
+        pub fn into_inner(self) -> R {
+            self.inner
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> Vec<FractionalIndex> {
+        let mut keys = vec![FractionalIndex::default()];
+        for _ in 0..300 {
+            let next = FractionalIndex::new_between(&keys[0], keys.last().unwrap())
+                .unwrap_or_else(|| FractionalIndex::new_after(keys.last().unwrap()));
+            keys.push(next);
+        }
+        keys
+    }
+
+    fn encode(keys: &[FractionalIndex]) -> Vec<u8> {
+        let mut writer = IndexWriter::new(Vec::new()).unwrap();
+        for key in keys {
+            writer.write(key).unwrap();
+        }
+        writer.into_inner()
+    }
+
+    #[test]
+    fn roundtrip() {
+        let keys = keys();
+        let buffer = encode(&keys);
+        assert_eq!(buffer[0], VERSION);
+
+        let decoded: Vec<FractionalIndex> = IndexReader::new(&buffer[..])
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(decoded, keys);
+    }
+
+    #[test]
+    fn empty_stream() {
+        let buffer = encode(&[]);
+        assert!(IndexReader::new(&buffer[..]).unwrap().next().is_none());
+        assert!(IndexReader::new(&[][..]).is_err());
+    }
+
+    #[test]
+    fn errors() {
+        let buffer = encode(&keys()[..2]);
+
+        let mut reader = IndexReader::new(&buffer[..buffer.len() - 1]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let err = IndexReader::new(&[VERSION + 1][..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // A length prefix of 2^40 bytes fails at the end of the stream,
+        // without allocating them.
+        let mut reader =
+            IndexReader::new(&[VERSION, 0x80, 0x80, 0x80, 0x80, 0x80, 0x20, 128][..]).unwrap();
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut reader = IndexReader::new(&[VERSION, 1, 3][..]).unwrap();
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn hex_lines() {
+        let keys = keys();
+        let mut writer = HexLineWriter::new(Vec::new());
+        for key in &keys {
+            writer.write(key).unwrap();
+        }
+        let buffer = writer.into_inner();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert_eq!(text.lines().next(), Some("80"));
+        assert_eq!(text.lines().count(), keys.len());
+
+        let decoded: Vec<FractionalIndex> = HexLineReader::new(&buffer[..])
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(decoded, keys);
+
+        let mut reader = HexLineReader::new(&b"80\r\n\n817f80"[..]);
+        assert_eq!(reader.next().unwrap().unwrap(), keys[0]);
+        assert_eq!(reader.next().unwrap().unwrap().to_string(), "817f80");
+        assert!(reader.next().is_none());
+
+        let mut reader = HexLineReader::new(&b"80\nzz\n"[..]);
+        assert!(reader.next().unwrap().is_ok());
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn hex_lines_max_len() {
+        let long = format!("{}80\n81\r\n80\n", "81".repeat(100));
+
+        let mut reader = HexLineReader::new(long.as_bytes());
+        reader.set_max_len(Some(2));
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // Only as much of the line as the limit allows was read.
+        assert_eq!(reader.into_inner().len(), long.len() - 6);
+
+        // A key of exactly the limit is accepted, with either line ending.
+        let mut reader = HexLineReader::new(&b"8180\r\n8180\n"[..]);
+        reader.set_max_len(Some(2));
+        assert_eq!(reader.count(), 2);
+
+        // The reader's limit applies rather than the thread-local one.
+        let mut reader = limits::with_max_len(Some(2), || HexLineReader::new(long.as_bytes()));
+        reader.set_max_len(None);
+        limits::with_max_len(Some(1), || {
+            assert_eq!(reader.next().unwrap().unwrap().len(), 101);
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn async_hex_lines() {
+        let keys = keys();
+        let mut writer = AsyncHexLineWriter::new(Vec::new());
+        for key in &keys {
+            writer.write(key).await.unwrap();
+        }
+        let buffer = writer.into_inner();
+
+        let mut reader = AsyncHexLineReader::new(&buffer[..]);
+        let mut decoded = Vec::new();
+        while let Some(key) = reader.read().await.unwrap() {
+            decoded.push(key);
+        }
+        assert_eq!(decoded, keys);
+
+        let long = format!("{}80\n", "81".repeat(100));
+        let mut reader = AsyncHexLineReader::new(long.as_bytes());
+        reader.set_max_len(Some(2));
+        let err = reader.read().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn async_roundtrip() {
+        let keys = keys();
+
+        let mut writer = AsyncIndexWriter::new(Vec::new()).await.unwrap();
+        for key in &keys {
+            writer.write(key).await.unwrap();
+        }
+        let buffer = writer.into_inner();
+        assert_eq!(buffer, encode(&keys));
+
+        let mut reader = AsyncIndexReader::new(&buffer[..]).await.unwrap();
+        let mut decoded = Vec::new();
+        while let Some(key) = reader.read().await.unwrap() {
+            decoded.push(key);
+        }
+        assert_eq!(decoded, keys);
+
+        let truncated = [VERSION, 0x80, 0x80, 0x80, 0x80, 0x80, 0x20, 128];
+        let mut reader = AsyncIndexReader::new(&truncated[..]).await.unwrap();
+        let err = reader.read().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,500 @@
+//! Conversion to and from keys generated by the
+//! [fractional-indexing](https://www.npmjs.com/package/fractional-indexing)
+//! JavaScript package, e.g. `"a0"`, `"a0V"` or `"Zz"`.
+//!
+//! Those keys are base62 strings made of an integer part (a head letter
+//! that determines its length, followed by digits) and a fractional part.
+//! [from_js_key] maps each digit to a byte above the terminator, so that
+//! the resulting [FractionalIndex]es compare in the same order as the
+//! original strings. New keys can then be generated between them as usual.
+//!
+//! [to_js_key] is the reverse mapping. It only succeeds for indexes in the
+//! image of [from_js_key]: keys generated by the methods of
+//! [FractionalIndex], even between two imported keys, usually contain bytes
+//! outside of the mapped range and return [JsKeyError]. While some readers
+//! only understand the JavaScript scheme, generate keys with [new_between]
+//! instead, which produces the same keys as the package's
+//! `generateKeyBetween` and can always be exported.
+//!
+//! ```rust
+//! use fractional_index::{js::{self, from_js_key, to_js_key}, FractionalIndex};
+//!
+//! let a = from_js_key("a0").unwrap();
+//! let b = from_js_key("a1").unwrap();
+//! assert!(from_js_key("Zz").unwrap() < a);
+//! assert!(a < from_js_key("a0V").unwrap());
+//!
+//! let c = FractionalIndex::new_between(&a, &b).unwrap();
+//! assert!(a < c && c < b);
+//! assert!(to_js_key(&c).is_err());
+//!
+//! let c = js::new_between(Some(&a), Some(&b)).unwrap();
+//! assert!(a < c && c < b);
+//! assert_eq!(to_js_key(&c).unwrap(), "a0V");
+//! ```
+use crate::{fract_index::TERMINATOR, DecodeError, FractionalIndex};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+const DIGITS: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// The byte that base62 digit zero is mapped to. Every digit is mapped
+/// above the terminator, so that a key compares before its extensions.
+const DIGIT_OFFSET: u8 = TERMINATOR + 1;
+
+/// Returns the value of a base62 digit, in the package's `0-9A-Za-z` order.
+fn digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'A'..=b'Z' => Some(c - b'A' + 10),
+        b'a'..=b'z' => Some(c - b'a' + 36),
+        _ => None,
+    }
+}
+
+/// Returns the length of the integer part of a key starting with `head`.
+fn integer_length(head: u8) -> Option<usize> {
+    match head {
+        b'a'..=b'z' => Some((head - b'a') as usize + 2),
+        b'A'..=b'Z' => Some((b'Z' - head) as usize + 2),
+        _ => None,
+    }
+}
+
+/// Converts a key generated by the JavaScript `fractional-indexing`
+/// package into a [FractionalIndex] with the same relative order.
+///
+/// Returns an error if `key` is not a valid key in that scheme: it must be
+/// base62, start with a letter followed by the number of integer digits it
+/// implies, and not end its fractional part with `0`.
+pub fn from_js_key(key: &str) -> Result<FractionalIndex, DecodeError> {
+    let bytes = key.as_bytes();
+    let invalid = |position: usize| DecodeError::InvalidChars {
+        // Every byte before `position` is ASCII.
+        character: key[position..].chars().next().unwrap_or_default(),
+        position,
+    };
+
+    let head = *bytes.first().ok_or(DecodeError::EmptyString)?;
+    let integer_length = integer_length(head).ok_or_else(|| invalid(0))?;
+
+    let mut index = Vec::with_capacity(bytes.len() + 1);
+    for (position, c) in bytes.iter().enumerate() {
+        index.push(digit(*c).ok_or_else(|| invalid(position))? + DIGIT_OFFSET);
+    }
+
+    if bytes.len() < integer_length {
+        return Err(DecodeError::InvalidLength);
+    }
+    if bytes.len() > integer_length && bytes.last() == Some(&b'0') {
+        return Err(invalid(bytes.len() - 1));
+    }
+
+    index.push(TERMINATOR);
+    FractionalIndex::from_bytes(index)
+}
+
+/// The error returned by [to_js_key] for an index that has no equivalent
+/// key in the JavaScript scheme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsKeyError {
+    /// A byte of the index does not correspond to a base62 digit.
+    ByteOutOfRange { byte: u8, position: usize },
+    /// The digits do not form a valid key, e.g. because the integer part
+    /// is incomplete or the fractional part ends with `0`.
+    InvalidKey,
+    /// [new_between] was given bounds that are not in increasing order, or
+    /// asked for a key before the smallest key of the scheme.
+    NoKeyBetween,
+}
+
+impl Display for JsKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsKeyError::ByteOutOfRange { byte, position } => write!(
+                f,
+                "The byte {:#04x} at offset {} has no base62 equivalent.",
+                byte, position
+            ),
+            JsKeyError::InvalidKey => write!(
+                f,
+                "The fractional index does not correspond to a valid base62 key."
+            ),
+            JsKeyError::NoKeyBetween => write!(f, "There is no base62 key between the bounds."),
+        }
+    }
+}
+
+impl Error for JsKeyError {}
+
+/// Converts a [FractionalIndex] produced by [from_js_key] back into its key
+/// in the JavaScript scheme.
+///
+/// Returns an error for any other index; see the [module
+/// documentation](self).
+pub fn to_js_key(index: &FractionalIndex) -> Result<String, JsKeyError> {
+    let bytes = index.as_bytes();
+    let digits = &bytes[..bytes.len() - 1];
+
+    let mut key = String::with_capacity(digits.len());
+    for (position, &byte) in digits.iter().enumerate() {
+        let value = byte
+            .checked_sub(DIGIT_OFFSET)
+            .filter(|value| (*value as usize) < DIGITS.len())
+            .ok_or(JsKeyError::ByteOutOfRange { byte, position })?;
+        key.push(DIGITS[value as usize] as char);
+    }
+
+    // Reuse the validation of the import path.
+    from_js_key(&key).map_err(|_| JsKeyError::InvalidKey)?;
+    Ok(key)
+}
+
+/// Returns a key strictly between `a` and `b`, which are digit strings
+/// without trailing zeros. A missing `b` is an open upper bound.
+fn midpoint(a: &[u8], b: Option<&[u8]>) -> Vec<u8> {
+    if let Some(b) = b {
+        // Copy the common prefix, treating `a` as padded with zeros.
+        let mut n = 0;
+        while b.get(n) == Some(a.get(n).unwrap_or(&b'0')) {
+            n += 1;
+        }
+        if n > 0 {
+            let mut key = b[..n].to_vec();
+            key.extend(midpoint(a.get(n..).unwrap_or_default(), Some(&b[n..])));
+            return key;
+        }
+    }
+
+    let digit_a = a.first().and_then(|c| digit(*c)).unwrap_or(0) as usize;
+    let digit_b = b
+        .and_then(|b| b.first())
+        .and_then(|c| digit(*c))
+        .map_or(DIGITS.len(), usize::from);
+    if digit_b - digit_a > 1 {
+        vec![DIGITS[(digit_a + digit_b).div_ceil(2)]]
+    } else if let Some(b) = b.filter(|b| b.len() > 1) {
+        b[..1].to_vec()
+    } else {
+        let mut key = vec![DIGITS[digit_a]];
+        key.extend(midpoint(a.get(1..).unwrap_or_default(), None));
+        key
+    }
+}
+
+/// Returns the next integer part after `integer`, or None after the largest.
+fn increment_integer(integer: &[u8]) -> Option<Vec<u8>> {
+    let head = integer[0];
+    let mut digits = integer[1..].to_vec();
+    for d in digits.iter_mut().rev() {
+        match digit(*d).map(usize::from) {
+            Some(value) if value + 1 < DIGITS.len() => {
+                *d = DIGITS[value + 1];
+                return Some([&[head], &digits[..]].concat());
+            }
+            _ => *d = DIGITS[0],
+        }
+    }
+    // Every digit carried, so move to the next head letter.
+    let head = match head {
+        b'Z' => return Some(vec![b'a', DIGITS[0]]),
+        b'z' => return None,
+        _ => head + 1,
+    };
+    if head > b'a' {
+        digits.push(DIGITS[0]);
+    } else {
+        digits.pop();
+    }
+    Some([&[head], &digits[..]].concat())
+}
+
+/// Returns the integer part before `integer`, or None before the smallest.
+fn decrement_integer(integer: &[u8]) -> Option<Vec<u8>> {
+    let last = DIGITS[DIGITS.len() - 1];
+    let head = integer[0];
+    let mut digits = integer[1..].to_vec();
+    for d in digits.iter_mut().rev() {
+        match digit(*d) {
+            Some(value) if value > 0 => {
+                *d = DIGITS[usize::from(value) - 1];
+                return Some([&[head], &digits[..]].concat());
+            }
+            _ => *d = last,
+        }
+    }
+    // Every digit borrowed, so move to the previous head letter.
+    let head = match head {
+        b'a' => return Some(vec![b'Z', last]),
+        b'A' => return None,
+        _ => head - 1,
+    };
+    if head < b'Z' {
+        digits.push(last);
+    } else {
+        digits.pop();
+    }
+    Some([&[head], &digits[..]].concat())
+}
+
+/// Splits a valid key into its integer and fractional parts.
+fn split_key(key: &str) -> (&[u8], &[u8]) {
+    let bytes = key.as_bytes();
+    // `key` was validated by `to_js_key`, so its head is a letter.
+    let len = integer_length(bytes[0]).unwrap_or(bytes.len());
+    bytes.split_at(len)
+}
+
+fn js_key_between(a: Option<&str>, b: Option<&str>) -> Result<Vec<u8>, JsKeyError> {
+    match (a, b) {
+        (None, None) => Ok(vec![b'a', DIGITS[0]]),
+        (None, Some(b)) => {
+            let (integer, fraction) = split_key(b);
+            if integer[0] == b'A' && integer[1..].iter().all(|d| *d == DIGITS[0]) {
+                // The smallest integer part, so only the fraction can shrink.
+                if fraction.is_empty() {
+                    return Err(JsKeyError::NoKeyBetween);
+                }
+                return Ok([integer, &midpoint(&[], Some(fraction))[..]].concat());
+            }
+            if !fraction.is_empty() {
+                return Ok(integer.to_vec());
+            }
+            decrement_integer(integer).ok_or(JsKeyError::NoKeyBetween)
+        }
+        (Some(a), None) => {
+            let (integer, fraction) = split_key(a);
+            Ok(increment_integer(integer)
+                .unwrap_or_else(|| [integer, &midpoint(fraction, None)[..]].concat()))
+        }
+        (Some(a), Some(b)) => {
+            if a >= b {
+                return Err(JsKeyError::NoKeyBetween);
+            }
+            let (integer_a, fraction_a) = split_key(a);
+            let (integer_b, fraction_b) = split_key(b);
+            if integer_a == integer_b {
+                return Ok([integer_a, &midpoint(fraction_a, Some(fraction_b))[..]].concat());
+            }
+            match increment_integer(integer_a) {
+                Some(integer) if integer.as_slice() < b.as_bytes() => Ok(integer),
+                _ => Ok([integer_a, &midpoint(fraction_a, None)[..]].concat()),
+            }
+        }
+    }
+}
+
+/// Generates a [FractionalIndex] between `a` and `b` that [to_js_key] can
+/// export, producing the same key as the JavaScript package's
+/// `generateKeyBetween`. Either bound can be None to generate a key at that
+/// end; with neither, the result is the key `"a0"`.
+///
+/// Returns an error if either bound cannot be exported, if `a` is not less
+/// than `b`, or if there is no key before `b`.
+pub fn new_between(
+    a: Option<&FractionalIndex>,
+    b: Option<&FractionalIndex>,
+) -> Result<FractionalIndex, JsKeyError> {
+    let a = a.map(to_js_key).transpose()?;
+    let b = b.map(to_js_key).transpose()?;
+    let key = js_key_between(a.as_deref(), b.as_deref())?;
+    // Every byte of `key` is a base62 digit.
+    let key = String::from_utf8(key).map_err(|_| JsKeyError::InvalidKey)?;
+    from_js_key(&key).map_err(|_| JsKeyError::InvalidKey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keys generated by the JavaScript package, in order.
+    const KEYS: &[&str] = &[
+        "A00000000000000000000000001",
+        "Zy",
+        "Zz",
+        "a0",
+        "a0G",
+        "a0V",
+        "a0l",
+        "a1",
+        "a2",
+        "az",
+        "b10",
+        "b1000V",
+        "bzz",
+        "c000",
+        "zzzzzzzzzzzzzzzzzzzzzzzzzzz",
+    ];
+
+    #[test]
+    fn preserves_order() {
+        let indexes: Vec<FractionalIndex> =
+            KEYS.iter().map(|key| from_js_key(key).unwrap()).collect();
+        for pair in indexes.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+        assert_eq!(from_js_key("a0").unwrap().as_bytes(), &[165, 129, 128]);
+    }
+
+    #[test]
+    fn roundtrip() {
+        for key in KEYS {
+            assert_eq!(to_js_key(&from_js_key(key).unwrap()).unwrap(), *key);
+        }
+    }
+
+    #[test]
+    fn not_representable() {
+        assert_eq!(
+            to_js_key(&FractionalIndex::default()),
+            Err(JsKeyError::InvalidKey)
+        );
+
+        // Keys generated by FractionalIndex are usually not representable, even
+        // when all of their bytes are: these correspond to "b" and "a00".
+        let a = from_js_key("a0").unwrap();
+        let b = from_js_key("a1").unwrap();
+        assert_eq!(
+            to_js_key(&FractionalIndex::new_after(&a)),
+            Err(JsKeyError::InvalidKey)
+        );
+        assert_eq!(
+            to_js_key(&FractionalIndex::new_between(&a, &b).unwrap()),
+            Err(JsKeyError::InvalidKey)
+        );
+
+        let above = FractionalIndex::from_bytes(vec![165, 191, 128]).unwrap();
+        assert_eq!(
+            to_js_key(&above),
+            Err(JsKeyError::ByteOutOfRange {
+                byte: 191,
+                position: 1
+            })
+        );
+    }
+
+    fn between(a: Option<&str>, b: Option<&str>) -> Result<String, JsKeyError> {
+        let a = a.map(|a| from_js_key(a).unwrap());
+        let b = b.map(|b| from_js_key(b).unwrap());
+        new_between(a.as_ref(), b.as_ref()).map(|index| to_js_key(&index).unwrap())
+    }
+
+    #[test]
+    fn generate_between() {
+        // Expected keys from the JavaScript package's own tests.
+        assert_eq!(between(None, None).unwrap(), "a0");
+        assert_eq!(between(None, Some("a0")).unwrap(), "Zz");
+        assert_eq!(between(None, Some("Zz")).unwrap(), "Zy");
+        assert_eq!(between(Some("a0"), None).unwrap(), "a1");
+        assert_eq!(between(Some("a1"), None).unwrap(), "a2");
+        assert_eq!(between(Some("a0"), Some("a1")).unwrap(), "a0V");
+        assert_eq!(between(Some("a1"), Some("a2")).unwrap(), "a1V");
+        assert_eq!(between(Some("a0V"), Some("a1")).unwrap(), "a0l");
+        assert_eq!(between(Some("Zz"), Some("a0")).unwrap(), "ZzV");
+        assert_eq!(between(Some("Zz"), Some("a1")).unwrap(), "a0");
+        assert_eq!(between(None, Some("Y00")).unwrap(), "Xzzz");
+        assert_eq!(between(Some("bzz"), None).unwrap(), "c000");
+        assert_eq!(between(Some("a0"), Some("a0V")).unwrap(), "a0G");
+        assert_eq!(between(Some("a0"), Some("a0G")).unwrap(), "a08");
+        assert_eq!(between(Some("b125"), Some("b129")).unwrap(), "b127");
+        assert_eq!(between(Some("a0"), Some("a1V")).unwrap(), "a1");
+        assert_eq!(between(Some("Zz"), Some("a01")).unwrap(), "a0");
+        assert_eq!(between(None, Some("a0V")).unwrap(), "a0");
+        assert_eq!(between(None, Some("b999")).unwrap(), "b99");
+        assert_eq!(
+            between(None, Some("A00000000000000000000000001")).unwrap(),
+            "A00000000000000000000000000"
+        );
+        assert_eq!(
+            between(None, Some("A000000000000000000000000001")).unwrap(),
+            "A000000000000000000000000000V"
+        );
+        assert_eq!(
+            between(Some("zzzzzzzzzzzzzzzzzzzzzzzzzzy"), None).unwrap(),
+            "zzzzzzzzzzzzzzzzzzzzzzzzzzz"
+        );
+        assert_eq!(
+            between(Some("zzzzzzzzzzzzzzzzzzzzzzzzzzz"), None).unwrap(),
+            "zzzzzzzzzzzzzzzzzzzzzzzzzzzV"
+        );
+
+        assert_eq!(
+            between(Some("a1"), Some("a0")),
+            Err(JsKeyError::NoKeyBetween)
+        );
+        assert_eq!(
+            between(Some("a0"), Some("a0")),
+            Err(JsKeyError::NoKeyBetween)
+        );
+        assert_eq!(
+            between(None, Some("A00000000000000000000000000")),
+            Err(JsKeyError::NoKeyBetween)
+        );
+        assert_eq!(
+            new_between(Some(&FractionalIndex::default()), None),
+            Err(JsKeyError::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn generated_keys_export() {
+        // Repeatedly insert at the front, the back and between neighbours,
+        // and check that every key can be exported in order.
+        let mut indexes = vec![new_between(None, None).unwrap()];
+        for i in 0..500 {
+            let index = match i % 3 {
+                0 => new_between(None, indexes.first()),
+                1 => new_between(indexes.last(), None),
+                _ => {
+                    let mid = indexes.len() / 2;
+                    new_between(Some(&indexes[mid - 1]), Some(&indexes[mid]))
+                }
+            }
+            .unwrap();
+            let position = indexes.binary_search(&index).unwrap_err();
+            indexes.insert(position, index);
+        }
+
+        let keys: Vec<String> = indexes
+            .iter()
+            .map(|index| to_js_key(index).unwrap())
+            .collect();
+        for pair in keys.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+        for (key, index) in keys.iter().zip(&indexes) {
+            assert_eq!(&from_js_key(key).unwrap(), index);
+        }
+    }
+
+    #[test]
+    fn invalid_keys() {
+        assert_eq!(from_js_key(""), Err(DecodeError::EmptyString));
+        assert_eq!(
+            from_js_key("0a"),
+            Err(DecodeError::InvalidChars {
+                character: '0',
+                position: 0
+            })
+        );
+        assert_eq!(
+            from_js_key("a0-"),
+            Err(DecodeError::InvalidChars {
+                character: '-',
+                position: 2
+            })
+        );
+        assert_eq!(from_js_key("b1"), Err(DecodeError::InvalidLength));
+        assert_eq!(
+            from_js_key("a0V0"),
+            Err(DecodeError::InvalidChars {
+                character: '0',
+                position: 3
+            })
+        );
+        // A trailing zero in the integer part is fine.
+        assert!(from_js_key("a0").is_ok());
+    }
+}
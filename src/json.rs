@@ -0,0 +1,129 @@
+//! Conversions between [FractionalIndex] and [serde_json::Value], enabled by
+//! the `json` feature.
+//!
+//! Keys are represented by the hex string of [FractionalIndex::to_string],
+//! as with [stringify](crate::stringify), so code that edits dynamic JSON
+//! (e.g. webhook payloads or ETL jobs) reads and writes the same form as
+//! structs that use it.
+//!
+//! ```rust
+//! use fractional_index::FractionalIndex;
+//! use serde_json::json;
+//!
+//! let key = FractionalIndex::new_after(&FractionalIndex::default());
+//! let mut payload = json!({ "id": 7 });
+//! payload["position"] = key.to_json_value();
+//! assert_eq!(payload, json!({ "id": 7, "position": "8180" }));
+//!
+//! let decoded = FractionalIndex::try_from_value(&payload["position"]).unwrap();
+//! assert_eq!(decoded, key);
+//! assert!(FractionalIndex::try_from_value(&payload["id"]).is_err());
+//! ```
+use crate::{DecodeError, FractionalIndex};
+use serde_json::Value;
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+
+/// The error returned by [FractionalIndex::try_from_value].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValueError {
+    /// The value is not a string.
+    NotAString,
+    /// The value is a string that is not a valid key.
+    Invalid(DecodeError),
+}
+
+impl Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueError::NotAString => write!(
+                f,
+                "Attempted to decode a JSON value that is not a string as a fractional index."
+            ),
+            ValueError::Invalid(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ValueError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ValueError::NotAString => None,
+            ValueError::Invalid(error) => Some(error),
+        }
+    }
+}
+
+impl FractionalIndex {
+    /// Returns the hex string of this key as a JSON value.
+    pub fn to_json_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+
+    /// Decodes a key from a JSON string holding its hex encoding.
+    pub fn try_from_value(value: &Value) -> Result<Self, ValueError> {
+        let s = value.as_str().ok_or(ValueError::NotAString)?;
+        FractionalIndex::from_string(s).map_err(ValueError::Invalid)
+    }
+}
+
+impl From<&FractionalIndex> for Value {
+    fn from(index: &FractionalIndex) -> Self {
+        index.to_json_value()
+    }
+}
+
+impl From<FractionalIndex> for Value {
+    fn from(index: FractionalIndex) -> Self {
+        index.to_json_value()
+    }
+}
+
+impl TryFrom<&Value> for FractionalIndex {
+    type Error = ValueError;
+
+    fn try_from(value: &Value) -> Result<Self, ValueError> {
+        FractionalIndex::try_from_value(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn roundtrip() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_between(&a, &b).unwrap();
+        for key in [a, b, c] {
+            let value = Value::from(&key);
+            assert_eq!(value, json!(key.to_string()));
+            // Matches the serde representation of `stringify`.
+            assert_eq!(
+                value,
+                crate::stringify::serialize(&key, serde_json::value::Serializer).unwrap()
+            );
+            assert_eq!(FractionalIndex::try_from(&value), Ok(key));
+        }
+    }
+
+    #[test]
+    fn errors() {
+        for value in [json!(null), json!(128), json!([128]), json!({})] {
+            assert_eq!(
+                FractionalIndex::try_from_value(&value),
+                Err(ValueError::NotAString)
+            );
+        }
+        assert!(matches!(
+            FractionalIndex::try_from_value(&json!("81")),
+            Err(ValueError::Invalid(_))
+        ));
+        assert!(matches!(
+            FractionalIndex::try_from_value(&json!("zz80")),
+            Err(ValueError::Invalid(_))
+        ));
+    }
+}
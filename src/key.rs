@@ -0,0 +1,170 @@
+//! A trait over the kinds of key that order a list.
+//!
+//! [FractionalKey] is implemented by [FractionalIndex] and the deprecated
+//! [ZenoIndex](crate::ZenoIndex), so that code which generates and stores
+//! keys can be written once and used with either during a migration. It
+//! only requires constructing keys relative to other keys and encoding them
+//! as strings that sort in the same order, so it can also be implemented by
+//! key types that are strings natively.
+//!
+//! Both implementations use the string form of [FractionalIndex::to_string]
+//! (a [ZenoIndex](crate::ZenoIndex) is the bytes of a [FractionalIndex]
+//! without the terminator), so stored strings stay valid when switching
+//! from one to the other.
+//!
+//! ```rust
+//! use fractional_index::{FractionalIndex, FractionalKey};
+//!
+//! fn append<K: FractionalKey>(keys: &mut Vec<K>) -> String {
+//!     let key = K::new(keys.last(), None).unwrap();
+//!     let encoded = key.encode();
+//!     keys.push(key);
+//!     encoded
+//! }
+//!
+//! let mut keys: Vec<FractionalIndex> = Vec::new();
+//! let first = append(&mut keys);
+//! let second = append(&mut keys);
+//! assert!(first < second);
+//! assert_eq!(FractionalIndex::decode(&second).unwrap(), keys[1]);
+//! ```
+use crate::{DecodeError, FractionalIndex};
+
+/// A key that orders the elements of a list, and can be constructed before,
+/// after or between other keys.
+pub trait FractionalKey: Ord + Clone + Sized {
+    /// Returns the key of the first element added to an empty list.
+    fn initial() -> Self;
+
+    /// Returns a key that compares before this one.
+    fn before(&self) -> Self;
+
+    /// Returns a key that compares after this one.
+    fn after(&self) -> Self;
+
+    /// Returns a key that compares between `lower` and `upper`, or None if
+    /// `lower` is not less than `upper`.
+    fn between(lower: &Self, upper: &Self) -> Option<Self>;
+
+    /// Encodes this key as a string. The strings of two keys compare in the
+    /// same order as the keys.
+    fn encode(&self) -> String;
+
+    /// Decodes a key from a string returned by [FractionalKey::encode].
+    fn decode(s: &str) -> Result<Self, DecodeError>;
+
+    /// Returns a key between `lower` and `upper`, which are unbounded if
+    /// None, as [FractionalIndex::new] does.
+    fn new(lower: Option<&Self>, upper: Option<&Self>) -> Option<Self> {
+        match (lower, upper) {
+            (Some(lower), Some(upper)) => Self::between(lower, upper),
+            (Some(lower), None) => Some(lower.after()),
+            (None, Some(upper)) => Some(upper.before()),
+            (None, None) => Some(Self::initial()),
+        }
+    }
+}
+
+impl FractionalKey for FractionalIndex {
+    fn initial() -> Self {
+        FractionalIndex::default()
+    }
+
+    fn before(&self) -> Self {
+        FractionalIndex::new_before(self)
+    }
+
+    fn after(&self) -> Self {
+        FractionalIndex::new_after(self)
+    }
+
+    fn between(lower: &Self, upper: &Self) -> Option<Self> {
+        FractionalIndex::new_between(lower, upper)
+    }
+
+    fn encode(&self) -> String {
+        self.to_string()
+    }
+
+    fn decode(s: &str) -> Result<Self, DecodeError> {
+        FractionalIndex::from_string(s)
+    }
+}
+
+#[allow(deprecated)]
+impl FractionalKey for crate::ZenoIndex {
+    fn initial() -> Self {
+        crate::ZenoIndex::default()
+    }
+
+    fn before(&self) -> Self {
+        crate::ZenoIndex::new_before(self)
+    }
+
+    fn after(&self) -> Self {
+        crate::ZenoIndex::new_after(self)
+    }
+
+    fn between(lower: &Self, upper: &Self) -> Option<Self> {
+        crate::ZenoIndex::new_between(lower, upper)
+    }
+
+    fn encode(&self) -> String {
+        let mut bytes = self.as_bytes().to_vec();
+        bytes.push(crate::zeno_index::MAGIC_CEIL);
+        FractionalIndex::from_bytes_unchecked(bytes).to_string()
+    }
+
+    fn decode(s: &str) -> Result<Self, DecodeError> {
+        let mut bytes = FractionalIndex::from_string(s)?.into_bytes();
+        bytes.pop();
+        Ok(crate::ZenoIndex::from_bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ZenoIndex;
+
+    /// Builds a list by inserting at the front, back and middle, and checks
+    /// that the keys and their encodings are in order.
+    fn build<K: FractionalKey + std::fmt::Debug>() -> Vec<K> {
+        let mut keys = vec![K::new(None, None).unwrap()];
+        for i in 0..60 {
+            let position = match i % 3 {
+                0 => 0,
+                1 => keys.len(),
+                _ => keys.len() / 2,
+            };
+            let lower = position.checked_sub(1).map(|i| &keys[i]);
+            let key = K::new(lower, keys.get(position)).unwrap();
+            keys.insert(position, key);
+        }
+        for pair in keys.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].encode() < pair[1].encode());
+            assert!(K::between(&pair[1], &pair[0]).is_none());
+        }
+        for key in &keys {
+            assert_eq!(&K::decode(&key.encode()).unwrap(), key);
+        }
+        keys
+    }
+
+    #[test]
+    fn implementations() {
+        let indexes: Vec<FractionalIndex> = build();
+        let zenos: Vec<ZenoIndex> = build();
+
+        // Strings encoded from either kind decode as the other, in order.
+        let migrated: Vec<FractionalIndex> = zenos
+            .iter()
+            .map(|zeno| FractionalIndex::decode(&zeno.encode()).unwrap())
+            .collect();
+        assert!(migrated.windows(2).all(|pair| pair[0] < pair[1]));
+        for index in &indexes {
+            assert!(ZenoIndex::decode(&index.encode()).is_ok());
+        }
+    }
+}
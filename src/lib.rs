@@ -1,10 +1,156 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+// The test harness references tests inside deprecated modules.
+#![cfg_attr(test, allow(deprecated))]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "defmt")]
+mod defmt_format;
+#[cfg(feature = "alloc")]
 mod hex;
+#[cfg(feature = "minicbor")]
+mod minicbor_codec;
+#[cfg(feature = "std")]
+mod rng;
+#[cfg(feature = "rocket")]
+mod rocket_types;
+#[cfg(feature = "std")]
+mod sha256;
+#[cfg(feature = "alloc")]
+mod spacing;
+#[cfg(feature = "sqlx")]
+mod sqlx_types;
 #[cfg(feature = "serde")]
 pub mod stringify;
+#[cfg(feature = "std")]
+mod varint;
 
+mod assertions;
+mod error;
+#[cfg(feature = "alloc")]
 mod fract_index;
+mod instrument;
+#[cfg(kani)]
+mod proofs;
+
+#[cfg(feature = "allocator_api")]
+pub mod allocator;
+#[cfg(feature = "std")]
+pub mod alphabet;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "bevy")]
+pub mod bevy;
+#[cfg(feature = "std")]
+pub mod borrowed;
+#[cfg(feature = "std")]
+pub mod bounds;
+#[cfg(feature = "std")]
+pub mod btree;
+#[cfg(feature = "std")]
+pub mod checksum;
+#[cfg(feature = "test-util")]
+pub mod concurrency;
+#[cfg(feature = "serde")]
+pub mod csv;
+#[cfg(feature = "std")]
+pub mod cursor;
+#[cfg(feature = "std")]
+pub mod desc;
+#[cfg(feature = "std")]
+pub mod digest;
+#[cfg(feature = "std")]
+pub mod doc_id;
+#[cfg(feature = "std")]
+pub mod document;
+#[cfg(feature = "std")]
+pub mod duplicates;
+#[cfg(feature = "std")]
+pub mod epoch;
+#[cfg(feature = "fdb")]
+pub mod fdb;
+pub mod fixed;
+pub mod format;
+#[cfg(feature = "std")]
+pub mod front_coding;
+#[cfg(feature = "std")]
+pub mod gaps;
+#[cfg(feature = "indexmap")]
+pub mod index_map;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "std")]
+pub mod js;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "std")]
+pub mod key;
+#[cfg(feature = "std")]
+pub mod limits;
+#[cfg(feature = "std")]
+pub mod ltree;
+#[cfg(feature = "std")]
+pub mod merge;
+#[cfg(feature = "std")]
+pub mod move_op;
+#[cfg(feature = "std")]
+pub mod ordered;
+#[cfg(feature = "std")]
+pub mod ordered_list;
+#[cfg(feature = "std")]
+pub mod ot;
+#[cfg(feature = "std")]
+pub mod outline;
+#[cfg(feature = "std")]
+pub mod packed;
+#[cfg(feature = "imbl")]
+pub mod persistent;
+#[cfg(feature = "std")]
+pub mod pg_copy;
+#[cfg(feature = "std")]
+pub mod position;
+#[cfg(feature = "std")]
+pub mod rebalance;
+#[cfg(feature = "std")]
+pub mod reconcile;
+#[cfg(feature = "std")]
+pub mod reorder;
+#[cfg(feature = "std")]
+pub mod replication;
+#[cfg(feature = "bytes")]
+pub mod shared;
+#[cfg(feature = "std")]
+pub mod shared_list;
+#[cfg(feature = "slotmap")]
+pub mod slot_order;
+#[cfg(feature = "std")]
+pub mod sorted_vec;
+#[cfg(feature = "std")]
+pub mod sql;
+#[cfg(feature = "std")]
+pub mod stable_cursor;
+#[cfg(feature = "std")]
+pub mod store;
+#[cfg(feature = "std")]
+pub mod strategy;
+#[cfg(feature = "std")]
+pub mod tagged;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "std")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod tiered;
+#[cfg(feature = "std")]
+pub mod transform;
+#[cfg(feature = "std")]
+pub mod versioned;
+#[cfg(feature = "warp")]
+pub mod warp;
+#[cfg(feature = "test-util")]
+pub mod workload;
 
 #[cfg(feature = "serde")]
 #[deprecated(
@@ -13,9 +159,25 @@ mod fract_index;
 )]
 pub mod lexico;
 
+#[cfg(feature = "alloc")]
 #[deprecated(since = "2.0.0", note = "Use FractionalIndex instead")]
 pub mod zeno_index;
 
-pub use fract_index::FractionalIndex;
+#[cfg(feature = "std")]
+pub use desc::Desc;
+pub use error::DecodeError;
+#[cfg(feature = "alloc")]
+pub use fract_index::{cmp_hex, DisplayPath, FractionalIndex, OrDefault};
+#[cfg(feature = "derive")]
+pub use fractional_index_derive::FractionallyOrdered;
+#[cfg(feature = "std")]
+pub use key::FractionalKey;
+#[cfg(feature = "std")]
+pub use ordered::FractionallyOrdered;
+#[cfg(feature = "std")]
+pub use ordered_list::OrderedList;
+#[cfg(feature = "std")]
+pub use tiered::Tiered;
 #[allow(deprecated)]
+#[cfg(feature = "alloc")]
 pub use zeno_index::ZenoIndex;
@@ -0,0 +1,84 @@
+//! A limit on the length of decoded keys.
+//!
+//! Keys received from untrusted sources can be arbitrarily long. Within a
+//! call to [with_max_len], every decoder in this crate rejects longer keys
+//! on the current thread with [DecodeError::TooLong] before allocating
+//! them: [FractionalIndex::from_bytes](crate::FractionalIndex::from_bytes), [FractionalIndex::from_string](crate::FractionalIndex::from_string), the
+//! serde deserializers (including [stringify](crate::stringify)), and the
+//! [io](crate::io) readers. The limit only applies inside the call, so a
+//! limit set by one library does not change how another decodes keys.
+//!
+//! Keys generated by this crate are not limited, and
+//! [FractionalIndex::from_bytes_unchecked](crate::FractionalIndex::from_bytes_unchecked) does not check the limit.
+//!
+//! ```rust
+//! use fractional_index::{limits, DecodeError, FractionalIndex};
+//!
+//! let decoded = limits::with_max_len(Some(256), || FractionalIndex::from_bytes(vec![1; 1024]));
+//! assert_eq!(decoded, Err(DecodeError::TooLong { max: 256 }));
+//! assert_eq!(limits::max_len(), None);
+//! ```
+use crate::DecodeError;
+use std::cell::Cell;
+
+/// The most bytes preallocated for a key from the length its encoding
+/// declares, as in serde's `size_hint::cautious`. Longer keys grow the
+/// buffer as their bytes arrive.
+pub(crate) const MAX_PREALLOCATION: usize = 4096;
+
+thread_local! {
+    static MAX_LEN: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Calls `f` with a maximum length in bytes for the keys decoded on this
+/// thread, or without a limit, and returns its result. The previous limit
+/// is restored when `f` returns.
+pub fn with_max_len<R>(max_len: Option<usize>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<usize>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            MAX_LEN.with(|max| max.set(self.0));
+        }
+    }
+
+    let _restore = Restore(MAX_LEN.with(|max| max.replace(max_len)));
+    f()
+}
+
+/// Returns the maximum length in bytes of keys decoded on this thread, if
+/// [with_max_len] set one.
+pub fn max_len() -> Option<usize> {
+    MAX_LEN.with(Cell::get)
+}
+
+/// Checks a key length against the limit.
+pub(crate) fn check_len(len: usize) -> Result<(), DecodeError> {
+    check_len_against(len, max_len())
+}
+
+/// Checks a key length against the given limit.
+pub(crate) fn check_len_against(len: usize, max_len: Option<usize>) -> Result<(), DecodeError> {
+    match max_len {
+        Some(max) if len > max => Err(DecodeError::TooLong { max }),
+        _ => Ok(()),
+    }
+}
+
+/// Collects the bytes of a key serialized as a sequence, stopping as soon
+/// as the limit is exceeded.
+#[cfg(feature = "serde")]
+pub(crate) fn collect_seq<'de, A: serde::de::SeqAccess<'de>>(
+    mut seq: A,
+) -> Result<Vec<u8>, A::Error> {
+    use serde::de::Error;
+
+    let hint = seq.size_hint().unwrap_or(0);
+    check_len(hint).map_err(A::Error::custom)?;
+    let mut bytes = Vec::with_capacity(hint.min(MAX_PREALLOCATION));
+    while let Some(byte) = seq.next_element()? {
+        bytes.push(byte);
+        check_len(bytes.len()).map_err(A::Error::custom)?;
+    }
+    Ok(bytes)
+}
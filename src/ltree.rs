@@ -0,0 +1,113 @@
+//! Conversion of [FractionalIndex]es to and from Postgres `ltree` paths.
+//!
+//! Each byte of the key becomes a label of three decimal digits, so the key
+//! `[129, 127, 128]` becomes the path `129.127.128`. `ltree` compares paths
+//! label by label, and labels of equal length compare like the numbers
+//! they contain, so paths sort in the same order as the keys. This allows
+//! `ltree` columns, their GiST indexes and operators such as `@>` to be
+//! used over orderings.
+//!
+//! ```rust
+//! use fractional_index::{ltree, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//! assert_eq!(ltree::to_ltree(&a), "128");
+//! assert_eq!(ltree::to_ltree(&b), "129.128");
+//! assert_eq!(ltree::from_ltree("129.128").unwrap(), b);
+//! ```
+//!
+//! The path can be bound as text and cast, e.g.
+//! `INSERT INTO item (position) VALUES ($1::ltree)`, and read back with
+//! `SELECT position::text`.
+use crate::{DecodeError, FractionalIndex};
+use std::{convert::TryFrom, fmt::Write};
+
+/// The number of digits in each label.
+const WIDTH: usize = 3;
+
+/// Returns the `ltree` path of `index`.
+pub fn to_ltree(index: &FractionalIndex) -> String {
+    let mut path = String::with_capacity(index.len() * (WIDTH + 1));
+    for (i, byte) in index.as_bytes().iter().enumerate() {
+        if i > 0 {
+            path.push('.');
+        }
+        write!(path, "{:03}", byte).expect("Writing to a String does not fail.");
+    }
+    path
+}
+
+/// Parses a path returned by [to_ltree].
+///
+/// Every label must have exactly three digits, since shorter labels would
+/// not sort in the same order as the key.
+pub fn from_ltree(path: &str) -> Result<FractionalIndex, DecodeError> {
+    if path.is_empty() {
+        return Err(DecodeError::EmptyString);
+    }
+
+    let mut bytes = Vec::with_capacity(path.len().div_ceil(WIDTH + 1));
+    let mut position = 0;
+    for label in path.split('.') {
+        if let Some((offset, character)) = label
+            .char_indices()
+            .find(|(_, character)| !character.is_ascii_digit())
+        {
+            return Err(DecodeError::InvalidChars {
+                character,
+                position: position + offset,
+            });
+        }
+        if label.len() != WIDTH {
+            return Err(DecodeError::InvalidLength);
+        }
+        let value: u16 = label.parse().expect("The label is three ASCII digits.");
+        let byte = u8::try_from(value).map_err(|_| DecodeError::DigitOutOfRange)?;
+        bytes.push(byte);
+        position += label.len() + 1;
+    }
+
+    FractionalIndex::from_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preserves_order() {
+        let mut keys = FractionalIndex::spaced(None, None, 300).unwrap();
+        keys.push(FractionalIndex::new_before(&keys[0]));
+        keys.push(FractionalIndex::new_between(&keys[0], &keys[1]).unwrap());
+        keys.sort();
+
+        let paths: Vec<String> = keys.iter().map(to_ltree).collect();
+        for pair in paths.windows(2) {
+            // Compare label by label, as ltree does.
+            assert!(pair[0].split('.').lt(pair[1].split('.')));
+        }
+        for (key, path) in keys.iter().zip(&paths) {
+            assert_eq!(from_ltree(path).as_ref(), Ok(key));
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(from_ltree(""), Err(DecodeError::EmptyString));
+        assert_eq!(
+            from_ltree("129.1x8"),
+            Err(DecodeError::InvalidChars {
+                character: 'x',
+                position: 5
+            })
+        );
+        assert_eq!(from_ltree("129.28"), Err(DecodeError::InvalidLength));
+        assert_eq!(from_ltree("129..128"), Err(DecodeError::InvalidLength));
+        assert_eq!(from_ltree("256.128"), Err(DecodeError::DigitOutOfRange));
+        assert_eq!(
+            from_ltree("128.129"),
+            Err(DecodeError::MissingTerminator { found: Some(129) })
+        );
+    }
+}
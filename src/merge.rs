@@ -0,0 +1,275 @@
+//! Merging of orderings exported by several replicas.
+//!
+//! Replicas that have been offline for a long time each hold their own
+//! ordering of a list. [merge_replicas] combines their exported
+//! `(index, id, replica)` streams, each sorted by index, into one
+//! ordering. Entries keep their keys, so the merged order agrees with the
+//! order of every stream, except where two entries have the same key: all
+//! but the first of them are given new keys in the gap after it.
+//!
+//! ```rust
+//! use fractional_index::{merge::merge_replicas, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//!
+//! // Both replicas appended an item after `a` while offline.
+//! let phone = vec![(a.clone(), "shared", 1), (b.clone(), "from phone", 1)];
+//! let laptop = vec![(a.clone(), "shared", 2), (b.clone(), "from laptop", 2)];
+//!
+//! let merged = merge_replicas(vec![phone, laptop]).unwrap();
+//! let ids: Vec<&str> = merged.iter().map(|entry| entry.id).collect();
+//! assert_eq!(ids, vec!["shared", "from phone", "from laptop"]);
+//! assert!(!merged[1].rekeyed && merged[2].rekeyed);
+//! assert!(merged[1].index < merged[2].index);
+//! ```
+use crate::{instrument, FractionalIndex};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeSet, BinaryHeap};
+use std::fmt::{self, Display};
+
+/// An entry of the merged ordering.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Merged<Id, R> {
+    pub index: FractionalIndex,
+    pub id: Id,
+    /// The replica the entry was taken from.
+    pub replica: R,
+    /// True if `index` differs from the index in the replica's stream.
+    pub rekeyed: bool,
+}
+
+/// The error returned by [merge_replicas] when a stream is not sorted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnsortedStream {
+    /// The position of the stream among the streams passed in.
+    pub stream: usize,
+    /// The position in the stream of the first index that is less than
+    /// the index before it.
+    pub position: usize,
+}
+
+impl Display for UnsortedStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The index at position {} of stream {} is less than the index before it.",
+            self.position, self.stream
+        )
+    }
+}
+
+impl std::error::Error for UnsortedStream {}
+
+/// The next entry of a stream, ordered for a min-heap by index, then
+/// replica, then the position of the stream.
+struct Head<Id, R> {
+    entry: (FractionalIndex, Id, R),
+    stream: usize,
+    position: usize,
+}
+
+impl<Id, R: Ord> Head<Id, R> {
+    fn sort_key(&self) -> (&FractionalIndex, &R, usize) {
+        (&self.entry.0, &self.entry.2, self.stream)
+    }
+}
+
+impl<Id, R: Ord> PartialEq for Head<Id, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.sort_key() == other.sort_key()
+    }
+}
+
+impl<Id, R: Ord> Eq for Head<Id, R> {}
+
+impl<Id, R: Ord> PartialOrd for Head<Id, R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Id, R: Ord> Ord for Head<Id, R> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+/// Merges streams of `(index, id, replica)` entries, each in ascending
+/// order of index, into one ordering.
+///
+/// Entries with equal indices are ordered by replica, then by the position
+/// of their stream. The first of them keeps its index and the others are
+/// given evenly spaced indices between it and the next distinct index, so
+/// only colliding entries are rekeyed. An id that appears more than once
+/// is kept only at its first position in the merged order.
+///
+/// Streams are consumed lazily, so an unsorted stream is only detected
+/// when its out-of-order entry is reached.
+pub fn merge_replicas<S, Id, R>(
+    streams: impl IntoIterator<Item = S>,
+) -> Result<Vec<Merged<Id, R>>, UnsortedStream>
+where
+    S: IntoIterator<Item = (FractionalIndex, Id, R)>,
+    Id: Ord + Clone,
+    R: Ord,
+{
+    let mut streams: Vec<S::IntoIter> = streams.into_iter().map(IntoIterator::into_iter).collect();
+    let mut heap = BinaryHeap::new();
+    for (stream, iter) in streams.iter_mut().enumerate() {
+        if let Some(entry) = iter.next() {
+            heap.push(Reverse(Head {
+                entry,
+                stream,
+                position: 0,
+            }));
+        }
+    }
+
+    let mut seen = BTreeSet::new();
+    let mut merged: Vec<(FractionalIndex, Id, R)> = Vec::new();
+    while let Some(Reverse(head)) = heap.pop() {
+        let Head {
+            entry,
+            stream,
+            position,
+        } = head;
+        if let Some(next) = streams[stream].next() {
+            if next.0 < entry.0 {
+                return Err(UnsortedStream {
+                    stream,
+                    position: position + 1,
+                });
+            }
+            heap.push(Reverse(Head {
+                entry: next,
+                stream,
+                position: position + 1,
+            }));
+        }
+        if seen.insert(entry.1.clone()) {
+            merged.push(entry);
+        }
+    }
+
+    let mut result = Vec::with_capacity(merged.len());
+    let mut entries = merged.into_iter().peekable();
+    while let Some((index, id, replica)) = entries.next() {
+        let mut collisions = Vec::new();
+        while let Some(entry) = entries.next_if(|(other, _, _)| *other == index) {
+            collisions.push(entry);
+        }
+        let upper = entries.peek().map(|(index, _, _)| index);
+        let keys = FractionalIndex::spaced(Some(&index), upper, collisions.len())
+            .expect("Merged indices are in ascending order.");
+        instrument::collisions("merge_replicas", collisions.len());
+        result.push(Merged {
+            index,
+            id,
+            replica,
+            rekeyed: false,
+        });
+        result.extend(
+            collisions
+                .into_iter()
+                .zip(keys)
+                .map(|((_, id, replica), index)| Merged {
+                    index,
+                    id,
+                    replica,
+                    rekeyed: true,
+                }),
+        );
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(
+        keys: &[FractionalIndex],
+        ids: &str,
+        replica: u32,
+    ) -> Vec<(FractionalIndex, char, u32)> {
+        keys.iter()
+            .cloned()
+            .zip(ids.chars())
+            .map(|(key, id)| (key, id, replica))
+            .collect()
+    }
+
+    #[test]
+    fn merges_in_order() {
+        let keys = FractionalIndex::spaced(None, None, 6).unwrap();
+        let streams = vec![
+            stream(
+                &[keys[0].clone(), keys[3].clone(), keys[4].clone()],
+                "adx",
+                3,
+            ),
+            stream(
+                &[keys[1].clone(), keys[4].clone(), keys[5].clone()],
+                "bef",
+                1,
+            ),
+            Vec::new(),
+            stream(&[keys[2].clone(), keys[4].clone()], "cy", 2),
+        ];
+        let merged = merge_replicas(streams).unwrap();
+
+        let ids: String = merged.iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, "abcdeyxf");
+        let rekeyed: Vec<char> = merged
+            .iter()
+            .filter(|entry| entry.rekeyed)
+            .map(|entry| entry.id)
+            .collect();
+        assert_eq!(rekeyed, vec!['y', 'x']);
+        for pair in merged.windows(2) {
+            assert!(pair[0].index < pair[1].index);
+        }
+        for entry in merged.iter().filter(|entry| !entry.rekeyed) {
+            assert_eq!(entry.index, keys["abcdef".find(entry.id).unwrap()]);
+        }
+    }
+
+    #[test]
+    fn duplicate_ids() {
+        let keys = FractionalIndex::spaced(None, None, 3).unwrap();
+        let streams = vec![
+            stream(&keys, "abc", 2),
+            stream(&[keys[0].clone(), keys[2].clone()], "ba", 1),
+        ];
+        let merged = merge_replicas(streams).unwrap();
+        let entries: Vec<(char, u32, bool)> = merged
+            .iter()
+            .map(|entry| (entry.id, entry.replica, entry.rekeyed))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![('b', 1, false), ('a', 2, true), ('c', 2, false)]
+        );
+    }
+
+    #[test]
+    fn unsorted() {
+        let keys = FractionalIndex::spaced(None, None, 3).unwrap();
+        let streams = vec![
+            stream(&keys, "abc", 1),
+            stream(
+                &[keys[0].clone(), keys[2].clone(), keys[1].clone()],
+                "xyz",
+                2,
+            ),
+        ];
+        assert_eq!(
+            merge_replicas(streams),
+            Err(UnsortedStream {
+                stream: 1,
+                position: 2
+            })
+        );
+    }
+}
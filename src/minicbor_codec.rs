@@ -0,0 +1,89 @@
+//! [minicbor] support, for peers that encode with minicbor rather than
+//! serde.
+//!
+//! Keys are encoded as a CBOR byte string (major type 2) holding the bytes
+//! returned by `as_bytes`, and decoded with `from_bytes`, so malformed keys
+//! are rejected when they are decoded.
+
+use crate::fixed::FixedFractionalIndex;
+use minicbor::{
+    decode::{self, Decoder},
+    encode::{self, Encoder, Write},
+    Decode, Encode,
+};
+
+fn invalid(position: usize) -> decode::Error {
+    decode::Error::message("invalid fractional index").at(position)
+}
+
+impl<C, const N: usize> Encode<C> for FixedFractionalIndex<N> {
+    fn encode<W: Write>(
+        &self,
+        e: &mut Encoder<W>,
+        _: &mut C,
+    ) -> Result<(), encode::Error<W::Error>> {
+        e.bytes(self.as_bytes())?.ok()
+    }
+}
+
+impl<'b, C, const N: usize> Decode<'b, C> for FixedFractionalIndex<N> {
+    fn decode(d: &mut Decoder<'b>, _: &mut C) -> Result<Self, decode::Error> {
+        let position = d.position();
+        FixedFractionalIndex::from_bytes(d.bytes()?).map_err(|_| invalid(position))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<C> Encode<C> for crate::FractionalIndex {
+    fn encode<W: Write>(
+        &self,
+        e: &mut Encoder<W>,
+        _: &mut C,
+    ) -> Result<(), encode::Error<W::Error>> {
+        e.bytes(self.as_bytes())?.ok()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'b, C> Decode<'b, C> for crate::FractionalIndex {
+    fn decode(d: &mut Decoder<'b>, _: &mut C) -> Result<Self, decode::Error> {
+        let position = d.position();
+        crate::FractionalIndex::from_bytes(d.bytes()?.to_vec()).map_err(|_| invalid(position))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{fixed::FixedFractionalIndex, FractionalIndex};
+
+    fn encode<T: minicbor::Encode<()>>(value: &T) -> Vec<u8> {
+        let mut buf = [0u8; 16];
+        let mut remaining = &mut buf[..];
+        minicbor::encode(value, &mut remaining).unwrap();
+        let len = 16 - remaining.len();
+        buf[..len].to_vec()
+    }
+
+    #[test]
+    fn round_trip() {
+        let key = FractionalIndex::new_after(&FractionalIndex::default());
+        let encoded = encode(&key);
+        // A byte string of length 2.
+        assert_eq!(encoded, vec![0x42, 129, 128]);
+        assert_eq!(minicbor::decode::<FractionalIndex>(&encoded).unwrap(), key);
+
+        let fixed: FixedFractionalIndex<4> = minicbor::decode(&encoded).unwrap();
+        assert_eq!(fixed.as_bytes(), key.as_bytes());
+        assert_eq!(encode(&fixed), encoded);
+    }
+
+    #[test]
+    fn invalid() {
+        // Missing terminator.
+        assert!(minicbor::decode::<FractionalIndex>(&[0x41, 129]).is_err());
+        // Not a byte string.
+        assert!(minicbor::decode::<FractionalIndex>(&[0x01]).is_err());
+        // Longer than the fixed capacity.
+        assert!(minicbor::decode::<FixedFractionalIndex<2>>(&[0x43, 129, 129, 128]).is_err());
+    }
+}
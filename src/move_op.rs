@@ -0,0 +1,261 @@
+//! Reordering operations that can be recorded, inverted and replayed, for
+//! implementing undo and redo on top of an [OrderedList].
+//!
+//! ```rust
+//! use fractional_index::{move_op::{MoveLog, MoveOp}, FractionalIndex, OrderedList};
+//!
+//! let mut list = OrderedList::new();
+//! let a = list.push_back("a");
+//! let b = list.push_back("b");
+//!
+//! // Move "a" after "b".
+//! let mut log = MoveLog::new();
+//! let op = MoveOp::new("a", a.clone(), FractionalIndex::new_after(&b));
+//! op.apply(&mut list).unwrap();
+//! log.push(op);
+//! assert_eq!(list.values().copied().collect::<Vec<_>>(), vec!["b", "a"]);
+//!
+//! log.undo().unwrap().apply(&mut list).unwrap();
+//! assert_eq!(list.values().copied().collect::<Vec<_>>(), vec!["a", "b"]);
+//!
+//! log.redo().unwrap().apply(&mut list).unwrap();
+//! assert_eq!(list.values().copied().collect::<Vec<_>>(), vec!["b", "a"]);
+//! ```
+use crate::{FractionalIndex, OrderedList};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+/// Moves the element identified by `id` from `from_index` to `to_index`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MoveOp<Id> {
+    pub id: Id,
+    pub from_index: FractionalIndex,
+    pub to_index: FractionalIndex,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MoveError {
+    /// The list has no element at `from_index`.
+    MissingSource,
+    /// The list already has an element at `to_index`.
+    DestinationOccupied,
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::MissingSource => {
+                write!(f, "The list has no element at the source index.")
+            }
+            MoveError::DestinationOccupied => {
+                write!(
+                    f,
+                    "The list already has an element at the destination index."
+                )
+            }
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+impl<Id> MoveOp<Id> {
+    pub fn new(id: Id, from_index: FractionalIndex, to_index: FractionalIndex) -> Self {
+        MoveOp {
+            id,
+            from_index,
+            to_index,
+        }
+    }
+
+    /// Moves the element at `from_index` to `to_index`. The list is left
+    /// unchanged if an error is returned.
+    pub fn apply<T, S>(&self, list: &mut OrderedList<T, S>) -> Result<(), MoveError> {
+        if !list.contains_key(&self.from_index) {
+            return Err(MoveError::MissingSource);
+        }
+        if self.from_index == self.to_index {
+            return Ok(());
+        }
+        if list.contains_key(&self.to_index) {
+            return Err(MoveError::DestinationOccupied);
+        }
+
+        let value = list
+            .remove(&self.from_index)
+            .expect("The source was checked above.");
+        list.insert(self.to_index.clone(), value);
+        Ok(())
+    }
+}
+
+impl<Id: Clone> MoveOp<Id> {
+    /// Returns the operation that reverses this one.
+    pub fn invert(&self) -> Self {
+        MoveOp {
+            id: self.id.clone(),
+            from_index: self.to_index.clone(),
+            to_index: self.from_index.clone(),
+        }
+    }
+}
+
+/// A history of applied [MoveOp]s with undo and redo stacks.
+///
+/// The log does not hold the list. [MoveLog::undo] and [MoveLog::redo]
+/// return the operation to apply to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MoveLog<Id> {
+    done: Vec<MoveOp<Id>>,
+    undone: Vec<MoveOp<Id>>,
+}
+
+impl<Id> Default for MoveLog<Id> {
+    fn default() -> Self {
+        MoveLog {
+            done: Vec::new(),
+            undone: Vec::new(),
+        }
+    }
+}
+
+impl<Id: Clone> MoveLog<Id> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an operation that has been applied. This clears the redo
+    /// stack.
+    pub fn push(&mut self, op: MoveOp<Id>) {
+        self.done.push(op);
+        self.undone.clear();
+    }
+
+    /// Returns the operation that undoes the most recent operation, or
+    /// None if there is nothing to undo.
+    pub fn undo(&mut self) -> Option<MoveOp<Id>> {
+        let op = self.done.pop()?;
+        let inverse = op.invert();
+        self.undone.push(op);
+        Some(inverse)
+    }
+
+    /// Returns the most recently undone operation, to be applied again, or
+    /// None if there is nothing to redo.
+    pub fn redo(&mut self) -> Option<MoveOp<Id>> {
+        let op = self.undone.pop()?;
+        self.done.push(op.clone());
+        Some(op)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Returns the applied operations, oldest first.
+    pub fn history(&self) -> &[MoveOp<Id>] {
+        &self.done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::Boundary;
+
+    fn values(list: &OrderedList<char>) -> String {
+        list.values().collect()
+    }
+
+    #[test]
+    fn apply_and_invert() {
+        let mut list = OrderedList::new();
+        let a = list.push_back('a');
+        let b = list.push_back('b');
+        let c = list.push_back('c');
+
+        let op = MoveOp::new(
+            'a',
+            a.clone(),
+            FractionalIndex::new_between(&b, &c).unwrap(),
+        );
+        op.apply(&mut list).unwrap();
+        assert_eq!(values(&list), "bac");
+
+        assert_eq!(op.apply(&mut list), Err(MoveError::MissingSource));
+        assert_eq!(
+            MoveOp::new('b', b.clone(), c.clone()).apply(&mut list),
+            Err(MoveError::DestinationOccupied)
+        );
+        assert_eq!(values(&list), "bac");
+
+        op.invert().apply(&mut list).unwrap();
+        assert_eq!(values(&list), "abc");
+        assert_eq!(op.invert().invert(), op);
+    }
+
+    #[test]
+    fn apply_with_strategy() {
+        let mut list = OrderedList::with_strategy(Boundary::plus(16));
+        let a = list.push_back('a');
+        let b = list.push_back('b');
+
+        MoveOp::new('a', a, FractionalIndex::new_after(&b))
+            .apply(&mut list)
+            .unwrap();
+        assert_eq!(list.values().collect::<String>(), "ba");
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut list = OrderedList::new();
+        let a = list.push_back('a');
+        let b = list.push_back('b');
+        let mut log = MoveLog::new();
+        assert!(log.undo().is_none());
+
+        let op = MoveOp::new('a', a.clone(), FractionalIndex::new_after(&b));
+        op.apply(&mut list).unwrap();
+        log.push(op);
+        assert_eq!(values(&list), "ba");
+
+        log.undo().unwrap().apply(&mut list).unwrap();
+        assert_eq!(values(&list), "ab");
+        assert!(!log.can_undo() && log.can_redo());
+
+        log.redo().unwrap().apply(&mut list).unwrap();
+        assert_eq!(values(&list), "ba");
+        assert!(log.redo().is_none());
+
+        log.undo().unwrap().apply(&mut list).unwrap();
+        let op = MoveOp::new('b', b.clone(), FractionalIndex::new_before(&a));
+        op.apply(&mut list).unwrap();
+        log.push(op);
+        assert_eq!(values(&list), "ba");
+        assert!(!log.can_redo());
+        assert_eq!(log.history().len(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let op = MoveOp::new(
+            7u32,
+            FractionalIndex::default(),
+            FractionalIndex::new_after(&FractionalIndex::default()),
+        );
+        let json = serde_json::to_string(&op).unwrap();
+        assert_eq!(json, r#"{"id":7,"from_index":[128],"to_index":[129,128]}"#);
+        assert_eq!(serde_json::from_str::<MoveOp<u32>>(&json).unwrap(), op);
+    }
+}
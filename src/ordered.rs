@@ -0,0 +1,83 @@
+//! Values ordered by a [FractionalIndex] field.
+//!
+//! [FractionallyOrdered] gives access to the index of a value, and moves it
+//! relative to other values. With the `derive` feature,
+//! `#[derive(FractionallyOrdered)]` implements it for a struct with a field
+//! marked `#[order]` (or its only [FractionalIndex] field), along with
+//! `PartialEq`, `Eq`, `PartialOrd` and `Ord` that compare only the index,
+//! and constructors `new_before`, `new_after` and `new_between` that take the
+//! other fields as arguments.
+//!
+//! The other fields keep their own serde and sqlx implementations, so the
+//! derive composes with e.g. `Serialize` or `sqlx::FromRow`.
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")]
+//! # {
+//! use fractional_index::{FractionalIndex, FractionallyOrdered};
+//!
+//! #[derive(Debug, FractionallyOrdered)]
+//! struct Task {
+//!     #[order]
+//!     position: FractionalIndex,
+//!     title: String,
+//! }
+//!
+//! let first = Task::new_after(None, "write".to_string());
+//! let third = Task::new_after(Some(&first), "ship".to_string());
+//! let second = Task::new_between(&first, &third, "test".to_string()).unwrap();
+//!
+//! let mut tasks = vec![third, first, second];
+//! tasks.sort();
+//! let titles: Vec<&str> = tasks.iter().map(|task| task.title.as_str()).collect();
+//! assert_eq!(titles, vec!["write", "test", "ship"]);
+//!
+//! // Move "ship" to the front.
+//! let (front, rest) = tasks.split_at_mut(1);
+//! rest[1].move_before(&front[0]);
+//! tasks.sort();
+//! assert_eq!(tasks[0].title, "ship");
+//! # }
+//! ```
+use crate::FractionalIndex;
+
+/// A value ordered by a [FractionalIndex].
+pub trait FractionallyOrdered {
+    /// Returns the index that orders this value.
+    fn index(&self) -> &FractionalIndex;
+
+    fn index_mut(&mut self) -> &mut FractionalIndex;
+
+    /// Gives this value an index before `next`.
+    fn move_before(&mut self, next: &Self) {
+        *self.index_mut() = FractionalIndex::new_before(next.index());
+    }
+
+    /// Gives this value an index after `prev`.
+    fn move_after(&mut self, prev: &Self) {
+        *self.index_mut() = FractionalIndex::new_after(prev.index());
+    }
+
+    /// Gives this value an index between `lower` and `upper`, which are
+    /// unbounded if None. Returns false, leaving the index unchanged, if
+    /// `lower` is not less than `upper`.
+    fn move_between(&mut self, lower: Option<&Self>, upper: Option<&Self>) -> bool {
+        match FractionalIndex::new(lower.map(Self::index), upper.map(Self::index)) {
+            Some(index) => {
+                *self.index_mut() = index;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl FractionallyOrdered for FractionalIndex {
+    fn index(&self) -> &FractionalIndex {
+        self
+    }
+
+    fn index_mut(&mut self) -> &mut FractionalIndex {
+        self
+    }
+}
@@ -0,0 +1,567 @@
+//! An ordered collection that assigns a [FractionalIndex] to each of its
+//! elements.
+//!
+//! ```rust
+//! use fractional_index::OrderedList;
+//!
+//! let mut list = OrderedList::new();
+//! list.push_back("a");
+//! list.push_back("c");
+//!
+//! // Insert "b" while walking over the list.
+//! let mut cursor = list.cursor_mut();
+//! while let Some(value) = cursor.move_next() {
+//!     if *value == "c" {
+//!         cursor.insert_before("b");
+//!     }
+//! }
+//!
+//! let values: Vec<&str> = list.values().copied().collect();
+//! assert_eq!(values, vec!["a", "b", "c"]);
+//! ```
+use crate::{
+    strategy::{KeyStrategy, Midpoint},
+    FractionalIndex,
+};
+use std::collections::btree_map::{self, BTreeMap};
+use std::fmt::{self, Display};
+use std::iter::FromIterator;
+use std::ops::Bound::{Excluded, Unbounded};
+
+/// The error returned by [OrderedList::try_from_sorted] when keys are not
+/// strictly ascending.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnsortedError {
+    /// The position of the first key that is not greater than the key
+    /// before it.
+    pub position: usize,
+}
+
+impl Display for UnsortedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The key at position {} is not greater than the key before it.",
+            self.position
+        )
+    }
+}
+
+impl std::error::Error for UnsortedError {}
+
+/// A list of values, each stored under a [FractionalIndex] that determines
+/// its position.
+///
+/// New elements are always inserted relative to existing ones (or at
+/// either end), and their keys are computed from their neighbors by a
+/// [KeyStrategy], which bisects the gap between them by default.
+#[derive(Clone, Debug)]
+pub struct OrderedList<T, S = Midpoint> {
+    entries: BTreeMap<FractionalIndex, T>,
+    strategy: S,
+}
+
+impl<T, S: Default> Default for OrderedList<T, S> {
+    fn default() -> Self {
+        OrderedList::with_strategy(S::default())
+    }
+}
+
+/// Lists are equal if they have the same entries, whatever their
+/// strategies.
+impl<T: PartialEq, S> PartialEq for OrderedList<T, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl<T: Eq, S> Eq for OrderedList<T, S> {}
+
+impl<T> OrderedList<T> {
+    /// Constructs an empty [OrderedList].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Constructs a list from entries whose keys are strictly ascending,
+    /// e.g. rows read from a database in key order.
+    pub fn try_from_sorted<I>(iter: I) -> Result<Self, UnsortedError>
+    where
+        I: IntoIterator<Item = (FractionalIndex, T)>,
+    {
+        let mut list = OrderedList::new();
+        for (position, (key, value)) in iter.into_iter().enumerate() {
+            if list.last().is_some_and(|(last, _)| last >= &key) {
+                return Err(UnsortedError { position });
+            }
+            list.entries.insert(key, value);
+        }
+        Ok(list)
+    }
+}
+
+impl<T, S> OrderedList<T, S> {
+    /// Constructs an empty [OrderedList] that computes keys with
+    /// `strategy`.
+    pub fn with_strategy(strategy: S) -> Self {
+        OrderedList {
+            entries: BTreeMap::new(),
+            strategy,
+        }
+    }
+
+    /// Returns the strategy used to compute new keys.
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    /// Returns the number of elements in the list.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the list contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns a reference to the value stored under `key`, if any.
+    pub fn get(&self, key: &FractionalIndex) -> Option<&T> {
+        self.entries.get(key)
+    }
+
+    /// Returns a mutable reference to the value stored under `key`, if any.
+    pub fn get_mut(&mut self, key: &FractionalIndex) -> Option<&mut T> {
+        self.entries.get_mut(key)
+    }
+
+    /// Returns true if the list contains an element stored under `key`.
+    pub fn contains_key(&self, key: &FractionalIndex) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns the first element of the list and its key.
+    pub fn first(&self) -> Option<(&FractionalIndex, &T)> {
+        self.entries.iter().next()
+    }
+
+    /// Returns the last element of the list and its key.
+    pub fn last(&self) -> Option<(&FractionalIndex, &T)> {
+        self.entries.iter().next_back()
+    }
+
+    /// Inserts a value under a key that was computed elsewhere (e.g. by
+    /// another replica). If an element was already stored under that key,
+    /// it is replaced and returned.
+    pub fn insert(&mut self, key: FractionalIndex, value: T) -> Option<T> {
+        self.entries.insert(key, value)
+    }
+
+    /// Removes the element stored under `key`, returning its value.
+    pub fn remove(&mut self, key: &FractionalIndex) -> Option<T> {
+        self.entries.remove(key)
+    }
+
+    /// Returns an iterator over the keys and values of the list, in order.
+    pub fn iter(&self) -> btree_map::Iter<'_, FractionalIndex, T> {
+        self.entries.iter()
+    }
+
+    /// Returns a mutable iterator over the keys and values of the list, in
+    /// order.
+    pub fn iter_mut(&mut self) -> btree_map::IterMut<'_, FractionalIndex, T> {
+        self.entries.iter_mut()
+    }
+
+    /// Returns an iterator over the keys of the list, in order.
+    pub fn keys(&self) -> btree_map::Keys<'_, FractionalIndex, T> {
+        self.entries.keys()
+    }
+
+    /// Returns an iterator over the values of the list, in order.
+    pub fn values(&self) -> btree_map::Values<'_, FractionalIndex, T> {
+        self.entries.values()
+    }
+
+    /// Returns a cursor positioned at the “ghost” position, which sits
+    /// between the last and the first element of the list.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T, S> {
+        CursorMut {
+            list: self,
+            current: None,
+        }
+    }
+
+    /// Returns a cursor positioned at the element stored under `key`, or
+    /// None if the list does not contain `key`.
+    pub fn cursor_mut_at(&mut self, key: &FractionalIndex) -> Option<CursorMut<'_, T, S>> {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        Some(CursorMut {
+            list: self,
+            current: Some(key.clone()),
+        })
+    }
+
+    pub(crate) fn key_after(&self, key: &FractionalIndex) -> Option<&FractionalIndex> {
+        self.entries
+            .range::<FractionalIndex, _>((Excluded(key), Unbounded))
+            .next()
+            .map(|(k, _)| k)
+    }
+
+    pub(crate) fn key_before(&self, key: &FractionalIndex) -> Option<&FractionalIndex> {
+        self.entries.range(..key).next_back().map(|(k, _)| k)
+    }
+}
+
+impl<T, S: KeyStrategy> OrderedList<T, S> {
+    /// Inserts a value before every other element, returning its key.
+    pub fn push_front(&mut self, value: T) -> FractionalIndex {
+        let upper = self.keys().next().cloned();
+        self.insert_between(None, upper.as_ref(), value)
+    }
+
+    /// Inserts a value after every other element, returning its key.
+    pub fn push_back(&mut self, value: T) -> FractionalIndex {
+        let lower = self.keys().next_back().cloned();
+        self.insert_between(lower.as_ref(), None, value)
+    }
+
+    /// Inserts a value immediately before the position of `key`, returning
+    /// the new element's key.
+    pub fn insert_before(&mut self, key: &FractionalIndex, value: T) -> FractionalIndex {
+        let lower = self.key_before(key).cloned();
+        self.insert_between(lower.as_ref(), Some(key), value)
+    }
+
+    /// Inserts a value immediately after the position of `key`, returning
+    /// the new element's key.
+    pub fn insert_after(&mut self, key: &FractionalIndex, value: T) -> FractionalIndex {
+        let upper = self.key_after(key).cloned();
+        self.insert_between(Some(key), upper.as_ref(), value)
+    }
+
+    fn insert_between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+        value: T,
+    ) -> FractionalIndex {
+        let key = self
+            .strategy
+            .between(lower, upper)
+            .expect("Keys of a BTreeMap are always distinct and in order.");
+        self.entries.insert(key.clone(), value);
+        key
+    }
+}
+
+impl<'a, T, S> IntoIterator for &'a OrderedList<T, S> {
+    type Item = (&'a FractionalIndex, &'a T);
+    type IntoIter = btree_map::Iter<'a, FractionalIndex, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, S> IntoIterator for OrderedList<T, S> {
+    type Item = (FractionalIndex, T);
+    type IntoIter = btree_map::IntoIter<FractionalIndex, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+/// Collects values in order, assigning them evenly spaced keys.
+impl<T, S: KeyStrategy + Default> FromIterator<T> for OrderedList<T, S> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::default();
+        list.extend(iter);
+        list
+    }
+}
+
+/// Collects entries whose keys are strictly ascending.
+///
+/// Panics if the keys are not strictly ascending; use
+/// [OrderedList::try_from_sorted] to handle this case.
+impl<T> FromIterator<(FractionalIndex, T)> for OrderedList<T> {
+    fn from_iter<I: IntoIterator<Item = (FractionalIndex, T)>>(iter: I) -> Self {
+        match OrderedList::try_from_sorted(iter) {
+            Ok(list) => list,
+            Err(err) => panic!("{}", err),
+        }
+    }
+}
+
+/// Appends values to the back of the list, assigning them keys with
+/// [KeyStrategy::between_many], which spaces them evenly for [Midpoint].
+impl<T, S: KeyStrategy> Extend<T> for OrderedList<T, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let values: Vec<T> = iter.into_iter().collect();
+        let last = self.entries.keys().next_back();
+        let keys = self
+            .strategy
+            .between_many(last, None, values.len())
+            .expect("An upper bound of None is always after the lower bound.");
+        self.entries.extend(keys.into_iter().zip(values));
+    }
+}
+
+/// A cursor over an [OrderedList] that can insert and remove elements
+/// while walking over the list.
+///
+/// Like [std::collections::linked_list::CursorMut], the cursor can point
+/// at a “ghost” position that sits between the last and first element.
+pub struct CursorMut<'a, T, S = Midpoint> {
+    list: &'a mut OrderedList<T, S>,
+    current: Option<FractionalIndex>,
+}
+
+impl<'a, T, S> CursorMut<'a, T, S> {
+    /// Returns the key of the current element, or None at the ghost
+    /// position.
+    pub fn key(&self) -> Option<&FractionalIndex> {
+        self.current.as_ref()
+    }
+
+    /// Returns the current element, or None at the ghost position.
+    pub fn current(&mut self) -> Option<&mut T> {
+        let key = self.current.as_ref()?;
+        self.list.entries.get_mut(key)
+    }
+
+    /// Returns the element after the current one without moving the cursor.
+    /// At the ghost position, this is the first element of the list.
+    pub fn peek_next(&self) -> Option<(&FractionalIndex, &T)> {
+        match &self.current {
+            Some(key) => self
+                .list
+                .entries
+                .range::<FractionalIndex, _>((Excluded(key), Unbounded))
+                .next(),
+            None => self.list.first(),
+        }
+    }
+
+    /// Returns the element before the current one without moving the
+    /// cursor. At the ghost position, this is the last element of the list.
+    pub fn peek_prev(&self) -> Option<(&FractionalIndex, &T)> {
+        match &self.current {
+            Some(key) => self.list.entries.range(..key).next_back(),
+            None => self.list.last(),
+        }
+    }
+
+    /// Moves the cursor to the next element and returns it. Moving past the
+    /// last element moves the cursor to the ghost position and returns None.
+    pub fn move_next(&mut self) -> Option<&mut T> {
+        self.current = match &self.current {
+            Some(key) => self.list.key_after(key).cloned(),
+            None => self.list.keys().next().cloned(),
+        };
+        self.current()
+    }
+
+    /// Moves the cursor to the previous element and returns it. Moving past
+    /// the first element moves the cursor to the ghost position and returns
+    /// None.
+    pub fn move_prev(&mut self) -> Option<&mut T> {
+        self.current = match &self.current {
+            Some(key) => self.list.key_before(key).cloned(),
+            None => self.list.keys().next_back().cloned(),
+        };
+        self.current()
+    }
+
+    /// Removes the current element and moves the cursor to the next one,
+    /// returning the removed element. At the ghost position, nothing is
+    /// removed and None is returned.
+    pub fn remove_current(&mut self) -> Option<(FractionalIndex, T)> {
+        let key = self.current.take()?;
+        self.current = self.list.key_after(&key).cloned();
+        let value = self.list.entries.remove(&key)?;
+        Some((key, value))
+    }
+}
+
+impl<'a, T, S: KeyStrategy> CursorMut<'a, T, S> {
+    /// Inserts a value between the current element and the previous one,
+    /// returning its key. At the ghost position, the value is inserted at
+    /// the back of the list. The cursor does not move.
+    pub fn insert_before(&mut self, value: T) -> FractionalIndex {
+        match &self.current {
+            Some(key) => self.list.insert_before(key, value),
+            None => self.list.push_back(value),
+        }
+    }
+
+    /// Inserts a value between the current element and the next one,
+    /// returning its key. At the ghost position, the value is inserted at
+    /// the front of the list. The cursor does not move.
+    pub fn insert_after(&mut self, value: T) -> FractionalIndex {
+        match &self.current {
+            Some(key) => self.list.insert_after(key, value),
+            None => self.list.push_front(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::Boundary;
+
+    fn values<T: Clone>(list: &OrderedList<T>) -> Vec<T> {
+        list.values().cloned().collect()
+    }
+
+    #[test]
+    fn relative_insertion() {
+        let mut list = OrderedList::new();
+        let b = list.push_back('b');
+        list.push_front('a');
+        let d = list.push_back('d');
+        list.insert_before(&d, 'c');
+        list.insert_after(&d, 'e');
+
+        assert_eq!(values(&list), vec!['a', 'b', 'c', 'd', 'e']);
+        assert_eq!(list.get(&b), Some(&'b'));
+        assert_eq!(list.remove(&b), Some('b'));
+        assert_eq!(values(&list), vec!['a', 'c', 'd', 'e']);
+    }
+
+    #[test]
+    fn collect_and_extend() {
+        let mut list: OrderedList<u32> = (0..1000).collect();
+        list.extend(1000..1010);
+        assert_eq!(values(&list), (0..1010).collect::<Vec<_>>());
+        // Keys are spaced evenly rather than appended one at a time.
+        assert!(list.keys().all(|key| key.len() <= 4));
+
+        let mut empty = OrderedList::new();
+        empty.extend(Vec::<u32>::new());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn extend_with_strategy() {
+        let mut extended = OrderedList::with_strategy(Boundary::plus(64));
+        let mut pushed = OrderedList::with_strategy(Boundary::plus(64));
+        extended.extend(0..100);
+        for i in 0..100 {
+            pushed.push_back(i);
+        }
+        assert!(extended.keys().eq(pushed.keys()));
+        assert!(extended.values().copied().eq(0..100));
+    }
+
+    #[test]
+    fn collect_sorted_entries() {
+        let list: OrderedList<char> = "abc".chars().collect();
+        let entries: Vec<(FractionalIndex, char)> = list.clone().into_iter().collect();
+        let collected: OrderedList<char> = entries.iter().cloned().collect();
+        assert_eq!(collected, list);
+
+        let mut reversed = entries.clone();
+        reversed.reverse();
+        assert_eq!(
+            OrderedList::try_from_sorted(reversed),
+            Err(UnsortedError { position: 1 })
+        );
+        let duplicated = vec![entries[0].clone(), entries[0].clone()];
+        assert_eq!(
+            OrderedList::try_from_sorted(duplicated),
+            Err(UnsortedError { position: 1 })
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn collect_unsorted_entries() {
+        let list: OrderedList<char> = "ab".chars().collect();
+        let _: OrderedList<char> = list.into_iter().rev().collect();
+    }
+
+    #[test]
+    fn cursor_walk() {
+        let mut list = OrderedList::new();
+        for c in "abc".chars() {
+            list.push_back(c);
+        }
+
+        let mut cursor = list.cursor_mut();
+        assert_eq!(cursor.move_next(), Some(&mut 'a'));
+        assert_eq!(cursor.move_next(), Some(&mut 'b'));
+        assert_eq!(cursor.peek_next().map(|(_, v)| *v), Some('c'));
+        assert_eq!(cursor.peek_prev().map(|(_, v)| *v), Some('a'));
+        assert_eq!(cursor.move_next(), Some(&mut 'c'));
+        assert_eq!(cursor.move_next(), None);
+        assert_eq!(cursor.key(), None);
+        assert_eq!(cursor.move_prev(), Some(&mut 'c'));
+    }
+
+    #[test]
+    fn cursor_insert_while_iterating() {
+        let mut list = OrderedList::new();
+        for c in "aceg".chars() {
+            list.push_back(c);
+        }
+
+        // Merge a sorted stream into the list.
+        let mut incoming = "bdfh".chars().peekable();
+        let mut cursor = list.cursor_mut();
+        while let Some(current) = cursor.move_next() {
+            let current = *current;
+            while let Some(c) = incoming.next_if(|c| *c < current) {
+                cursor.insert_before(c);
+            }
+        }
+        for c in incoming {
+            cursor.insert_before(c);
+        }
+
+        assert_eq!(values(&list), "abcdefgh".chars().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn cursor_insert_after() {
+        let mut list = OrderedList::new();
+        let a = list.push_back('a');
+        list.push_back('d');
+
+        let mut cursor = list.cursor_mut_at(&a).unwrap();
+        cursor.insert_after('c');
+        cursor.insert_after('b');
+        assert_eq!(cursor.move_next(), Some(&mut 'b'));
+
+        let mut cursor = list.cursor_mut();
+        cursor.insert_after('_');
+
+        assert_eq!(values(&list), vec!['_', 'a', 'b', 'c', 'd']);
+    }
+
+    #[test]
+    fn cursor_remove() {
+        let mut list = OrderedList::new();
+        for c in "abcd".chars() {
+            list.push_back(c);
+        }
+
+        let mut cursor = list.cursor_mut();
+        while let Some(c) = cursor.move_next() {
+            if *c == 'b' {
+                let (_, removed) = cursor.remove_current().unwrap();
+                assert_eq!(removed, 'b');
+                assert_eq!(cursor.current(), Some(&mut 'c'));
+            }
+        }
+
+        assert_eq!(values(&list), vec!['a', 'c', 'd']);
+    }
+}
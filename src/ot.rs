@@ -0,0 +1,145 @@
+//! Operational transformation of concurrent inserts.
+//!
+//! Two replicas that insert into the same gap of a list without seeing
+//! each other's insert usually compute the same key, since key generation
+//! is deterministic. A server that applies one of the inserts first can
+//! pass the other to [transform_insert], which moves it to a nearby key on
+//! the side chosen by comparing replica ids. Every server therefore agrees
+//! on the relative order of the two inserts, without conflict-handling
+//! code of its own.
+//!
+//! ```rust
+//! use fractional_index::{ot::{transform_insert, InsertOp}, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//!
+//! // Two replicas insert between `a` and `b` at the same time.
+//! let first = InsertOp::between(Some(&a), Some(&b), 2).unwrap();
+//! let second = InsertOp::between(Some(&a), Some(&b), 1).unwrap();
+//! assert_eq!(first.key, second.key);
+//!
+//! // The server applies `first`, then transforms `second` against it.
+//! // Replica 1 has the lower id, so its insert goes first.
+//! let key = transform_insert(&first, &second).unwrap();
+//! assert!(a < key && key < first.key);
+//! ```
+use crate::{instrument, FractionalIndex};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// An insert made by `replica` at `key`, in the gap between `lower` and
+/// `upper` as the replica saw it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InsertOp<R> {
+    pub lower: Option<FractionalIndex>,
+    pub upper: Option<FractionalIndex>,
+    pub key: FractionalIndex,
+    pub replica: R,
+}
+
+impl<R> InsertOp<R> {
+    /// Constructs an insert by `replica` into the gap between the given
+    /// optional bounds, with the key [FractionalIndex::new] computes for
+    /// it.
+    ///
+    /// Returns None if the bounds are not in order or are equal.
+    pub fn between(
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+        replica: R,
+    ) -> Option<Self> {
+        let key = FractionalIndex::new(lower, upper)?;
+        Some(InsertOp {
+            lower: lower.cloned(),
+            upper: upper.cloned(),
+            key,
+            replica,
+        })
+    }
+}
+
+/// Returns the key at which to apply `incoming`, an insert concurrent with
+/// `applied`, after `applied` has been applied.
+///
+/// If the keys of the inserts differ, the key of `incoming` is returned
+/// unchanged. Otherwise, the insert of the replica with the lower id is
+/// ordered first: the key of `incoming` is replaced by a key between
+/// `applied.key` and the bound of `incoming` on the side it moves to. An
+/// insert with the same replica id as `applied` is ordered after it.
+///
+/// Returns None if that bound is not on the expected side of
+/// `applied.key`, i.e. if the inserts were not made into the same gap.
+pub fn transform_insert<R: Ord>(
+    applied: &InsertOp<R>,
+    incoming: &InsertOp<R>,
+) -> Option<FractionalIndex> {
+    if applied.key != incoming.key {
+        return Some(incoming.key.clone());
+    }
+    instrument::collisions("transform_insert", 1);
+    if incoming.replica < applied.replica {
+        FractionalIndex::new(incoming.lower.as_ref(), Some(&applied.key))
+    } else {
+        FractionalIndex::new(Some(&applied.key), incoming.upper.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_by_replica() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let bounds = [
+            (None, None),
+            (Some(&a), None),
+            (None, Some(&b)),
+            (Some(&a), Some(&b)),
+        ];
+        for (lower, upper) in bounds.iter().copied() {
+            let x = InsertOp::between(lower, upper, 'x').unwrap();
+            let y = InsertOp::between(lower, upper, 'y').unwrap();
+
+            // Whichever insert is applied first, `x` ends up before `y`.
+            let y_key = transform_insert(&x, &y).unwrap();
+            let x_key = transform_insert(&y, &x).unwrap();
+            for key in [&y_key, &x_key] {
+                assert!(lower.is_none_or(|lower| lower < key));
+                assert!(upper.is_none_or(|upper| key < upper));
+            }
+            assert!(x.key < y_key);
+            assert!(x_key < y.key);
+        }
+    }
+
+    #[test]
+    fn no_collision() {
+        let a = FractionalIndex::default();
+        let x = InsertOp::between(None, Some(&a), 2).unwrap();
+        let y = InsertOp::between(Some(&a), None, 1).unwrap();
+        assert_eq!(transform_insert(&x, &y), Some(y.key.clone()));
+
+        let same = InsertOp::between(None, Some(&a), 2).unwrap();
+        assert!(transform_insert(&x, &same).unwrap() > x.key);
+    }
+
+    #[test]
+    fn different_gaps() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let x = InsertOp::between(Some(&a), None, 1).unwrap();
+        // A gap whose upper bound is below the colliding key.
+        let y = InsertOp {
+            lower: None,
+            upper: Some(a.clone()),
+            key: x.key.clone(),
+            replica: 2,
+        };
+        assert_eq!(x.key, b);
+        assert_eq!(transform_insert(&x, &y), None);
+    }
+}
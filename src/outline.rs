@@ -0,0 +1,436 @@
+//! Trees of nodes ordered among their siblings, as in an outliner.
+//!
+//! [Outline] keeps an [OrderedList] of node ids for the roots and for the
+//! children of each node, and tracks the parent and key of each node. A
+//! node's key only orders it among its siblings, so moving a node (by
+//! [Outline::move_subtree], [Outline::indent] or [Outline::outdent])
+//! changes the parent and key of that node alone, and its descendants move
+//! with it without changing their keys.
+//!
+//! ```rust
+//! use fractional_index::{document::Placement, outline::Outline};
+//!
+//! let mut outline = Outline::new();
+//! outline.insert("groceries", None, Placement::Back).unwrap();
+//! outline.insert("milk", None, Placement::Back).unwrap();
+//! outline.insert("eggs", None, Placement::Back).unwrap();
+//!
+//! // Indenting makes a node the last child of its previous sibling.
+//! outline.indent(&"milk").unwrap();
+//! outline.indent(&"eggs").unwrap();
+//! assert_eq!(outline.parent(&"eggs"), Some(Some(&"groceries")));
+//!
+//! // Outdenting makes a node the next sibling of its parent.
+//! outline.outdent(&"milk").unwrap();
+//! assert_eq!(
+//!     outline.nodes(),
+//!     vec![(0, &"groceries"), (1, &"eggs"), (0, &"milk")]
+//! );
+//! ```
+use crate::{document::Placement, FractionalIndex, OrderedList};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+/// The error returned when a change to an [Outline] is invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutlineError {
+    /// The node is not in the outline.
+    UnknownNode,
+    /// The parent is not in the outline.
+    UnknownParent,
+    /// The node is already in the outline.
+    DuplicateNode,
+    /// The node used as an anchor is not a child of the destination parent.
+    AnchorNotSibling,
+    /// The destination parent is the node itself or one of its
+    /// descendants.
+    Cycle,
+    /// The node has no previous sibling to indent it under.
+    NoPreviousSibling,
+    /// The node is a root, so it cannot be outdented.
+    AlreadyRoot,
+}
+
+impl Display for OutlineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutlineError::UnknownNode => write!(f, "The node is not in the outline."),
+            OutlineError::UnknownParent => write!(f, "The parent is not in the outline."),
+            OutlineError::DuplicateNode => write!(f, "The node is already in the outline."),
+            OutlineError::AnchorNotSibling => {
+                write!(
+                    f,
+                    "The anchor node is not a child of the destination parent."
+                )
+            }
+            OutlineError::Cycle => write!(
+                f,
+                "The destination parent is the node itself or one of its descendants."
+            ),
+            OutlineError::NoPreviousSibling => {
+                write!(f, "The node has no previous sibling to indent it under.")
+            }
+            OutlineError::AlreadyRoot => write!(f, "The node is a root and cannot be outdented."),
+        }
+    }
+}
+
+impl std::error::Error for OutlineError {}
+
+/// A tree of nodes, where each node has a [FractionalIndex] among its
+/// siblings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outline<Id> {
+    roots: OrderedList<Id>,
+    /// The children of each node that has any.
+    children: BTreeMap<Id, OrderedList<Id>>,
+    /// The parent (None for roots) and key of each node.
+    nodes: BTreeMap<Id, (Option<Id>, FractionalIndex)>,
+}
+
+impl<Id> Default for Outline<Id> {
+    fn default() -> Self {
+        Outline {
+            roots: OrderedList::new(),
+            children: BTreeMap::new(),
+            nodes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<Id: Ord + Clone> Outline<Id> {
+    /// Constructs an empty outline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns true if the outline has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn contains(&self, id: &Id) -> bool {
+        self.nodes.contains_key(id)
+    }
+
+    /// Returns the parent of a node (None for a root), or None if the node
+    /// is not in the outline.
+    pub fn parent(&self, id: &Id) -> Option<Option<&Id>> {
+        self.nodes.get(id).map(|(parent, _)| parent.as_ref())
+    }
+
+    /// Returns the key of a node among its siblings.
+    pub fn key(&self, id: &Id) -> Option<&FractionalIndex> {
+        self.nodes.get(id).map(|(_, key)| key)
+    }
+
+    /// Returns the roots, in order.
+    pub fn roots(&self) -> impl DoubleEndedIterator<Item = &Id> + '_ {
+        self.roots.values()
+    }
+
+    /// Returns the children of a node, in order. A node with no children,
+    /// or that is not in the outline, has no children.
+    pub fn children(&self, id: &Id) -> impl DoubleEndedIterator<Item = &Id> + '_ {
+        self.children
+            .get(id)
+            .into_iter()
+            .flat_map(OrderedList::values)
+    }
+
+    /// Returns every node in document order (each node followed by its
+    /// descendants), with its depth. Roots have depth 0.
+    pub fn nodes(&self) -> Vec<(usize, &Id)> {
+        let mut nodes = Vec::with_capacity(self.len());
+        let mut stack: Vec<(usize, &Id)> = self.roots().rev().map(|id| (0, id)).collect();
+        while let Some((depth, id)) = stack.pop() {
+            nodes.push((depth, id));
+            stack.extend(self.children(id).rev().map(|child| (depth + 1, child)));
+        }
+        nodes
+    }
+
+    /// Returns true if `ancestor` is `id` or one of its ancestors.
+    fn is_ancestor_or_self(&self, ancestor: &Id, id: &Id) -> bool {
+        let mut current = Some(id);
+        while let Some(id) = current {
+            if id == ancestor {
+                return true;
+            }
+            current = self.parent(id).flatten();
+        }
+        false
+    }
+
+    /// Returns the key of the anchor of `placement`, after checking that
+    /// the parent exists and the anchor is one of its children.
+    fn anchor(
+        &self,
+        parent: Option<&Id>,
+        placement: Placement<Id>,
+    ) -> Result<Option<&FractionalIndex>, OutlineError> {
+        if parent.is_some_and(|parent| !self.nodes.contains_key(parent)) {
+            return Err(OutlineError::UnknownParent);
+        }
+        placement.anchor(
+            &self.nodes,
+            |anchor_parent| anchor_parent.as_ref() == parent,
+            OutlineError::AnchorNotSibling,
+        )
+    }
+
+    /// Inserts a node that is not in the outline, returning its key. The
+    /// placement must have been checked with [Self::anchor].
+    fn place(&mut self, id: Id, parent: Option<&Id>, placement: Placement<Id>) -> FractionalIndex {
+        let anchor = self
+            .anchor(parent, placement)
+            .expect("The placement was checked.")
+            .cloned();
+        let siblings = match parent {
+            Some(parent) => self.children.entry(parent.clone()).or_default(),
+            None => &mut self.roots,
+        };
+        let key = placement.insert_into(siblings, anchor.as_ref(), id.clone());
+        self.nodes.insert(id, (parent.cloned(), key.clone()));
+        key
+    }
+
+    /// Removes a node from its siblings, leaving its children in place.
+    fn detach(&mut self, id: &Id) -> Option<(Option<Id>, FractionalIndex)> {
+        let (parent, key) = self.nodes.remove(id)?;
+        match &parent {
+            Some(parent_id) => {
+                let siblings = self
+                    .children
+                    .get_mut(parent_id)
+                    .expect("Every child is in its parent's list.");
+                siblings.remove(&key);
+                if siblings.is_empty() {
+                    self.children.remove(parent_id);
+                }
+            }
+            None => {
+                self.roots.remove(&key);
+            }
+        }
+        Some((parent, key))
+    }
+
+    /// Inserts a new node under `parent` (or as a root if None), returning
+    /// its key.
+    pub fn insert(
+        &mut self,
+        id: Id,
+        parent: Option<&Id>,
+        placement: Placement<Id>,
+    ) -> Result<FractionalIndex, OutlineError> {
+        if self.nodes.contains_key(&id) {
+            return Err(OutlineError::DuplicateNode);
+        }
+        self.anchor(parent, placement)?;
+        Ok(self.place(id, parent, placement))
+    }
+
+    /// Moves a node and its descendants under `parent` (or to the roots if
+    /// None), returning the node's new key. The keys of its descendants are
+    /// unchanged. The outline is unchanged if an error is returned.
+    ///
+    /// Placing a node before or after itself leaves it where it is.
+    pub fn move_subtree(
+        &mut self,
+        id: &Id,
+        parent: Option<&Id>,
+        placement: Placement<Id>,
+    ) -> Result<FractionalIndex, OutlineError> {
+        let (current_parent, key) = self.nodes.get(id).ok_or(OutlineError::UnknownNode)?;
+        if let Placement::Before(anchor) | Placement::After(anchor) = placement {
+            if anchor == id && current_parent.as_ref() == parent {
+                return Ok(key.clone());
+            }
+        }
+        if parent.is_some_and(|parent| self.is_ancestor_or_self(id, parent)) {
+            return Err(OutlineError::Cycle);
+        }
+        self.anchor(parent, placement)?;
+        self.detach(id);
+        Ok(self.place(id.clone(), parent, placement))
+    }
+
+    /// Makes a node the last child of its previous sibling, returning its
+    /// new key.
+    pub fn indent(&mut self, id: &Id) -> Result<FractionalIndex, OutlineError> {
+        let (parent, key) = self.nodes.get(id).ok_or(OutlineError::UnknownNode)?;
+        let siblings = match parent {
+            Some(parent) => &self.children[parent],
+            None => &self.roots,
+        };
+        let previous = siblings
+            .key_before(key)
+            .and_then(|key| siblings.get(key))
+            .ok_or(OutlineError::NoPreviousSibling)?
+            .clone();
+        self.move_subtree(id, Some(&previous), Placement::Back)
+    }
+
+    /// Makes a node the next sibling of its parent, returning its new key.
+    pub fn outdent(&mut self, id: &Id) -> Result<FractionalIndex, OutlineError> {
+        let parent = self
+            .parent(id)
+            .ok_or(OutlineError::UnknownNode)?
+            .ok_or(OutlineError::AlreadyRoot)?
+            .clone();
+        let grandparent = self.parent(&parent).flatten().cloned();
+        self.move_subtree(id, grandparent.as_ref(), Placement::After(&parent))
+    }
+
+    /// Removes a node and its descendants, returning their ids in document
+    /// order, or an empty vector if the node is not in the outline.
+    pub fn remove(&mut self, id: &Id) -> Vec<Id> {
+        if self.detach(id).is_none() {
+            return Vec::new();
+        }
+        let mut removed = Vec::new();
+        let mut stack = vec![id.clone()];
+        while let Some(id) = stack.pop() {
+            if let Some(children) = self.children.remove(&id) {
+                for (_, child) in children.into_iter().rev() {
+                    self.nodes.remove(&child);
+                    stack.push(child);
+                }
+            }
+            removed.push(id);
+        }
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(outline: &Outline<char>) -> String {
+        outline
+            .nodes()
+            .into_iter()
+            .map(|(depth, id)| format!("{}{}", " ".repeat(depth), id))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn assert_consistent(outline: &Outline<char>) {
+        let nodes = outline.nodes();
+        assert_eq!(nodes.len(), outline.len());
+        for (_, id) in nodes {
+            let parent = outline.parent(id).unwrap();
+            let key = outline.key(id).unwrap();
+            let siblings = match parent {
+                Some(parent) => &outline.children[parent],
+                None => &outline.roots,
+            };
+            assert_eq!(siblings.get(key), Some(id));
+        }
+        assert!(outline
+            .children
+            .values()
+            .all(|children| !children.is_empty()));
+    }
+
+    #[test]
+    fn indent_and_outdent() {
+        let mut outline = Outline::new();
+        for id in "abcd".chars() {
+            outline.insert(id, None, Placement::Back).unwrap();
+        }
+        assert_eq!(outline.indent(&'a'), Err(OutlineError::NoPreviousSibling));
+        outline.indent(&'b').unwrap();
+        outline.indent(&'c').unwrap();
+        outline.indent(&'c').unwrap();
+        assert_eq!(render(&outline), "a, b,  c,d");
+
+        // The subtree of `b` moves with it.
+        let c_key = outline.key(&'c').cloned();
+        outline.outdent(&'b').unwrap();
+        assert_eq!(render(&outline), "a,b, c,d");
+        assert_eq!(outline.key(&'c').cloned(), c_key);
+        assert_eq!(outline.outdent(&'b'), Err(OutlineError::AlreadyRoot));
+
+        outline.outdent(&'c').unwrap();
+        assert_eq!(render(&outline), "a,b,c,d");
+        assert_consistent(&outline);
+    }
+
+    #[test]
+    fn move_subtree() {
+        let mut outline = Outline::new();
+        outline.insert('a', None, Placement::Back).unwrap();
+        outline.insert('b', Some(&'a'), Placement::Back).unwrap();
+        outline.insert('c', Some(&'b'), Placement::Back).unwrap();
+        outline.insert('d', None, Placement::Front).unwrap();
+        outline
+            .insert('e', Some(&'a'), Placement::Before(&'b'))
+            .unwrap();
+        assert_eq!(render(&outline), "d,a, e, b,  c");
+
+        outline
+            .move_subtree(&'b', Some(&'d'), Placement::Front)
+            .unwrap();
+        assert_eq!(render(&outline), "d, b,  c,a, e");
+        outline
+            .move_subtree(&'a', None, Placement::Before(&'d'))
+            .unwrap();
+        assert_eq!(render(&outline), "a, e,d, b,  c");
+        outline
+            .move_subtree(&'e', Some(&'a'), Placement::After(&'e'))
+            .unwrap();
+        assert_eq!(render(&outline), "a, e,d, b,  c");
+        assert_eq!(outline.children(&'d').collect::<Vec<_>>(), vec![&'b']);
+        assert_eq!(outline.roots().collect::<Vec<_>>(), vec![&'a', &'d']);
+        assert_consistent(&outline);
+
+        assert_eq!(outline.remove(&'d'), vec!['d', 'b', 'c']);
+        assert_eq!(outline.remove(&'d'), Vec::<char>::new());
+        assert_eq!(render(&outline), "a, e");
+        assert_eq!(outline.len(), 2);
+        assert_consistent(&outline);
+    }
+
+    #[test]
+    fn errors() {
+        let mut outline = Outline::new();
+        outline.insert('a', None, Placement::Back).unwrap();
+        outline.insert('b', Some(&'a'), Placement::Back).unwrap();
+        outline.insert('c', Some(&'b'), Placement::Back).unwrap();
+
+        assert_eq!(
+            outline.insert('a', None, Placement::Back),
+            Err(OutlineError::DuplicateNode)
+        );
+        assert_eq!(
+            outline.insert('x', Some(&'y'), Placement::Back),
+            Err(OutlineError::UnknownParent)
+        );
+        assert_eq!(
+            outline.insert('x', None, Placement::After(&'b')),
+            Err(OutlineError::AnchorNotSibling)
+        );
+        assert_eq!(
+            outline.move_subtree(&'x', None, Placement::Back),
+            Err(OutlineError::UnknownNode)
+        );
+        assert_eq!(
+            outline.move_subtree(&'a', Some(&'c'), Placement::Back),
+            Err(OutlineError::Cycle)
+        );
+        assert_eq!(
+            outline.move_subtree(&'b', Some(&'b'), Placement::Back),
+            Err(OutlineError::Cycle)
+        );
+        assert_eq!(outline.indent(&'x'), Err(OutlineError::UnknownNode));
+        assert_eq!(render(&outline), "a, b,  c");
+    }
+}
@@ -0,0 +1,324 @@
+//! A compact, sorted list of keys stored in one contiguous buffer.
+//!
+//! [PackedIndexList] stores the bytes of every key back to back, with a
+//! `u32` end offset per key, instead of one heap allocation per
+//! [FractionalIndex]. This keeps large orderings (millions of keys) small
+//! and cache-friendly for read-heavy work such as binary searches and
+//! scans, at the cost of O(n) insertions and removals in the middle.
+//!
+//! Keys are returned as byte slices, which compare in the same order as
+//! the corresponding [FractionalIndex]es.
+//!
+//! ```rust
+//! use fractional_index::{packed::PackedIndexList, FractionalIndex};
+//!
+//! let mut list = PackedIndexList::new();
+//! let a = list.push_back();
+//! let c = list.push_back();
+//! let b = list.insert_at(1);
+//!
+//! assert_eq!(list.len(), 3);
+//! assert_eq!(list.binary_search(&b), Ok(1));
+//! let keys: Vec<&[u8]> = list.iter().collect();
+//! assert_eq!(keys, vec![a.as_bytes(), b.as_bytes(), c.as_bytes()]);
+//! ```
+use crate::{
+    format::ByteFormat, fract_index::TERMINATOR, ordered_list::UnsortedError, FractionalIndex,
+};
+use std::{convert::TryFrom, iter::FusedIterator, ops::Range};
+
+/// A sorted list of distinct keys, packed into a single buffer.
+///
+/// The total length of the keys is limited to `u32::MAX` bytes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PackedIndexList {
+    bytes: Vec<u8>,
+    /// The end offset in `bytes` of each key.
+    ends: Vec<u32>,
+}
+
+fn offset(len: usize) -> u32 {
+    u32::try_from(len).expect("A packed index list should hold at most u32::MAX bytes of keys.")
+}
+
+impl PackedIndexList {
+    /// Constructs an empty list.
+    pub fn new() -> Self {
+        PackedIndexList::default()
+    }
+
+    /// Constructs an empty list with room for `keys` keys totalling `bytes`
+    /// bytes.
+    pub fn with_capacity(keys: usize, bytes: usize) -> Self {
+        PackedIndexList {
+            bytes: Vec::with_capacity(bytes),
+            ends: Vec::with_capacity(keys),
+        }
+    }
+
+    /// Constructs a list from keys in strictly ascending order, or returns
+    /// the position of the first key that is out of order.
+    pub fn try_from_sorted<'a, I>(keys: I) -> Result<Self, UnsortedError>
+    where
+        I: IntoIterator<Item = &'a FractionalIndex>,
+    {
+        let mut list = PackedIndexList::new();
+        for key in keys {
+            list.push(key)?;
+        }
+        Ok(list)
+    }
+
+    /// Returns the number of keys in the list.
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Returns true if the list contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Returns the total length of the keys in bytes.
+    pub fn byte_len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn range(&self, position: usize) -> Range<usize> {
+        let start = match position {
+            0 => 0,
+            _ => self.ends[position - 1] as usize,
+        };
+        start..self.ends[position] as usize
+    }
+
+    /// Returns the bytes of the key at `position`.
+    pub fn get(&self, position: usize) -> Option<&[u8]> {
+        if position >= self.len() {
+            return None;
+        }
+        Some(&self.bytes[self.range(position)])
+    }
+
+    /// Returns a copy of the key at `position`.
+    pub fn get_index(&self, position: usize) -> Option<FractionalIndex> {
+        self.get(position)
+            .map(|bytes| FractionalIndex::from_bytes_unchecked(bytes.to_vec()))
+    }
+
+    /// Returns the bytes of the last key.
+    pub fn last(&self) -> Option<&[u8]> {
+        self.len().checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    /// Searches for a key, returning its position if it is found, or the
+    /// position at which it would be inserted otherwise.
+    pub fn binary_search(&self, key: &FractionalIndex) -> Result<usize, usize> {
+        let key = key.as_bytes();
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.bytes[self.range(mid)].cmp(key) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+
+    /// Returns an iterator over the bytes of the keys, in order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            list: self,
+            front: 0,
+            back: self.len(),
+        }
+    }
+
+    /// Appends an existing key, which must be greater than every key in the
+    /// list.
+    pub fn push(&mut self, key: &FractionalIndex) -> Result<(), UnsortedError> {
+        if self.last().is_some_and(|last| last >= key.as_bytes()) {
+            return Err(UnsortedError {
+                position: self.len(),
+            });
+        }
+        self.bytes.extend_from_slice(key.as_bytes());
+        self.ends.push(offset(self.bytes.len()));
+        Ok(())
+    }
+
+    /// Appends a new key after every key in the list, returning it.
+    pub fn push_back(&mut self) -> FractionalIndex {
+        self.insert_at(self.len())
+    }
+
+    /// Inserts a new key at `position`, between the keys currently at
+    /// `position - 1` and `position`, returning it.
+    ///
+    /// This moves every key after `position`. Panics if
+    /// `position > self.len()`.
+    pub fn insert_at(&mut self, position: usize) -> FractionalIndex {
+        assert!(
+            position <= self.len(),
+            "Insertion position (is {}) should be <= len (is {}).",
+            position,
+            self.len()
+        );
+
+        let format = ByteFormat::DEFAULT;
+        let mut key = match (
+            position.checked_sub(1).and_then(|i| self.get(i)),
+            self.get(position),
+        ) {
+            (Some(lower), Some(upper)) => format
+                .between(lower, upper)
+                .expect("Keys of a packed index list should be distinct and in order."),
+            (Some(lower), None) => format.after(lower),
+            (None, Some(upper)) => format.before(upper),
+            (None, None) => Vec::new(),
+        };
+        key.push(TERMINATOR);
+
+        let at = match position {
+            0 => 0,
+            _ => self.ends[position - 1] as usize,
+        };
+        // Check that the offsets will fit before modifying the list.
+        let _ = offset(self.bytes.len() + key.len());
+        let len = offset(key.len());
+        self.bytes.splice(at..at, key.iter().copied());
+        for end in &mut self.ends[position..] {
+            *end += len;
+        }
+        self.ends.insert(position, offset(at) + len);
+        FractionalIndex::from_bytes_unchecked(key)
+    }
+
+    /// Removes the key at `position`, returning it.
+    ///
+    /// This moves every key after `position`. Panics if
+    /// `position >= self.len()`.
+    pub fn remove(&mut self, position: usize) -> FractionalIndex {
+        let range = self.range(position);
+        let len = offset(range.len());
+        let key = self.bytes.drain(range).collect();
+        self.ends.remove(position);
+        for end in &mut self.ends[position..] {
+            *end -= len;
+        }
+        FractionalIndex::from_bytes_unchecked(key)
+    }
+
+    /// Shrinks the buffers to fit the keys.
+    pub fn shrink_to_fit(&mut self) {
+        self.bytes.shrink_to_fit();
+        self.ends.shrink_to_fit();
+    }
+}
+
+/// An iterator over the keys of a [PackedIndexList], returned by
+/// [PackedIndexList::iter].
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+    list: &'a PackedIndexList,
+    front: usize,
+    back: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let key = &self.list.bytes[self.list.range(self.front)];
+        self.front += 1;
+        Some(key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(&self.list.bytes[self.list.range(self.back)])
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl FusedIterator for Iter<'_> {}
+
+impl<'a> IntoIterator for &'a PackedIndexList {
+    type Item = &'a [u8];
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_sorted_vec() {
+        let mut list = PackedIndexList::new();
+        let mut expected: Vec<FractionalIndex> = Vec::new();
+        for i in 0..500usize {
+            let position = (i * 7919) % (expected.len() + 1);
+            let key = list.insert_at(position);
+            expected.insert(position, key);
+            if i % 5 == 4 {
+                let position = (i * 31) % expected.len();
+                assert_eq!(list.remove(position), expected.remove(position));
+            }
+        }
+
+        assert_eq!(list.len(), expected.len());
+        assert_eq!(
+            list.byte_len(),
+            expected.iter().map(|key| key.len()).sum::<usize>()
+        );
+        for (position, key) in expected.iter().enumerate() {
+            assert_eq!(list.get(position), Some(key.as_bytes()));
+            assert_eq!(list.binary_search(key), Ok(position));
+        }
+        assert!(list.iter().eq(expected.iter().map(|key| key.as_bytes())));
+        assert!(list
+            .iter()
+            .rev()
+            .eq(expected.iter().rev().map(|key| key.as_bytes())));
+        assert_eq!(list.get(expected.len()), None);
+
+        let missing = FractionalIndex::new_between(&expected[3], &expected[4]).unwrap();
+        assert_eq!(list.binary_search(&missing), Err(4));
+        assert_eq!(PackedIndexList::try_from_sorted(&expected).unwrap(), list);
+    }
+
+    #[test]
+    fn push() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let mut list = PackedIndexList::with_capacity(2, 8);
+        list.push(&a).unwrap();
+        assert_eq!(list.push(&a), Err(UnsortedError { position: 1 }));
+        list.push(&b).unwrap();
+        assert_eq!(list.get_index(1), Some(b));
+        assert_eq!(
+            PackedIndexList::try_from_sorted(&[a.clone(), a]),
+            Err(UnsortedError { position: 1 })
+        );
+    }
+}
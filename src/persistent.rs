@@ -0,0 +1,305 @@
+//! A persistent ordered list, whose clones share structure.
+//!
+//! [PersistentOrderedList] has the same interface as
+//! [OrderedList](crate::OrderedList), but is backed by an
+//! [imbl](https://docs.rs/imbl) `OrdMap`. Cloning a list is O(1) and
+//! updates are O(log n), copying only the nodes they touch, so keeping a
+//! snapshot of every version (e.g. for undo, diffing or time-travel
+//! debugging) is cheap. [PersistentOrderedList::diff] compares two versions
+//! efficiently when they share structure.
+//!
+//! ```rust
+//! use fractional_index::persistent::{Change, PersistentOrderedList};
+//!
+//! let mut list = PersistentOrderedList::new();
+//! let a = list.push_back("a");
+//! list.push_back("c");
+//! let before = list.clone();
+//!
+//! let b = list.insert_after(&a, "b");
+//! let values: Vec<&str> = list.values().copied().collect();
+//! assert_eq!(values, vec!["a", "b", "c"]);
+//! assert_eq!(before.len(), 2);
+//!
+//! let changes: Vec<_> = before.diff(&list).collect();
+//! assert_eq!(changes, vec![Change::Inserted(&b, &"b")]);
+//! ```
+use crate::{ordered_list::UnsortedError, FractionalIndex, OrderedList};
+use imbl::{ordmap::DiffItem, OrdMap};
+use std::iter::FromIterator;
+use std::ops::Bound::{Excluded, Unbounded};
+
+/// A list of values, each stored under a [FractionalIndex] that determines
+/// its position, with O(1) clones.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PersistentOrderedList<T: Clone> {
+    entries: OrdMap<FractionalIndex, T>,
+}
+
+/// A difference between two versions of a [PersistentOrderedList],
+/// returned by [PersistentOrderedList::diff].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change<'a, T> {
+    /// An element is only present in the newer version.
+    Inserted(&'a FractionalIndex, &'a T),
+    /// An element's value differs between the versions.
+    Updated {
+        key: &'a FractionalIndex,
+        old: &'a T,
+        new: &'a T,
+    },
+    /// An element is only present in the older version.
+    Removed(&'a FractionalIndex, &'a T),
+}
+
+impl<T: Clone> Default for PersistentOrderedList<T> {
+    fn default() -> Self {
+        PersistentOrderedList {
+            entries: OrdMap::new(),
+        }
+    }
+}
+
+impl<T: Clone> PersistentOrderedList<T> {
+    /// Constructs an empty [PersistentOrderedList].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of elements in the list.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the list contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true if both lists share the same root, in which case they
+    /// are equal.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.entries.ptr_eq(&other.entries)
+    }
+
+    /// Returns a reference to the value stored under `key`, if any.
+    pub fn get(&self, key: &FractionalIndex) -> Option<&T> {
+        self.entries.get(key)
+    }
+
+    /// Returns a mutable reference to the value stored under `key`, if any.
+    pub fn get_mut(&mut self, key: &FractionalIndex) -> Option<&mut T> {
+        self.entries.get_mut(key)
+    }
+
+    /// Returns true if the list contains an element stored under `key`.
+    pub fn contains_key(&self, key: &FractionalIndex) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns the first element of the list and its key.
+    pub fn first(&self) -> Option<(&FractionalIndex, &T)> {
+        self.entries.get_min().map(|(key, value)| (key, value))
+    }
+
+    /// Returns the last element of the list and its key.
+    pub fn last(&self) -> Option<(&FractionalIndex, &T)> {
+        self.entries.get_max().map(|(key, value)| (key, value))
+    }
+
+    fn insert_between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+        value: T,
+    ) -> FractionalIndex {
+        let key = FractionalIndex::new(lower, upper)
+            .expect("Keys of a persistent ordered list are always distinct and in order.");
+        self.entries.insert(key.clone(), value);
+        key
+    }
+
+    /// Inserts a value before every other element, returning its key.
+    pub fn push_front(&mut self, value: T) -> FractionalIndex {
+        let first = self.first().map(|(key, _)| key.clone());
+        self.insert_between(None, first.as_ref(), value)
+    }
+
+    /// Inserts a value after every other element, returning its key.
+    pub fn push_back(&mut self, value: T) -> FractionalIndex {
+        let last = self.last().map(|(key, _)| key.clone());
+        self.insert_between(last.as_ref(), None, value)
+    }
+
+    /// Inserts a value immediately before the position of `key`, returning
+    /// the new element's key.
+    pub fn insert_before(&mut self, key: &FractionalIndex, value: T) -> FractionalIndex {
+        let lower = self
+            .entries
+            .range(..key)
+            .next_back()
+            .map(|(key, _)| key.clone());
+        self.insert_between(lower.as_ref(), Some(key), value)
+    }
+
+    /// Inserts a value immediately after the position of `key`, returning
+    /// the new element's key.
+    pub fn insert_after(&mut self, key: &FractionalIndex, value: T) -> FractionalIndex {
+        let upper = self
+            .entries
+            .range::<_, FractionalIndex>((Excluded(key), Unbounded))
+            .next()
+            .map(|(key, _)| key.clone());
+        self.insert_between(Some(key), upper.as_ref(), value)
+    }
+
+    /// Inserts a value under a key that was computed elsewhere. If an
+    /// element was already stored under that key, it is replaced and
+    /// returned.
+    pub fn insert(&mut self, key: FractionalIndex, value: T) -> Option<T> {
+        self.entries.insert(key, value)
+    }
+
+    /// Removes the element stored under `key`, returning its value.
+    pub fn remove(&mut self, key: &FractionalIndex) -> Option<T> {
+        self.entries.remove(key)
+    }
+
+    /// Returns an iterator over the elements of the list and their keys, in
+    /// order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&FractionalIndex, &T)> + '_ {
+        self.entries.iter()
+    }
+
+    /// Returns an iterator over the keys of the list, in order.
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &FractionalIndex> + '_ {
+        self.entries.keys()
+    }
+
+    /// Returns an iterator over the values of the list, in order.
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &T> + '_ {
+        self.entries.values()
+    }
+
+    /// Constructs a list from entries in strictly ascending key order, or
+    /// returns the position of the first entry that is out of order.
+    pub fn try_from_sorted<I>(iter: I) -> Result<Self, UnsortedError>
+    where
+        I: IntoIterator<Item = (FractionalIndex, T)>,
+    {
+        let mut entries = OrdMap::new();
+        let mut last: Option<FractionalIndex> = None;
+        for (position, (key, value)) in iter.into_iter().enumerate() {
+            if last.as_ref().is_some_and(|last| *last >= key) {
+                return Err(UnsortedError { position });
+            }
+            last = Some(key.clone());
+            entries.insert(key, value);
+        }
+        Ok(PersistentOrderedList { entries })
+    }
+}
+
+impl<T: Clone + PartialEq> PersistentOrderedList<T> {
+    /// Returns the changes from this version of the list to `newer`, in key
+    /// order. Subtrees shared by both versions are skipped without being
+    /// compared.
+    pub fn diff<'a>(&'a self, newer: &'a Self) -> impl Iterator<Item = Change<'a, T>> + 'a {
+        self.entries.diff(&newer.entries).map(|item| match item {
+            DiffItem::Add(key, value) => Change::Inserted(key, value),
+            DiffItem::Update {
+                old: (key, old),
+                new: (_, new),
+            } => Change::Updated { key, old, new },
+            DiffItem::Remove(key, value) => Change::Removed(key, value),
+        })
+    }
+}
+
+impl<T: Clone> From<OrderedList<T>> for PersistentOrderedList<T> {
+    fn from(list: OrderedList<T>) -> Self {
+        PersistentOrderedList {
+            entries: list.into_iter().collect(),
+        }
+    }
+}
+
+impl<T: Clone> From<&PersistentOrderedList<T>> for OrderedList<T> {
+    fn from(list: &PersistentOrderedList<T>) -> Self {
+        OrderedList::try_from_sorted(list.iter().map(|(key, value)| (key.clone(), value.clone())))
+            .expect("Keys of a persistent ordered list are always distinct and in order.")
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PersistentOrderedList<T> {
+    /// Collects values in order, assigning them evenly spaced keys.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        OrderedList::from_iter(iter).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots() {
+        let mut list: PersistentOrderedList<u32> = (0..100).collect();
+        let keys: Vec<FractionalIndex> = list.keys().cloned().collect();
+        let snapshot = list.clone();
+        assert!(snapshot.ptr_eq(&list));
+
+        let front = list.push_front(1000);
+        let inserted = list.insert_before(&keys[50], 1001);
+        let after = list.insert_after(&keys[50], 1002);
+        list.remove(&keys[10]);
+        *list.get_mut(&keys[20]).unwrap() = 2000;
+
+        assert_eq!(snapshot.len(), 100);
+        assert!(snapshot.values().copied().eq(0..100));
+        assert_eq!(list.len(), 102);
+        assert_eq!(list.first(), Some((&front, &1000)));
+        assert_eq!(list.last().map(|(_, value)| *value), Some(99));
+        let values: Vec<u32> = list.values().copied().skip(49).take(4).collect();
+        assert_eq!(values, vec![49, 1001, 50, 1002]);
+
+        let changes: Vec<Change<u32>> = snapshot.diff(&list).collect();
+        assert_eq!(
+            changes,
+            vec![
+                Change::Inserted(&front, &1000),
+                Change::Removed(&keys[10], &10),
+                Change::Updated {
+                    key: &keys[20],
+                    old: &20,
+                    new: &2000
+                },
+                Change::Inserted(&inserted, &1001),
+                Change::Inserted(&after, &1002),
+            ]
+        );
+        assert_eq!(list.diff(&list).count(), 0);
+    }
+
+    #[test]
+    fn conversions() {
+        let list: OrderedList<&str> = ["a", "b", "c"].iter().copied().collect();
+        let persistent = PersistentOrderedList::from(list.clone());
+        assert_eq!(OrderedList::from(&persistent), list);
+
+        let entries: Vec<(FractionalIndex, &str)> = list
+            .iter()
+            .map(|(key, value)| (key.clone(), *value))
+            .collect();
+        assert_eq!(
+            PersistentOrderedList::try_from_sorted(entries.clone()).unwrap(),
+            persistent
+        );
+        let mut reversed = entries;
+        reversed.reverse();
+        assert_eq!(
+            PersistentOrderedList::try_from_sorted(reversed),
+            Err(UnsortedError { position: 1 })
+        );
+    }
+}
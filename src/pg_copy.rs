@@ -0,0 +1,214 @@
+//! Bulk loading of `(id, FractionalIndex)` rows into Postgres with
+//! `COPY ... FROM STDIN (FORMAT binary)`, for backfills and rebalances that
+//! touch too many rows for individual `INSERT`s or `UPDATE`s.
+//!
+//! [CopyEncoder] produces the binary COPY format and can be used with any
+//! client that accepts raw COPY data. With the `postgres` feature,
+//! [copy_in] streams rows over an sqlx connection.
+//!
+//! ```rust
+//! use fractional_index::{pg_copy::CopyEncoder, FractionalIndex};
+//!
+//! let mut encoder = CopyEncoder::new();
+//! encoder.push(&1i64, &FractionalIndex::default());
+//! let data = encoder.finish();
+//! assert!(data.starts_with(b"PGCOPY\n\xff\r\n\0"));
+//! ```
+use crate::{sql::Dialect, FractionalIndex};
+
+const SIGNATURE: &[u8] = b"PGCOPY\n\xff\r\n\0";
+
+/// The number of bytes [copy_in] buffers before sending them.
+#[cfg(feature = "postgres")]
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A value that can be written as the id column of a binary COPY row.
+///
+/// The encoding must match the column's type: [i32] for `integer`, [i64]
+/// for `bigint`, and [str] or [String] for `text`.
+pub trait CopyId {
+    /// Appends the binary representation of this value, without a length
+    /// prefix.
+    fn encode(&self, buf: &mut Vec<u8>);
+}
+
+impl CopyId for i32 {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+impl CopyId for i64 {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+impl CopyId for str {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl CopyId for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.as_str().encode(buf);
+    }
+}
+
+impl<T: CopyId + ?Sized> CopyId for &T {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (**self).encode(buf);
+    }
+}
+
+/// Encodes `(id, FractionalIndex)` rows in Postgres's binary COPY format.
+///
+/// The header is written on construction and the trailer by
+/// [CopyEncoder::finish]. Data can be taken incrementally with
+/// [CopyEncoder::take] to stream large loads.
+pub struct CopyEncoder {
+    buf: Vec<u8>,
+}
+
+impl Default for CopyEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CopyEncoder {
+    /// Constructs an encoder, writing the header.
+    pub fn new() -> Self {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(SIGNATURE);
+        // Flags and header extension length.
+        buf.extend_from_slice(&0i32.to_be_bytes());
+        buf.extend_from_slice(&0i32.to_be_bytes());
+        CopyEncoder { buf }
+    }
+
+    /// Appends a row with the given id and index.
+    pub fn push<Id: CopyId + ?Sized>(&mut self, id: &Id, index: &FractionalIndex) {
+        self.buf.extend_from_slice(&2i16.to_be_bytes());
+
+        let start = self.buf.len();
+        self.buf.extend_from_slice(&[0; 4]);
+        id.encode(&mut self.buf);
+        let len = (self.buf.len() - start - 4) as i32;
+        self.buf[start..start + 4].copy_from_slice(&len.to_be_bytes());
+
+        self.buf
+            .extend_from_slice(&(index.len() as i32).to_be_bytes());
+        self.buf.extend_from_slice(index.as_bytes());
+    }
+
+    /// Returns the number of bytes buffered since construction or the last
+    /// call to [CopyEncoder::take].
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns true if no bytes are buffered.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the buffered bytes, leaving the buffer empty.
+    pub fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buf)
+    }
+
+    /// Writes the trailer and returns the remaining buffered bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.buf.extend_from_slice(&(-1i16).to_be_bytes());
+        self.buf
+    }
+}
+
+/// Returns the `COPY` statement used by [copy_in]. Identifiers are quoted,
+/// so they are matched case-sensitively and can't be schema-qualified.
+pub fn copy_statement(table: &str, id_column: &str, index_column: &str) -> String {
+    format!(
+        "COPY {} ({}, {}) FROM STDIN (FORMAT binary)",
+        Dialect::Postgres.quote(table),
+        Dialect::Postgres.quote(id_column),
+        Dialect::Postgres.quote(index_column)
+    )
+}
+
+/// Streams `(id, index)` rows into `table` with a binary `COPY`, returning
+/// the number of rows copied.
+///
+/// The statement is built by [copy_statement]. To update existing rows,
+/// copy into a temporary table and `UPDATE ... FROM` it.
+#[cfg(feature = "postgres")]
+pub async fn copy_in<I, Id, B>(
+    conn: &mut sqlx::PgConnection,
+    table: &str,
+    id_column: &str,
+    index_column: &str,
+    rows: I,
+) -> Result<u64, sqlx::Error>
+where
+    I: IntoIterator<Item = (Id, B)>,
+    Id: CopyId,
+    B: std::borrow::Borrow<FractionalIndex>,
+{
+    let statement = copy_statement(table, id_column, index_column);
+    let mut copy = conn.copy_in_raw(&statement).await?;
+
+    let mut encoder = CopyEncoder::new();
+    for (id, index) in rows {
+        encoder.push(&id, index.borrow());
+        if encoder.len() >= CHUNK_SIZE {
+            copy.send(encoder.take()).await?;
+        }
+    }
+    copy.send(encoder.finish()).await?;
+    copy.finish().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        let mut encoder = CopyEncoder::new();
+        encoder.push(&7i32, &FractionalIndex::default());
+        encoder.push(
+            "ab",
+            &FractionalIndex::new_after(&FractionalIndex::default()),
+        );
+        let data = encoder.finish();
+
+        let mut expected = b"PGCOPY\n\xff\r\n\0".to_vec();
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[0, 2, 0, 0, 0, 4, 0, 0, 0, 7, 0, 0, 0, 1, 128]);
+        expected.extend_from_slice(&[0, 2, 0, 0, 0, 2, b'a', b'b', 0, 0, 0, 2, 129, 128]);
+        expected.extend_from_slice(&[0xff, 0xff]);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn take_splits_stream() {
+        let mut encoder = CopyEncoder::new();
+        let mut data = encoder.take();
+        assert!(encoder.is_empty());
+        encoder.push(&1i64, &FractionalIndex::default());
+        data.extend(encoder.finish());
+
+        let mut whole = CopyEncoder::new();
+        whole.push(&1i64, &FractionalIndex::default());
+        assert_eq!(data, whole.finish());
+    }
+
+    #[test]
+    fn statement() {
+        assert_eq!(
+            copy_statement("items", "id", "My \"Index\""),
+            r#"COPY "items" ("id", "My ""Index""") FROM STDIN (FORMAT binary)"#
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Helpers for translating between positions in a sorted slice of
+//! [FractionalIndex]es and the bounds used to construct new ones.
+//!
+//! ```rust
+//! use fractional_index::{position::{neighbors_at, position_for}, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let c = FractionalIndex::new_after(&a);
+//! let keys = vec![a.clone(), c.clone()];
+//!
+//! // Construct a key that belongs at position 1.
+//! let (lower, upper) = neighbors_at(&keys, 1);
+//! let b = FractionalIndex::new(lower, upper).unwrap();
+//! assert_eq!(position_for(&keys, &b), 1);
+//! ```
+use crate::FractionalIndex;
+
+/// Returns the position at which `key` would be inserted into the sorted
+/// slice `keys` to keep it sorted. If `keys` already contains `key`, this
+/// is the position of the existing key.
+pub fn position_for(keys: &[FractionalIndex], key: &FractionalIndex) -> usize {
+    keys.partition_point(|k| k < key)
+}
+
+/// Returns the bounds to pass to [FractionalIndex::new] to construct a key
+/// that belongs at `position` in the sorted slice `keys`, i.e. the keys
+/// that would be immediately before and after an element inserted at
+/// that position.
+///
+/// Panics if `position > keys.len()`.
+pub fn neighbors_at(
+    keys: &[FractionalIndex],
+    position: usize,
+) -> (Option<&FractionalIndex>, Option<&FractionalIndex>) {
+    assert!(
+        position <= keys.len(),
+        "Position (is {}) should be <= len (is {}).",
+        position,
+        keys.len()
+    );
+    let lower = position.checked_sub(1).map(|i| &keys[i]);
+    let upper = keys.get(position);
+    (lower, upper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(n: usize) -> Vec<FractionalIndex> {
+        let mut keys = vec![FractionalIndex::default()];
+        for _ in 1..n {
+            keys.push(FractionalIndex::new_after(keys.last().unwrap()));
+        }
+        keys
+    }
+
+    #[test]
+    fn positions() {
+        let keys = keys(3);
+        assert_eq!(
+            position_for(&keys, &FractionalIndex::new_before(&keys[0])),
+            0
+        );
+        assert_eq!(position_for(&keys, &keys[0]), 0);
+        assert_eq!(position_for(&keys, &keys[1]), 1);
+        assert_eq!(
+            position_for(
+                &keys,
+                &FractionalIndex::new_between(&keys[1], &keys[2]).unwrap()
+            ),
+            2
+        );
+        assert_eq!(
+            position_for(&keys, &FractionalIndex::new_after(&keys[2])),
+            3
+        );
+        assert_eq!(position_for(&[], &keys[0]), 0);
+    }
+
+    #[test]
+    fn neighbors() {
+        let keys = keys(2);
+        assert_eq!(neighbors_at(&keys, 0), (None, Some(&keys[0])));
+        assert_eq!(neighbors_at(&keys, 1), (Some(&keys[0]), Some(&keys[1])));
+        assert_eq!(neighbors_at(&keys, 2), (Some(&keys[1]), None));
+        assert_eq!(neighbors_at(&[], 0), (None, None));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut keys = keys(4);
+        for position in [0, 5, 2, 3, 7, 1] {
+            let (lower, upper) = neighbors_at(&keys, position);
+            let key = FractionalIndex::new(lower, upper).unwrap();
+            assert_eq!(position_for(&keys, &key), position);
+            keys.insert(position, key);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn neighbors_out_of_bounds() {
+        neighbors_at(&keys(2), 3);
+    }
+}
@@ -0,0 +1,82 @@
+//! Kani proof harnesses for the key generation invariants.
+//!
+//! These are compiled only by `cargo kani`, which sets `cfg(kani)`:
+//!
+//! ```text
+//! cargo install --locked kani-verifier && cargo kani setup
+//! cargo kani --features std
+//! ```
+//!
+//! Each harness checks every valid key up to [MAX_LEN] bytes, which is
+//! enough to reach every branch of [ByteFormat]'s derivation: a key is
+//! valid if and only if it ends with the terminator. Kani additionally
+//! checks that no harness can panic or overflow, including the `panic!`s
+//! in `derive_before` and `derive_after` and the `delta` arithmetic of
+//! `Derived::parts`.
+use crate::{fixed::FixedFractionalIndex, format::ByteFormat};
+
+/// The longest input key considered by the harnesses.
+const MAX_LEN: usize = 4;
+
+/// Capacity for generated keys, which are at most one byte longer than the
+/// longest input.
+const CAPACITY: usize = MAX_LEN + 1;
+
+/// Returns an arbitrary valid key of up to [MAX_LEN] bytes.
+fn any_key() -> FixedFractionalIndex<CAPACITY> {
+    let bytes: [u8; MAX_LEN] = kani::any();
+    let len: usize = kani::any();
+    kani::assume((1..=MAX_LEN).contains(&len));
+    kani::assume(bytes[len - 1] == ByteFormat::DEFAULT.terminator());
+    FixedFractionalIndex::from_bytes(&bytes[..len]).unwrap()
+}
+
+#[kani::proof]
+#[kani::unwind(7)]
+fn new_before_is_less() {
+    let key = any_key();
+    let before = FixedFractionalIndex::new_before(&key).unwrap();
+    assert!(before < key);
+}
+
+#[kani::proof]
+#[kani::unwind(7)]
+fn new_after_is_greater() {
+    let key = any_key();
+    let after = FixedFractionalIndex::new_after(&key).unwrap();
+    assert!(key < after);
+}
+
+#[kani::proof]
+#[kani::unwind(7)]
+fn new_between_is_between() {
+    let left = any_key();
+    let right = any_key();
+    match FixedFractionalIndex::new_between(&left, &right).unwrap() {
+        Some(between) => assert!(left < between && between < right),
+        None => assert!(left >= right),
+    }
+}
+
+#[cfg(feature = "std")]
+mod heap {
+    use super::*;
+    use crate::FractionalIndex;
+
+    fn any_index() -> FractionalIndex {
+        FractionalIndex::from_bytes(any_key().as_bytes().to_vec()).unwrap()
+    }
+
+    #[kani::proof]
+    #[kani::unwind(7)]
+    fn fractional_index_invariants() {
+        let left = any_index();
+        let right = any_index();
+        assert!(FractionalIndex::new_before(&left) < left);
+        assert!(left < FractionalIndex::new_after(&left));
+        match FractionalIndex::new_between(&left, &right) {
+            Some(between) => assert!(left < between && between < right),
+            None => assert!(left >= right),
+        }
+    }
+}
@@ -0,0 +1,441 @@
+//! Planning of rebalances, which replace long keys with shorter ones while
+//! preserving order.
+//!
+//! [plan] computes the remapping needed to bring every key within a target
+//! length, without applying it, along with [RebalanceStats] for reviewing
+//! its cost. Only keys in windows around the long keys are rewritten: each
+//! window is widened until evenly spaced keys between its unchanged
+//! neighbors fit within the target.
+//!
+//! ```rust
+//! use fractional_index::{rebalance::plan, FractionalIndex};
+//!
+//! let mut lower = FractionalIndex::default();
+//! let mut upper = FractionalIndex::new_after(&lower);
+//! let mut keys = vec![lower.clone(), upper.clone()];
+//! for i in 0..20 {
+//!     let key = FractionalIndex::new_between(&lower, &upper).unwrap();
+//!     keys.push(key.clone());
+//!     if i % 2 == 0 {
+//!         lower = key;
+//!     } else {
+//!         upper = key;
+//!     }
+//! }
+//! keys.sort();
+//!
+//! let plan = plan(&keys, 4).unwrap();
+//! assert!(plan.stats.max_len_after <= 4);
+//! assert!(plan.stats.bytes_saved() > 0);
+//! ```
+//!
+//! [relabel] instead replaces every key, e.g. to anonymize an export or to
+//! minimize a test fixture.
+use crate::{instrument, ordered_list::UnsortedError, FractionalIndex};
+use std::collections::BTreeMap;
+
+/// The cost and effect of a [RebalancePlan].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RebalanceStats {
+    /// The number of keys that are rewritten.
+    pub rows_touched: usize,
+    /// The total length of the rewritten keys before the rebalance.
+    pub bytes_before: usize,
+    /// The total length of the rewritten keys after the rebalance.
+    pub bytes_after: usize,
+    /// The length of the longest key before the rebalance.
+    pub max_len_before: usize,
+    /// The length of the longest key after the rebalance.
+    pub max_len_after: usize,
+}
+
+impl RebalanceStats {
+    /// Returns the number of bytes saved across all keys, which may be
+    /// negative if short keys in a window had to be lengthened.
+    pub fn bytes_saved(&self) -> i64 {
+        self.bytes_before as i64 - self.bytes_after as i64
+    }
+}
+
+/// A proposed rebalance, returned by [plan].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RebalancePlan {
+    /// Pairs of old and new keys, in order. Keys that are not listed are
+    /// unchanged.
+    pub remap: Vec<(FractionalIndex, FractionalIndex)>,
+    pub stats: RebalanceStats,
+}
+
+impl RebalancePlan {
+    /// Splits the remapping into chunks of at most `max_rows` updates, to
+    /// apply and commit one at a time.
+    ///
+    /// The updates are ordered so that, when applied in order, the keys
+    /// stay distinct and in the same order after every update: first the
+    /// keys that decrease, in ascending order, then the keys that increase,
+    /// in descending order. Readers may therefore see a partly applied
+    /// rebalance, but never an inconsistent order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_rows` is 0.
+    pub fn into_chunks(self, max_rows: usize) -> RebalanceChunks {
+        assert!(max_rows > 0, "Chunks must hold at least one update.");
+        let (mut decreasing, increasing): (Vec<_>, Vec<_>) =
+            self.remap.into_iter().partition(|(old, new)| new < old);
+        decreasing.extend(increasing.into_iter().rev());
+        RebalanceChunks {
+            updates: decreasing.into_iter(),
+            max_rows,
+        }
+    }
+
+    /// Returns the chunks of [RebalancePlan::into_chunks] as a
+    /// [Stream](futures_core::Stream), enabled by the `stream` feature.
+    ///
+    /// The next chunk is only produced when the stream is polled, so a
+    /// consumer that commits each chunk before polling for the next one
+    /// applies backpressure to the rebalance.
+    #[cfg(feature = "stream")]
+    pub fn into_stream(self, max_rows: usize) -> RebalanceStream {
+        RebalanceStream {
+            chunks: self.into_chunks(max_rows),
+        }
+    }
+}
+
+/// An iterator over chunks of the updates of a [RebalancePlan], returned
+/// by [RebalancePlan::into_chunks].
+#[derive(Clone, Debug)]
+pub struct RebalanceChunks {
+    updates: std::vec::IntoIter<(FractionalIndex, FractionalIndex)>,
+    max_rows: usize,
+}
+
+impl Iterator for RebalanceChunks {
+    type Item = Vec<(FractionalIndex, FractionalIndex)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk: Vec<_> = self.updates.by_ref().take(self.max_rows).collect();
+        (!chunk.is_empty()).then_some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let chunks = self.updates.len().div_ceil(self.max_rows);
+        (chunks, Some(chunks))
+    }
+}
+
+impl ExactSizeIterator for RebalanceChunks {}
+
+impl std::iter::FusedIterator for RebalanceChunks {}
+
+/// A stream of chunks of the updates of a [RebalancePlan], returned by
+/// [RebalancePlan::into_stream].
+#[cfg(feature = "stream")]
+#[derive(Clone, Debug)]
+pub struct RebalanceStream {
+    chunks: RebalanceChunks,
+}
+
+#[cfg(feature = "stream")]
+impl futures_core::Stream for RebalanceStream {
+    type Item = Vec<(FractionalIndex, FractionalIndex)>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        std::task::Poll::Ready(self.chunks.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+/// A range of positions to rewrite, and their new keys.
+struct Window {
+    start: usize,
+    end: usize,
+    keys: Vec<FractionalIndex>,
+}
+
+fn fill(keys: &[FractionalIndex], start: usize, end: usize) -> Vec<FractionalIndex> {
+    let lower = start.checked_sub(1).map(|i| &keys[i]);
+    let upper = keys.get(end);
+    FractionalIndex::spaced(lower, upper, end - start)
+        .expect("The keys were checked to be strictly ascending.")
+}
+
+fn longest(keys: &[FractionalIndex]) -> usize {
+    keys.iter().map(|key| key.len()).max().unwrap_or(0)
+}
+
+/// Plans a rebalance of `keys`, which must be strictly ascending, so that no
+/// key is longer than `max_len` bytes.
+///
+/// If `max_len` is too small to be reached even by rewriting every key, the
+/// plan rewrites every key with the shortest evenly spaced keys.
+pub fn plan(keys: &[FractionalIndex], max_len: usize) -> Result<RebalancePlan, UnsortedError> {
+    if let Some(position) = keys.windows(2).position(|pair| pair[0] >= pair[1]) {
+        return Err(UnsortedError {
+            position: position + 1,
+        });
+    }
+
+    let mut windows: Vec<Window> = Vec::new();
+    let mut i = 0;
+    while i < keys.len() {
+        if keys[i].len() <= max_len {
+            i += 1;
+            continue;
+        }
+
+        let mut start = i;
+        let mut end = i + 1;
+        while end < keys.len() && keys[end].len() > max_len {
+            end += 1;
+        }
+        // Merge with the previous window if they touch, since its new keys
+        // would otherwise be computed against stale neighbors.
+        if let Some(previous) = windows.last() {
+            if previous.end >= start {
+                start = previous.start;
+                windows.pop();
+            }
+        }
+
+        let mut new_keys = fill(keys, start, end);
+        let mut widen_start = true;
+        while longest(&new_keys) > max_len && (start > 0 || end < keys.len()) {
+            if (widen_start && start > 0) || end == keys.len() {
+                start -= 1;
+            } else {
+                end += 1;
+            }
+            widen_start = !widen_start;
+            if let Some(previous) = windows.last() {
+                if previous.end >= start {
+                    start = previous.start;
+                    windows.pop();
+                }
+            }
+            new_keys = fill(keys, start, end);
+        }
+
+        windows.push(Window {
+            start,
+            end,
+            keys: new_keys,
+        });
+        i = end;
+    }
+
+    let mut plan = RebalancePlan::default();
+    plan.stats.max_len_before = longest(keys);
+    let mut max_len_after = 0;
+    let mut next = 0;
+    for window in windows {
+        max_len_after = max_len_after.max(longest(&keys[next..window.start]));
+        for (old, new) in keys[window.start..window.end].iter().zip(window.keys) {
+            max_len_after = max_len_after.max(new.len());
+            if *old == new {
+                continue;
+            }
+            plan.stats.rows_touched += 1;
+            plan.stats.bytes_before += old.len();
+            plan.stats.bytes_after += new.len();
+            plan.remap.push((old.clone(), new));
+        }
+        next = window.end;
+    }
+    plan.stats.max_len_after = max_len_after.max(longest(&keys[next..]));
+    instrument::rebalanced(plan.stats.rows_touched);
+    Ok(plan)
+}
+
+/// Maps each distinct key in `keys`, which may be in any order and contain
+/// duplicates, to a fresh key. The new keys are in the same order as the
+/// old ones and are evenly spaced, and equal keys map to the same new key.
+///
+/// The new keys are the shortest that can hold the set, and they depend
+/// only on the number of distinct keys, so they reveal nothing else about
+/// the original keys.
+pub fn relabel<'a, I>(keys: I) -> BTreeMap<FractionalIndex, FractionalIndex>
+where
+    I: IntoIterator<Item = &'a FractionalIndex>,
+{
+    let mut old: Vec<&FractionalIndex> = keys.into_iter().collect();
+    old.sort();
+    old.dedup();
+    let new = FractionalIndex::spaced(None, None, old.len())
+        .expect("Missing bounds are always in order.");
+    old.into_iter().cloned().zip(new).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn relabel_keys() {
+        let mut keys: Vec<FractionalIndex> = FractionalIndex::spaced(None, None, 3).unwrap();
+        for _ in 0..20 {
+            keys.push(FractionalIndex::new_between(&keys[0], keys.last().unwrap()).unwrap());
+        }
+        keys.push(keys[5].clone());
+        keys.reverse();
+
+        let mapping = relabel(&keys);
+        assert_eq!(mapping.len(), 23);
+        assert!(mapping.values().all(|key| key.len() == 2));
+        let new: Vec<&FractionalIndex> = mapping.values().collect();
+        for pair in new.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+        for a in &keys {
+            for b in &keys {
+                assert_eq!(a.cmp(b), mapping[a].cmp(&mapping[b]));
+            }
+        }
+        assert!(relabel(&[]).is_empty());
+    }
+
+    /// Applies a plan to a copy of `keys`.
+    fn apply(keys: &[FractionalIndex], plan: &RebalancePlan) -> Vec<FractionalIndex> {
+        let remap: HashMap<&[u8], &FractionalIndex> = plan
+            .remap
+            .iter()
+            .map(|(old, new)| (old.as_bytes(), new))
+            .collect();
+        keys.iter()
+            .map(|key| (*remap.get(key.as_bytes()).unwrap_or(&key)).clone())
+            .collect()
+    }
+
+    fn check(keys: &[FractionalIndex], max_len: usize) -> RebalancePlan {
+        let plan = plan(keys, max_len).unwrap();
+        let rebalanced = apply(keys, &plan);
+        for pair in rebalanced.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+        assert_eq!(
+            plan.stats.max_len_after,
+            rebalanced.iter().map(|key| key.len()).max().unwrap_or(0)
+        );
+        assert_eq!(plan.stats.rows_touched, plan.remap.len());
+        plan
+    }
+
+    #[test]
+    fn local_rebalance() {
+        // Short keys at the ends, with a cluster of long keys in the middle.
+        let mut keys: Vec<FractionalIndex> = FractionalIndex::spaced(None, None, 50).unwrap();
+        let (mut lower, mut upper) = (keys[25].clone(), keys[26].clone());
+        for i in 0..40 {
+            let key = FractionalIndex::new_between(&lower, &upper).unwrap();
+            keys.push(key.clone());
+            // Alternate sides, so that each key halves the remaining gap.
+            if i % 2 == 0 {
+                lower = key;
+            } else {
+                upper = key;
+            }
+        }
+        keys.sort();
+        let before = keys.iter().map(|key| key.len()).max().unwrap();
+        assert!(before > 4);
+
+        let plan = check(&keys, 4);
+        assert!(plan.stats.max_len_after <= 4);
+        assert!(plan.stats.rows_touched < keys.len());
+        assert!(plan.stats.bytes_saved() > 0);
+        assert_eq!(plan.stats.max_len_before, before);
+    }
+
+    #[test]
+    fn already_balanced() {
+        let keys = FractionalIndex::spaced(None, None, 100).unwrap();
+        let plan = check(&keys, 8);
+        assert!(plan.remap.is_empty());
+        assert_eq!(plan.stats.bytes_saved(), 0);
+        assert!(check(&[], 1).remap.is_empty());
+    }
+
+    #[test]
+    fn unreachable_target() {
+        let mut keys = vec![FractionalIndex::default()];
+        for _ in 0..300 {
+            keys.push(FractionalIndex::new_after(keys.last().unwrap()));
+        }
+        // 300 keys can't all be one byte long, so every key is rewritten.
+        let plan = check(&keys, 1);
+        assert!(plan.stats.max_len_after > 1);
+        assert!(plan.stats.max_len_after < plan.stats.max_len_before);
+    }
+
+    #[test]
+    fn chunks() {
+        // Long keys at both ends, so that some keys decrease and others
+        // increase.
+        let mut keys = vec![FractionalIndex::default()];
+        for _ in 0..200 {
+            keys.insert(0, FractionalIndex::new_before(&keys[0]));
+            keys.push(FractionalIndex::new_after(keys.last().unwrap()));
+        }
+        let plan = check(&keys, 2);
+        let rows = plan.remap.len();
+        let remap: BTreeMap<_, _> = plan.remap.iter().cloned().collect();
+        let expected: Vec<_> = keys
+            .iter()
+            .map(|key| remap.get(key).unwrap_or(key).clone())
+            .collect();
+        let chunks = plan.into_chunks(7);
+        assert_eq!(chunks.len(), rows.div_ceil(7));
+
+        // Every prefix of the updates leaves the keys distinct and sorted.
+        let mut current = keys;
+        for chunk in chunks {
+            assert!(!chunk.is_empty() && chunk.len() <= 7);
+            for (old, new) in chunk {
+                let position = current.binary_search(&old).unwrap();
+                current[position] = new;
+                assert!(current.windows(2).all(|w| w[0] < w[1]));
+            }
+        }
+        assert_eq!(current, expected);
+    }
+
+    #[cfg(feature = "stream")]
+    #[tokio::test]
+    async fn stream() {
+        use futures_core::Stream;
+        use std::pin::Pin;
+
+        let mut keys = vec![FractionalIndex::default()];
+        for _ in 0..300 {
+            keys.push(FractionalIndex::new_after(keys.last().unwrap()));
+        }
+        let plan = check(&keys, 2);
+        let expected: Vec<_> = plan.clone().into_chunks(4).collect();
+        let mut stream = plan.into_stream(4);
+        let mut chunks = Vec::new();
+        while let Some(chunk) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+        {
+            chunks.push(chunk);
+        }
+        assert_eq!(chunks, expected);
+    }
+
+    #[test]
+    fn unsorted() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        assert_eq!(
+            plan(&[a.clone(), b, a], 4),
+            Err(UnsortedError { position: 2 })
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! Server-side checks of keys proposed by clients.
+//!
+//! Clients usually compute keys optimistically, from their own (possibly
+//! stale) view of a list, and send them to a server. The server then
+//! compares each proposed key with the current neighbors of the position
+//! the client intended: a key that still falls between them is accepted
+//! as is, a key that no longer does is nudged to the nearest edge of the
+//! gap, and bytes that are not a valid key are rejected. Either way, the
+//! server replies with the authoritative key.
+//!
+//! ```rust
+//! use fractional_index::{reconcile::{reconcile_in, Reconciled}, FractionalIndex, OrderedList};
+//!
+//! let mut list = OrderedList::new();
+//! let a = list.push_back("a");
+//! let b = list.push_back("b");
+//!
+//! // A client inserts "x" after "a"...
+//! let proposed = FractionalIndex::new_between(&a, &b).unwrap();
+//! // ...but another client inserts "y" there first.
+//! list.insert(proposed.clone(), "y");
+//!
+//! let reconciled = reconcile_in(&list, proposed.as_bytes(), Some(&a)).unwrap();
+//! assert!(matches!(reconciled, Reconciled::Adjusted { .. }));
+//! let key = reconciled.into_key();
+//! assert!(a < key && key < proposed);
+//! ```
+use crate::{instrument, DecodeError, FractionalIndex, OrderedList};
+use std::fmt::{self, Display};
+
+/// The authoritative key for a proposal that was not rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reconciled {
+    /// The proposed key falls within the gap and was kept.
+    Accepted(FractionalIndex),
+    /// The proposed key fell outside the gap, or on one of its bounds, and
+    /// was replaced by `key`.
+    Adjusted {
+        proposed: FractionalIndex,
+        key: FractionalIndex,
+    },
+}
+
+impl Reconciled {
+    /// Returns the authoritative key.
+    pub fn key(&self) -> &FractionalIndex {
+        match self {
+            Reconciled::Accepted(key) | Reconciled::Adjusted { key, .. } => key,
+        }
+    }
+
+    /// Returns the authoritative key, consuming the outcome.
+    pub fn into_key(self) -> FractionalIndex {
+        match self {
+            Reconciled::Accepted(key) | Reconciled::Adjusted { key, .. } => key,
+        }
+    }
+
+    /// Returns true if the proposed key was kept.
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Reconciled::Accepted(_))
+    }
+}
+
+/// The reason a proposal was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rejected {
+    /// The proposed bytes are not a valid key.
+    Invalid(DecodeError),
+    /// The lower bound is not less than the upper bound, so no key fits
+    /// between them.
+    UnorderedBounds,
+    /// The key the proposal was placed after is not in the list.
+    UnknownAnchor,
+}
+
+impl Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejected::Invalid(err) => write!(f, "The proposed key is invalid: {}", err),
+            Rejected::UnorderedBounds => {
+                write!(f, "The lower bound is not less than the upper bound.")
+            }
+            Rejected::UnknownAnchor => write!(f, "The anchor key is not in the list."),
+        }
+    }
+}
+
+impl std::error::Error for Rejected {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Rejected::Invalid(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// The number of evenly spaced slots a gap is divided into when a proposal
+/// is nudged into it. More slots keep the key closer to the bound the
+/// proposal crossed, at the cost of longer keys.
+const NUDGE_SLOTS: usize = 8;
+
+/// Checks the `proposed` key bytes against the current bounds of the gap
+/// the client placed them in.
+///
+/// A key strictly between the bounds is accepted. Otherwise it is nudged
+/// into the gap: it is replaced by the first of a few evenly spaced keys in
+/// the gap if it is at or before `lower`, or by the last of them if it is
+/// at or after `upper`. This keeps the key near the bound it crossed while
+/// leaving room for later inserts on either side.
+///
+/// The length of the proposed bytes is checked against
+/// [limits::max_len](crate::limits::max_len).
+pub fn reconcile(
+    proposed: &[u8],
+    lower: Option<&FractionalIndex>,
+    upper: Option<&FractionalIndex>,
+) -> Result<Reconciled, Rejected> {
+    if let (Some(lower), Some(upper)) = (lower, upper) {
+        if lower >= upper {
+            return Err(Rejected::UnorderedBounds);
+        }
+    }
+    let proposed = FractionalIndex::from_bytes(proposed.to_vec()).map_err(Rejected::Invalid)?;
+
+    let slot = if lower.is_some_and(|lower| proposed <= *lower) {
+        0
+    } else if upper.is_some_and(|upper| proposed >= *upper) {
+        NUDGE_SLOTS - 1
+    } else {
+        return Ok(Reconciled::Accepted(proposed));
+    };
+    let key = FractionalIndex::at_fraction_between(lower, upper, slot, NUDGE_SLOTS)
+        .expect("The bounds were checked above.");
+    instrument::collisions("reconcile", 1);
+    Ok(Reconciled::Adjusted { proposed, key })
+}
+
+/// Checks the `proposed` key bytes for an insertion into `list` directly
+/// after the key `after`, or at the front if it is None, like
+/// [reconcile] with the current neighbors of that position as bounds.
+///
+/// The proposal is rejected if `after` is not a key of `list`.
+pub fn reconcile_in<T>(
+    list: &OrderedList<T>,
+    proposed: &[u8],
+    after: Option<&FractionalIndex>,
+) -> Result<Reconciled, Rejected> {
+    let upper = match after {
+        Some(after) => {
+            if !list.contains_key(after) {
+                return Err(Rejected::UnknownAnchor);
+            }
+            list.key_after(after)
+        }
+        None => list.keys().next(),
+    };
+    reconcile(proposed, after, upper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(bytes: &[u8]) -> FractionalIndex {
+        FractionalIndex::from_bytes(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn accept_and_nudge() {
+        let (a, b) = (key(&[100, 128]), key(&[110, 128]));
+        let inside = key(&[105, 128]);
+        assert_eq!(
+            reconcile(inside.as_bytes(), Some(&a), Some(&b)),
+            Ok(Reconciled::Accepted(inside.clone()))
+        );
+        assert!(reconcile(inside.as_bytes(), None, None)
+            .unwrap()
+            .is_accepted());
+
+        for (proposed, expected) in [
+            (key(&[90, 128]), key(&[101, 128])),
+            (a.clone(), key(&[101, 128])),
+            (b.clone(), key(&[109, 128])),
+            (key(&[120, 128]), key(&[109, 128])),
+        ] {
+            let reconciled = reconcile(proposed.as_bytes(), Some(&a), Some(&b)).unwrap();
+            assert_eq!(
+                reconciled,
+                Reconciled::Adjusted {
+                    proposed,
+                    key: expected
+                }
+            );
+        }
+
+        let key = reconcile(b.as_bytes(), None, Some(&a)).unwrap().into_key();
+        assert!(key < a);
+        let key = reconcile(a.as_bytes(), Some(&b), None).unwrap().into_key();
+        assert!(b < key);
+    }
+
+    #[test]
+    fn narrow_gap() {
+        let a = key(&[100, 128]);
+        let b = FractionalIndex::new_between(&a, &FractionalIndex::new_after(&a)).unwrap();
+        for proposed in [&a, &b] {
+            let key = reconcile(proposed.as_bytes(), Some(&a), Some(&b))
+                .unwrap()
+                .into_key();
+            assert!(a < key && key < b);
+        }
+    }
+
+    #[test]
+    fn in_list() {
+        let mut list = OrderedList::new();
+        let a = list.push_back('a');
+        let b = list.push_back('b');
+        let front = FractionalIndex::new_before(&a);
+
+        assert!(reconcile_in(&list, front.as_bytes(), None)
+            .unwrap()
+            .is_accepted());
+        let key = reconcile_in(&list, b.as_bytes(), None).unwrap().into_key();
+        assert!(key < a);
+        let key = reconcile_in(&list, b.as_bytes(), Some(&a))
+            .unwrap()
+            .into_key();
+        assert!(a < key && key < b);
+        let key = reconcile_in(&list, a.as_bytes(), Some(&b))
+            .unwrap()
+            .into_key();
+        assert!(b < key);
+        assert_eq!(
+            reconcile_in(&list, a.as_bytes(), Some(&front)),
+            Err(Rejected::UnknownAnchor)
+        );
+    }
+
+    #[test]
+    fn rejections() {
+        let (a, b) = (key(&[100, 128]), key(&[110, 128]));
+        assert_eq!(
+            reconcile(&[105], Some(&a), Some(&b)),
+            Err(Rejected::Invalid(DecodeError::MissingTerminator {
+                found: Some(105)
+            }))
+        );
+        assert_eq!(
+            reconcile(a.as_bytes(), Some(&b), Some(&a)),
+            Err(Rejected::UnorderedBounds)
+        );
+        assert_eq!(
+            reconcile(a.as_bytes(), Some(&a), Some(&a)),
+            Err(Rejected::UnorderedBounds)
+        );
+    }
+}
@@ -0,0 +1,565 @@
+//! Planning of reorders that rewrite as few keys as possible.
+//!
+//! Given the current order of a list and the desired order of its ids,
+//! [plan] keeps the keys of the largest set of elements that are already
+//! in the desired relative order (a longest increasing subsequence), and
+//! computes new keys for the others only. A drag-and-drop that moves `k`
+//! elements therefore updates at most `k` rows, however long the list is.
+//!
+//! ```rust
+//! use fractional_index::{reorder::plan, FractionalIndex};
+//!
+//! let keys = FractionalIndex::new_after_many(&FractionalIndex::default(), 5);
+//! let current: Vec<(char, FractionalIndex)> = "abcde".chars().zip(keys).collect();
+//!
+//! // Move "a" and "b" to the end.
+//! let desired: Vec<char> = "cdeab".chars().collect();
+//! let moves = plan(&current, &desired).unwrap();
+//! assert_eq!(moves.len(), 2);
+//! assert_eq!((moves[0].0, moves[1].0), ('a', 'b'));
+//! assert!(current[4].1 < moves[0].1 && moves[0].1 < moves[1].1);
+//! ```
+//!
+//! [diff_orders] returns the same changes as [MoveOp]s, which a sync layer
+//! can send instead of a snapshot of the whole order. In the other
+//! direction, [apply_moves] turns requests to move ids before or after
+//! other ids into the key updates to persist.
+//!
+//! For a single drag and drop between positions of a list of keys,
+//! [key_for_move] returns the new key of the moved item.
+use crate::{move_op::MoveOp, FractionalIndex};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display};
+use std::hash::Hash;
+
+/// The error returned by [plan] when its arguments are inconsistent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReorderError {
+    /// The key of the current element at `position` is not greater than
+    /// the key before it.
+    Unsorted { position: usize },
+    /// The id at `position` of the desired order is not in the current
+    /// order.
+    UnknownId { position: usize },
+    /// The id at `position` of the desired order appears earlier in it.
+    DuplicateId { position: usize },
+    /// Some ids of the current order are not in the desired order.
+    MissingIds,
+}
+
+impl Display for ReorderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReorderError::Unsorted { position } => write!(
+                f,
+                "The key at position {} is not greater than the key before it.",
+                position
+            ),
+            ReorderError::UnknownId { position } => write!(
+                f,
+                "The id at position {} of the desired order is not in the current order.",
+                position
+            ),
+            ReorderError::DuplicateId { position } => write!(
+                f,
+                "The id at position {} of the desired order appears more than once.",
+                position
+            ),
+            ReorderError::MissingIds => write!(
+                f,
+                "Some ids of the current order are missing from the desired order."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReorderError {}
+
+/// A request to move an id directly before or after another id, as sent by
+/// a client.
+///
+/// With the `serde` feature, serializes like a JSON Patch operation, e.g.
+/// `{"op":"before","id":"a","target":"b"}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "op", rename_all = "snake_case"))]
+pub enum RelativeMove<Id> {
+    Before { id: Id, target: Id },
+    After { id: Id, target: Id },
+}
+
+impl<Id> RelativeMove<Id> {
+    /// Returns the id to move.
+    pub fn id(&self) -> &Id {
+        match self {
+            RelativeMove::Before { id, .. } | RelativeMove::After { id, .. } => id,
+        }
+    }
+
+    /// Returns the id to move next to.
+    pub fn target(&self) -> &Id {
+        match self {
+            RelativeMove::Before { target, .. } | RelativeMove::After { target, .. } => target,
+        }
+    }
+}
+
+/// The error returned by [apply_moves].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApplyMovesError {
+    /// The id of the move at `position` is not in the list.
+    UnknownId { position: usize },
+    /// The target of the move at `position` is not in the list.
+    UnknownTarget { position: usize },
+    /// The current list is invalid.
+    Invalid(ReorderError),
+}
+
+impl Display for ApplyMovesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyMovesError::UnknownId { position } => write!(
+                f,
+                "The id of the move at position {} is not in the list.",
+                position
+            ),
+            ApplyMovesError::UnknownTarget { position } => write!(
+                f,
+                "The target of the move at position {} is not in the list.",
+                position
+            ),
+            ApplyMovesError::Invalid(error) => write!(f, "Invalid list: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ApplyMovesError {}
+
+/// Returns the positions in `seq` of a longest strictly increasing
+/// subsequence, in ascending order.
+fn longest_increasing(seq: &[usize]) -> Vec<usize> {
+    // `tails[l]` is the position of the smallest value that ends an
+    // increasing subsequence of length `l + 1`.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = Vec::with_capacity(seq.len());
+    for (i, value) in seq.iter().enumerate() {
+        let l = tails.partition_point(|&tail| seq[tail] < *value);
+        previous.push(l.checked_sub(1).map(|l| tails[l]));
+        if l == tails.len() {
+            tails.push(i);
+        } else {
+            tails[l] = i;
+        }
+    }
+
+    let mut positions = Vec::with_capacity(tails.len());
+    let mut next = tails.last().copied();
+    while let Some(i) = next {
+        positions.push(i);
+        next = previous[i];
+    }
+    positions.reverse();
+    positions
+}
+
+/// Plans a reorder of `current`, a list of ids and their keys in ascending
+/// order of key, into the `desired` order of the same ids.
+///
+/// Returns the ids whose keys must change and their new keys, in the
+/// desired order. Every other id keeps its key. The number of changed ids
+/// is the smallest possible, and the new keys of consecutive changed ids
+/// are evenly spaced between their unchanged neighbors, except that no new
+/// key is the current key of another id, so the changes can be written one
+/// at a time, in any order.
+pub fn plan<Id>(
+    current: &[(Id, FractionalIndex)],
+    desired: &[Id],
+) -> Result<Vec<(Id, FractionalIndex)>, ReorderError>
+where
+    Id: Hash + Eq + Clone,
+{
+    if let Some(position) = current.windows(2).position(|pair| pair[0].1 >= pair[1].1) {
+        return Err(ReorderError::Unsorted {
+            position: position + 1,
+        });
+    }
+
+    let positions: HashMap<&Id, usize> = current
+        .iter()
+        .enumerate()
+        .map(|(position, (id, _))| (id, position))
+        .collect();
+    let mut seen = vec![false; current.len()];
+    let mut seq = Vec::with_capacity(desired.len());
+    for (position, id) in desired.iter().enumerate() {
+        let &from = positions
+            .get(id)
+            .ok_or(ReorderError::UnknownId { position })?;
+        if std::mem::replace(&mut seen[from], true) {
+            return Err(ReorderError::DuplicateId { position });
+        }
+        seq.push(from);
+    }
+    if seq.len() < current.len() {
+        return Err(ReorderError::MissingIds);
+    }
+
+    let holders: BTreeMap<&FractionalIndex, &Id> =
+        current.iter().map(|(id, key)| (key, id)).collect();
+    let mut moves = Vec::new();
+    let mut lower: Option<&FractionalIndex> = None;
+    let mut start = 0;
+    let kept = longest_increasing(&seq);
+    // Assign keys to each run of moved ids between two kept ones (or the
+    // ends of the list).
+    for end in kept.into_iter().map(Some).chain(std::iter::once(None)) {
+        let upper = end.map(|end| &current[seq[end]].1);
+        let run = &desired[start..end.unwrap_or(desired.len())];
+        let mut keys = FractionalIndex::spaced(lower, upper, run.len())
+            .expect("Kept keys are in ascending order.");
+        for i in 0..keys.len() {
+            match holders.get(&keys[i]) {
+                Some(holder) if *holder != &run[i] => {}
+                _ => continue,
+            }
+            // The key is still held by another moved id, so move just past
+            // it, staying below both the next held key and the next new key.
+            let next_held = holders.range::<&FractionalIndex, _>(&keys[i]..).nth(1);
+            let upper = match (next_held.map(|(key, _)| *key), keys.get(i + 1)) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            keys[i] = FractionalIndex::new(Some(&keys[i]), upper)
+                .expect("New keys are in ascending order.");
+        }
+        moves.extend(run.iter().cloned().zip(keys));
+        if let Some(end) = end {
+            lower = upper;
+            start = end + 1;
+        }
+    }
+    Ok(moves)
+}
+
+/// Returns the new key of the item at position `from` of `items`, a list of
+/// keys in ascending order, when it is moved to position `to`, e.g. by a
+/// drag and drop.
+///
+/// `to` is the item's position after the move, as in a list where the item
+/// is removed and inserted again at `to`, so moving it to the back is
+/// `to == items.len() - 1`. If `from == to`, the item keeps its key.
+///
+/// Panics if `from` or `to` is out of bounds, or if the keys around `to`
+/// are not in ascending order.
+pub fn key_for_move(items: &[FractionalIndex], from: usize, to: usize) -> FractionalIndex {
+    assert!(
+        from < items.len() && to < items.len(),
+        "Positions (are {} and {}) should be < the number of items (is {}).",
+        from,
+        to,
+        items.len()
+    );
+    // The neighbors of the item at `to`, once it is removed from `from`.
+    let (lower, upper) = match to.cmp(&from) {
+        std::cmp::Ordering::Equal => return items[from].clone(),
+        std::cmp::Ordering::Less => (to.checked_sub(1).map(|i| &items[i]), Some(&items[to])),
+        std::cmp::Ordering::Greater => (Some(&items[to]), items.get(to + 1)),
+    };
+    FractionalIndex::new(lower, upper).expect("The keys are in ascending order.")
+}
+
+/// Computes the fewest [MoveOp]s that turn the `old` order, a list of ids
+/// and their keys in ascending order of key, into the `new` order of the
+/// same ids.
+///
+/// The moves are those of [plan], in the new order. No move's destination
+/// is the current key of another element, so the moves can be applied to
+/// an [OrderedList](crate::OrderedList) one at a time, in any order.
+pub fn diff_orders<Id>(
+    old: &[(Id, FractionalIndex)],
+    new: &[Id],
+) -> Result<Vec<MoveOp<Id>>, ReorderError>
+where
+    Id: Hash + Eq + Clone,
+{
+    let moves = plan(old, new)?;
+    let from: HashMap<&Id, &FractionalIndex> = old.iter().map(|(id, key)| (id, key)).collect();
+    Ok(moves
+        .into_iter()
+        .filter(|(id, key)| from[id] != key)
+        .map(|(id, key)| {
+            let from = from[&id].clone();
+            MoveOp::new(id, from, key)
+        })
+        .collect())
+}
+
+/// Applies `moves`, in order, to `current`, a list of ids and their keys in
+/// ascending order of key.
+///
+/// Returns the ids whose keys must change and their new keys, as [plan]
+/// does for the resulting order. Moving an id relative to itself has no
+/// effect. Nothing is returned unless every move is valid.
+pub fn apply_moves<Id>(
+    current: &[(Id, FractionalIndex)],
+    moves: &[RelativeMove<Id>],
+) -> Result<Vec<(Id, FractionalIndex)>, ApplyMovesError>
+where
+    Id: Hash + Eq + Clone,
+{
+    let mut order: Vec<&Id> = current.iter().map(|(id, _)| id).collect();
+    for (position, relative) in moves.iter().enumerate() {
+        let from = order
+            .iter()
+            .position(|id| *id == relative.id())
+            .ok_or(ApplyMovesError::UnknownId { position })?;
+        let id = order.remove(from);
+        let target = match order.iter().position(|id| *id == relative.target()) {
+            Some(target) => target,
+            None if id == relative.target() => from,
+            None => return Err(ApplyMovesError::UnknownTarget { position }),
+        };
+        let to = match relative {
+            RelativeMove::Before { .. } => target,
+            RelativeMove::After { .. } if id == relative.target() => target,
+            RelativeMove::After { .. } => target + 1,
+        };
+        order.insert(to, id);
+    }
+
+    let desired: Vec<Id> = order.into_iter().cloned().collect();
+    plan(current, &desired).map_err(ApplyMovesError::Invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderedList;
+
+    fn current(ids: &str) -> Vec<(char, FractionalIndex)> {
+        let keys = FractionalIndex::spaced(None, None, ids.len()).unwrap();
+        ids.chars().zip(keys).collect()
+    }
+
+    /// Applies the moves and returns the ids in order of their new keys.
+    fn apply(current: &[(char, FractionalIndex)], moves: &[(char, FractionalIndex)]) -> String {
+        let mut keys: HashMap<char, FractionalIndex> = current.iter().cloned().collect();
+        keys.extend(moves.iter().cloned());
+        let mut ids: Vec<(FractionalIndex, char)> =
+            keys.into_iter().map(|(id, key)| (key, id)).collect();
+        ids.sort();
+        ids.into_iter().map(|(_, id)| id).collect()
+    }
+
+    #[test]
+    fn minimal_moves() {
+        let list = current("abcdefghij");
+        for (desired, expected_moves) in [
+            ("abcdefghij", 0),
+            ("jabcdefghi", 1),
+            ("bcdefghija", 1),
+            ("abdefghcij", 1),
+            ("hijabcdefg", 3),
+            ("jihgfedcba", 9),
+            ("badcfehgji", 5),
+        ] {
+            let ids: Vec<char> = desired.chars().collect();
+            let moves = plan(&list, &ids).unwrap();
+            assert_eq!(moves.len(), expected_moves, "{}", desired);
+            assert_eq!(apply(&list, &moves), desired);
+        }
+    }
+
+    #[test]
+    fn keys_of_other_moved_ids() {
+        // z and x swap places with their neighbors, and x's evenly spaced
+        // key would be z's current key.
+        let list: Vec<(char, FractionalIndex)> = [
+            ('L', vec![127, 128]),
+            ('z', vec![128]),
+            ('U', vec![129, 128]),
+            ('x', vec![130, 128]),
+        ]
+        .iter()
+        .map(|(id, bytes)| (*id, FractionalIndex::from_bytes(bytes.clone()).unwrap()))
+        .collect();
+        let desired: Vec<char> = "zLxU".chars().collect();
+        let moves = plan(&list, &desired).unwrap();
+        for (id, key) in &moves {
+            assert!(list.iter().all(|(other, held)| other == id || held != key));
+        }
+
+        // Writing the moves one at a time never gives two ids the same key.
+        for reverse in [false, true] {
+            let mut keys: BTreeMap<FractionalIndex, char> =
+                list.iter().map(|(id, key)| (key.clone(), *id)).collect();
+            let mut moves = moves.clone();
+            if reverse {
+                moves.reverse();
+            }
+            for (id, key) in moves {
+                keys.retain(|_, other| *other != id);
+                assert!(keys.insert(key, id).is_none());
+            }
+            let mut order: Vec<(FractionalIndex, char)> = keys.into_iter().collect();
+            order.sort();
+            assert_eq!(
+                order.into_iter().map(|(_, id)| id).collect::<String>(),
+                "zLxU"
+            );
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let list = current("abcdefghij");
+        for desired in [
+            "abcdefghij",
+            "jihgfedcba",
+            "badcfehgji",
+            "hijabcdefg",
+            "aibhcgdfej",
+        ] {
+            let ids: Vec<char> = desired.chars().collect();
+            let ops = diff_orders(&list, &ids).unwrap();
+            assert_eq!(ops.len(), plan(&list, &ids).unwrap().len(), "{}", desired);
+
+            // Apply the moves one at a time, in both orders.
+            for reverse in [false, true] {
+                let mut ordered: OrderedList<char> = OrderedList::new();
+                for (id, key) in &list {
+                    ordered.insert(key.clone(), *id);
+                }
+                let mut ops = ops.clone();
+                if reverse {
+                    ops.reverse();
+                }
+                for op in &ops {
+                    op.apply(&mut ordered).unwrap();
+                }
+                assert_eq!(ordered.values().collect::<String>(), desired);
+            }
+        }
+        assert_eq!(diff_orders(&list, &['a']), Err(ReorderError::MissingIds));
+    }
+
+    #[test]
+    fn relative_moves() {
+        let list = current("abcde");
+        let before = |id, target| RelativeMove::Before { id, target };
+        let after = |id, target| RelativeMove::After { id, target };
+        for (moves, expected) in [
+            (vec![], "abcde"),
+            (vec![before('e', 'a')], "eabcd"),
+            (vec![after('a', 'e')], "bcdea"),
+            (vec![after('a', 'b'), before('e', 'd')], "baced"),
+            (vec![after('c', 'c'), before('c', 'c')], "abcde"),
+            (vec![after('a', 'e'), after('e', 'a')], "bcdae"),
+        ] {
+            let updates = apply_moves(&list, &moves).unwrap();
+            assert_eq!(apply(&list, &updates), expected, "{:?}", moves);
+            assert_eq!(updates.len(), {
+                let ids: Vec<char> = expected.chars().collect();
+                plan(&list, &ids).unwrap().len()
+            });
+        }
+
+        assert_eq!(
+            apply_moves(&list, &[before('a', 'b'), after('x', 'a')]),
+            Err(ApplyMovesError::UnknownId { position: 1 })
+        );
+        assert_eq!(
+            apply_moves(&list, &[before('a', 'x')]),
+            Err(ApplyMovesError::UnknownTarget { position: 0 })
+        );
+        let mut unsorted = list;
+        unsorted.swap(0, 1);
+        assert_eq!(
+            apply_moves(&unsorted, &[]),
+            Err(ApplyMovesError::Invalid(ReorderError::Unsorted {
+                position: 1
+            }))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn relative_move_serde() {
+        let json =
+            r#"[{"op":"before","id":"a","target":"b"},{"op":"after","id":"c","target":"a"}]"#;
+        let moves: Vec<RelativeMove<String>> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            moves,
+            vec![
+                RelativeMove::Before {
+                    id: "a".to_string(),
+                    target: "b".to_string()
+                },
+                RelativeMove::After {
+                    id: "c".to_string(),
+                    target: "a".to_string()
+                },
+            ]
+        );
+        assert_eq!(serde_json::to_string(&moves).unwrap(), json);
+    }
+
+    #[test]
+    fn move_by_position() {
+        let keys = FractionalIndex::new_after_many(&FractionalIndex::default(), 5);
+        for from in 0..keys.len() {
+            for to in 0..keys.len() {
+                let key = key_for_move(&keys, from, to);
+                let mut expected: Vec<&FractionalIndex> = keys.iter().collect();
+                let item = expected.remove(from);
+                expected.insert(to, item);
+
+                let mut moved = keys.clone();
+                moved[from] = key;
+                let mut order: Vec<usize> = (0..keys.len()).collect();
+                order.sort_by_key(|&i| &moved[i]);
+                let order: Vec<&FractionalIndex> = order.into_iter().map(|i| &keys[i]).collect();
+                assert_eq!(order, expected, "from {} to {}", from, to);
+            }
+        }
+        assert_eq!(key_for_move(&keys, 2, 2), keys[2]);
+        assert_eq!(key_for_move(&keys[..1], 0, 0), keys[0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn move_out_of_bounds() {
+        let keys = FractionalIndex::new_after_many(&FractionalIndex::default(), 3);
+        key_for_move(&keys, 0, 3);
+    }
+
+    #[test]
+    fn longest_increasing_subsequence() {
+        assert_eq!(longest_increasing(&[]), Vec::<usize>::new());
+        assert_eq!(longest_increasing(&[3, 1, 2, 0, 4]), vec![1, 2, 4]);
+        assert_eq!(longest_increasing(&[4, 3, 2, 1]).len(), 1);
+    }
+
+    #[test]
+    fn errors() {
+        let list = current("abc");
+        assert_eq!(
+            plan(&list, &['a', 'b', 'x']),
+            Err(ReorderError::UnknownId { position: 2 })
+        );
+        assert_eq!(
+            plan(&list, &['a', 'b', 'a']),
+            Err(ReorderError::DuplicateId { position: 2 })
+        );
+        assert_eq!(plan(&list, &['a', 'b']), Err(ReorderError::MissingIds));
+
+        let mut unsorted = list;
+        unsorted.swap(0, 1);
+        assert_eq!(
+            plan(&unsorted, &['a', 'b', 'c']),
+            Err(ReorderError::Unsorted { position: 1 })
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Decoding of order columns from Postgres logical replication, for change
+//! data capture consumers that keep an ordered view of a table downstream.
+//!
+//! Logical decoding plugins send `bytea` values in one of Postgres's text
+//! forms, `\x8180` (the default `hex` output) or `\201\200` (the `escape`
+//! output), unless `pgoutput` is asked for binary values. [decode_text]
+//! decodes either text form, [decode_pgoutput] decodes a column of a
+//! `pgoutput` tuple, and with the `json` feature, [decode_wal2json] decodes
+//! a column value of a `wal2json` change.
+//!
+//! ```rust
+//! use fractional_index::{replication, FractionalIndex};
+//!
+//! let key = FractionalIndex::new_after(&FractionalIndex::default());
+//! assert_eq!(replication::decode_text(r"\x8180").unwrap(), key);
+//! assert_eq!(replication::decode_text(r"\201\200").unwrap(), key);
+//!
+//! // A text column of an insert, update or delete message.
+//! let column = replication::decode_pgoutput(b't', br"\x8180").unwrap();
+//! assert_eq!(column, replication::Column::Index(key));
+//! ```
+use crate::{hex::hex_to_bytes, limits::check_len, DecodeError, FractionalIndex};
+use std::convert::TryFrom;
+
+/// A column of a `pgoutput` tuple.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Column {
+    /// The column is NULL.
+    Null,
+    /// The column is a TOASTed value that did not change, so it was not
+    /// sent. Its value is the one last received for the row.
+    Unchanged,
+    Index(FractionalIndex),
+}
+
+/// Decodes a `bytea` value in either of Postgres's text forms: `\x`
+/// followed by hex digits, or the escape form, where bytes are written as
+/// `\` and three octal digits, `\\`, or as their ASCII character.
+pub fn decode_text(text: &str) -> Result<FractionalIndex, DecodeError> {
+    if text.is_empty() {
+        return Err(DecodeError::EmptyString);
+    }
+    let bytes = match text.strip_prefix(r"\x") {
+        Some(hex) => {
+            if hex.len() % 2 != 0 {
+                return Err(DecodeError::InvalidLength);
+            }
+            check_len(hex.len() / 2)?;
+            hex_to_bytes(hex).map_err(|err| DecodeError::InvalidChars {
+                character: err.character,
+                position: err.position + 2,
+            })?
+        }
+        None => unescape(text)?,
+    };
+    FractionalIndex::from_bytes(bytes)
+}
+
+/// Decodes the escape form of a `bytea` value.
+fn unescape(text: &str) -> Result<Vec<u8>, DecodeError> {
+    let chars = text.as_bytes();
+    let mut bytes = Vec::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != b'\\' {
+            bytes.push(chars[i]);
+            i += 1;
+        } else if chars.get(i + 1) == Some(&b'\\') {
+            bytes.push(b'\\');
+            i += 2;
+        } else {
+            let digits = chars.get(i + 1..i + 4).ok_or(DecodeError::Truncated)?;
+            let mut byte: u32 = 0;
+            for (j, digit) in digits.iter().enumerate() {
+                if !(b'0'..=b'7').contains(digit) {
+                    let position = i + 1 + j;
+                    return Err(DecodeError::InvalidChars {
+                        character: text[position..].chars().next().unwrap(),
+                        position,
+                    });
+                }
+                byte = byte * 8 + u32::from(digit - b'0');
+            }
+            bytes.push(u8::try_from(byte).map_err(|_| DecodeError::DigitOutOfRange)?);
+            i += 4;
+        }
+    }
+    Ok(bytes)
+}
+
+/// Decodes a column of a `pgoutput` tuple (in an insert, update or delete
+/// message) from its kind byte (`n`, `u`, `t` or `b`) and its data, which
+/// is empty for `n` and `u`.
+///
+/// Text values (`t`) are decoded with [decode_text], and binary values
+/// (`b`, sent when the subscription sets `binary`) are the key's bytes.
+pub fn decode_pgoutput(kind: u8, data: &[u8]) -> Result<Column, DecodeError> {
+    match kind {
+        b'n' => Ok(Column::Null),
+        b'u' => Ok(Column::Unchanged),
+        b't' => {
+            let text = std::str::from_utf8(data).map_err(|err| DecodeError::UnexpectedByte {
+                byte: data[err.valid_up_to()],
+                position: err.valid_up_to(),
+            })?;
+            decode_text(text).map(Column::Index)
+        }
+        b'b' => FractionalIndex::from_bytes(data.to_vec()).map(Column::Index),
+        _ => Err(DecodeError::UnexpectedByte {
+            byte: kind,
+            position: 0,
+        }),
+    }
+}
+
+/// Decodes a column value of a `wal2json` change (e.g. an element of
+/// `columnvalues` in format 1, or the `value` of a column in format 2),
+/// which is a string in the text form of `bytea`, or None if it is null.
+#[cfg(feature = "json")]
+pub fn decode_wal2json(
+    value: &serde_json::Value,
+) -> Result<Option<FractionalIndex>, crate::json::ValueError> {
+    use crate::json::ValueError;
+    use serde_json::Value;
+
+    match value {
+        Value::Null => Ok(None),
+        Value::String(text) => decode_text(text).map(Some).map_err(ValueError::Invalid),
+        _ => Err(ValueError::NotAString),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_forms() {
+        let keys = [
+            FractionalIndex::default(),
+            FractionalIndex::from_bytes(vec![b'A', b'\\', 0, 127, 128]).unwrap(),
+            FractionalIndex::new_before(&FractionalIndex::default()),
+        ];
+        let escaped = [r"\200", r"A\\\000\177\200", r"\177\200"];
+        for (key, escaped) in keys.iter().zip(escaped.iter()) {
+            assert_eq!(
+                &decode_text(&format!(r"\x{}", key.to_string())).unwrap(),
+                key
+            );
+            assert_eq!(&decode_text(escaped).unwrap(), key);
+        }
+        // Printable bytes may also be written as octal.
+        assert_eq!(decode_text(r"\101\\\000\177\200").unwrap(), keys[1]);
+    }
+
+    #[test]
+    fn invalid_text() {
+        assert_eq!(decode_text(""), Err(DecodeError::EmptyString));
+        assert_eq!(decode_text(r"\x818"), Err(DecodeError::InvalidLength));
+        assert_eq!(
+            decode_text(r"\x81g0"),
+            Err(DecodeError::InvalidChars {
+                character: 'g',
+                position: 4
+            })
+        );
+        assert_eq!(decode_text(r"\20"), Err(DecodeError::Truncated));
+        assert_eq!(
+            decode_text(r"\208"),
+            Err(DecodeError::InvalidChars {
+                character: '8',
+                position: 3
+            })
+        );
+        assert_eq!(decode_text(r"\400"), Err(DecodeError::DigitOutOfRange));
+        assert_eq!(
+            decode_text(r"\x8181"),
+            Err(DecodeError::MissingTerminator { found: Some(0x81) })
+        );
+    }
+
+    #[test]
+    fn pgoutput() {
+        let key = FractionalIndex::new_after(&FractionalIndex::default());
+        assert_eq!(decode_pgoutput(b'n', b""), Ok(Column::Null));
+        assert_eq!(decode_pgoutput(b'u', b""), Ok(Column::Unchanged));
+        assert_eq!(
+            decode_pgoutput(b't', br"\201\200"),
+            Ok(Column::Index(key.clone()))
+        );
+        assert_eq!(
+            decode_pgoutput(b'b', key.as_bytes()),
+            Ok(Column::Index(key))
+        );
+        assert_eq!(
+            decode_pgoutput(b't', &[0x80]),
+            Err(DecodeError::UnexpectedByte {
+                byte: 0x80,
+                position: 0
+            })
+        );
+        assert_eq!(
+            decode_pgoutput(b'x', b""),
+            Err(DecodeError::UnexpectedByte {
+                byte: b'x',
+                position: 0
+            })
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn wal2json() {
+        use crate::json::ValueError;
+        use serde_json::json;
+
+        // A format 2 change, as wal2json writes it.
+        let change: serde_json::Value = serde_json::from_str(
+            r#"{"action":"I","table":"item","columns":[
+                {"name":"id","type":"integer","value":1},
+                {"name":"position","type":"bytea","value":"\\x8180"},
+                {"name":"parent","type":"bytea","value":null}]}"#,
+        )
+        .unwrap();
+        let key = FractionalIndex::new_after(&FractionalIndex::default());
+        let columns = &change["columns"];
+        assert_eq!(decode_wal2json(&columns[1]["value"]), Ok(Some(key)));
+        assert_eq!(decode_wal2json(&columns[2]["value"]), Ok(None));
+        assert_eq!(
+            decode_wal2json(&columns[0]["value"]),
+            Err(ValueError::NotAString)
+        );
+        assert_eq!(
+            decode_wal2json(&json!("")),
+            Err(ValueError::Invalid(DecodeError::EmptyString))
+        );
+    }
+}
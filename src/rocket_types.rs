@@ -0,0 +1,49 @@
+//! [rocket] support, for taking keys from route segments and form fields.
+//!
+//! Both parse the hex representation returned by
+//! [FractionalIndex::to_string], with [FractionalIndex::from_string].
+//! A segment that does not parse makes the route not match, and a form field
+//! that does not parse is reported as a form error.
+
+use crate::{DecodeError, FractionalIndex};
+use rocket::{
+    form::{self, FromFormField, ValueField},
+    request::FromParam,
+};
+
+impl<'a> FromParam<'a> for FractionalIndex {
+    type Error = DecodeError;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        FractionalIndex::from_string(param)
+    }
+}
+
+impl<'v> FromFormField<'v> for FractionalIndex {
+    fn from_value(field: ValueField<'v>) -> form::Result<'v, Self> {
+        FractionalIndex::from_string(field.value).map_err(|err| form::Error::custom(err).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn param() {
+        let key = FractionalIndex::new_after(&Default::default());
+        assert_eq!(FractionalIndex::from_param("8180"), Ok(key));
+        assert_eq!(
+            FractionalIndex::from_param(""),
+            Err(DecodeError::EmptyString)
+        );
+    }
+
+    #[test]
+    fn form_field() {
+        let key = FractionalIndex::new_after(&Default::default());
+        let field = ValueField::parse("position=8180");
+        assert_eq!(FractionalIndex::from_value(field).unwrap(), key);
+        assert!(FractionalIndex::from_value(ValueField::from_value("81")).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Fractional indexes backed by [Bytes], which can be sliced out of a
+//! received buffer without copying.
+//!
+//! A [SharedIndex] holds its key as a [Bytes] handle, so constructing one
+//! from a slice of a network buffer only validates the terminator, and
+//! cloning one shares the underlying buffer instead of copying it. Keys
+//! generated from a [SharedIndex] are newly allocated.
+//!
+//! [SharedIndex::split_prefixed] reads keys framed as in the [io](crate::io)
+//! module: a varint length followed by the key's bytes.
+//!
+//! ```rust
+//! use bytes::Bytes;
+//! use fractional_index::{shared::SharedIndex, FractionalIndex};
+//!
+//! let message = Bytes::from_static(&[2, 127, 128, 1, 128]);
+//! let mut rest = message.clone();
+//! let a = SharedIndex::split_prefixed(&mut rest).unwrap();
+//! let b = SharedIndex::split_prefixed(&mut rest).unwrap();
+//! assert!(rest.is_empty());
+//!
+//! assert!(a < b);
+//! assert_eq!(b, FractionalIndex::default());
+//! // Both keys point into `message`.
+//! assert_eq!(a.as_bytes().as_ptr(), message[1..].as_ptr());
+//! ```
+use crate::{limits::check_len, varint::read_varint, DecodeError, FractionalIndex};
+use bytes::{Buf, Bytes};
+use std::{cmp::Ordering, convert::TryFrom, ops::Deref};
+
+/// A [FractionalIndex] whose bytes are held in a shared [Bytes] buffer.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SharedIndex(Bytes);
+
+impl SharedIndex {
+    /// Constructs a [SharedIndex] from a buffer holding exactly one key,
+    /// without copying it.
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, DecodeError> {
+        FractionalIndex::validate(&bytes)?;
+        Ok(SharedIndex(bytes))
+    }
+
+    /// Reads a key prefixed by its varint length from the front of `buf`,
+    /// advancing `buf` past it. The key shares `buf`'s underlying buffer.
+    ///
+    /// On error, `buf` is left unchanged.
+    pub fn split_prefixed(buf: &mut Bytes) -> Result<Self, DecodeError> {
+        let mut input = &buf[..];
+        let len = read_varint(&mut input)?;
+        let start = buf.len() - input.len();
+        let len = usize::try_from(len).map_err(|_| DecodeError::InvalidLength)?;
+        check_len(len)?;
+        if input.len() < len {
+            return Err(DecodeError::Truncated);
+        }
+
+        let index = SharedIndex::from_bytes(buf.slice(start..start + len))?;
+        buf.advance(start + len);
+        Ok(index)
+    }
+
+    /// Returns the byte representation of this index.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the underlying [Bytes] handle.
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+
+    /// Copies this index into a [FractionalIndex].
+    pub fn to_index(&self) -> FractionalIndex {
+        FractionalIndex::from_bytes_unchecked(self.0.to_vec())
+    }
+
+    /// Constructs an index that compares before the given one. See
+    /// [FractionalIndex::new_before].
+    pub fn new_before(index: &SharedIndex) -> SharedIndex {
+        FractionalIndex::new_before(&index.to_index()).into()
+    }
+
+    /// Constructs an index that compares after the given one. See
+    /// [FractionalIndex::new_after].
+    pub fn new_after(index: &SharedIndex) -> SharedIndex {
+        FractionalIndex::new_after(&index.to_index()).into()
+    }
+
+    /// Constructs an index that compares between the given two. See
+    /// [FractionalIndex::new_between].
+    pub fn new_between(left: &SharedIndex, right: &SharedIndex) -> Option<SharedIndex> {
+        FractionalIndex::new_between(&left.to_index(), &right.to_index()).map(Into::into)
+    }
+}
+
+impl Default for SharedIndex {
+    fn default() -> Self {
+        FractionalIndex::default().into()
+    }
+}
+
+impl From<FractionalIndex> for SharedIndex {
+    fn from(index: FractionalIndex) -> Self {
+        SharedIndex(Bytes::from(index.into_bytes()))
+    }
+}
+
+impl From<SharedIndex> for FractionalIndex {
+    fn from(index: SharedIndex) -> Self {
+        index.to_index()
+    }
+}
+
+impl PartialEq<FractionalIndex> for SharedIndex {
+    fn eq(&self, other: &FractionalIndex) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialOrd<FractionalIndex> for SharedIndex {
+    fn partial_cmp(&self, other: &FractionalIndex) -> Option<Ordering> {
+        Some(self.as_bytes().cmp(other.as_bytes()))
+    }
+}
+
+impl Deref for SharedIndex {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_prefixed() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let mut message = Vec::new();
+        for key in [&a, &b] {
+            message.push(key.as_bytes().len() as u8);
+            message.extend_from_slice(key.as_bytes());
+        }
+        let mut buf = Bytes::from(message);
+        assert_eq!(SharedIndex::split_prefixed(&mut buf).unwrap(), a);
+        assert_eq!(SharedIndex::split_prefixed(&mut buf).unwrap(), b);
+        assert_eq!(
+            SharedIndex::split_prefixed(&mut buf),
+            Err(DecodeError::Truncated)
+        );
+
+        let mut truncated = Bytes::from_static(&[3, 1, 128]);
+        assert_eq!(
+            SharedIndex::split_prefixed(&mut truncated),
+            Err(DecodeError::Truncated)
+        );
+        assert_eq!(truncated.len(), 3);
+
+        let mut unterminated = Bytes::from_static(&[1, 1]);
+        assert_eq!(
+            SharedIndex::split_prefixed(&mut unterminated),
+            Err(DecodeError::MissingTerminator { found: Some(1) })
+        );
+        assert_eq!(unterminated.len(), 2);
+    }
+
+    #[test]
+    fn generation() {
+        let a = SharedIndex::default();
+        let b = SharedIndex::new_after(&a);
+        let c = SharedIndex::new_between(&a, &b).unwrap();
+        let d = SharedIndex::new_before(&a);
+        assert!(d < a && a < c && c < b);
+        assert!(SharedIndex::new_between(&b, &a).is_none());
+        assert_eq!(FractionalIndex::from(c.clone()), c.to_index());
+        assert_eq!(c.clone().into_bytes(), Bytes::from(c.to_vec()));
+    }
+}
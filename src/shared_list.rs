@@ -0,0 +1,391 @@
+//! An [OrderedList] that can be shared between threads.
+//!
+//! [SharedOrderedList] is a cheaply cloneable handle to a list behind a
+//! read-write lock. Reads run concurrently, and inserts, moves and removals
+//! are serialized, so each computes its key from the current neighbors.
+//! [SharedOrderedList::snapshot] returns the list as of the call, which can
+//! be iterated (e.g. to send it to a new client) without holding the lock:
+//! the next write copies the list instead of waiting for the snapshot to be
+//! dropped.
+//!
+//! With the `subscriptions` feature, [SharedOrderedList::subscribe] returns a
+//! receiver of a [ListEvent] for every change, in the order the changes were
+//! made, so a layer that pushes changes to clients doesn't need to diff
+//! snapshots.
+//!
+//! ```rust
+//! use fractional_index::shared_list::SharedOrderedList;
+//! use std::thread;
+//!
+//! let list = SharedOrderedList::new();
+//! let a = list.push_back("a");
+//!
+//! let handles: Vec<_> = (0..4)
+//!     .map(|_| {
+//!         let list = list.clone();
+//!         thread::spawn(move || list.push_back("b"))
+//!     })
+//!     .collect();
+//! let snapshot = list.snapshot();
+//! for handle in handles {
+//!     handle.join().unwrap();
+//! }
+//!
+//! assert_eq!(list.len(), 5);
+//! assert!(snapshot.len() <= 5);
+//! assert_eq!(list.snapshot().first().map(|(key, _)| key.clone()), Some(a));
+//! ```
+use crate::{FractionalIndex, OrderedList};
+use std::sync::{Arc, PoisonError, RwLock};
+#[cfg(feature = "subscriptions")]
+use tokio::sync::broadcast;
+
+/// The number of events a subscriber can fall behind before it misses
+/// events.
+#[cfg(feature = "subscriptions")]
+const EVENT_CAPACITY: usize = 1024;
+
+/// A change to a [SharedOrderedList], as received by subscribers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListEvent {
+    /// An element was inserted at `key`.
+    Insert { key: FractionalIndex },
+    /// The element at `from` was moved to `to`.
+    Move {
+        from: FractionalIndex,
+        to: FractionalIndex,
+    },
+    /// The element at `key` was removed.
+    Remove { key: FractionalIndex },
+    /// The list was changed by [SharedOrderedList::update], so subscribers
+    /// should take a new snapshot.
+    Reset,
+}
+
+/// A handle to an [OrderedList] shared between threads. Clones of the
+/// handle refer to the same list.
+#[derive(Debug)]
+pub struct SharedOrderedList<T> {
+    list: Arc<RwLock<Arc<OrderedList<T>>>>,
+    #[cfg(feature = "subscriptions")]
+    events: broadcast::Sender<ListEvent>,
+}
+
+impl<T> Clone for SharedOrderedList<T> {
+    fn clone(&self) -> Self {
+        SharedOrderedList {
+            list: self.list.clone(),
+            #[cfg(feature = "subscriptions")]
+            events: self.events.clone(),
+        }
+    }
+}
+
+impl<T> Default for SharedOrderedList<T> {
+    fn default() -> Self {
+        SharedOrderedList::from(OrderedList::new())
+    }
+}
+
+impl<T> From<OrderedList<T>> for SharedOrderedList<T> {
+    fn from(list: OrderedList<T>) -> Self {
+        SharedOrderedList {
+            list: Arc::new(RwLock::new(Arc::new(list))),
+            #[cfg(feature = "subscriptions")]
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        }
+    }
+}
+
+impl<T> SharedOrderedList<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `f` with the list while holding a read lock, which blocks
+    /// writers but not other readers.
+    pub fn read<R>(&self, f: impl FnOnce(&OrderedList<T>) -> R) -> R {
+        // A panic while the lock was held can't leave the list itself in an
+        // invalid state, so a poisoned lock is still usable.
+        let list = self.list.read().unwrap_or_else(PoisonError::into_inner);
+        f(&list)
+    }
+
+    /// Returns the list as it is now. Later writes are not visible in the
+    /// snapshot.
+    pub fn snapshot(&self) -> Arc<OrderedList<T>> {
+        self.list
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.read(OrderedList::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read(OrderedList::is_empty)
+    }
+
+    pub fn contains_key(&self, key: &FractionalIndex) -> bool {
+        self.read(|list| list.contains_key(key))
+    }
+
+    /// Returns a receiver of the events of every later change to the list.
+    ///
+    /// A subscriber that falls more than 1024 events behind receives
+    /// [RecvError::Lagged](broadcast::error::RecvError::Lagged), after which
+    /// it should take a new snapshot. To get a snapshot consistent with the
+    /// events, subscribe first: events for changes already in the snapshot
+    /// are then received too, and can be recognized by their keys.
+    #[cfg(feature = "subscriptions")]
+    pub fn subscribe(&self) -> broadcast::Receiver<ListEvent> {
+        self.events.subscribe()
+    }
+}
+
+impl<T: Clone> SharedOrderedList<T> {
+    /// Calls `f` with the list while holding the write lock, so that a
+    /// sequence of changes is applied atomically.
+    ///
+    /// If a snapshot of the list is alive, the list is copied first.
+    /// Subscribers receive a [ListEvent::Reset].
+    pub fn update<R>(&self, f: impl FnOnce(&mut OrderedList<T>) -> R) -> R {
+        self.write(f, |_| Some(ListEvent::Reset))
+    }
+
+    /// Calls `f` with the list while holding the write lock, and sends the
+    /// event returned by `event` for its result, if any, before releasing
+    /// it, so that events are received in the order of the changes.
+    fn write<R>(
+        &self,
+        f: impl FnOnce(&mut OrderedList<T>) -> R,
+        event: impl FnOnce(&R) -> Option<ListEvent>,
+    ) -> R {
+        let mut list = self.list.write().unwrap_or_else(PoisonError::into_inner);
+        let result = f(Arc::make_mut(&mut list));
+        #[cfg(feature = "subscriptions")]
+        if let Some(event) = event(&result) {
+            // Sending only fails if there are no subscribers.
+            let _ = self.events.send(event);
+        }
+        #[cfg(not(feature = "subscriptions"))]
+        let _ = event;
+        result
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get(&self, key: &FractionalIndex) -> Option<T> {
+        self.read(|list| list.get(key).cloned())
+    }
+
+    /// Inserts a value before every other element, returning its key.
+    pub fn push_front(&self, value: T) -> FractionalIndex {
+        self.write(|list| list.push_front(value), inserted)
+    }
+
+    /// Inserts a value after every other element, returning its key.
+    pub fn push_back(&self, value: T) -> FractionalIndex {
+        self.write(|list| list.push_back(value), inserted)
+    }
+
+    /// Inserts a value immediately before the element at `anchor`,
+    /// returning its key, or None if the list has no element at `anchor`.
+    pub fn insert_before(&self, anchor: &FractionalIndex, value: T) -> Option<FractionalIndex> {
+        self.write(
+            |list| {
+                list.contains_key(anchor)
+                    .then(|| list.insert_before(anchor, value))
+            },
+            |key| key.as_ref().and_then(inserted),
+        )
+    }
+
+    /// Inserts a value immediately after the element at `anchor`,
+    /// returning its key, or None if the list has no element at `anchor`.
+    pub fn insert_after(&self, anchor: &FractionalIndex, value: T) -> Option<FractionalIndex> {
+        self.write(
+            |list| {
+                list.contains_key(anchor)
+                    .then(|| list.insert_after(anchor, value))
+            },
+            |key| key.as_ref().and_then(inserted),
+        )
+    }
+
+    /// Moves the element at `key` immediately before the element at
+    /// `anchor`, returning its new key. Returns None, leaving the list
+    /// unchanged, if either is missing or they are the same element.
+    pub fn move_before(
+        &self,
+        key: &FractionalIndex,
+        anchor: &FractionalIndex,
+    ) -> Option<FractionalIndex> {
+        self.move_relative(key, anchor, OrderedList::insert_before)
+    }
+
+    /// Moves the element at `key` immediately after the element at
+    /// `anchor`, returning its new key. Returns None, leaving the list
+    /// unchanged, if either is missing or they are the same element.
+    pub fn move_after(
+        &self,
+        key: &FractionalIndex,
+        anchor: &FractionalIndex,
+    ) -> Option<FractionalIndex> {
+        self.move_relative(key, anchor, OrderedList::insert_after)
+    }
+
+    fn move_relative(
+        &self,
+        key: &FractionalIndex,
+        anchor: &FractionalIndex,
+        insert: fn(&mut OrderedList<T>, &FractionalIndex, T) -> FractionalIndex,
+    ) -> Option<FractionalIndex> {
+        self.write(
+            |list| {
+                if key == anchor || !list.contains_key(anchor) {
+                    return None;
+                }
+                let value = list.remove(key)?;
+                Some(insert(list, anchor, value))
+            },
+            |to| {
+                to.as_ref().map(|to| ListEvent::Move {
+                    from: key.clone(),
+                    to: to.clone(),
+                })
+            },
+        )
+    }
+
+    /// Removes the element stored under `key`, returning its value.
+    pub fn remove(&self, key: &FractionalIndex) -> Option<T> {
+        self.write(
+            |list| list.remove(key),
+            |value| {
+                value
+                    .as_ref()
+                    .map(|_| ListEvent::Remove { key: key.clone() })
+            },
+        )
+    }
+}
+
+fn inserted(key: &FractionalIndex) -> Option<ListEvent> {
+    Some(ListEvent::Insert { key: key.clone() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn values(list: &SharedOrderedList<u32>) -> Vec<u32> {
+        list.snapshot().values().copied().collect()
+    }
+
+    #[test]
+    fn relative_changes() {
+        let list = SharedOrderedList::new();
+        let a = list.push_back(1);
+        let c = list.push_back(3);
+        let b = list.insert_after(&a, 2).unwrap();
+        list.push_front(0);
+        assert_eq!(values(&list), vec![0, 1, 2, 3]);
+        assert_eq!(list.get(&b), Some(2));
+
+        let missing = FractionalIndex::new_after(&c);
+        assert_eq!(list.insert_before(&missing, 9), None);
+        assert_eq!(list.move_after(&a, &missing), None);
+        assert_eq!(list.move_after(&a, &a), None);
+        assert_eq!(values(&list), vec![0, 1, 2, 3]);
+
+        let a = list.move_after(&a, &c).unwrap();
+        assert_eq!(values(&list), vec![0, 2, 3, 1]);
+        list.move_before(&a, &b).unwrap();
+        assert_eq!(values(&list), vec![0, 1, 2, 3]);
+        assert_eq!(list.remove(&b), Some(2));
+        assert_eq!(list.len(), 3);
+    }
+
+    #[test]
+    fn snapshots() {
+        let list = SharedOrderedList::new();
+        list.push_back(1);
+        let snapshot = list.snapshot();
+        list.push_back(2);
+        assert_eq!(snapshot.values().copied().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(values(&list), vec![1, 2]);
+        // Without live snapshots, writes don't copy the list.
+        drop(snapshot);
+        let before = Arc::as_ptr(&list.snapshot());
+        list.push_back(3);
+        assert_eq!(Arc::as_ptr(&list.snapshot()), before);
+    }
+
+    #[test]
+    fn concurrent_inserts() {
+        let list = SharedOrderedList::new();
+        let first = list.push_back(0);
+        thread::scope(|scope| {
+            for thread in 1..=8 {
+                let list = list.clone();
+                let first = first.clone();
+                scope.spawn(move || {
+                    for i in 0..100 {
+                        let value = thread * 1000 + i;
+                        if i % 2 == 0 {
+                            list.insert_after(&first, value).unwrap();
+                        } else {
+                            list.push_back(value);
+                        }
+                        let snapshot = list.snapshot();
+                        assert!(snapshot
+                            .keys()
+                            .zip(snapshot.keys().skip(1))
+                            .all(|(a, b)| a < b));
+                    }
+                });
+            }
+        });
+        assert_eq!(list.len(), 801);
+        let mut all = values(&list);
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), 801);
+    }
+
+    #[cfg(feature = "subscriptions")]
+    #[test]
+    fn subscriptions() {
+        use tokio::sync::broadcast::error::TryRecvError;
+
+        let list = SharedOrderedList::new();
+        let a = list.push_back(1);
+        let mut events = list.subscribe();
+        let b = list.insert_before(&a, 2).unwrap();
+        let missing = FractionalIndex::new_between(&a, &FractionalIndex::new_after(&a)).unwrap();
+        assert_eq!(list.insert_after(&missing, 3), None);
+        let c = list.move_after(&b, &a).unwrap();
+        assert_eq!(list.remove(&missing), None);
+        list.remove(&a);
+        list.update(|list| list.push_front(4));
+
+        let expected = [
+            ListEvent::Insert { key: b.clone() },
+            ListEvent::Move { from: b, to: c },
+            ListEvent::Remove { key: a },
+            ListEvent::Reset,
+        ];
+        for event in expected {
+            assert_eq!(events.try_recv(), Ok(event));
+        }
+        assert_eq!(events.try_recv(), Err(TryRecvError::Empty));
+
+        for i in 0..1100 {
+            list.push_back(i);
+        }
+        assert_eq!(events.try_recv(), Err(TryRecvError::Lagged(76)));
+    }
+}
@@ -0,0 +1,299 @@
+//! An order over the keys of a [SlotMap], enabled by the `slotmap` feature.
+//!
+//! [SlotOrder] stores a [FractionalIndex] for each slot key, next to the
+//! slot map that owns the values, and keeps a map from each index back to
+//! its key. Inserts, moves and removals update both, so the keys can be
+//! iterated in order, or looked up by neighbor, without sorting.
+//!
+//! ```rust
+//! use fractional_index::slot_order::SlotOrder;
+//! use slotmap::SlotMap;
+//!
+//! let mut widgets = SlotMap::new();
+//! let mut order = SlotOrder::new();
+//! let button = widgets.insert("button");
+//! let label = widgets.insert("label");
+//! let icon = widgets.insert("icon");
+//! order.push_back(button);
+//! order.push_front(label);
+//! order.insert_after(label, icon).unwrap();
+//!
+//! let names: Vec<_> = order.values(&widgets).map(|(_, name)| *name).collect();
+//! assert_eq!(names, vec!["label", "icon", "button"]);
+//!
+//! // Moving a key keeps one index per key.
+//! order.push_front(button);
+//! assert_eq!(order.ids().collect::<Vec<_>>(), vec![button, label, icon]);
+//!
+//! // Keys removed from the slot map are dropped with `retain`.
+//! widgets.remove(label);
+//! order.retain(|id| widgets.contains_key(id));
+//! assert_eq!(order.next(button), Some(icon));
+//! ```
+use crate::FractionalIndex;
+use slotmap::{Key, SlotMap};
+use std::collections::{BTreeMap, HashMap};
+
+/// An order over slot map keys, kept as a [FractionalIndex] for each key.
+///
+/// The index of each key is stored by its full key, including the version
+/// of its slot, so a key whose slot was removed and reused stays distinct
+/// from the new key until it is removed or dropped with
+/// [SlotOrder::retain].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotOrder<K: Key> {
+    indexes: HashMap<K, FractionalIndex>,
+    ids: BTreeMap<FractionalIndex, K>,
+}
+
+impl<K: Key> Default for SlotOrder<K> {
+    fn default() -> Self {
+        SlotOrder {
+            indexes: HashMap::new(),
+            ids: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Key> SlotOrder<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn contains(&self, id: K) -> bool {
+        self.indexes.contains_key(&id)
+    }
+
+    /// Returns the fractional index of `id`.
+    pub fn index_of(&self, id: K) -> Option<&FractionalIndex> {
+        self.indexes.get(&id)
+    }
+
+    /// Iterates over the keys and their indexes in order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&FractionalIndex, K)> {
+        self.ids.iter().map(|(index, id)| (index, *id))
+    }
+
+    /// Iterates over the keys in order.
+    pub fn ids(&self) -> impl DoubleEndedIterator<Item = K> + '_ {
+        self.ids.values().copied()
+    }
+
+    /// Iterates over the keys in order with their values in `slots`,
+    /// skipping keys that are not in `slots`.
+    pub fn values<'a, V>(
+        &'a self,
+        slots: &'a SlotMap<K, V>,
+    ) -> impl DoubleEndedIterator<Item = (K, &'a V)> {
+        self.ids()
+            .filter_map(move |id| slots.get(id).map(|value| (id, value)))
+    }
+
+    pub fn first(&self) -> Option<K> {
+        self.ids.values().next().copied()
+    }
+
+    pub fn last(&self) -> Option<K> {
+        self.ids.values().next_back().copied()
+    }
+
+    /// Returns the key after `id`, or None if `id` is last or not in the
+    /// order.
+    pub fn next(&self, id: K) -> Option<K> {
+        let index = self.indexes.get(&id)?;
+        self.ids
+            .range(crate::bounds::after(index))
+            .next()
+            .map(|(_, id)| *id)
+    }
+
+    /// Returns the key before `id`, or None if `id` is first or not in the
+    /// order.
+    pub fn prev(&self, id: K) -> Option<K> {
+        let index = self.indexes.get(&id)?;
+        self.ids.range(..index).next_back().map(|(_, id)| *id)
+    }
+
+    /// Inserts `id` before every other key, or moves it there, and returns
+    /// its new index.
+    pub fn push_front(&mut self, id: K) -> FractionalIndex {
+        self.remove(id);
+        let upper = self.ids.keys().next().cloned();
+        self.place(id, None, upper.as_ref())
+    }
+
+    /// Inserts `id` after every other key, or moves it there, and returns
+    /// its new index.
+    pub fn push_back(&mut self, id: K) -> FractionalIndex {
+        self.remove(id);
+        let lower = self.ids.keys().next_back().cloned();
+        self.place(id, lower.as_ref(), None)
+    }
+
+    /// Inserts `id` immediately before `anchor`, or moves it there, and
+    /// returns its new index. Returns None, leaving the order unchanged, if
+    /// `anchor` is not in the order.
+    ///
+    /// If `anchor` is `id` itself, its index is returned unchanged.
+    pub fn insert_before(&mut self, anchor: K, id: K) -> Option<FractionalIndex> {
+        if anchor == id {
+            return self.index_of(id).cloned();
+        }
+        let upper = self.indexes.get(&anchor)?.clone();
+        self.remove(id);
+        let lower = self.ids.range(..&upper).next_back().map(|(i, _)| i.clone());
+        Some(self.place(id, lower.as_ref(), Some(&upper)))
+    }
+
+    /// Inserts `id` immediately after `anchor`, or moves it there, and
+    /// returns its new index. Returns None, leaving the order unchanged, if
+    /// `anchor` is not in the order.
+    ///
+    /// If `anchor` is `id` itself, its index is returned unchanged.
+    pub fn insert_after(&mut self, anchor: K, id: K) -> Option<FractionalIndex> {
+        if anchor == id {
+            return self.index_of(id).cloned();
+        }
+        let lower = self.indexes.get(&anchor)?.clone();
+        self.remove(id);
+        let upper = self
+            .ids
+            .range(crate::bounds::after(&lower))
+            .next()
+            .map(|(i, _)| i.clone());
+        Some(self.place(id, Some(&lower), upper.as_ref()))
+    }
+
+    /// Stores `id` with a new index between `lower` and `upper`, which are
+    /// adjacent in the order.
+    fn place(
+        &mut self,
+        id: K,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> FractionalIndex {
+        let index =
+            FractionalIndex::new(lower, upper).expect("Indexes are always distinct and in order.");
+        self.indexes.insert(id, index.clone());
+        self.ids.insert(index.clone(), id);
+        index
+    }
+
+    /// Removes `id` from the order, returning its index.
+    pub fn remove(&mut self, id: K) -> Option<FractionalIndex> {
+        let index = self.indexes.remove(&id)?;
+        self.ids.remove(&index);
+        Some(index)
+    }
+
+    /// Keeps only the keys for which `keep` returns true, e.g. the keys
+    /// still in a slot map.
+    pub fn retain(&mut self, mut keep: impl FnMut(K) -> bool) {
+        let indexes = &mut self.indexes;
+        self.ids.retain(|_, id| {
+            let kept = keep(*id);
+            if !kept {
+                indexes.remove(id);
+            }
+            kept
+        });
+    }
+}
+
+impl<K: Key> Extend<K> for SlotOrder<K> {
+    /// Appends each key with [SlotOrder::push_back].
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for id in iter {
+            self.push_back(id);
+        }
+    }
+}
+
+impl<K: Key> std::iter::FromIterator<K> for SlotOrder<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut order = SlotOrder::new();
+        order.extend(iter);
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slotmap::DefaultKey;
+
+    fn names(order: &SlotOrder<DefaultKey>, slots: &SlotMap<DefaultKey, char>) -> String {
+        order.values(slots).map(|(_, name)| *name).collect()
+    }
+
+    fn assert_consistent(order: &SlotOrder<DefaultKey>) {
+        assert_eq!(order.indexes.len(), order.ids.len());
+        for (index, id) in order.iter() {
+            assert_eq!(order.index_of(id), Some(index));
+        }
+    }
+
+    #[test]
+    fn relative_insertion() {
+        let mut slots = SlotMap::new();
+        let [a, b, c, d] = ['a', 'b', 'c', 'd'].map(|name| slots.insert(name));
+        let mut order = SlotOrder::new();
+        order.push_back(b);
+        order.push_front(a);
+        order.insert_after(b, d).unwrap();
+        order.insert_before(d, c).unwrap();
+        assert_eq!(names(&order, &slots), "abcd");
+        assert_eq!((order.first(), order.last()), (Some(a), Some(d)));
+        assert_eq!((order.prev(a), order.next(a)), (None, Some(b)));
+        assert_eq!((order.prev(d), order.next(d)), (Some(c), None));
+
+        // Moves keep one index per key.
+        order.insert_after(d, a).unwrap();
+        order.insert_before(b, c).unwrap();
+        assert_eq!(names(&order, &slots), "cbda");
+        assert_eq!(order.len(), 4);
+        assert_consistent(&order);
+
+        let index = order.index_of(b).cloned();
+        assert_eq!(order.insert_after(b, b), index);
+        let missing = slots.insert('x');
+        slots.remove(missing);
+        assert_eq!(order.insert_after(missing, a), None);
+        assert_eq!(order.next(missing), None);
+        assert_eq!(names(&order, &slots), "cbda");
+
+        let index = order.index_of(d).cloned();
+        assert_eq!(order.remove(d), index);
+        assert_eq!(order.remove(d), None);
+        assert_eq!(names(&order, &slots), "cba");
+        assert_consistent(&order);
+    }
+
+    #[test]
+    fn reused_slots() {
+        let mut slots = SlotMap::new();
+        let a = slots.insert('a');
+        let b = slots.insert('b');
+        let mut order: SlotOrder<_> = vec![a, b].into_iter().collect();
+
+        // The new key reuses the slot of `a`, but not its index.
+        slots.remove(a);
+        let c = slots.insert('c');
+        order.push_back(c);
+        assert_eq!(order.len(), 3);
+        assert_eq!(names(&order, &slots), "bc");
+
+        order.retain(|id| slots.contains_key(id));
+        assert_eq!(order.ids().collect::<Vec<_>>(), vec![b, c]);
+        assert!(!order.contains(a));
+        assert_consistent(&order);
+    }
+}
@@ -0,0 +1,170 @@
+//! Helpers for keeping a `Vec<(FractionalIndex, T)>` sorted by its
+//! [FractionalIndex].
+//!
+//! For small lists, a sorted [Vec] is often a better fit than a
+//! [BTreeMap](std::collections::BTreeMap). These functions maintain the
+//! invariant that the entries are sorted by key and that keys are
+//! distinct, and compute new keys from an entry's neighbors.
+//!
+//! ```rust
+//! use fractional_index::sorted_vec::{insert_at_position, move_entry};
+//!
+//! let mut list = Vec::new();
+//! insert_at_position(&mut list, 0, "b");
+//! insert_at_position(&mut list, 0, "a");
+//! insert_at_position(&mut list, 2, "c");
+//! move_entry(&mut list, 2, 0);
+//!
+//! let values: Vec<&str> = list.iter().map(|(_, v)| *v).collect();
+//! assert_eq!(values, vec!["c", "a", "b"]);
+//! ```
+use crate::FractionalIndex;
+
+fn key_between<T>(list: &[(FractionalIndex, T)], position: usize) -> FractionalIndex {
+    let lower = position.checked_sub(1).map(|i| &list[i].0);
+    let upper = list.get(position).map(|(k, _)| k);
+    FractionalIndex::new(lower, upper)
+        .expect("Entries of a sorted vec should be distinct and in order.")
+}
+
+/// Inserts an entry with an existing key, keeping the list sorted.
+///
+/// Returns the position at which the entry was inserted, or, if the list
+/// already contains an entry with an equal key, an error holding that
+/// entry's position along with the rejected key and value, leaving the
+/// list unchanged.
+pub fn insert_sorted<T>(
+    list: &mut Vec<(FractionalIndex, T)>,
+    key: FractionalIndex,
+    value: T,
+) -> Result<usize, (usize, FractionalIndex, T)> {
+    match list.binary_search_by(|(k, _)| k.cmp(&key)) {
+        Ok(position) => Err((position, key, value)),
+        Err(position) => {
+            list.insert(position, (key, value));
+            Ok(position)
+        }
+    }
+}
+
+/// Inserts a value so that it ends up at `position` in the list, computing
+/// its key from the entries that will be on either side of it.
+///
+/// Returns the key of the new entry.
+///
+/// Panics if `position > list.len()`.
+pub fn insert_at_position<T>(
+    list: &mut Vec<(FractionalIndex, T)>,
+    position: usize,
+    value: T,
+) -> FractionalIndex {
+    assert!(
+        position <= list.len(),
+        "Insertion position (is {}) should be <= len (is {}).",
+        position,
+        list.len()
+    );
+    let key = key_between(list, position);
+    list.insert(position, (key.clone(), value));
+    key
+}
+
+/// Moves the entry at position `from` so that it ends up at position `to`,
+/// assigning it a new key computed from its new neighbors. Positions are
+/// interpreted like [Vec::remove] followed by [Vec::insert].
+///
+/// Returns the new key of the moved entry.
+///
+/// Panics if either position is out of bounds.
+pub fn move_entry<T>(
+    list: &mut Vec<(FractionalIndex, T)>,
+    from: usize,
+    to: usize,
+) -> FractionalIndex {
+    assert!(
+        to < list.len(),
+        "Destination position (is {}) should be < len (is {}).",
+        to,
+        list.len()
+    );
+    let (_, value) = list.remove(from);
+    insert_at_position(list, to, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values<T: Copy>(list: &[(FractionalIndex, T)]) -> Vec<T> {
+        list.iter().map(|(_, v)| *v).collect()
+    }
+
+    fn assert_sorted<T>(list: &[(FractionalIndex, T)]) {
+        for pair in list.windows(2) {
+            assert!(pair[0].0 < pair[1].0);
+        }
+    }
+
+    #[test]
+    fn insert_at_positions() {
+        let mut list = Vec::new();
+        insert_at_position(&mut list, 0, 'b');
+        insert_at_position(&mut list, 1, 'd');
+        insert_at_position(&mut list, 1, 'c');
+        insert_at_position(&mut list, 0, 'a');
+        insert_at_position(&mut list, 4, 'e');
+
+        assert_eq!(values(&list), vec!['a', 'b', 'c', 'd', 'e']);
+        assert_sorted(&list);
+    }
+
+    #[test]
+    fn insert_sorted_positions() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_after(&b);
+
+        let mut list = Vec::new();
+        assert_eq!(insert_sorted(&mut list, b.clone(), 'b'), Ok(0));
+        assert_eq!(insert_sorted(&mut list, c, 'c'), Ok(1));
+        assert_eq!(insert_sorted(&mut list, a, 'a'), Ok(0));
+        assert_eq!(values(&list), vec!['a', 'b', 'c']);
+        assert_sorted(&list);
+    }
+
+    #[test]
+    fn insert_sorted_duplicate() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+
+        let mut list = Vec::new();
+        assert_eq!(insert_sorted(&mut list, a, 'a'), Ok(0));
+        assert_eq!(insert_sorted(&mut list, b.clone(), 'b'), Ok(1));
+        assert_eq!(insert_sorted(&mut list, b.clone(), 'B'), Err((1, b, 'B')));
+        assert_eq!(values(&list), vec!['a', 'b']);
+    }
+
+    #[test]
+    fn move_entries() {
+        let mut list = Vec::new();
+        for (i, c) in "abcde".chars().enumerate() {
+            insert_at_position(&mut list, i, c);
+        }
+
+        move_entry(&mut list, 0, 4);
+        assert_eq!(values(&list), vec!['b', 'c', 'd', 'e', 'a']);
+        assert_sorted(&list);
+
+        move_entry(&mut list, 4, 0);
+        assert_eq!(values(&list), vec!['a', 'b', 'c', 'd', 'e']);
+        assert_sorted(&list);
+
+        move_entry(&mut list, 1, 2);
+        assert_eq!(values(&list), vec!['a', 'c', 'b', 'd', 'e']);
+        assert_sorted(&list);
+
+        move_entry(&mut list, 3, 3);
+        assert_eq!(values(&list), vec!['a', 'c', 'b', 'd', 'e']);
+        assert_sorted(&list);
+    }
+}
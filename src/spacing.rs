@@ -0,0 +1,310 @@
+//! Generation of evenly spaced keys, used to build lists in bulk and to
+//! rebalance them.
+//!
+//! A key `d1 .. dk T` (where `T` is the terminator) can be read as the
+//! base-256 fraction `0.d1 .. dk T`, and keys compare in the same order as
+//! these fractions. For each length `k`, the keys with `k` digits form an
+//! evenly spaced grid. [spaced] finds the smallest `k` for which the grid
+//! has enough points between the bounds, and spreads the keys over them,
+//! so key length grows with the logarithm (base 256) of the number of keys.
+
+use crate::fract_index::TERMINATOR;
+use alloc::{vec, vec::Vec};
+
+/// Returns the first `k` bytes of `key`, padded with zeros.
+fn prefix(key: &[u8], k: usize) -> Vec<u8> {
+    let mut digits: Vec<u8> = key.iter().take(k).copied().collect();
+    digits.resize(k, 0);
+    digits
+}
+
+/// Adds `n` to a big-endian number. Returns false on overflow.
+fn add(digits: &mut [u8], mut n: u128) -> bool {
+    for digit in digits.iter_mut().rev() {
+        if n == 0 {
+            break;
+        }
+        let sum = u128::from(*digit) + (n & 0xff);
+        *digit = sum as u8;
+        n = (n >> 8) + (sum >> 8);
+    }
+    n == 0
+}
+
+/// Subtracts one from a big-endian number. Returns false on underflow.
+fn decrement(digits: &mut [u8]) -> bool {
+    for digit in digits.iter_mut().rev() {
+        if *digit > 0 {
+            *digit -= 1;
+            return true;
+        }
+        *digit = u8::MAX;
+    }
+    false
+}
+
+/// Returns `hi - lo` as a big-endian number. Both must have the same
+/// length, and `lo` must not be greater than `hi`.
+pub fn difference(lo: &[u8], hi: &[u8]) -> Vec<u8> {
+    let mut diff = vec![0; hi.len()];
+    let mut borrow = false;
+    for i in (0..hi.len()).rev() {
+        let (d, b1) = hi[i].overflowing_sub(lo[i]);
+        let (d, b2) = d.overflowing_sub(borrow as u8);
+        diff[i] = d;
+        borrow = b1 || b2;
+    }
+    diff
+}
+
+/// Returns the number of values in `lo..=hi`, saturating at `u128::MAX`,
+/// or None if the range is empty. Both must have the same length.
+fn count(lo: &[u8], hi: &[u8]) -> Option<u128> {
+    if hi < lo {
+        return None;
+    }
+
+    let diff = difference(lo, hi);
+    let split = diff.len().saturating_sub(16);
+    if diff[..split].iter().any(|digit| *digit != 0) {
+        return Some(u128::MAX);
+    }
+    let value = diff[split..]
+        .iter()
+        .fold(0u128, |value, digit| (value << 8) | u128::from(*digit));
+    Some(value.saturating_add(1))
+}
+
+/// Returns the smallest `k`-digit key that is greater than `lower`.
+fn lowest(lower: &[u8], k: usize) -> Option<Vec<u8>> {
+    let mut digits = prefix(lower, k);
+    // `digits` followed by the terminator is not greater than `lower` if
+    // `lower` continues with the terminator or a greater byte.
+    if lower.len() > k && lower[k] >= TERMINATOR && !add(&mut digits, 1) {
+        return None;
+    }
+    Some(digits)
+}
+
+/// Returns the largest `k`-digit key that is less than `upper`.
+fn highest(upper: &[u8], k: usize) -> Option<Vec<u8>> {
+    let mut digits = prefix(upper, k);
+    let fits = match upper.get(k) {
+        Some(&byte) if byte > TERMINATOR => true,
+        Some(&byte) if byte == TERMINATOR => upper.len() > k + 1,
+        _ => false,
+    };
+    if !fits && !decrement(&mut digits) {
+        return None;
+    }
+    Some(digits)
+}
+
+/// Returns the number of terminated keys of at most `max_len` bytes
+/// strictly between the given bounds, saturating at `u128::MAX`.
+#[cfg(feature = "std")]
+pub fn capacity(lower: &[u8], upper: &[u8], max_len: usize) -> u128 {
+    (0..max_len)
+        .filter_map(|k| count(&lowest(lower, k)?, &highest(upper, k)?))
+        .fold(0, u128::saturating_add)
+}
+
+/// Returns `a * b / d`, rounded down, for `a, b < d` without overflowing.
+fn mul_div(a: u128, b: u128, d: u128) -> u128 {
+    let (mut quotient, mut remainder) = (0u128, 0u128);
+    for bit in (0..128).rev() {
+        // Invariant: quotient * d + remainder == a * (b >> bit), with
+        // remainder < d. Doubling cannot overflow while d <= 2^127.
+        quotient <<= 1;
+        remainder <<= 1;
+        if remainder >= d {
+            remainder -= d;
+            quotient += 1;
+        }
+        if b >> bit & 1 == 1 {
+            let (sum, overflow) = remainder.overflowing_add(a);
+            remainder = sum;
+            if overflow || remainder >= d {
+                remainder = remainder.wrapping_sub(d);
+                quotient += 1;
+            }
+        }
+    }
+    quotient
+}
+
+/// `n` evenly spaced slots between two bounds.
+struct Grid {
+    /// The first `k`-digit key in the range.
+    lo: Vec<u8>,
+    /// The number of `k`-digit keys in the range.
+    m: u128,
+    n: u128,
+}
+
+impl Grid {
+    /// Finds the shortest grid with at least `n` keys strictly between the
+    /// given bounds, which must be in order.
+    fn new(lower: Option<&[u8]>, upper: Option<&[u8]>, n: usize) -> Grid {
+        let mut k = 0;
+        loop {
+            let lo = match lower {
+                Some(lower) => lowest(lower, k),
+                None => Some(vec![0; k]),
+            };
+            let hi = match upper {
+                Some(upper) => highest(upper, k),
+                None => Some(vec![u8::MAX; k]),
+            };
+            if let (Some(lo), Some(hi)) = (lo, hi) {
+                if let Some(m) = count(&lo, &hi) {
+                    if m >= n as u128 {
+                        return Grid {
+                            lo,
+                            m,
+                            n: n as u128,
+                        };
+                    }
+                }
+            }
+            k += 1;
+        }
+    }
+
+    /// Returns the terminated key of slot `i`, which is at the center of
+    /// the `i`th of `n` equal slices of the range.
+    fn key(&self, i: u128) -> Vec<u8> {
+        let slices = 2 * self.n;
+        let odd = 2 * i + 1;
+        let offset = self.m / slices * odd + mul_div(self.m % slices, odd, slices);
+        let mut key = self.lo.clone();
+        assert!(add(&mut key, offset), "The offset is within the range.");
+        key.push(TERMINATOR);
+        key
+    }
+}
+
+/// Returns `n` evenly spaced, terminated keys in ascending order, strictly
+/// between the given bounds, which must be in order.
+pub fn spaced(lower: Option<&[u8]>, upper: Option<&[u8]>, n: usize) -> Vec<Vec<u8>> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let grid = Grid::new(lower, upper, n);
+    (0..n as u128).map(|i| grid.key(i)).collect()
+}
+
+/// Returns the key at position `i` of [spaced] with the same arguments,
+/// without computing the others. Requires `i < n`.
+pub fn nth(lower: Option<&[u8]>, upper: Option<&[u8]>, i: usize, n: usize) -> Vec<u8> {
+    Grid::new(lower, upper, n).key(i as u128)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Bounds<'a> = (Option<&'a [u8]>, Option<&'a [u8]>);
+
+    #[test]
+    fn unbounded() {
+        assert_eq!(spaced(None, None, 1), vec![vec![128]]);
+        assert_eq!(spaced(None, None, 2), vec![vec![64, 128], vec![192, 128]]);
+        assert!(spaced(None, None, 256).iter().all(|key| key.len() == 2));
+        assert!(spaced(None, None, 257).iter().all(|key| key.len() == 3));
+        assert!(spaced(None, None, 0).is_empty());
+    }
+
+    #[test]
+    fn bounded() {
+        let cases: [Bounds; 8] = [
+            (Some(&[128]), None),
+            (None, Some(&[128])),
+            (Some(&[128]), Some(&[129, 128])),
+            (Some(&[127, 255, 255, 128]), Some(&[128])),
+            (Some(&[128]), Some(&[128, 0, 128])),
+            (Some(&[5, 128, 7, 128]), Some(&[5, 128, 8, 128])),
+            (Some(&[255, 255, 128]), None),
+            (None, Some(&[0, 0, 128])),
+        ];
+        for (lower, upper) in cases {
+            for n in [1, 2, 3, 255, 256, 1000] {
+                let keys = spaced(lower, upper, n);
+                assert_eq!(keys.len(), n);
+                for pair in keys.windows(2) {
+                    assert!(pair[0] < pair[1]);
+                }
+                if let Some(lower) = lower {
+                    assert!(lower < keys[0].as_slice());
+                }
+                if let Some(upper) = upper {
+                    assert!(keys[n - 1].as_slice() < upper);
+                }
+                assert!(keys.iter().all(|key| key.last() == Some(&TERMINATOR)));
+            }
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut digits = [0, 255, 255];
+        assert!(add(&mut digits, 1));
+        assert_eq!(digits, [1, 0, 0]);
+        assert!(decrement(&mut digits));
+        assert_eq!(digits, [0, 255, 255]);
+        assert!(!add(&mut [255], 1));
+        assert!(!decrement(&mut [0, 0]));
+
+        assert_eq!(count(&[1, 0], &[1, 0]), Some(1));
+        assert_eq!(count(&[0, 255], &[1, 0]), Some(2));
+        assert_eq!(count(&[1, 0], &[0, 255]), None);
+        assert_eq!(count(&[0; 20], &[255; 20]), Some(u128::MAX));
+    }
+
+    #[test]
+    fn nth_matches_spaced() {
+        let lower: &[u8] = &[3, 128];
+        let upper: &[u8] = &[3, 129, 128];
+        for n in [1, 2, 3, 256, 1000] {
+            let keys = spaced(Some(lower), Some(upper), n);
+            for (i, key) in keys.iter().enumerate() {
+                assert_eq!(*key, nth(Some(lower), Some(upper), i, n));
+            }
+        }
+
+        // Large slot counts do not overflow.
+        let n = usize::MAX;
+        let first = nth(None, None, 0, n);
+        let middle = nth(None, None, n / 2, n);
+        let last = nth(None, None, n - 1, n);
+        assert!(first < middle && middle < last);
+        assert_eq!(middle, vec![128, 0, 0, 0, 0, 0, 0, 0, TERMINATOR]);
+    }
+
+    #[test]
+    fn capacity_matches_enumeration() {
+        // Every key of at most two bytes.
+        let mut keys = vec![vec![TERMINATOR]];
+        keys.extend((0..=u8::MAX).map(|digit| vec![digit, TERMINATOR]));
+        keys.sort();
+
+        for (lower, upper) in [(2, 3), (0, 256), (5, 200), (128, 129), (127, 130)] {
+            let (lower, upper) = (&keys[lower], &keys[upper]);
+            let expected = keys
+                .iter()
+                .filter(|key| lower < *key && *key < upper)
+                .count();
+            assert_eq!(capacity(lower, upper, 2), expected as u128);
+        }
+        assert_eq!(capacity(&[128], &[128], 4), 0);
+        assert_eq!(capacity(&[0, 128], &[255, 128], 40), u128::MAX);
+    }
+
+    #[test]
+    fn mul_div_exact() {
+        assert_eq!(mul_div(6, 7, 10), 4);
+        assert_eq!(mul_div(0, 7, 10), 0);
+        let d = u128::MAX / 2;
+        assert_eq!(mul_div(d - 1, d - 1, d), d - 2);
+    }
+}
@@ -0,0 +1,374 @@
+//! Helpers for adding an order column to an existing SQL table.
+//!
+//! [order_column_ddl] returns the statements that add a nullable column of
+//! the dialect's binary type and an index on it. With the `sqlx` feature,
+//! [backfill] then assigns evenly spaced keys to the existing rows.
+//!
+//! In Postgres, [domain_ddl] creates a domain type that checks that keys
+//! are well-formed, which can be used as the column type instead. In any
+//! dialect, [check_constraint] returns a `CHECK` constraint for the column.
+//!
+//! Schemas that store keys as hex strings (from
+//! [FractionalIndex::to_string](crate::FractionalIndex::to_string)) can
+//! use [hex_collation_cmp] as a collation, registered with
+//! [with_hex_collation] for sqlx or [register_hex_collation] for rusqlite;
+//! see [HEX_COLLATION].
+//!
+//! ```rust
+//! use fractional_index::sql::{order_column_ddl, Dialect};
+//!
+//! assert_eq!(
+//!     order_column_ddl(Dialect::Postgres, "item", "position"),
+//!     vec![
+//!         r#"ALTER TABLE "item" ADD COLUMN "position" bytea"#,
+//!         r#"CREATE INDEX "item_position_idx" ON "item" ("position")"#,
+//!     ]
+//! );
+//! ```
+#[cfg(feature = "sqlx")]
+use crate::FractionalIndex;
+use std::cmp::Ordering;
+
+/// The name under which [hex_collation_cmp] is registered by
+/// [with_hex_collation] and [register_hex_collation].
+///
+/// Lowercase hex strings already sort like the bytes they encode under
+/// SQLite's default `BINARY` collation. This collation also orders
+/// uppercase or mixed-case hex correctly, e.g. for keys written by other
+/// tools:
+///
+/// ```sql
+/// SELECT * FROM item ORDER BY position COLLATE fractional_index_hex
+/// ```
+///
+/// With rusqlite, register it with [register_hex_collation].
+pub const HEX_COLLATION: &str = "fractional_index_hex";
+
+/// Compares two hex-encoded keys case-insensitively, in the order of the
+/// bytes they encode.
+///
+/// Strings that are not valid hex are still compared consistently, so this
+/// is a valid collation for any column.
+pub fn hex_collation_cmp(a: &str, b: &str) -> Ordering {
+    let a = a.bytes().map(|c| c.to_ascii_lowercase());
+    let b = b.bytes().map(|c| c.to_ascii_lowercase());
+    a.cmp(b)
+}
+
+/// Registers [hex_collation_cmp] as [HEX_COLLATION] on every connection opened
+/// with these options.
+#[cfg(feature = "sqlite")]
+pub fn with_hex_collation(
+    options: sqlx::sqlite::SqliteConnectOptions,
+) -> sqlx::sqlite::SqliteConnectOptions {
+    options.collation(HEX_COLLATION, hex_collation_cmp)
+}
+
+/// Registers [hex_collation_cmp] as [HEX_COLLATION] on a rusqlite
+/// connection.
+#[cfg(feature = "rusqlite")]
+pub fn register_hex_collation(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    conn.create_collation(HEX_COLLATION, hex_collation_cmp)
+}
+
+/// The SQL dialects supported by this module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    Postgres,
+    Sqlite,
+    MySql,
+}
+
+impl Dialect {
+    /// Returns the column type used to store a [FractionalIndex](crate::FractionalIndex).
+    /// Each of these types compares bytewise.
+    pub fn column_type(self) -> &'static str {
+        match self {
+            Dialect::Postgres => "bytea",
+            Dialect::Sqlite => "blob",
+            Dialect::MySql => "varbinary(255)",
+        }
+    }
+
+    /// Quotes an identifier.
+    pub fn quote(self, identifier: &str) -> String {
+        match self {
+            Dialect::Postgres | Dialect::Sqlite => {
+                format!("\"{}\"", identifier.replace('"', "\"\""))
+            }
+            Dialect::MySql => format!("`{}`", identifier.replace('`', "``")),
+        }
+    }
+
+    /// Returns the placeholder for the `n`th (1-based) query parameter.
+    pub fn placeholder(self, n: usize) -> String {
+        match self {
+            Dialect::Postgres => format!("${}", n),
+            Dialect::Sqlite | Dialect::MySql => "?".to_string(),
+        }
+    }
+}
+
+/// Returns the statements that add a nullable order column named `column`
+/// to `table`, and an index on it named `{table}_{column}_idx`.
+///
+/// The column is nullable so that it can be added to a table with existing
+/// rows. Once it has been backfilled, it can be made `NOT NULL`.
+pub fn order_column_ddl(dialect: Dialect, table: &str, column: &str) -> Vec<String> {
+    let table_quoted = dialect.quote(table);
+    let column_quoted = dialect.quote(column);
+    vec![
+        format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table_quoted,
+            column_quoted,
+            dialect.column_type()
+        ),
+        format!(
+            "CREATE INDEX {} ON {} ({})",
+            dialect.quote(&format!("{}_{}_idx", table, column)),
+            table_quoted,
+            column_quoted
+        ),
+    ]
+}
+
+/// Returns the statement that creates a Postgres domain named `name` over
+/// `bytea`, which only admits well-formed keys (non-empty and ending with
+/// the terminator byte).
+///
+/// Columns of the domain reject malformed keys written by any client, and
+/// decode like `bytea` columns. With the `postgres` feature, arrays of the
+/// domain also decode as `Vec<FractionalIndex>`.
+///
+/// ```rust
+/// use fractional_index::sql::domain_ddl;
+///
+/// assert_eq!(
+///     domain_ddl("fractional_index"),
+///     r#"CREATE DOMAIN "fractional_index" AS bytea CHECK (substring(VALUE FROM length(VALUE)) = '\x80'::bytea)"#
+/// );
+/// ```
+pub fn domain_ddl(name: &str) -> String {
+    format!(
+        r"CREATE DOMAIN {} AS bytea CHECK (substring(VALUE FROM length(VALUE)) = '\x80'::bytea)",
+        Dialect::Postgres.quote(name)
+    )
+}
+
+/// Returns a boolean expression that holds for the well-formed keys in
+/// `column`: those of 1 to `max_len` bytes that end with the terminator
+/// byte. `max_len` should match the limit set with
+/// [limits::with_max_len](crate::limits::with_max_len), if any, or the size
+/// of the column type.
+///
+/// Like any `CHECK` expression, it does not reject NULLs.
+///
+/// ```rust
+/// use fractional_index::sql::{check_expr, Dialect};
+///
+/// assert_eq!(
+///     check_expr(Dialect::Sqlite, "position", 64),
+///     r#"length("position") BETWEEN 1 AND 64 AND substr("position", -1) = x'80'"#
+/// );
+/// ```
+pub fn check_expr(dialect: Dialect, column: &str, max_len: usize) -> String {
+    let column = dialect.quote(column);
+    let last_byte = match dialect {
+        Dialect::Postgres => format!(r"substring({0} FROM length({0})) = '\x80'::bytea", column),
+        Dialect::Sqlite => format!("substr({}, -1) = x'80'", column),
+        Dialect::MySql => format!("RIGHT({}, 1) = X'80'", column),
+    };
+    format!(
+        "length({}) BETWEEN 1 AND {} AND {}",
+        column, max_len, last_byte
+    )
+}
+
+/// Returns a constraint named `{table}_{column}_check` that checks
+/// [check_expr], so the database rejects malformed keys when they are
+/// written rather than when they are next decoded.
+///
+/// The constraint can be added to a `CREATE TABLE` statement, or to an
+/// existing table with `ALTER TABLE ... ADD` in Postgres and MySQL. SQLite
+/// can't add constraints to existing tables.
+///
+/// ```rust
+/// use fractional_index::sql::{check_constraint, Dialect};
+///
+/// assert_eq!(
+///     format!("ALTER TABLE item ADD {}", check_constraint(Dialect::MySql, "item", "position", 255)),
+///     "ALTER TABLE item ADD CONSTRAINT `item_position_check` \
+///      CHECK (length(`position`) BETWEEN 1 AND 255 AND RIGHT(`position`, 1) = X'80')"
+/// );
+/// ```
+pub fn check_constraint(dialect: Dialect, table: &str, column: &str, max_len: usize) -> String {
+    format!(
+        "CONSTRAINT {} CHECK ({})",
+        dialect.quote(&format!("{}_{}_check", table, column)),
+        check_expr(dialect, column, max_len)
+    )
+}
+
+/// Assigns keys to the rows of `table` where `column` is NULL, returning the
+/// number of rows updated.
+///
+/// Rows are ordered by `order_by`, a raw SQL expression such as
+/// `"created_at, id"`, and placed after any keys already in the column. The
+/// keys are evenly spaced, so their length grows logarithmically with the
+/// number of rows. Rows are identified by the integer column `id_column`.
+///
+/// Each row is updated with a separate statement; pass a transaction to
+/// make the backfill atomic.
+#[cfg(feature = "sqlx")]
+pub async fn backfill<DB>(
+    conn: &mut DB::Connection,
+    dialect: Dialect,
+    table: &str,
+    id_column: &str,
+    column: &str,
+    order_by: &str,
+) -> Result<u64, sqlx::Error>
+where
+    DB: sqlx::Database,
+    for<'e> &'e mut DB::Connection: sqlx::Executor<'e, Database = DB>,
+    for<'q> DB::Arguments<'q>: sqlx::IntoArguments<'q, DB>,
+    for<'q> i64: sqlx::Encode<'q, DB> + sqlx::Decode<'q, DB> + sqlx::Type<DB>,
+    for<'q> Vec<u8>: sqlx::Encode<'q, DB> + sqlx::Decode<'q, DB> + sqlx::Type<DB>,
+    usize: sqlx::ColumnIndex<DB::Row>,
+{
+    let table = dialect.quote(table);
+    let id_column = dialect.quote(id_column);
+    let column = dialect.quote(column);
+
+    let last: Option<(FractionalIndex,)> = sqlx::query_as(&format!(
+        "SELECT {column} FROM {table} WHERE {column} IS NOT NULL ORDER BY {column} DESC LIMIT 1",
+        column = column,
+        table = table
+    ))
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let ids: Vec<(i64,)> = sqlx::query_as(&format!(
+        "SELECT {} FROM {} WHERE {} IS NULL ORDER BY {}",
+        id_column, table, column, order_by
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let keys = FractionalIndex::spaced(last.as_ref().map(|(key,)| key), None, ids.len())
+        .expect("An upper bound of None is always after the lower bound.");
+    let update = format!(
+        "UPDATE {} SET {} = {} WHERE {} = {}",
+        table,
+        column,
+        dialect.placeholder(1),
+        id_column,
+        dialect.placeholder(2)
+    );
+    for ((id,), key) in ids.iter().zip(keys) {
+        sqlx::query(&update)
+            .bind(key)
+            .bind(*id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(ids.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_collation() {
+        use crate::FractionalIndex;
+
+        let mut keys = vec![FractionalIndex::default()];
+        for i in 0..100 {
+            let last = keys.last().unwrap();
+            keys.push(if i % 2 == 0 {
+                FractionalIndex::new_after(last)
+            } else {
+                FractionalIndex::new_between(&keys[0], last).unwrap()
+            });
+        }
+        keys.sort();
+
+        for pair in keys.windows(2) {
+            let (a, b) = (pair[0].to_string(), pair[1].to_string());
+            assert_eq!(hex_collation_cmp(&a, &b), Ordering::Less);
+            assert_eq!(hex_collation_cmp(&a.to_uppercase(), &b), Ordering::Less);
+            assert_eq!(hex_collation_cmp(&a, &b.to_uppercase()), Ordering::Less);
+        }
+        assert_eq!(hex_collation_cmp("8F80", "8f80"), Ordering::Equal);
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn rusqlite_hex_collation() {
+        use crate::FractionalIndex;
+
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        register_hex_collation(&conn).unwrap();
+        conn.execute(
+            "create table item (id integer primary key, position text not null)",
+            [],
+        )
+        .unwrap();
+
+        let mut keys = vec![FractionalIndex::default()];
+        for _ in 0..10 {
+            keys.push(FractionalIndex::new_after(keys.last().unwrap()));
+        }
+        for (i, key) in keys.iter().enumerate() {
+            // Mix lower and uppercase, which the BINARY collation would missort.
+            let s = if i % 2 == 0 {
+                key.to_string()
+            } else {
+                key.to_string().to_uppercase()
+            };
+            conn.execute(
+                "insert into item (id, position) values (?1, ?2)",
+                rusqlite::params![i as i64, s],
+            )
+            .unwrap();
+        }
+
+        let mut statement = conn
+            .prepare(&format!(
+                "select id from item order by position collate {}",
+                HEX_COLLATION
+            ))
+            .unwrap();
+        let ids: Vec<i64> = statement
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(ids, (0..keys.len() as i64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn ddl() {
+        assert_eq!(
+            order_column_ddl(Dialect::Sqlite, "item", "position"),
+            vec![
+                r#"ALTER TABLE "item" ADD COLUMN "position" blob"#,
+                r#"CREATE INDEX "item_position_idx" ON "item" ("position")"#,
+            ]
+        );
+        assert_eq!(
+            check_expr(Dialect::Postgres, "position", 255),
+            r#"length("position") BETWEEN 1 AND 255 AND substring("position" FROM length("position")) = '\x80'::bytea"#
+        );
+        assert_eq!(
+            order_column_ddl(Dialect::MySql, "my`item", "position"),
+            vec![
+                "ALTER TABLE `my``item` ADD COLUMN `position` varbinary(255)",
+                "CREATE INDEX `my``item_position_idx` ON `my``item` (`position`)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! [sqlx] support for any database whose driver can encode and decode
+//! `Vec<u8>`, e.g. `blob` in SQLite or `bytea` in Postgres.
+//!
+//! Nullable columns can be decoded as `Option<FractionalIndex>`, or as
+//! [OrDefault] to treat NULL as the default index.
+//!
+//! With the `postgres` feature, `Vec<FractionalIndex>` and
+//! `Vec<Option<FractionalIndex>>` can also be bound and decoded as
+//! `bytea[]`, or decoded from arrays of a domain over `bytea`, such as the
+//! one created by [domain_ddl](crate::sql::domain_ddl). Postgres reports
+//! columns of such a domain as `bytea`, so they need no special handling.
+
+use crate::{FractionalIndex, OrDefault};
+use sqlx::{encode::IsNull, error::BoxDynError, Database, Decode, Encode, Type, ValueRef};
+
+impl<DB: Database> Type<DB> for FractionalIndex
+where
+    Vec<u8>: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <Vec<u8> as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <Vec<u8> as Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'q, DB: Database> Encode<'q, DB> for FractionalIndex
+where
+    Vec<u8>: Encode<'q, DB>,
+{
+    fn encode_by_ref(
+        &self,
+        buf: &mut <DB as Database>::ArgumentBuffer<'q>,
+    ) -> Result<IsNull, BoxDynError> {
+        self.as_bytes().to_vec().encode(buf)
+    }
+}
+
+impl<'r, DB: Database> Decode<'r, DB> for FractionalIndex
+where
+    Vec<u8>: Decode<'r, DB>,
+{
+    fn decode(value: <DB as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let bytes = <Vec<u8> as Decode<DB>>::decode(value)?;
+        Ok(FractionalIndex::from_bytes(bytes)?)
+    }
+}
+
+impl<DB: Database> Type<DB> for OrDefault
+where
+    Vec<u8>: Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <Vec<u8> as Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <Vec<u8> as Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'r, DB: Database> Decode<'r, DB> for OrDefault
+where
+    Vec<u8>: Decode<'r, DB>,
+{
+    fn decode(value: <DB as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        if value.is_null() {
+            return Ok(OrDefault::default());
+        }
+        <FractionalIndex as Decode<DB>>::decode(value).map(OrDefault)
+    }
+}
+
+#[cfg(feature = "postgres")]
+mod postgres {
+    use super::*;
+    use sqlx::postgres::{PgHasArrayType, PgTypeInfo, PgTypeKind};
+
+    /// Returns true if `ty` is `bytea`, or a domain over it.
+    fn is_bytea(ty: &PgTypeInfo) -> bool {
+        match ty.kind() {
+            PgTypeKind::Domain(base) => is_bytea(base),
+            _ => <Vec<u8> as Type<sqlx::Postgres>>::compatible(ty),
+        }
+    }
+
+    /// Returns true if `ty` is an array of `bytea`, or of a domain over it.
+    fn is_bytea_array(ty: &PgTypeInfo) -> bool {
+        match ty.kind() {
+            PgTypeKind::Array(element) => is_bytea(element),
+            _ => <Vec<u8> as PgHasArrayType>::array_compatible(ty),
+        }
+    }
+
+    impl PgHasArrayType for FractionalIndex {
+        fn array_type_info() -> PgTypeInfo {
+            <Vec<u8> as PgHasArrayType>::array_type_info()
+        }
+
+        fn array_compatible(ty: &PgTypeInfo) -> bool {
+            is_bytea_array(ty)
+        }
+    }
+
+    impl PgHasArrayType for OrDefault {
+        fn array_type_info() -> PgTypeInfo {
+            <Vec<u8> as PgHasArrayType>::array_type_info()
+        }
+
+        fn array_compatible(ty: &PgTypeInfo) -> bool {
+            is_bytea_array(ty)
+        }
+    }
+}
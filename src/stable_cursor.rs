@@ -0,0 +1,179 @@
+//! Insertion points that are anchored to neighboring items rather than to
+//! keys, so they remain valid after the list is rebalanced.
+//!
+//! A [FractionalIndex] computed for an insertion point becomes stale as
+//! soon as the keys around it are rewritten. A [StableCursor] instead
+//! records the ids of the items on either side, and computes a fresh key
+//! from their current keys when it is resolved.
+//!
+//! ```rust
+//! use fractional_index::{stable_cursor::StableCursor, OrderedList};
+//!
+//! let mut list = OrderedList::new();
+//! list.push_back("a");
+//! let b = list.push_back("b");
+//! let cursor = StableCursor::at(&list, &b, |value| *value);
+//!
+//! // Rebalance the list by assigning new keys to every item.
+//! let mut list: OrderedList<&str> = list.into_iter().map(|(_, value)| value).collect();
+//! assert!(!list.contains_key(&b));
+//!
+//! let key = cursor
+//!     .resolve(&list, |id| {
+//!         list.iter().find(|(_, value)| *value == id).map(|(key, _)| key.clone())
+//!     })
+//!     .unwrap();
+//! list.insert(key, "between");
+//! assert_eq!(list.values().copied().collect::<Vec<_>>(), vec!["a", "between", "b"]);
+//! ```
+use crate::{FractionalIndex, OrderedList};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A position between two items of a list, identified by their ids.
+///
+/// Either side may be None, for positions at the start or end of the list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StableCursor<Id> {
+    /// The item immediately before the position.
+    pub prev: Option<Id>,
+    /// The item immediately after the position.
+    pub next: Option<Id>,
+}
+
+impl<Id> StableCursor<Id> {
+    /// Constructs a cursor between the given items.
+    pub fn between(prev: Option<Id>, next: Option<Id>) -> Self {
+        StableCursor { prev, next }
+    }
+
+    /// Constructs a cursor at the position where `key` would be inserted
+    /// into `list`, i.e. immediately before the item stored under `key` if
+    /// there is one. `id_of` returns the id of an item.
+    pub fn at<T, S>(
+        list: &OrderedList<T, S>,
+        key: &FractionalIndex,
+        id_of: impl Fn(&T) -> Id,
+    ) -> Self {
+        let prev = list.key_before(key).and_then(|key| list.get(key));
+        let next = if list.contains_key(key) {
+            list.get(key)
+        } else {
+            list.key_after(key).and_then(|key| list.get(key))
+        };
+        StableCursor {
+            prev: prev.map(&id_of),
+            next: next.map(&id_of),
+        }
+    }
+
+    /// Returns a key for the cursor's position in `list`, given `key_of`,
+    /// which returns the current key of an item (or None if it has been
+    /// removed).
+    ///
+    /// The position is immediately after `prev` if it still exists, or
+    /// immediately before `next` otherwise. A cursor whose anchors were
+    /// None resolves to the start or end of the list. Returns None if the
+    /// cursor was anchored to items that have all been removed. The key
+    /// bisects the gap at the position, as [FractionalIndex::new] does,
+    /// whatever the list's strategy.
+    pub fn resolve<T, S>(
+        &self,
+        list: &OrderedList<T, S>,
+        key_of: impl Fn(&Id) -> Option<FractionalIndex>,
+    ) -> Option<FractionalIndex> {
+        if let Some(prev) = &self.prev {
+            if let Some(prev) = key_of(prev) {
+                return FractionalIndex::new(Some(&prev), list.key_after(&prev));
+            }
+        }
+        if let Some(next) = &self.next {
+            if let Some(next) = key_of(next) {
+                return FractionalIndex::new(list.key_before(&next), Some(&next));
+            }
+        }
+
+        match (&self.prev, &self.next) {
+            (None, _) => FractionalIndex::new(None, list.first().map(|(key, _)| key)),
+            (_, None) => FractionalIndex::new(list.last().map(|(key, _)| key), None),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::Boundary;
+    use std::collections::HashMap;
+
+    fn keys_by_id(list: &OrderedList<char>) -> HashMap<char, FractionalIndex> {
+        list.iter().map(|(key, id)| (*id, key.clone())).collect()
+    }
+
+    fn rebalance(list: OrderedList<char>) -> OrderedList<char> {
+        list.into_iter().map(|(_, id)| id).collect()
+    }
+
+    fn insert(list: &mut OrderedList<char>, cursor: &StableCursor<char>, id: char) {
+        let keys = keys_by_id(list);
+        let key = cursor.resolve(list, |id| keys.get(id).cloned()).unwrap();
+        list.insert(key, id);
+    }
+
+    fn values(list: &OrderedList<char>) -> String {
+        list.values().collect()
+    }
+
+    #[test]
+    fn survives_rebalance() {
+        let mut list: OrderedList<char> = "abc".chars().collect();
+        list.push_back('d');
+        let c = list.keys().nth(2).unwrap().clone();
+        let cursor = StableCursor::at(&list, &c, |id| *id);
+        assert_eq!(cursor, StableCursor::between(Some('b'), Some('c')));
+
+        let mut list = rebalance(list);
+        assert_ne!(list.get(&c), Some(&'c'));
+        insert(&mut list, &cursor, 'x');
+        assert_eq!(values(&list), "abxcd");
+    }
+
+    #[test]
+    fn list_with_strategy() {
+        let mut list = OrderedList::with_strategy(Boundary::minus(16));
+        list.extend("abc".chars());
+        let b = list.keys().nth(1).unwrap().clone();
+        let cursor = StableCursor::at(&list, &b, |id| *id);
+        assert_eq!(cursor, StableCursor::between(Some('a'), Some('b')));
+
+        let keys: HashMap<char, FractionalIndex> =
+            list.iter().map(|(key, id)| (*id, key.clone())).collect();
+        let key = cursor.resolve(&list, |id| keys.get(id).cloned()).unwrap();
+        list.insert(key, 'x');
+        assert_eq!(list.values().collect::<String>(), "axbc");
+    }
+
+    #[test]
+    fn anchors_removed() {
+        let mut list: OrderedList<char> = "abc".chars().collect();
+        let cursor = StableCursor::between(Some('a'), Some('b'));
+
+        // With `prev` removed, the position is before `next`.
+        let a = list.first().unwrap().0.clone();
+        list.remove(&a);
+        insert(&mut list, &cursor, 'x');
+        assert_eq!(values(&list), "xbc");
+
+        // With both removed, the position is lost.
+        let mut list: OrderedList<char> = "c".chars().collect();
+        let keys = keys_by_id(&list);
+        assert!(cursor.resolve(&list, |id| keys.get(id).cloned()).is_none());
+
+        // Cursors at either end stay at that end.
+        insert(&mut list, &StableCursor::between(None, Some('a')), 's');
+        insert(&mut list, &StableCursor::between(Some('a'), None), 'e');
+        assert_eq!(values(&list), "sce");
+    }
+}
@@ -0,0 +1,346 @@
+//! Ordering operations over any storage backend.
+//!
+//! [OrderStore] abstracts the few queries that placing and rebalancing
+//! items need: looking up the key of an item, finding the keys next to a
+//! key, and persisting a key. [insert] and [rebalance] are implemented once
+//! on top of it, so a backend (sqlx, Diesel, SeaORM, or a key-value store)
+//! only needs to implement the trait. [MemoryStore] is an in-memory
+//! implementation, e.g. for tests.
+//!
+//! ```rust
+//! use fractional_index::{
+//!     document::Placement,
+//!     store::{insert, rebalance, MemoryStore, OrderStore},
+//! };
+//!
+//! let mut store = MemoryStore::new();
+//! insert(&mut store, &"b", Placement::Back).unwrap();
+//! insert(&mut store, &"a", Placement::Front).unwrap();
+//! for _ in 0..20 {
+//!     insert(&mut store, &"c", Placement::After(&"a")).unwrap();
+//!     insert(&mut store, &"c", Placement::Before(&"b")).unwrap();
+//! }
+//! assert_eq!(store.ids().copied().collect::<Vec<_>>(), vec!["a", "c", "b"]);
+//!
+//! let stats = rebalance(&mut store, 2).unwrap();
+//! assert!(stats.rows_touched > 0);
+//! assert!(store.items().unwrap().iter().all(|(_, key)| key.len() <= 2));
+//! ```
+use crate::{
+    document::Placement,
+    ordered_list::UnsortedError,
+    rebalance::{self, RebalanceStats},
+    FractionalIndex,
+};
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+/// Storage for the keys of an ordered collection of items.
+///
+/// Implementations for databases typically map each method to one query on
+/// an order column, e.g. [OrderStore::next_key] to
+/// `SELECT position FROM item WHERE position > $1 ORDER BY position LIMIT 1`.
+pub trait OrderStore {
+    type Id;
+    type Error;
+
+    /// Returns the key of `id`, or None if the store has no such item.
+    fn key_of(&mut self, id: &Self::Id) -> Result<Option<FractionalIndex>, Self::Error>;
+
+    /// Returns the smallest key greater than `key`, or the smallest key if
+    /// `key` is None.
+    fn next_key(
+        &mut self,
+        key: Option<&FractionalIndex>,
+    ) -> Result<Option<FractionalIndex>, Self::Error>;
+
+    /// Returns the largest key less than `key`, or the largest key if `key`
+    /// is None.
+    fn prev_key(
+        &mut self,
+        key: Option<&FractionalIndex>,
+    ) -> Result<Option<FractionalIndex>, Self::Error>;
+
+    /// Returns every item and its key, in ascending order of key.
+    fn items(&mut self) -> Result<Vec<(Self::Id, FractionalIndex)>, Self::Error>;
+
+    /// Persists `key` as the key of `id`, adding the item if it is not in
+    /// the store.
+    ///
+    /// Returns an error, leaving the store unchanged, if a different item
+    /// holds `key`, e.g. from a unique index on the order column.
+    fn set_key(&mut self, id: &Self::Id, key: FractionalIndex) -> Result<(), Self::Error>;
+}
+
+/// The error returned by the operations of this module.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StoreError<E> {
+    /// The backend returned an error.
+    Backend(E),
+    /// The item used as an anchor is not in the store.
+    UnknownAnchor,
+    /// The keys returned by [OrderStore::items] are not strictly ascending.
+    Unsorted(UnsortedError),
+}
+
+impl<E> From<E> for StoreError<E> {
+    fn from(error: E) -> Self {
+        StoreError::Backend(error)
+    }
+}
+
+impl<E: Display> Display for StoreError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Backend(error) => write!(f, "{}", error),
+            StoreError::UnknownAnchor => write!(f, "The anchor item is not in the store."),
+            StoreError::Unsorted(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for StoreError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoreError::Backend(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Places `id` as given by `placement`, and persists and returns its new
+/// key. If `id` is already in the store, it is moved.
+pub fn insert<S: OrderStore>(
+    store: &mut S,
+    id: &S::Id,
+    placement: Placement<'_, S::Id>,
+) -> Result<FractionalIndex, StoreError<S::Error>> {
+    let (lower, upper) = match placement {
+        Placement::Front => (None, store.next_key(None)?),
+        Placement::Back => (store.prev_key(None)?, None),
+        Placement::Before(anchor) => {
+            let anchor = store.key_of(anchor)?.ok_or(StoreError::UnknownAnchor)?;
+            (store.prev_key(Some(&anchor))?, Some(anchor))
+        }
+        Placement::After(anchor) => {
+            let anchor = store.key_of(anchor)?.ok_or(StoreError::UnknownAnchor)?;
+            let upper = store.next_key(Some(&anchor))?;
+            (Some(anchor), upper)
+        }
+    };
+    let key = FractionalIndex::new(lower.as_ref(), upper.as_ref())
+        .expect("The store returned neighboring keys in order.");
+    store.set_key(id, key.clone())?;
+    Ok(key)
+}
+
+/// Rewrites the keys of the store, as planned by [rebalance::plan], so that
+/// no key is longer than `max_len` bytes. Returns the cost of the
+/// rebalance.
+///
+/// Keys are persisted one item at a time, in the order of
+/// [RebalancePlan::into_chunks](rebalance::RebalancePlan::into_chunks), so
+/// no key is given to an item while another holds it. A backend that
+/// supports transactions should still run this in one.
+pub fn rebalance<S: OrderStore>(
+    store: &mut S,
+    max_len: usize,
+) -> Result<RebalanceStats, StoreError<S::Error>> {
+    let items = store.items()?;
+    let keys: Vec<FractionalIndex> = items.iter().map(|(_, key)| key.clone()).collect();
+    let plan = rebalance::plan(&keys, max_len).map_err(StoreError::Unsorted)?;
+    let stats = plan.stats.clone();
+    for (old, new) in plan.into_chunks(usize::MAX).flatten() {
+        let i = keys
+            .binary_search(&old)
+            .expect("The plan only remaps keys of the store.");
+        store.set_key(&items[i].0, new)?;
+    }
+    Ok(stats)
+}
+
+/// The error returned by [MemoryStore::set_key](OrderStore::set_key) when
+/// the key is held by a different item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyOccupied;
+
+impl Display for KeyOccupied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The key is already held by a different item.")
+    }
+}
+
+impl std::error::Error for KeyOccupied {}
+
+/// An [OrderStore] held in memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryStore<Id> {
+    keys: BTreeMap<Id, FractionalIndex>,
+    ids: BTreeMap<FractionalIndex, Id>,
+}
+
+impl<Id> Default for MemoryStore<Id> {
+    fn default() -> Self {
+        MemoryStore {
+            keys: BTreeMap::new(),
+            ids: BTreeMap::new(),
+        }
+    }
+}
+
+impl<Id: Ord + Clone> MemoryStore<Id> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Iterates over the ids in order of their keys.
+    pub fn ids(&self) -> impl Iterator<Item = &Id> {
+        self.ids.values()
+    }
+
+    /// Removes `id`, returning its key.
+    pub fn remove(&mut self, id: &Id) -> Option<FractionalIndex> {
+        let key = self.keys.remove(id)?;
+        self.ids.remove(&key);
+        Some(key)
+    }
+}
+
+impl<Id: Ord + Clone> OrderStore for MemoryStore<Id> {
+    type Id = Id;
+    type Error = KeyOccupied;
+
+    fn key_of(&mut self, id: &Id) -> Result<Option<FractionalIndex>, KeyOccupied> {
+        Ok(self.keys.get(id).cloned())
+    }
+
+    fn next_key(
+        &mut self,
+        key: Option<&FractionalIndex>,
+    ) -> Result<Option<FractionalIndex>, KeyOccupied> {
+        let next = match key {
+            Some(key) => self.ids.range(crate::bounds::after(key)).next(),
+            None => self.ids.iter().next(),
+        };
+        Ok(next.map(|(key, _)| key.clone()))
+    }
+
+    fn prev_key(
+        &mut self,
+        key: Option<&FractionalIndex>,
+    ) -> Result<Option<FractionalIndex>, KeyOccupied> {
+        let prev = match key {
+            Some(key) => self.ids.range(..key).next_back(),
+            None => self.ids.iter().next_back(),
+        };
+        Ok(prev.map(|(key, _)| key.clone()))
+    }
+
+    fn items(&mut self) -> Result<Vec<(Id, FractionalIndex)>, KeyOccupied> {
+        Ok(self
+            .ids
+            .iter()
+            .map(|(key, id)| (id.clone(), key.clone()))
+            .collect())
+    }
+
+    fn set_key(&mut self, id: &Id, key: FractionalIndex) -> Result<(), KeyOccupied> {
+        if self.ids.get(&key).is_some_and(|holder| holder != id) {
+            return Err(KeyOccupied);
+        }
+        if let Some(old) = self.keys.insert(id.clone(), key.clone()) {
+            self.ids.remove(&old);
+        }
+        self.ids.insert(key, id.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(store: &MemoryStore<char>) -> String {
+        store.ids().collect()
+    }
+
+    #[test]
+    fn placements() {
+        let mut store = MemoryStore::new();
+        insert(&mut store, &'b', Placement::Back).unwrap();
+        insert(&mut store, &'d', Placement::Back).unwrap();
+        insert(&mut store, &'a', Placement::Front).unwrap();
+        insert(&mut store, &'c', Placement::After(&'b')).unwrap();
+        insert(&mut store, &'e', Placement::After(&'d')).unwrap();
+        assert_eq!(ids(&store), "abcde");
+
+        // Moves keep a single key per item.
+        insert(&mut store, &'a', Placement::Before(&'e')).unwrap();
+        insert(&mut store, &'c', Placement::Front).unwrap();
+        assert_eq!(ids(&store), "cbdae");
+        assert_eq!(store.len(), 5);
+
+        assert_eq!(
+            insert(&mut store, &'f', Placement::After(&'x')),
+            Err(StoreError::UnknownAnchor)
+        );
+        let key = store.key_of(&'b').unwrap();
+        assert_eq!(store.remove(&'b'), key);
+        assert_eq!(store.remove(&'b'), None);
+        assert_eq!(ids(&store), "cdae");
+    }
+
+    #[test]
+    fn rebalancing() {
+        let mut store = MemoryStore::new();
+        insert(&mut store, &0, Placement::Back).unwrap();
+        insert(&mut store, &1, Placement::Back).unwrap();
+        for i in 2..40 {
+            let anchor = if i % 2 == 0 { i - 2 } else { i - 1 };
+            insert(&mut store, &i, Placement::After(&anchor)).unwrap();
+        }
+        let before: Vec<u32> = store.ids().copied().collect();
+        assert!(store.items().unwrap().iter().any(|(_, key)| key.len() > 4));
+
+        let stats = rebalance(&mut store, 4).unwrap();
+        assert!(stats.rows_touched > 0);
+        assert_eq!(store.ids().copied().collect::<Vec<_>>(), before);
+        assert!(store.items().unwrap().iter().all(|(_, key)| key.len() <= 4));
+        assert_eq!(rebalance(&mut store, 4).unwrap().rows_touched, 0);
+    }
+
+    #[test]
+    fn rebalance_onto_held_keys() {
+        // Rebalancing appended keys gives some items the old keys of items
+        // that are rewritten later in the list.
+        let mut store = MemoryStore::new();
+        for i in 0..12 {
+            insert(&mut store, &i, Placement::Back).unwrap();
+        }
+        let before: Vec<u32> = store.ids().copied().collect();
+
+        let stats = rebalance(&mut store, 1).unwrap();
+        assert!(stats.rows_touched > 0);
+        assert_eq!(store.len(), 12);
+        assert_eq!(store.ids().copied().collect::<Vec<_>>(), before);
+    }
+
+    #[test]
+    fn set_held_key() {
+        let mut store = MemoryStore::new();
+        let a = insert(&mut store, &'a', Placement::Back).unwrap();
+        let b = insert(&mut store, &'b', Placement::Back).unwrap();
+        assert_eq!(store.set_key(&'b', a.clone()), Err(KeyOccupied));
+        assert_eq!(store.key_of(&'b'), Ok(Some(b)));
+        assert_eq!(store.set_key(&'a', a), Ok(()));
+        assert_eq!(ids(&store), "ab");
+    }
+}
@@ -0,0 +1,332 @@
+//! Strategies for choosing a key in the gap between two keys.
+//!
+//! [FractionalIndex::new] always bisects the gap, which keeps keys short
+//! when inserts are spread evenly, but grows keys by a byte for every few
+//! inserts when they are concentrated on one side of a gap (e.g. typing,
+//! or appending to a log). A [KeyStrategy] chooses where in the gap a new
+//! key goes, and [OrderedList](crate::OrderedList) is generic over it:
+//!
+//! - [Midpoint] bisects the gap, as [FractionalIndex::new] does.
+//! - [Boundary] places keys near one end of the gap, leaving room for more
+//!   inserts on that side (LSEQ's boundary+ and boundary- strategies).
+//! - [Lseq] alternates between boundary+ and boundary- by the depth of the
+//!   gap, as chosen by a seed.
+//! - [Jittered] places keys at a random point of the gap, so concurrent
+//!   inserts into the same gap are unlikely to get equal keys, within a
+//!   budget of extra key length.
+//!
+//! ```rust
+//! use fractional_index::{strategy::Boundary, OrderedList};
+//!
+//! let mut bisected = OrderedList::new();
+//! let mut boundary = OrderedList::with_strategy(Boundary::plus(64));
+//! for i in 0..1000 {
+//!     bisected.push_back(i);
+//!     boundary.push_back(i);
+//! }
+//! // Appends stay shorter when keys are placed near the lower bound.
+//! let bisected_len = bisected.keys().map(|key| key.len()).max();
+//! let boundary_len = boundary.keys().map(|key| key.len()).max();
+//! assert!(boundary_len < bisected_len);
+//! ```
+use crate::{rng::SplitMix, FractionalIndex};
+
+/// Chooses the key of a new element from its neighbors.
+pub trait KeyStrategy {
+    /// Returns a key between `lower` and `upper`, which are unbounded if
+    /// None, or None if `lower` is not less than `upper`.
+    fn between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> Option<FractionalIndex>;
+
+    /// Returns `n` keys in ascending order between `lower` and `upper`, or
+    /// None if `lower` is not less than `upper`. By default, each key is
+    /// chosen by [between](KeyStrategy::between) after the previous one.
+    fn between_many(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+        n: usize,
+    ) -> Option<Vec<FractionalIndex>> {
+        if let (Some(lower), Some(upper)) = (lower, upper) {
+            if lower >= upper {
+                return None;
+            }
+        }
+        let mut keys: Vec<FractionalIndex> = Vec::with_capacity(n);
+        for _ in 0..n {
+            let key = self.between(keys.last().or(lower), upper)?;
+            keys.push(key);
+        }
+        Some(keys)
+    }
+}
+
+/// Bisects the gap, as [FractionalIndex::new] does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Midpoint;
+
+impl KeyStrategy for Midpoint {
+    fn between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> Option<FractionalIndex> {
+        FractionalIndex::new(lower, upper)
+    }
+
+    /// Returns evenly spaced keys, whose length grows logarithmically with
+    /// `n`, rather than bisecting the gap `n` times.
+    fn between_many(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+        n: usize,
+    ) -> Option<Vec<FractionalIndex>> {
+        FractionalIndex::spaced(lower, upper, n)
+    }
+}
+
+/// Places keys at the center of the first (boundary+) or last (boundary-)
+/// of `slices` equal slices of the gap, at the shortest length at which
+/// the gap has room for `slices` keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Boundary {
+    slices: usize,
+    minus: bool,
+}
+
+impl Boundary {
+    /// Places keys near the lower end of the gap, for inserts that mostly
+    /// follow the previous one.
+    ///
+    /// Panics if `slices` is 0.
+    pub fn plus(slices: usize) -> Self {
+        assert!(
+            slices > 0,
+            "The gap must be divided into at least one slice."
+        );
+        Boundary {
+            slices,
+            minus: false,
+        }
+    }
+
+    /// Places keys near the upper end of the gap, for inserts that mostly
+    /// precede the previous one.
+    ///
+    /// Panics if `slices` is 0.
+    pub fn minus(slices: usize) -> Self {
+        Boundary {
+            minus: true,
+            ..Boundary::plus(slices)
+        }
+    }
+}
+
+impl KeyStrategy for Boundary {
+    fn between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> Option<FractionalIndex> {
+        let slice = if self.minus { self.slices - 1 } else { 0 };
+        FractionalIndex::at_fraction_between(lower, upper, slice, self.slices)
+    }
+}
+
+/// Chooses between boundary+ and boundary- for each depth of gap, i.e. the
+/// number of leading bytes its bounds have in common, as in LSEQ.
+///
+/// The choice for each depth is derived from the seed, so replicas with the
+/// same seed make the same choices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lseq {
+    slices: usize,
+    seed: u64,
+}
+
+impl Lseq {
+    /// Panics if `slices` is 0.
+    pub fn new(slices: usize, seed: u64) -> Self {
+        assert!(
+            slices > 0,
+            "The gap must be divided into at least one slice."
+        );
+        Lseq { slices, seed }
+    }
+}
+
+impl KeyStrategy for Lseq {
+    fn between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> Option<FractionalIndex> {
+        let depth = match (lower, upper) {
+            (Some(lower), Some(upper)) => lower
+                .as_bytes()
+                .iter()
+                .zip(upper.as_bytes())
+                .take_while(|(a, b)| a == b)
+                .count(),
+            _ => 0,
+        };
+        let plus = SplitMix::new(self.seed ^ depth as u64).next_u64() & 1 == 0;
+        let slice = if plus { 0 } else { self.slices - 1 };
+        FractionalIndex::at_fraction_between(lower, upper, slice, self.slices)
+    }
+}
+
+/// Places keys at the center of a random one of `slices` equal slices of
+/// the gap.
+///
+/// More slices make equal keys from concurrent inserts less likely, but
+/// make keys longer. The jitter budget bounds this growth: a key is at most
+/// `budget` bytes longer than the one [FractionalIndex::new] returns for the
+/// same gap. When the slice drawn gives a longer key, the number of slices
+/// is halved and another is drawn, down to a single slice, i.e. bisecting.
+#[derive(Clone, Debug)]
+pub struct Jittered {
+    slices: usize,
+    budget: usize,
+    rng: SplitMix,
+}
+
+impl Jittered {
+    /// Panics if `slices` is 0.
+    pub fn new(slices: usize, budget: usize, seed: u64) -> Self {
+        assert!(
+            slices > 0,
+            "The gap must be divided into at least one slice."
+        );
+        Jittered {
+            slices,
+            budget,
+            rng: SplitMix::new(seed),
+        }
+    }
+}
+
+impl KeyStrategy for Jittered {
+    fn between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> Option<FractionalIndex> {
+        let midpoint = FractionalIndex::new(lower, upper)?;
+        let mut slices = self.slices;
+        while slices > 1 {
+            let slice = self.rng.below(slices as u64) as usize;
+            let key = FractionalIndex::at_fraction_between(lower, upper, slice, slices)?;
+            if key.len() <= midpoint.len().saturating_add(self.budget) {
+                return Some(key);
+            }
+            slices /= 2;
+        }
+        Some(midpoint)
+    }
+}
+
+impl<S: KeyStrategy + ?Sized> KeyStrategy for &mut S {
+    fn between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> Option<FractionalIndex> {
+        (**self).between(lower, upper)
+    }
+
+    fn between_many(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+        n: usize,
+    ) -> Option<Vec<FractionalIndex>> {
+        (**self).between_many(lower, upper, n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderedList;
+
+    /// Inserts values into a list, either appending them or inserting them
+    /// after the first element, and returns the longest key.
+    fn longest_key<S: KeyStrategy>(strategy: S, appends: bool) -> usize {
+        let mut list = OrderedList::with_strategy(strategy);
+        let first = list.push_back(0);
+        for i in 1..500 {
+            if appends {
+                list.push_back(i);
+            } else {
+                list.insert_after(&first, i);
+            }
+            let keys: Vec<_> = list.keys().collect();
+            assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+        assert_eq!(list.len(), 500);
+        list.keys().map(|key| key.len()).max().unwrap()
+    }
+
+    #[test]
+    fn strategies() {
+        // Inserting after the first element inserts before the previous
+        // insert each time, which boundary- is for.
+        let minus = longest_key(Boundary::minus(64), false);
+        assert!(minus < longest_key(Midpoint, false));
+        assert!(minus < longest_key(Boundary::plus(64), false));
+        assert!(longest_key(Boundary::plus(64), true) <= longest_key(Boundary::minus(64), true));
+        longest_key(Lseq::new(16, 7), false);
+        longest_key(Jittered::new(16, 2, 7), true);
+        longest_key(&mut Jittered::new(1, 0, 7), false);
+    }
+
+    #[test]
+    fn jitter_budget() {
+        for budget in 0..3 {
+            let mut jittered = Jittered::new(1024, budget, 7);
+            let mut lower = FractionalIndex::default();
+            let mut jitters = 0;
+            for _ in 0..100 {
+                let midpoint = FractionalIndex::new_after(&lower);
+                let key = jittered.between(Some(&lower), None).unwrap();
+                assert!(key > lower);
+                assert!(key.len() <= midpoint.len() + budget);
+                if key != midpoint {
+                    jitters += 1;
+                }
+                lower = key;
+            }
+            assert!(jitters > 0);
+        }
+    }
+
+    #[test]
+    fn midpoint_matches_new() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        for (lower, upper) in [
+            (None, None),
+            (Some(&a), None),
+            (None, Some(&a)),
+            (Some(&a), Some(&b)),
+        ] {
+            assert_eq!(
+                Midpoint.between(lower, upper),
+                FractionalIndex::new(lower, upper)
+            );
+        }
+    }
+
+    #[test]
+    fn out_of_order() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        assert_eq!(Boundary::plus(4).between(Some(&b), Some(&a)), None);
+        assert_eq!(Jittered::new(4, 1, 0).between(Some(&a), Some(&a)), None);
+    }
+}
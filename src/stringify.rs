@@ -55,3 +55,139 @@ where
     let s = String::deserialize(deserializer)?;
     FractionalIndex::from_string(&s).map_err(serde::de::Error::custom)
 }
+
+/// Serializes maps keyed by [FractionalIndex] with hex string keys, so that
+/// they can be written in formats that require string keys, like JSON.
+///
+/// Works with any map type that iterates over and collects from key-value
+/// pairs, such as `BTreeMap`:
+///
+/// ```rust
+/// use fractional_index::FractionalIndex;
+/// use serde::{Serialize, Deserialize};
+/// use std::collections::BTreeMap;
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Board {
+///   #[serde(with="fractional_index::stringify::map")]
+///   cards: BTreeMap<FractionalIndex, String>,
+/// }
+///
+/// let a = FractionalIndex::default();
+/// let b = FractionalIndex::new_after(&a);
+/// let board = Board {
+///   cards: vec![(a, "first".to_string()), (b, "second".to_string())].into_iter().collect(),
+/// };
+///
+/// let json = serde_json::to_string(&board).unwrap();
+/// assert_eq!(json, r#"{"cards":{"80":"first","8180":"second"}}"#);
+/// assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+/// ```
+pub mod map {
+    use super::*;
+    use serde::{
+        de::{MapAccess, Visitor},
+        Serialize,
+    };
+    use std::{fmt, iter::FromIterator, marker::PhantomData};
+
+    struct Key<'a>(&'a FractionalIndex);
+
+    impl Serialize for Key<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::serialize(self.0, serializer)
+        }
+    }
+
+    struct OwnedKey(FractionalIndex);
+
+    impl<'de> Deserialize<'de> for OwnedKey {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            super::deserialize(deserializer).map(OwnedKey)
+        }
+    }
+
+    // The map is taken as any iterable (a `&M` in practice) rather than as a
+    // `&M`, since resolving `&M: IntoIterator` before `M` is known overflows
+    // when a dependency implements it recursively (e.g. Bevy's `&Res<T>`).
+    pub fn serialize<'a, I, V, S>(map: I, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        I: IntoIterator<Item = (&'a FractionalIndex, &'a V)>,
+        V: Serialize + 'a,
+        S: Serializer,
+    {
+        serializer.collect_map(map.into_iter().map(|(key, value)| (Key(key), value)))
+    }
+
+    pub fn deserialize<'de, M, V, D>(deserializer: D) -> Result<M, D::Error>
+    where
+        M: FromIterator<(FractionalIndex, V)>,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(MapVisitor(PhantomData))
+    }
+
+    struct MapVisitor<M, V>(PhantomData<(M, V)>);
+
+    impl<'de, M, V> Visitor<'de> for MapVisitor<M, V>
+    where
+        M: FromIterator<(FractionalIndex, V)>,
+        V: Deserialize<'de>,
+    {
+        type Value = M;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map keyed by fractional indexes as hex strings")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<M, A::Error> {
+            let mut entries = Vec::with_capacity(access.size_hint().unwrap_or(0));
+            while let Some((OwnedKey(key), value)) = access.next_entry()? {
+                entries.push((key, value));
+            }
+            Ok(entries.into_iter().collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Maps {
+        #[serde(with = "map")]
+        sorted: BTreeMap<FractionalIndex, u32>,
+        #[serde(with = "map")]
+        nested: BTreeMap<FractionalIndex, Vec<u32>>,
+    }
+
+    #[test]
+    fn maps() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_between(&a, &b).unwrap();
+        let maps = Maps {
+            sorted: vec![(a.clone(), 1), (b.clone(), 2), (c.clone(), 3)]
+                .into_iter()
+                .collect(),
+            nested: vec![(c, vec![1, 2])].into_iter().collect(),
+        };
+
+        let json = serde_json::to_string(&maps).unwrap();
+        assert_eq!(
+            json,
+            r#"{"sorted":{"80":1,"817f80":3,"8180":2},"nested":{"817f80":[1,2]}}"#
+        );
+        assert_eq!(serde_json::from_str::<Maps>(&json).unwrap(), maps);
+
+        let bytes = bincode::serialize(&maps).unwrap();
+        assert_eq!(bincode::deserialize::<Maps>(&bytes).unwrap(), maps);
+
+        let invalid = r#"{"sorted":{"81":1},"nested":{}}"#;
+        assert!(serde_json::from_str::<Maps>(invalid).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! A versioned binary encoding of [FractionalIndex]es, for stored data that
+//! must outlive the current representation.
+//!
+//! [FractionalIndex::as_bytes] is the bare key, so stored bytes do not say
+//! which representation produced them. [encode_v1] instead prefixes the key
+//! with a header of the two [MAGIC] bytes and a version byte, and [decode]
+//! reads any supported version. A future representation (e.g. a different
+//! alphabet, terminator or compression) gets a new version, and data
+//! written by either can be told apart without a migration.
+//!
+//! Encodings of the same version compare in the same order as the keys
+//! they encode, so they can still be used as sort keys. Encodings of
+//! different versions should not be compared with each other.
+//!
+//! ```rust
+//! use fractional_index::{tagged, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//!
+//! let encoded = tagged::encode_v1(&b);
+//! assert_eq!(encoded, vec![b'F', b'I', 1, 129, 128]);
+//! assert_eq!(tagged::version(&encoded), Ok(1));
+//! assert_eq!(tagged::decode(&encoded), Ok(b.clone()));
+//!
+//! // The bare bytes are rejected rather than misread.
+//! assert!(tagged::decode(b.as_bytes()).is_err());
+//! assert!(tagged::encode_v1(&a) < encoded);
+//! ```
+use crate::{DecodeError, FractionalIndex};
+
+/// The bytes that start every encoding.
+pub const MAGIC: [u8; 2] = *b"FI";
+
+/// The version written by [encode_v1], in which the header is followed by
+/// the bytes of [FractionalIndex::as_bytes].
+pub const V1: u8 = 1;
+
+/// The length of the header.
+pub const HEADER_LEN: usize = MAGIC.len() + 1;
+
+/// Encodes `index` as version 1.
+pub fn encode_v1(index: &FractionalIndex) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN + index.as_bytes().len());
+    out.extend_from_slice(&MAGIC);
+    out.push(V1);
+    out.extend_from_slice(index.as_bytes());
+    out
+}
+
+/// Returns the version of an encoding, after checking its header.
+///
+/// Returns [DecodeError::Truncated] if `bytes` is shorter than the header,
+/// and [DecodeError::UnexpectedByte] if it does not start with [MAGIC].
+pub fn version(bytes: &[u8]) -> Result<u8, DecodeError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(DecodeError::Truncated);
+    }
+    if let Some(position) = (0..MAGIC.len()).find(|&i| bytes[i] != MAGIC[i]) {
+        return Err(DecodeError::UnexpectedByte {
+            byte: bytes[position],
+            position,
+        });
+    }
+    Ok(bytes[MAGIC.len()])
+}
+
+/// Decodes an encoding of any supported version.
+///
+/// Returns [DecodeError::UnsupportedVersion] for versions this crate does
+/// not know, e.g. data written by a newer version of it.
+pub fn decode(bytes: &[u8]) -> Result<FractionalIndex, DecodeError> {
+    match version(bytes)? {
+        V1 => FractionalIndex::from_bytes(bytes[HEADER_LEN..].to_vec()),
+        version => Err(DecodeError::UnsupportedVersion { version }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let a = FractionalIndex::default();
+        let mut keys = FractionalIndex::new_before_many(&a, 20);
+        keys.push(a.clone());
+        keys.extend(FractionalIndex::new_after_many(&a, 20));
+        let encoded: Vec<Vec<u8>> = keys.iter().map(encode_v1).collect();
+        for (key, encoded) in keys.iter().zip(&encoded) {
+            assert_eq!(&encoded[..HEADER_LEN], b"FI\x01");
+            assert_eq!(decode(encoded).as_ref(), Ok(key));
+        }
+        assert!(encoded.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(decode(b""), Err(DecodeError::Truncated));
+        assert_eq!(decode(b"FI"), Err(DecodeError::Truncated));
+        assert_eq!(
+            decode(&[b'F', b'X', 1, 128]),
+            Err(DecodeError::UnexpectedByte {
+                byte: b'X',
+                position: 1
+            })
+        );
+        assert_eq!(
+            decode(&[b'F', b'I', 2, 128]),
+            Err(DecodeError::UnsupportedVersion { version: 2 })
+        );
+        assert_eq!(
+            decode(&[b'F', b'I', 1]),
+            Err(DecodeError::MissingTerminator { found: None })
+        );
+        assert_eq!(
+            decode(&[b'F', b'I', 1, 129]),
+            Err(DecodeError::MissingTerminator { found: Some(129) })
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Deterministic key factories for tests, enabled by the `test-util`
+//! feature.
+//!
+//! Every function returns the same keys on every run, so tests in
+//! downstream crates can build predictable orderings and compare against
+//! hard-coded keys.
+//!
+//! ```rust
+//! use fractional_index::test_util::{keys, nth_key};
+//!
+//! let keys = keys(3);
+//! assert_eq!(keys[2], nth_key(2));
+//! assert_eq!(nth_key(0).to_string(), "80");
+//! assert_eq!(nth_key(1).to_string(), "8180");
+//! assert!(keys[0] < keys[1] && keys[1] < keys[2]);
+//! ```
+use crate::FractionalIndex;
+
+/// Constructs a key from bytes that do not include the terminator. Every
+/// such byte string is a valid key.
+pub fn from_unterminated(bytes: &[u8]) -> FractionalIndex {
+    let mut bytes = bytes.to_vec();
+    bytes.push(crate::fract_index::TERMINATOR);
+    FractionalIndex::from_bytes_unchecked(bytes)
+}
+
+/// Returns the key at position `i` of [keys].
+pub fn nth_key(i: usize) -> FractionalIndex {
+    appended(i + 1).pop().expect("The sequence is not empty.")
+}
+
+/// Returns `n` keys in ascending order, starting from
+/// [FractionalIndex::default]. The first keys of any two calls are the
+/// same, so `keys(n)[i] == nth_key(i)`.
+pub fn keys(n: usize) -> Vec<FractionalIndex> {
+    appended(n)
+}
+
+/// Returns the `n` keys obtained by starting from
+/// [FractionalIndex::default] and repeatedly calling
+/// [FractionalIndex::new_after], as when appending to a list.
+pub fn appended(n: usize) -> Vec<FractionalIndex> {
+    let mut keys: Vec<FractionalIndex> = Vec::with_capacity(n);
+    for _ in 0..n {
+        keys.push(match keys.last() {
+            Some(last) => FractionalIndex::new_after(last),
+            None => FractionalIndex::default(),
+        });
+    }
+    keys
+}
+
+/// Returns the `n` keys obtained by starting from
+/// [FractionalIndex::default] and repeatedly calling
+/// [FractionalIndex::new_before], as when prepending to a list, in
+/// ascending order.
+pub fn prepended(n: usize) -> Vec<FractionalIndex> {
+    let mut keys: Vec<FractionalIndex> = Vec::with_capacity(n);
+    for _ in 0..n {
+        keys.push(match keys.last() {
+            Some(first) => FractionalIndex::new_before(first),
+            None => FractionalIndex::default(),
+        });
+    }
+    keys.reverse();
+    keys
+}
+
+/// Returns `n` keys in ascending order, obtained by repeatedly inserting
+/// between the two most recent keys, alternating which one is replaced.
+///
+/// Key length grows linearly with `n`, which makes this the worst case
+/// for key growth, e.g. for testing rebalancing.
+pub fn bisected(n: usize) -> Vec<FractionalIndex> {
+    let mut keys = appended(n.min(2));
+    if n > 2 {
+        let mut lower = keys[0].clone();
+        let mut upper = keys[1].clone();
+        for i in 0..n - 2 {
+            let key = FractionalIndex::new_between(&lower, &upper)
+                .expect("The bounds are distinct and in order.");
+            keys.push(key.clone());
+            if i % 2 == 0 {
+                lower = key;
+            } else {
+                upper = key;
+            }
+        }
+        keys.sort();
+    }
+    keys
+}
+
+/// Returns `n` evenly spaced keys in ascending order, as used when
+/// building or rebalancing a list in bulk.
+pub fn spaced(n: usize) -> Vec<FractionalIndex> {
+    FractionalIndex::spaced(None, None, n).expect("Missing bounds are always in order.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_ascending(keys: &[FractionalIndex], n: usize) {
+        assert_eq!(keys.len(), n);
+        for pair in keys.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+    }
+
+    #[test]
+    fn sequences() {
+        for n in [0, 1, 2, 3, 100] {
+            assert_ascending(&appended(n), n);
+            assert_ascending(&prepended(n), n);
+            assert_ascending(&bisected(n), n);
+            assert_ascending(&spaced(n), n);
+        }
+        let keys = keys(300);
+        assert_eq!(nth_key(299), keys[299]);
+        assert_eq!(prepended(2)[0].to_string(), "7f80");
+        assert!(bisected(20).iter().any(|key| key.len() > 6));
+        assert_eq!(from_unterminated(&[129]), nth_key(1));
+        assert_eq!(from_unterminated(&[]), FractionalIndex::default());
+    }
+}
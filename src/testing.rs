@@ -0,0 +1,78 @@
+//! The invariants of [FractionalIndex], as functions for property tests.
+//!
+//! Each function returns whether the invariant holds, so it can be used
+//! with `assert!`, `prop_assert!` or `quickcheck`'s `TestResult`:
+//!
+//! ```rust
+//! use fractional_index::{testing, FractionalIndex};
+//!
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//! let c = FractionalIndex::new_between(&a, &b).unwrap();
+//!
+//! assert!(testing::strictly_between(Some(&a), &c, Some(&b)));
+//! assert!(testing::strictly_ordered([&a, &c, &b]));
+//! assert!(testing::roundtrips_via_string(&c));
+//! ```
+use crate::FractionalIndex;
+
+/// Returns true if `key` is greater than `lower` and less than `upper`.
+/// A missing bound is satisfied by any key, as in [FractionalIndex::new].
+pub fn strictly_between(
+    lower: Option<&FractionalIndex>,
+    key: &FractionalIndex,
+    upper: Option<&FractionalIndex>,
+) -> bool {
+    lower.is_none_or(|lower| lower < key) && upper.is_none_or(|upper| key < upper)
+}
+
+/// Returns true if every key is less than the next, i.e. the keys are
+/// sorted and distinct.
+pub fn strictly_ordered<'a, I>(keys: I) -> bool
+where
+    I: IntoIterator<Item = &'a FractionalIndex>,
+{
+    let mut keys = keys.into_iter();
+    let Some(mut previous) = keys.next() else {
+        return true;
+    };
+    keys.all(|key| std::mem::replace(&mut previous, key) < key)
+}
+
+/// Returns true if `key` is decoded unchanged from its string
+/// representation, and that representation is in the same order relative
+/// to the default key as `key` is.
+pub fn roundtrips_via_string(key: &FractionalIndex) -> bool {
+    let s = key.to_string();
+    let default = FractionalIndex::default();
+    FractionalIndex::from_string(&s).as_ref() == Ok(key)
+        && s.parse::<FractionalIndex>().as_ref() == Ok(key)
+        && s.as_str().cmp(default.to_string().as_str()) == key.cmp(&default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invariants() {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let c = FractionalIndex::new_between(&a, &b).unwrap();
+
+        assert!(strictly_between(None, &a, None));
+        assert!(strictly_between(Some(&a), &c, None));
+        assert!(!strictly_between(Some(&a), &a, Some(&b)));
+        assert!(!strictly_between(None, &b, Some(&c)));
+
+        assert!(strictly_ordered(&Vec::new()));
+        assert!(strictly_ordered([&a]));
+        assert!(strictly_ordered([&a, &c, &b]));
+        assert!(!strictly_ordered([&a, &b, &c]));
+        assert!(!strictly_ordered(&vec![a.clone(), a.clone()]));
+
+        for key in [&a, &b, &c, &FractionalIndex::new_before(&a)] {
+            assert!(roundtrips_via_string(key));
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Keys with a priority tier, for lists where some items (e.g. pinned ones)
+//! always sort before the rest.
+//!
+//! A [Tiered] key compares by its tier first and its [FractionalIndex]
+//! second, so every key of a lower tier sorts before every key of a higher
+//! tier, and the keys of each tier keep the full fractional ordering.
+//! [Tiered::to_bytes] prefixes the key's bytes with the tier, so the
+//! encoded form sorts in the same order.
+//!
+//! ```rust
+//! use fractional_index::Tiered;
+//!
+//! let first = Tiered::initial(Tiered::NORMAL);
+//! let second = Tiered::new_after(&first);
+//! let pinned = Tiered::initial(Tiered::PINNED);
+//!
+//! let mut keys = vec![second.clone(), first.clone(), pinned.clone()];
+//! keys.sort();
+//! assert_eq!(keys, vec![pinned.clone(), first.clone(), second]);
+//!
+//! // Between keys of different tiers, the new key joins the lower tier.
+//! let also_pinned = Tiered::new_between(&pinned, &first).unwrap();
+//! assert_eq!(also_pinned.tier(), Tiered::PINNED);
+//! assert!(pinned.to_bytes() < also_pinned.to_bytes());
+//! assert!(also_pinned.to_bytes() < first.to_bytes());
+//! ```
+use crate::{
+    hex::{bytes_to_hex, hex_to_bytes},
+    limits::check_len,
+    DecodeError, FractionalIndex,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A [FractionalIndex] within a priority tier. Lower tiers sort first.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Tiered {
+    tier: u8,
+    index: FractionalIndex,
+}
+
+impl Tiered {
+    /// The tier of pinned items, which sort before [Tiered::NORMAL] ones.
+    pub const PINNED: u8 = 0;
+
+    /// The tier of items that are not pinned.
+    pub const NORMAL: u8 = 1;
+
+    pub fn new(tier: u8, index: FractionalIndex) -> Self {
+        Tiered { tier, index }
+    }
+
+    /// Returns the key of the first item added to an empty tier.
+    pub fn initial(tier: u8) -> Self {
+        Tiered::new(tier, FractionalIndex::default())
+    }
+
+    pub fn tier(&self) -> u8 {
+        self.tier
+    }
+
+    /// Returns the index of this key within its tier.
+    pub fn index(&self) -> &FractionalIndex {
+        &self.index
+    }
+
+    pub fn into_index(self) -> FractionalIndex {
+        self.index
+    }
+
+    /// Constructs a key in the same tier that compares as before the given
+    /// one.
+    pub fn new_before(upper: &Tiered) -> Tiered {
+        Tiered::new(upper.tier, FractionalIndex::new_before(&upper.index))
+    }
+
+    /// Constructs a key in the same tier that compares as after the given
+    /// one.
+    pub fn new_after(lower: &Tiered) -> Tiered {
+        Tiered::new(lower.tier, FractionalIndex::new_after(&lower.index))
+    }
+
+    /// Constructs a key that compares as between the given two. If they
+    /// are in different tiers, the new key is in the tier of `lower`, after
+    /// `lower`.
+    ///
+    /// Returns None if the keys are not in order or are equal.
+    pub fn new_between(lower: &Tiered, upper: &Tiered) -> Option<Tiered> {
+        if lower.tier == upper.tier {
+            let index = FractionalIndex::new_between(&lower.index, &upper.index)?;
+            Some(Tiered::new(lower.tier, index))
+        } else if lower.tier < upper.tier {
+            Some(Tiered::new_after(lower))
+        } else {
+            None
+        }
+    }
+
+    /// Constructs a key in `tier` between the given optional bounds, like
+    /// [FractionalIndex::new], e.g. to pin an item after the last pinned
+    /// one.
+    ///
+    /// Returns None if a bound is in another tier, or if the bounds are not
+    /// in order or are equal.
+    pub fn new_in(tier: u8, lower: Option<&Tiered>, upper: Option<&Tiered>) -> Option<Tiered> {
+        if lower.into_iter().chain(upper).any(|key| key.tier != tier) {
+            return None;
+        }
+        FractionalIndex::new(lower.map(Tiered::index), upper.map(Tiered::index))
+            .map(|index| Tiered::new(tier, index))
+    }
+
+    /// Returns the tier followed by the bytes of the index, which compare
+    /// in the same order as the keys.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.index.as_bytes().len());
+        bytes.push(self.tier);
+        bytes.extend_from_slice(self.index.as_bytes());
+        bytes
+    }
+
+    /// Decodes a key from the bytes returned by [Tiered::to_bytes].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let (&tier, index) = bytes.split_first().ok_or(DecodeError::Truncated)?;
+        let index = FractionalIndex::from_bytes(index.to_vec())?;
+        Ok(Tiered::new(tier, index))
+    }
+
+    /// Returns the hex encoding of [Tiered::to_bytes].
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        bytes_to_hex(&self.to_bytes())
+    }
+
+    /// Decodes a key from the string returned by [Tiered::to_string].
+    pub fn from_string(s: &str) -> Result<Self, DecodeError> {
+        if s.is_empty() {
+            return Err(DecodeError::EmptyString);
+        }
+        check_len(s.len() / 2)?;
+        let bytes = hex_to_bytes(s).map_err(|err| DecodeError::InvalidChars {
+            character: err.character,
+            position: err.position,
+        })?;
+        Tiered::from_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiers_sort_first() {
+        let mut keys = vec![Tiered::initial(Tiered::NORMAL)];
+        for i in 0..40 {
+            let tier = [Tiered::PINNED, Tiered::NORMAL, 7][i % 3];
+            let last = keys.iter().filter(|key| key.tier() == tier).max();
+            let first = keys.iter().filter(|key| key.tier() == tier).min();
+            let key = if i % 2 == 0 {
+                Tiered::new_in(tier, last, None)
+            } else {
+                Tiered::new_in(tier, None, first)
+            };
+            keys.push(key.unwrap());
+        }
+        keys.sort();
+        for pair in keys.windows(2) {
+            assert!(pair[0].tier() <= pair[1].tier());
+            assert!(pair[0].to_bytes() < pair[1].to_bytes());
+            assert!(pair[0].to_string() < pair[1].to_string());
+            let between = Tiered::new_between(&pair[0], &pair[1]).unwrap();
+            assert!(pair[0] < between && between < pair[1]);
+            assert_eq!(between.tier(), pair[0].tier());
+            assert_eq!(Tiered::new_between(&pair[1], &pair[0]), None);
+        }
+        for key in &keys {
+            assert_eq!(&Tiered::from_bytes(&key.to_bytes()).unwrap(), key);
+            assert_eq!(&Tiered::from_string(&key.to_string()).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn new_in_other_tier() {
+        let pinned = Tiered::initial(Tiered::PINNED);
+        assert_eq!(Tiered::new_in(Tiered::NORMAL, Some(&pinned), None), None);
+        assert_eq!(
+            Tiered::new_in(Tiered::NORMAL, None, None),
+            Some(Tiered::initial(Tiered::NORMAL))
+        );
+    }
+
+    #[test]
+    fn invalid_encodings() {
+        assert_eq!(Tiered::from_bytes(&[]), Err(DecodeError::Truncated));
+        assert_eq!(
+            Tiered::from_bytes(&[0]),
+            Err(DecodeError::MissingTerminator { found: None })
+        );
+        assert_eq!(Tiered::from_string(""), Err(DecodeError::EmptyString));
+    }
+}
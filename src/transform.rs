@@ -0,0 +1,217 @@
+//! Order-preserving transforms applied to keys when they are stored.
+//!
+//! The bytes of a [FractionalIndex] reveal how it was constructed: keys
+//! grow by a byte every few appends, and bisected keys have telltale
+//! digits such as `7f` and `81`. A [KeyTransform] rewrites the bytes
+//! before they are stored and inverts the rewrite when they are read, so
+//! keys can be kept in less-trusted systems (e.g. a shared database or a
+//! search index) while still sorting in the same order there.
+//!
+//! [Prefixed] namespaces keys, and [KeyedByteMap] replaces each byte with
+//! a code chosen by a secret seed.
+//!
+//! ```rust
+//! use fractional_index::{transform::{KeyTransform, KeyedByteMap}, FractionalIndex};
+//!
+//! let transform = KeyedByteMap::new(0x5eed);
+//! let a = FractionalIndex::default();
+//! let b = FractionalIndex::new_after(&a);
+//!
+//! let (stored_a, stored_b) = (transform.encode(&a), transform.encode(&b));
+//! assert!(stored_a < stored_b);
+//! assert_eq!(transform.decode(&stored_b).unwrap(), b);
+//! ```
+//!
+//! These transforms are obfuscation, not encryption: they preserve order
+//! and key length by design, and an observer who can choose keys and see
+//! their stored form can recover the mapping.
+use crate::{rng::SplitMix, DecodeError, FractionalIndex};
+use std::fmt;
+
+/// A reversible rewrite of key bytes that preserves their lexicographic
+/// order.
+///
+/// Implementations must ensure that `a < b` implies
+/// `transform(a) < transform(b)` for all byte strings, and that
+/// `invert(transform(a)) == Ok(a)`.
+pub trait KeyTransform {
+    /// Rewrites the bytes of a key for storage.
+    fn transform(&self, bytes: &[u8]) -> Vec<u8>;
+
+    /// Recovers the bytes of a key from the output of
+    /// [KeyTransform::transform].
+    fn invert(&self, stored: &[u8]) -> Result<Vec<u8>, DecodeError>;
+
+    /// Returns the stored form of `index`.
+    fn encode(&self, index: &FractionalIndex) -> Vec<u8> {
+        self.transform(index.as_bytes())
+    }
+
+    /// Decodes a [FractionalIndex] from its stored form.
+    fn decode(&self, stored: &[u8]) -> Result<FractionalIndex, DecodeError> {
+        FractionalIndex::from_bytes(self.invert(stored)?)
+    }
+}
+
+impl<T: KeyTransform + ?Sized> KeyTransform for &T {
+    fn transform(&self, bytes: &[u8]) -> Vec<u8> {
+        (**self).transform(bytes)
+    }
+
+    fn invert(&self, stored: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        (**self).invert(stored)
+    }
+}
+
+/// Prepends a fixed prefix, e.g. to store the keys of several lists in one
+/// ordered keyspace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Prefixed(pub Vec<u8>);
+
+impl KeyTransform for Prefixed {
+    fn transform(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut stored = Vec::with_capacity(self.0.len() + bytes.len());
+        stored.extend_from_slice(&self.0);
+        stored.extend_from_slice(bytes);
+        stored
+    }
+
+    fn invert(&self, stored: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        if stored.len() < self.0.len() {
+            return Err(DecodeError::Truncated);
+        }
+        if let Some(position) = (0..self.0.len()).find(|&i| stored[i] != self.0[i]) {
+            return Err(DecodeError::UnexpectedByte {
+                byte: stored[position],
+                position,
+            });
+        }
+        Ok(stored[self.0.len()..].to_vec())
+    }
+}
+
+/// Replaces each byte with a two-byte code. The codes increase with the
+/// byte, so order is preserved, but the gaps between them are chosen
+/// pseudo-randomly from a seed, which hides the values of the digits.
+#[derive(Clone, PartialEq, Eq)]
+pub struct KeyedByteMap {
+    codes: Box<[u16; 256]>,
+}
+
+/// Omits the codes, which would reveal the mapping.
+impl fmt::Debug for KeyedByteMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedByteMap").finish_non_exhaustive()
+    }
+}
+
+impl KeyedByteMap {
+    /// Derives the codes from `seed`. The same seed always produces the
+    /// same codes, so it must be kept for as long as keys are stored.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = SplitMix::new(seed);
+        let mut codes = Box::new([0u16; 256]);
+        // Each code is 1 to 256 greater than the last, which keeps the
+        // last code within u16.
+        let mut code = rng.below(256) as u16;
+        for slot in codes.iter_mut() {
+            *slot = code;
+            code = code.saturating_add(1 + rng.below(256) as u16);
+        }
+        KeyedByteMap { codes }
+    }
+}
+
+impl KeyTransform for KeyedByteMap {
+    fn transform(&self, bytes: &[u8]) -> Vec<u8> {
+        bytes
+            .iter()
+            .flat_map(|byte| self.codes[*byte as usize].to_be_bytes())
+            .collect()
+    }
+
+    fn invert(&self, stored: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        if !stored.len().is_multiple_of(2) {
+            return Err(DecodeError::Truncated);
+        }
+        stored
+            .chunks(2)
+            .enumerate()
+            .map(|(i, pair)| {
+                let code = u16::from_be_bytes([pair[0], pair[1]]);
+                self.codes
+                    .binary_search(&code)
+                    .map(|byte| byte as u8)
+                    .map_err(|_| DecodeError::UnexpectedByte {
+                        byte: pair[1],
+                        position: 2 * i + 1,
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> Vec<FractionalIndex> {
+        let mut keys = FractionalIndex::spaced(None, None, 300).unwrap();
+        for i in 0..20 {
+            keys.push(FractionalIndex::new_between(&keys[i], &keys[i + 1]).unwrap());
+            keys.push(FractionalIndex::new_before(&keys[i]));
+        }
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    fn check(transform: &impl KeyTransform) {
+        let keys = keys();
+        let stored: Vec<Vec<u8>> = keys.iter().map(|key| transform.encode(key)).collect();
+        for pair in stored.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+        for (key, stored) in keys.iter().zip(&stored) {
+            assert_eq!(transform.decode(stored).as_ref(), Ok(key));
+        }
+    }
+
+    #[test]
+    fn prefixed() {
+        let transform = Prefixed(b"list:1/".to_vec());
+        check(&transform);
+        assert_eq!(
+            transform.decode(b"list:2/\x80"),
+            Err(DecodeError::UnexpectedByte {
+                byte: b'2',
+                position: 5
+            })
+        );
+        assert_eq!(transform.decode(b"list"), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn keyed_byte_map() {
+        for seed in [0, 1, u64::MAX] {
+            let transform = KeyedByteMap::new(seed);
+            for pair in transform.codes.windows(2) {
+                assert!(pair[0] < pair[1]);
+            }
+            check(&transform);
+        }
+        assert!(KeyedByteMap::new(1) != KeyedByteMap::new(2));
+        assert_eq!(format!("{:?}", KeyedByteMap::new(1)), "KeyedByteMap { .. }");
+
+        let transform = KeyedByteMap::new(7);
+        let stored = transform.encode(&FractionalIndex::default());
+        assert_eq!(transform.decode(&stored[..1]), Err(DecodeError::Truncated));
+        let missing = (0..=u16::MAX)
+            .find(|code| transform.codes.binary_search(code).is_err())
+            .unwrap();
+        assert!(matches!(
+            transform.decode(&missing.to_be_bytes()),
+            Err(DecodeError::UnexpectedByte { position: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,54 @@
+//! Unsigned LEB128 variable-length integers, used by the binary encodings.
+
+use crate::DecodeError;
+
+pub fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Reads a varint from the front of `input`, advancing it past the varint.
+pub fn read_varint(input: &mut &[u8]) -> Result<u64, DecodeError> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input.split_first().ok_or(DecodeError::Truncated)?;
+        *input = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(DecodeError::InvalidLength)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut out = Vec::new();
+            write_varint(&mut out, value);
+            let mut input = &out[..];
+            assert_eq!(read_varint(&mut input).unwrap(), value);
+            assert!(input.is_empty());
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let mut input: &[u8] = &[0x80, 0x80];
+        assert!(matches!(
+            read_varint(&mut input),
+            Err(DecodeError::Truncated)
+        ));
+    }
+}
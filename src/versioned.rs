@@ -0,0 +1,357 @@
+//! An ordering of elements that keeps the history of their keys, for
+//! reading the order as it was at any earlier revision.
+//!
+//! [VersionedOrderedList] assigns a [FractionalIndex] to each element id,
+//! like an [OrderedList](crate::OrderedList) of ids. Changes are made to
+//! a working revision, which [VersionedOrderedList::commit] seals. Every
+//! committed revision can then be read with
+//! [VersionedOrderedList::iter_at], which makes audit views and history
+//! scrubbing possible without a separate event log.
+//!
+//! Only the keys that change are recorded, so a revision that moves one
+//! element costs one history entry however long the list is.
+//!
+//! ```rust
+//! use fractional_index::versioned::VersionedOrderedList;
+//!
+//! let mut list = VersionedOrderedList::new();
+//! let a = list.push_back("a");
+//! list.push_back("b");
+//! let r1 = list.commit();
+//!
+//! // Move "a" to the end.
+//! list.push_back("a");
+//! list.remove(&"b");
+//! let r2 = list.commit();
+//!
+//! let ids = |revision| -> Vec<&str> {
+//!     list.iter_at(revision).map(|(_, id)| *id).collect()
+//! };
+//! assert_eq!(ids(0), Vec::<&str>::new());
+//! assert_eq!(ids(r1), vec!["a", "b"]);
+//! assert_eq!(ids(r2), vec!["a"]);
+//! assert_eq!(list.key_at(&"a", r1), Some(&a));
+//! ```
+use crate::FractionalIndex;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+/// A revision number. Revision 0 is the empty list, and each
+/// [VersionedOrderedList::commit] creates the next one.
+pub type Revision = u64;
+
+/// The error returned by [VersionedOrderedList::insert] when the key is
+/// already assigned to a different element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyOccupied;
+
+impl Display for KeyOccupied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The key is already assigned to a different element.")
+    }
+}
+
+impl std::error::Error for KeyOccupied {}
+
+/// An ordering of element ids that records the key of each element at
+/// every revision.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionedOrderedList<Id> {
+    /// The last committed revision.
+    revision: Revision,
+    /// The current order, including uncommitted changes.
+    current: BTreeMap<FractionalIndex, Id>,
+    /// The key of each element from each revision in which it changed,
+    /// in ascending order of revision. `None` means the element was
+    /// removed.
+    history: BTreeMap<Id, Vec<(Revision, Option<FractionalIndex>)>>,
+}
+
+impl<Id> Default for VersionedOrderedList<Id> {
+    fn default() -> Self {
+        VersionedOrderedList {
+            revision: 0,
+            current: BTreeMap::new(),
+            history: BTreeMap::new(),
+        }
+    }
+}
+
+/// Returns the key from the last entry of `history` at or before
+/// `revision`.
+fn key_at(
+    history: &[(Revision, Option<FractionalIndex>)],
+    revision: Revision,
+) -> Option<&FractionalIndex> {
+    let end = history.partition_point(|(r, _)| *r <= revision);
+    end.checked_sub(1).and_then(|i| history[i].1.as_ref())
+}
+
+impl<Id: Ord + Clone> VersionedOrderedList<Id> {
+    /// Constructs an empty list at revision 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the last committed revision.
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// Returns true if changes have been made since the last commit.
+    pub fn is_dirty(&self) -> bool {
+        self.history
+            .values()
+            .any(|history| history.last().is_some_and(|(r, _)| *r > self.revision))
+    }
+
+    /// Seals the working revision and returns its number. Later changes
+    /// are made to the revision after it.
+    ///
+    /// A new revision is created even if nothing changed, so revisions can
+    /// be kept in step with an external clock.
+    pub fn commit(&mut self) -> Revision {
+        self.revision += 1;
+        self.revision
+    }
+
+    /// Returns the number of elements in the current order.
+    pub fn len(&self) -> usize {
+        self.current.len()
+    }
+
+    /// Returns true if the current order has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.current.is_empty()
+    }
+
+    /// Returns the current key of `id`, including uncommitted changes.
+    pub fn key(&self, id: &Id) -> Option<&FractionalIndex> {
+        self.history
+            .get(id)
+            .and_then(|history| history.last())
+            .and_then(|(_, key)| key.as_ref())
+    }
+
+    /// Returns the key of `id` at a committed `revision`.
+    pub fn key_at(&self, id: &Id, revision: Revision) -> Option<&FractionalIndex> {
+        self.check_revision(revision);
+        self.history
+            .get(id)
+            .and_then(|history| key_at(history, revision))
+    }
+
+    /// Returns an iterator over the current keys and ids, in order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&FractionalIndex, &Id)> + '_ {
+        self.current.iter()
+    }
+
+    /// Returns the keys and ids at a committed `revision`, in order.
+    ///
+    /// This takes O(n log n) time in the number of elements that have ever
+    /// been in the list. Panics if `revision` is greater than
+    /// [VersionedOrderedList::revision].
+    pub fn iter_at(
+        &self,
+        revision: Revision,
+    ) -> impl DoubleEndedIterator<Item = (&FractionalIndex, &Id)> + '_ {
+        self.check_revision(revision);
+        let order: BTreeMap<&FractionalIndex, &Id> = self
+            .history
+            .iter()
+            .filter_map(|(id, history)| key_at(history, revision).map(|key| (key, id)))
+            .collect();
+        order.into_iter()
+    }
+
+    /// Returns the changes made in a committed `revision`, in order of id,
+    /// as each changed id with its new key, or `None` if it was removed.
+    pub fn changes_at(&self, revision: Revision) -> Vec<(&Id, Option<&FractionalIndex>)> {
+        self.check_revision(revision);
+        self.history
+            .iter()
+            .filter_map(|(id, history)| {
+                history
+                    .binary_search_by_key(&revision, |(r, _)| *r)
+                    .ok()
+                    .map(|i| (id, history[i].1.as_ref()))
+            })
+            .collect()
+    }
+
+    /// Returns the revisions in which the key of `id` changed, with the
+    /// new key or `None` if it was removed, oldest first. The last entry
+    /// may be from the uncommitted working revision.
+    pub fn history(
+        &self,
+        id: &Id,
+    ) -> impl DoubleEndedIterator<Item = (Revision, Option<&FractionalIndex>)> + '_ {
+        self.history
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|(revision, key)| (*revision, key.as_ref()))
+    }
+
+    fn check_revision(&self, revision: Revision) {
+        assert!(
+            revision <= self.revision,
+            "Revision (is {}) should be <= the last committed revision (is {}).",
+            revision,
+            self.revision
+        );
+    }
+
+    /// Records `key` as the key of `id` in the working revision.
+    fn record(&mut self, id: Id, key: Option<FractionalIndex>) {
+        let working = self.revision + 1;
+        let history = self.history.entry(id.clone()).or_default();
+        if history.last().is_some_and(|(r, _)| *r == working) {
+            history.pop();
+        }
+        let previous = history.last().and_then(|(_, key)| key.as_ref());
+        if previous != key.as_ref() {
+            history.push((working, key));
+        }
+        if history.is_empty() {
+            self.history.remove(&id);
+        }
+    }
+
+    /// Assigns `key` to `id`, inserting it or moving it from its current
+    /// key, which is returned. Fails if another element has `key`.
+    pub fn insert(
+        &mut self,
+        id: Id,
+        key: FractionalIndex,
+    ) -> Result<Option<FractionalIndex>, KeyOccupied> {
+        if self.current.get(&key).is_some_and(|other| *other != id) {
+            return Err(KeyOccupied);
+        }
+        let previous = self.key(&id).cloned();
+        if let Some(previous) = &previous {
+            self.current.remove(previous);
+        }
+        self.current.insert(key.clone(), id.clone());
+        self.record(id, Some(key));
+        Ok(previous)
+    }
+
+    /// Moves `id` before every other element, inserting it if needed, and
+    /// returns its new key.
+    pub fn push_front(&mut self, id: Id) -> FractionalIndex {
+        let key = match self.current.keys().next() {
+            Some(first) => FractionalIndex::new_before(first),
+            None => FractionalIndex::default(),
+        };
+        self.insert(id, key.clone())
+            .expect("A key before every other key is unoccupied.");
+        key
+    }
+
+    /// Moves `id` after every other element, inserting it if needed, and
+    /// returns its new key.
+    pub fn push_back(&mut self, id: Id) -> FractionalIndex {
+        let key = match self.current.keys().next_back() {
+            Some(last) => FractionalIndex::new_after(last),
+            None => FractionalIndex::default(),
+        };
+        self.insert(id, key.clone())
+            .expect("A key after every other key is unoccupied.");
+        key
+    }
+
+    /// Removes `id`, returning its key.
+    pub fn remove(&mut self, id: &Id) -> Option<FractionalIndex> {
+        let key = self.key(id)?.clone();
+        self.current.remove(&key);
+        self.record(id.clone(), None);
+        Some(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(list: &VersionedOrderedList<char>, revision: Revision) -> String {
+        list.iter_at(revision).map(|(_, id)| *id).collect()
+    }
+
+    #[test]
+    fn time_travel() {
+        let mut list = VersionedOrderedList::new();
+        let mut expected = vec![String::new()];
+        for (i, id) in "abcdef".chars().enumerate() {
+            if i % 2 == 0 {
+                list.push_back(id);
+            } else {
+                list.push_front(id);
+            }
+            if i % 3 == 2 {
+                list.remove(&'a');
+            }
+            expected.push(list.iter().map(|(_, id)| *id).collect());
+            assert!(list.is_dirty());
+            assert_eq!(list.commit(), i as Revision + 1);
+            assert!(!list.is_dirty());
+        }
+        assert_eq!(expected[3], "bc");
+        assert_eq!(expected[6], "fdbce");
+
+        let b = list.key(&'b').unwrap().clone();
+        let e = list.key(&'e').unwrap().clone();
+        let between = FractionalIndex::new_between(&b, list.key(&'c').unwrap()).unwrap();
+        assert_eq!(list.insert('e', between.clone()), Ok(Some(e.clone())));
+        assert_eq!(list.insert('a', b.clone()), Err(KeyOccupied));
+        assert_eq!(ids(&list, 6), "fdbce");
+        expected.push("fdbec".to_string());
+        assert_eq!(list.commit(), 7);
+
+        for (revision, expected) in expected.iter().enumerate() {
+            assert_eq!(ids(&list, revision as Revision), *expected);
+        }
+        assert_eq!(list.key_at(&'e', 6), Some(&e));
+        assert_eq!(list.key_at(&'e', 7), Some(&between));
+        assert_eq!(list.key_at(&'e', 4), None);
+        assert_eq!(list.changes_at(7), vec![(&'e', Some(&between))]);
+        assert_eq!(list.changes_at(3).len(), 2);
+        assert_eq!(
+            list.history(&'a').collect::<Vec<_>>(),
+            vec![(1, Some(&FractionalIndex::default())), (3, None)]
+        );
+    }
+
+    #[test]
+    fn working_revision() {
+        let mut list = VersionedOrderedList::new();
+        let a = list.push_back('a');
+        list.commit();
+
+        // Changes that cancel out within a revision leave no history.
+        list.push_back('b');
+        list.remove(&'b');
+        list.push_back('a');
+        list.insert('a', a.clone()).unwrap();
+        assert!(!list.is_dirty());
+        assert_eq!(list.history(&'b').count(), 0);
+        assert_eq!(list.commit(), 2);
+        assert!(list.changes_at(2).is_empty());
+
+        // Only the last change to an element in a revision is kept.
+        list.push_front('b');
+        let b = list.push_back('b');
+        assert_eq!(list.commit(), 3);
+        assert_eq!(list.changes_at(3), vec![(&'b', Some(&b))]);
+        assert_eq!(ids(&list, 1), "a");
+        assert_eq!(ids(&list, 3), "ab");
+    }
+
+    #[test]
+    #[should_panic(expected = "Revision (is 1)")]
+    fn uncommitted_revision() {
+        let mut list = VersionedOrderedList::new();
+        list.push_back('a');
+        list.key_at(&'a', 1);
+    }
+}
@@ -0,0 +1,63 @@
+//! [warp] filters that extract keys from requests, enabled by the `warp`
+//! feature.
+//!
+//! [param] extracts a key from the next path segment, in the hex
+//! representation returned by [FractionalIndex::to_string]. A segment that
+//! is not a valid key rejects the request with [InvalidIndex], which a
+//! recovery filter can turn into a `400 Bad Request`.
+//!
+//! ```rust
+//! use fractional_index::{warp::param, FractionalIndex};
+//! use warp::Filter;
+//!
+//! let route = warp::path("items")
+//!     .and(param())
+//!     .map(|key: FractionalIndex| key.to_string());
+//! ```
+use crate::{DecodeError, FractionalIndex};
+use ::warp::{reject::Reject, Filter, Rejection};
+
+/// The rejection of a request whose path segment is not a valid key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidIndex(pub DecodeError);
+
+impl Reject for InvalidIndex {}
+
+/// Extracts a key from the next path segment, parsed with
+/// [FractionalIndex::from_string].
+pub fn param() -> impl Filter<Extract = (FractionalIndex,), Error = Rejection> + Copy {
+    ::warp::path::param::<String>().and_then(|segment: String| async move {
+        FractionalIndex::from_string(&segment)
+            .map_err(|err| ::warp::reject::custom(InvalidIndex(err)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn extract_param() {
+        let key = FractionalIndex::new_after(&FractionalIndex::default());
+        let filter = ::warp::path("items").and(param());
+
+        let extracted = ::warp::test::request()
+            .path("/items/8180")
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(extracted, key);
+
+        let rejection = ::warp::test::request()
+            .path("/items/81")
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            rejection.find::<InvalidIndex>(),
+            Some(&InvalidIndex(DecodeError::MissingTerminator {
+                found: Some(0x81)
+            }))
+        );
+    }
+}
@@ -0,0 +1,374 @@
+//! Simulated workloads for comparing key allocation strategies. Enabled by
+//! the `test-util` feature.
+//!
+//! How long keys grow depends on where a list is edited: appending grows
+//! keys slowly, while repeatedly inserting into the same gap grows them by
+//! a byte every few inserts. A [Workload] replays a seeded mix of inserts,
+//! moves and deletes, placed according to a [Pattern], against an
+//! [Allocation] strategy, and its [Report] describes the distribution of
+//! the resulting key lengths.
+//!
+//! ```rust
+//! use fractional_index::workload::{Bisect, Rebalancing, Workload};
+//!
+//! let workload = Workload::adversarial(100);
+//! let bisect = workload.run(&mut Bisect);
+//! let rebalancing = workload.run(&mut Rebalancing { max_len: 8 });
+//!
+//! assert!(bisect.max_len() > 50);
+//! assert!(rebalancing.max_len() <= 8);
+//! assert!(rebalancing.rewritten > 0);
+//! ```
+use crate::{rebalance, rng::SplitMix, strategy::KeyStrategy, FractionalIndex};
+use std::collections::BTreeMap;
+
+/// Where the inserts and moves of a [Workload] place items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// At the end of the list, e.g. a log or a chat.
+    Append,
+    /// At the front of the list, e.g. a feed with the newest item first.
+    Front,
+    /// At a uniformly random position.
+    Random,
+    /// Alternately directly after and directly before the last placed
+    /// item, which always splits the same gap. This is the worst case for
+    /// key growth.
+    Adversarial,
+}
+
+/// A seeded sequence of operations on a list.
+///
+/// Each operation is an insert, a move or a delete, chosen at random in
+/// proportion to their weights, or an insert if every weight is 0. Moves
+/// and deletes pick a random item, and are replaced by inserts while the
+/// list is empty.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Workload {
+    pub pattern: Pattern,
+    /// The number of operations.
+    pub operations: usize,
+    pub insert_weight: u32,
+    pub move_weight: u32,
+    pub delete_weight: u32,
+    pub seed: u64,
+}
+
+impl Workload {
+    /// Constructs a workload of inserts only.
+    pub fn new(pattern: Pattern, operations: usize) -> Self {
+        Workload {
+            pattern,
+            operations,
+            insert_weight: 1,
+            move_weight: 0,
+            delete_weight: 0,
+            seed: 0,
+        }
+    }
+
+    /// Mostly appends, with a few moves to the end and deletes.
+    pub fn append_heavy(operations: usize) -> Self {
+        Workload {
+            insert_weight: 8,
+            move_weight: 1,
+            delete_weight: 1,
+            ..Workload::new(Pattern::Append, operations)
+        }
+    }
+
+    /// Mostly inserts at the front, with a few moves to the front and
+    /// deletes.
+    pub fn front_heavy(operations: usize) -> Self {
+        Workload {
+            insert_weight: 8,
+            move_weight: 1,
+            delete_weight: 1,
+            ..Workload::new(Pattern::Front, operations)
+        }
+    }
+
+    /// Inserts, moves and deletes at random positions.
+    pub fn random(operations: usize) -> Self {
+        Workload {
+            insert_weight: 2,
+            move_weight: 2,
+            delete_weight: 1,
+            ..Workload::new(Pattern::Random, operations)
+        }
+    }
+
+    /// Inserts that always split the same gap.
+    pub fn adversarial(operations: usize) -> Self {
+        Workload::new(Pattern::Adversarial, operations)
+    }
+
+    /// Replays the workload against `strategy`, starting from an empty
+    /// list.
+    pub fn run<A: Allocation + ?Sized>(&self, strategy: &mut A) -> Report {
+        let mut rng = SplitMix::new(self.seed);
+        let mut keys: Vec<FractionalIndex> = Vec::new();
+        let mut report = Report::default();
+        // The position of the last placed item, for the adversarial pattern.
+        let mut last = 0;
+        let total = u64::from(self.insert_weight)
+            + u64::from(self.move_weight)
+            + u64::from(self.delete_weight);
+
+        for i in 0..self.operations {
+            let choice = if total == 0 { 0 } else { rng.below(total) };
+            let is_insert = keys.is_empty() || total == 0 || choice < u64::from(self.insert_weight);
+            if !is_insert {
+                let removed = rng.below(keys.len() as u64) as usize;
+                keys.remove(removed);
+                if last > removed {
+                    last -= 1;
+                }
+                let is_move = choice < u64::from(self.insert_weight) + u64::from(self.move_weight);
+                if !is_move {
+                    continue;
+                }
+            }
+
+            let position = match self.pattern {
+                Pattern::Append => keys.len(),
+                Pattern::Front => 0,
+                Pattern::Random => rng.below(keys.len() as u64 + 1) as usize,
+                Pattern::Adversarial if i % 2 == 0 => (last + 1).min(keys.len()),
+                Pattern::Adversarial => last.min(keys.len()),
+            };
+            let lower = position.checked_sub(1).map(|i| &keys[i]);
+            let upper = keys.get(position);
+            let key = strategy.between(lower, upper);
+            report.generated += 1;
+            report.max_generated_len = report.max_generated_len.max(key.len());
+            keys.insert(position, key);
+            last = position;
+
+            let remap = strategy.maintain(&keys);
+            if !remap.is_empty() {
+                report.rewritten += remap.len();
+                let mut remap = remap.into_iter().peekable();
+                for key in keys.iter_mut() {
+                    if let Some((_, new)) = remap.next_if(|(old, _)| old == key) {
+                        *key = new;
+                    }
+                }
+            }
+        }
+
+        report.operations = self.operations;
+        for key in &keys {
+            *report.lengths.entry(key.len()).or_default() += 1;
+        }
+        report
+    }
+}
+
+/// A strategy for allocating keys.
+pub trait Allocation {
+    /// Returns a key between the given optional bounds, which are distinct
+    /// and in order.
+    fn between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> FractionalIndex;
+
+    /// Called with the keys of the list, in order, after each key is
+    /// placed. Returns pairs of old and new keys to rewrite, in order, e.g.
+    /// from a [rebalance::plan].
+    fn maintain(&mut self, _keys: &[FractionalIndex]) -> Vec<(FractionalIndex, FractionalIndex)> {
+        Vec::new()
+    }
+}
+
+/// Allocates keys with a [KeyStrategy] and never rewrites them.
+impl<S: KeyStrategy> Allocation for S {
+    fn between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> FractionalIndex {
+        KeyStrategy::between(self, lower, upper).expect("The bounds are in order.")
+    }
+}
+
+/// Allocates keys with [FractionalIndex::new] and never rewrites them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bisect;
+
+impl Allocation for Bisect {
+    fn between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> FractionalIndex {
+        FractionalIndex::new(lower, upper).expect("The bounds are in order.")
+    }
+}
+
+/// Allocates keys with [FractionalIndex::new], and rebalances with
+/// [rebalance::plan] whenever a key is longer than `max_len`.
+#[derive(Clone, Copy, Debug)]
+pub struct Rebalancing {
+    pub max_len: usize,
+}
+
+impl Allocation for Rebalancing {
+    fn between(
+        &mut self,
+        lower: Option<&FractionalIndex>,
+        upper: Option<&FractionalIndex>,
+    ) -> FractionalIndex {
+        Bisect.between(lower, upper)
+    }
+
+    fn maintain(&mut self, keys: &[FractionalIndex]) -> Vec<(FractionalIndex, FractionalIndex)> {
+        if keys.iter().all(|key| key.len() <= self.max_len) {
+            return Vec::new();
+        }
+        rebalance::plan(keys, self.max_len)
+            .expect("The keys of the list are in order.")
+            .remap
+    }
+}
+
+/// The outcome of a [Workload].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub operations: usize,
+    /// The number of keys allocated by inserts and moves.
+    pub generated: usize,
+    /// The number of keys rewritten by [Allocation::maintain].
+    pub rewritten: usize,
+    /// The length of the longest key allocated, including keys that were
+    /// later deleted or rewritten.
+    pub max_generated_len: usize,
+    /// The number of keys of each length at the end of the workload.
+    pub lengths: BTreeMap<usize, usize>,
+}
+
+impl Report {
+    /// Returns the number of keys at the end of the workload.
+    pub fn len(&self) -> usize {
+        self.lengths.values().sum()
+    }
+
+    /// Returns true if no keys remain at the end of the workload.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total length in bytes of the remaining keys.
+    pub fn total_bytes(&self) -> usize {
+        self.lengths.iter().map(|(len, count)| len * count).sum()
+    }
+
+    /// Returns the mean length of the remaining keys, or 0 if there are
+    /// none.
+    pub fn mean_len(&self) -> f64 {
+        match self.len() {
+            0 => 0.0,
+            n => self.total_bytes() as f64 / n as f64,
+        }
+    }
+
+    /// Returns the length of the longest remaining key, or 0 if there are
+    /// none.
+    pub fn max_len(&self) -> usize {
+        self.lengths.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// Returns the smallest length that at least a `fraction` of the
+    /// remaining keys do not exceed, e.g. `0.99` for the 99th percentile,
+    /// or 0 if there are none.
+    pub fn percentile(&self, fraction: f64) -> usize {
+        let target = (fraction.clamp(0.0, 1.0) * self.len() as f64).ceil() as usize;
+        let mut seen = 0;
+        for (len, count) in &self.lengths {
+            seen += count;
+            if seen >= target.max(1) {
+                return *len;
+            }
+        }
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        let n = 2000;
+        let append = Workload::new(Pattern::Append, n).run(&mut Bisect);
+        let front = Workload::new(Pattern::Front, n).run(&mut Bisect);
+        let random = Workload::new(Pattern::Random, n).run(&mut Bisect);
+        let adversarial = Workload::adversarial(n).run(&mut Bisect);
+        for report in [&append, &front, &random, &adversarial] {
+            assert_eq!(report.len(), n);
+            assert_eq!(report.generated, n);
+            assert_eq!(report.rewritten, 0);
+        }
+        assert!(append.max_len() < 20);
+        assert!(front.max_len() < 20);
+        assert!(random.max_len() < 40);
+        assert!(adversarial.max_len() > 500);
+        assert_eq!(adversarial.max_generated_len, adversarial.max_len());
+    }
+
+    #[test]
+    fn mixed_operations() {
+        for workload in [
+            Workload::append_heavy(1000),
+            Workload::front_heavy(1000),
+            Workload::random(1000),
+        ] {
+            let report = workload.run(&mut Bisect);
+            assert!(report.len() < 1000);
+            assert!(report.generated > report.len());
+            assert_eq!(report, workload.run(&mut Bisect));
+            let reseeded = Workload {
+                seed: 1,
+                ..workload
+            };
+            assert!(reseeded.run(&mut Bisect) != report);
+        }
+        let deletes = Workload {
+            insert_weight: 0,
+            move_weight: 0,
+            delete_weight: 1,
+            ..Workload::random(10)
+        };
+        // Every other operation inserts into the list emptied before it.
+        assert_eq!(deletes.run(&mut Bisect).generated, 5);
+    }
+
+    #[test]
+    fn rebalancing() {
+        for workload in [Workload::adversarial(100), Workload::random(500)] {
+            let report = workload.run(&mut Rebalancing { max_len: 6 });
+            assert!(report.max_len() <= 6, "{:?}", report);
+        }
+    }
+
+    #[test]
+    fn statistics() {
+        let report = Report {
+            lengths: [(2, 5), (3, 4), (10, 1)].iter().copied().collect(),
+            ..Report::default()
+        };
+        assert_eq!(report.len(), 10);
+        assert_eq!(report.total_bytes(), 32);
+        assert_eq!(report.mean_len(), 3.2);
+        assert_eq!(report.max_len(), 10);
+        assert_eq!(report.percentile(0.0), 2);
+        assert_eq!(report.percentile(0.5), 2);
+        assert_eq!(report.percentile(0.9), 3);
+        assert_eq!(report.percentile(0.99), 10);
+        assert_eq!(Report::default().percentile(0.5), 0);
+        assert_eq!(Report::default().mean_len(), 0.0);
+    }
+}
@@ -4,9 +4,10 @@
 
 #![allow(deprecated)]
 
+use alloc::vec::Vec;
+use core::cmp::Ordering;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::cmp::Ordering;
 
 /// The largest value less than the magic byte.
 const MAGIC_FLOOR: u8 = 0b0111_1111; // =127
@@ -300,6 +301,7 @@ mod tests {
     };
 
     #[test]
+    #[allow(clippy::op_ref)]
     fn test_zeno_index() {
         let mut indices: Vec<ZenoIndex> = Vec::new();
 
@@ -0,0 +1,93 @@
+//! Round trips of list snapshots through the `csv` crate.
+use fractional_index::{FractionalIndex, OrderedList};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Row {
+    #[serde(with = "fractional_index::csv")]
+    position: FractionalIndex,
+    value: String,
+    #[serde(with = "fractional_index::csv::option")]
+    anchor: Option<FractionalIndex>,
+}
+
+fn rows() -> Vec<Row> {
+    let mut list = OrderedList::new();
+    let first = list.push_back("first".to_string());
+    list.push_back("with, comma".to_string());
+    list.push_front("with \"quotes\"\nand a newline".to_string());
+    list.insert_after(&first, "".to_string());
+    list.iter()
+        .map(|(key, value)| Row {
+            position: key.clone(),
+            value: value.clone(),
+            anchor: (key != &first).then(|| first.clone()),
+        })
+        .collect()
+}
+
+#[test]
+fn snapshot_roundtrip() {
+    let rows = rows();
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in &rows {
+        writer.serialize(row).unwrap();
+    }
+    let data = writer.into_inner().unwrap();
+
+    let mut reader = csv::Reader::from_reader(&data[..]);
+    let decoded: Vec<Row> = reader.deserialize().collect::<Result<_, _>>().unwrap();
+    assert_eq!(decoded, rows);
+
+    // Key fields are never quoted, even when other fields are.
+    let mut reader = csv::Reader::from_reader(&data[..]);
+    for (record, row) in reader.records().zip(&rows) {
+        let record = record.unwrap();
+        assert_eq!(record[0], row.position.to_string());
+        assert_eq!(record[0].parse::<FractionalIndex>().unwrap(), row.position);
+    }
+    for line in String::from_utf8(data).unwrap().lines() {
+        assert!(!line.starts_with('"'));
+    }
+}
+
+#[test]
+fn always_quoted() {
+    let rows = rows();
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::Always)
+        .delimiter(b';')
+        .from_writer(Vec::new());
+    for row in &rows {
+        writer.serialize(row).unwrap();
+    }
+    let data = writer.into_inner().unwrap();
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .from_reader(&data[..]);
+    let decoded: Vec<Row> = reader.deserialize().collect::<Result<_, _>>().unwrap();
+    assert_eq!(decoded, rows);
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .from_reader(&data[..]);
+    let decoded: Vec<Row> = reader
+        .byte_records()
+        .map(|record| record.unwrap().deserialize(None).unwrap())
+        .collect();
+    assert_eq!(decoded, rows);
+}
+
+#[test]
+fn invalid_fields() {
+    let data = "position,value,anchor\n80,a,zz\n";
+    let mut reader = csv::Reader::from_reader(data.as_bytes());
+    let err = reader.deserialize::<Row>().next().unwrap().unwrap_err();
+    assert!(err.to_string().contains("invalid character 'z'"));
+
+    let data = "position,value,anchor\n,a,\n";
+    let mut reader = csv::Reader::from_reader(data.as_bytes());
+    let err = reader.deserialize::<Row>().next().unwrap().unwrap_err();
+    assert!(err.to_string().contains("empty string"));
+}
@@ -0,0 +1,63 @@
+//! Checks the code generated by `#[derive(FractionallyOrdered)]`.
+use fractional_index::{FractionalIndex, FractionallyOrdered};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, FractionallyOrdered, Serialize, Deserialize)]
+struct Task {
+    title: String,
+    #[order]
+    position: FractionalIndex,
+    // Named like the parameters of the constructors.
+    upper: bool,
+}
+
+#[derive(Debug, FractionallyOrdered)]
+struct Row<T>(u32, FractionalIndex, T);
+
+fn titles(tasks: &[Task]) -> Vec<&str> {
+    tasks.iter().map(|task| task.title.as_str()).collect()
+}
+
+#[test]
+fn named_fields() {
+    let b = Task::new_after(None, "b".to_string(), false);
+    let c = Task::new_after(Some(&b), "c".to_string(), true);
+    let a = Task::new_before(Some(&b), "a".to_string(), false);
+    let bc = Task::new_between(&b, &c, "bc".to_string(), true).unwrap();
+    assert!(c.upper);
+    assert!(Task::new_between(&c, &b, "x".to_string(), false).is_none());
+
+    let mut tasks = vec![c, bc, a, b];
+    tasks.sort();
+    assert_eq!(titles(&tasks), vec!["a", "b", "bc", "c"]);
+
+    // Equality only compares the index.
+    let mut renamed = tasks[0].clone();
+    renamed.title = "renamed".to_string();
+    assert_eq!(renamed, tasks[0]);
+
+    let last = tasks[3].clone();
+    tasks[0].move_after(&last);
+    assert!(tasks[1].move_between(None, Some(&last)));
+    assert!(!tasks[2].move_between(Some(&last), Some(&last)));
+    tasks.sort();
+    let titles = titles(&tasks);
+    assert_eq!(titles.last(), Some(&"a"));
+    assert!(titles.iter().position(|t| *t == "b") < titles.iter().position(|t| *t == "c"));
+
+    let json = serde_json::to_string(&tasks[0]).unwrap();
+    let task: Task = serde_json::from_str(&json).unwrap();
+    assert_eq!(task.index(), tasks[0].index());
+}
+
+#[test]
+fn tuple_struct() {
+    let first = Row::new_after(None, 1, "first");
+    let second = Row::new_after(Some(&first), 2, "second");
+    let zeroth = Row::new_before(Some(&first), 0, "zeroth");
+    let mut rows = [second, first, zeroth];
+    rows.sort();
+    let values: Vec<(u32, &str)> = rows.iter().map(|row| (row.0, row.2)).collect();
+    assert_eq!(values, vec![(0, "zeroth"), (1, "first"), (2, "second")]);
+    assert_eq!(rows[0].index(), &rows[0].1);
+}
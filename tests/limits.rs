@@ -0,0 +1,76 @@
+use fractional_index::{
+    io::{IndexReader, IndexWriter},
+    limits, DecodeError, FractionalIndex,
+};
+
+fn key(len: usize) -> FractionalIndex {
+    let mut bytes = vec![1; len - 1];
+    bytes.push(128);
+    FractionalIndex::from_bytes(bytes).unwrap()
+}
+
+#[test]
+fn max_len() {
+    let short = key(16);
+    let long = key(17);
+    let longer = key(18);
+    let json = serde_json::to_string(&long).unwrap();
+    let hex = long.to_string();
+    let mut writer = IndexWriter::new(Vec::new()).unwrap();
+    writer.write(&long).unwrap();
+    let stream = writer.into_inner();
+
+    assert_eq!(limits::max_len(), None);
+    limits::with_max_len(Some(16), || {
+        assert_eq!(limits::max_len(), Some(16));
+
+        let too_long = DecodeError::TooLong { max: 16 };
+        assert_eq!(
+            FractionalIndex::from_bytes(long.as_bytes().to_vec()),
+            Err(too_long.clone())
+        );
+        assert_eq!(FractionalIndex::from_string(&hex), Err(too_long.clone()));
+        assert_eq!(
+            FractionalIndex::from_digits(&long.to_digits()),
+            Err(too_long.clone())
+        );
+        assert_eq!(
+            FractionalIndex::from_bytes(short.as_bytes().to_vec()),
+            Ok(short.clone())
+        );
+        assert!(serde_json::from_str::<FractionalIndex>(&json).is_err());
+        assert!(IndexReader::new(&stream[..]).unwrap().read().is_err());
+
+        // A reader's own limit replaces the thread's.
+        let mut reader = IndexReader::new(&stream[..]).unwrap();
+        reader.set_max_len(None);
+        assert_eq!(reader.read().unwrap(), Some(long.clone()));
+
+        // Generated keys are not limited.
+        let between = FractionalIndex::new_between(&longer, &long).unwrap();
+        assert!(between.len() > 16);
+
+        // Other threads are not limited.
+        let json = json.clone();
+        std::thread::spawn(move || serde_json::from_str::<FractionalIndex>(&json).unwrap())
+            .join()
+            .unwrap();
+    });
+
+    assert_eq!(
+        serde_json::from_str::<FractionalIndex>(&json).unwrap(),
+        long
+    );
+}
+
+#[test]
+fn huge_declared_length() {
+    // A sequence that declares 2^40 bytes but holds none is rejected
+    // without preallocating them.
+    let encoded = (1u64 << 40).to_le_bytes();
+    assert!(bincode::deserialize::<FractionalIndex>(&encoded).is_err());
+
+    let mut encoded = encoded.to_vec();
+    encoded.extend_from_slice(&[129, 128]);
+    assert!(bincode::deserialize::<FractionalIndex>(&encoded).is_err());
+}
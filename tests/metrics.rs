@@ -0,0 +1,158 @@
+//! Checks the metrics recorded with the `metrics` feature, using a minimal
+//! recorder that logs every update.
+use fractional_index::{duplicates::resolve_duplicates, rebalance, FractionalIndex};
+use metrics::{
+    Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+    SharedString, Unit,
+};
+use std::sync::{Arc, Mutex};
+
+type Log = Arc<Mutex<Vec<(String, f64)>>>;
+
+/// Logs updates to one metric, identified by its name and labels.
+struct Handle {
+    name: String,
+    log: Log,
+}
+
+impl Handle {
+    fn push(&self, value: f64) {
+        self.log.lock().unwrap().push((self.name.clone(), value));
+    }
+}
+
+impl CounterFn for Handle {
+    fn increment(&self, value: u64) {
+        self.push(value as f64);
+    }
+
+    fn absolute(&self, value: u64) {
+        self.push(value as f64);
+    }
+}
+
+impl HistogramFn for Handle {
+    fn record(&self, value: f64) {
+        self.push(value);
+    }
+}
+
+#[derive(Default)]
+struct LogRecorder(Log);
+
+impl LogRecorder {
+    fn handle(&self, key: &Key) -> Arc<Handle> {
+        let mut name = key.name().to_string();
+        for label in key.labels() {
+            name.push_str(&format!(" {}={}", label.key(), label.value()));
+        }
+        Arc::new(Handle {
+            name,
+            log: self.0.clone(),
+        })
+    }
+
+    /// Returns the logged updates of metrics whose name starts with
+    /// `prefix`.
+    fn updates(&self, prefix: &str) -> Vec<(String, f64)> {
+        let log = self.0.lock().unwrap();
+        log.iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Recorder for LogRecorder {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+        Counter::from_arc(self.handle(key))
+    }
+
+    fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(self.handle(key))
+    }
+}
+
+fn update(name: &str, value: f64) -> (String, f64) {
+    (name.to_string(), value)
+}
+
+#[test]
+fn key_lengths() {
+    let recorder = LogRecorder::default();
+    metrics::with_local_recorder(&recorder, || {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        FractionalIndex::new_between(&a, &b).unwrap();
+    });
+    assert_eq!(
+        recorder.updates("fractional_index_key_length"),
+        vec![
+            update("fractional_index_key_length operation=new_after", 2.0),
+            update("fractional_index_key_length operation=new_between", 3.0),
+        ]
+    );
+}
+
+#[test]
+fn collisions_and_rebalances() {
+    let recorder = LogRecorder::default();
+    metrics::with_local_recorder(&recorder, || {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        let entries = vec![
+            (a.clone(), 1),
+            (a.clone(), 2),
+            (a.clone(), 3),
+            (b.clone(), 4),
+        ];
+        assert_eq!(resolve_duplicates(&entries).len(), 2);
+        // No collisions are recorded if there are none.
+        assert!(resolve_duplicates(&entries[2..]).is_empty());
+
+        // Bisecting alternately from either side makes the keys grow.
+        let (mut lower, mut upper) = (a.clone(), b.clone());
+        let mut keys = vec![a.clone(), b.clone()];
+        for i in 0..20 {
+            let key = FractionalIndex::new_between(&lower, &upper).unwrap();
+            keys.push(key.clone());
+            if i % 2 == 0 {
+                lower = key;
+            } else {
+                upper = key;
+            }
+        }
+        keys.sort();
+        let plan = rebalance::plan(&keys, 4).unwrap();
+        assert!(plan.stats.rows_touched > 0);
+        // Planning a rebalance that changes nothing is not a trigger.
+        assert!(rebalance::plan(&[a, b], 3).unwrap().remap.is_empty());
+        recorder
+            .0
+            .lock()
+            .unwrap()
+            .retain(|(name, _)| !name.contains("key_length"));
+
+        assert_eq!(
+            recorder.updates("fractional_index"),
+            vec![
+                update("fractional_index_collisions source=resolve_duplicates", 2.0),
+                update("fractional_index_rebalances", 1.0),
+                update(
+                    "fractional_index_rebalanced_keys",
+                    plan.stats.rows_touched as f64
+                ),
+            ]
+        );
+    });
+}
@@ -0,0 +1,280 @@
+//! These tests require a Postgres server, so they are ignored by default.
+//! Run them with `DATABASE_URL` set, e.g.
+//! `DATABASE_URL=postgres://postgres@localhost/postgres cargo test
+//! --features postgres --test postgres -- --ignored`.
+
+use fractional_index::{FractionalIndex, OrDefault};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+
+async fn connect() -> PgPool {
+    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL should be set");
+    PgPoolOptions::new().connect(&url).await.unwrap()
+}
+
+fn keys() -> Vec<FractionalIndex> {
+    let mut keys = vec![FractionalIndex::default()];
+    for _ in 0..10 {
+        keys.push(FractionalIndex::new_after(keys.last().unwrap()));
+    }
+    keys
+}
+
+#[tokio::test]
+#[ignore = "requires DATABASE_URL"]
+async fn array_roundtrip() {
+    let pool = connect().await;
+
+    let keys = keys();
+    let (decoded,): (Vec<FractionalIndex>,) = sqlx::query_as("select $1::bytea[]")
+        .bind(&keys)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(decoded, keys);
+
+    let (empty,): (Vec<FractionalIndex>,) = sqlx::query_as("select $1::bytea[]")
+        .bind(Vec::<FractionalIndex>::new())
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(empty.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires DATABASE_URL"]
+async fn array_null_elements() {
+    let pool = connect().await;
+
+    let keys = keys();
+    let nullable = vec![Some(keys[0].clone()), None, Some(keys[1].clone())];
+    let (decoded,): (Vec<Option<FractionalIndex>>,) = sqlx::query_as("select $1::bytea[]")
+        .bind(&nullable)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(decoded, nullable);
+
+    let (defaulted,): (Vec<OrDefault>,) = sqlx::query_as("select $1::bytea[]")
+        .bind(&nullable)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        defaulted,
+        vec![
+            OrDefault(keys[0].clone()),
+            OrDefault::default(),
+            OrDefault(keys[1].clone())
+        ]
+    );
+
+    // A NULL element can't be decoded without Option or OrDefault.
+    let result: Result<(Vec<FractionalIndex>,), _> = sqlx::query_as("select $1::bytea[]")
+        .bind(&nullable)
+        .fetch_one(&pool)
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires DATABASE_URL"]
+async fn array_column() {
+    let pool = connect().await;
+
+    let mut tx = pool.begin().await.unwrap();
+    sqlx::query(
+        "create temporary table snapshot (id integer primary key, ordering bytea[] not null)",
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+
+    let keys = keys();
+    sqlx::query("insert into snapshot (id, ordering) values (1, $1)")
+        .bind(&keys)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+
+    let (ordering,): (Vec<FractionalIndex>,) =
+        sqlx::query_as("select ordering from snapshot where id = 1")
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+    assert_eq!(ordering, keys);
+}
+
+#[tokio::test]
+#[ignore = "requires DATABASE_URL"]
+async fn copy_in() {
+    let pool = connect().await;
+
+    let mut conn = pool.acquire().await.unwrap();
+    sqlx::query("create temporary table item (id bigint primary key, position bytea not null)")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+
+    let mut keys = vec![FractionalIndex::default()];
+    for _ in 0..20_000 {
+        keys.push(FractionalIndex::new_after(keys.last().unwrap()));
+    }
+
+    let copied = fractional_index::pg_copy::copy_in(
+        &mut conn,
+        "item",
+        "id",
+        "position",
+        keys.iter().enumerate().map(|(i, key)| (i as i64, key)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(copied, keys.len() as u64);
+
+    let rows: Vec<(i64, FractionalIndex)> =
+        sqlx::query_as("select id, position from item order by position")
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+    assert_eq!(rows.len(), keys.len());
+    for (i, (id, key)) in rows.into_iter().enumerate() {
+        assert_eq!(id, i as i64);
+        assert_eq!(key, keys[i]);
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires DATABASE_URL"]
+async fn add_order_column() {
+    use fractional_index::sql::{backfill, order_column_ddl, Dialect};
+
+    let pool = connect().await;
+
+    let mut conn = pool.acquire().await.unwrap();
+    sqlx::query("create temporary table legacy (id bigint primary key, name text not null)")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    sqlx::query(
+        "insert into legacy (id, name) select i, md5(i::text) from generate_series(1, 500) i",
+    )
+    .execute(&mut *conn)
+    .await
+    .unwrap();
+
+    for statement in order_column_ddl(Dialect::Postgres, "legacy", "position") {
+        sqlx::query(&statement).execute(&mut *conn).await.unwrap();
+    }
+    let updated = backfill::<sqlx::Postgres>(
+        &mut conn,
+        Dialect::Postgres,
+        "legacy",
+        "id",
+        "position",
+        "name",
+    )
+    .await
+    .unwrap();
+    assert_eq!(updated, 500);
+
+    let by_name: Vec<(i64,)> = sqlx::query_as("select id from legacy order by name")
+        .fetch_all(&mut *conn)
+        .await
+        .unwrap();
+    let by_position: Vec<(i64,)> = sqlx::query_as("select id from legacy order by position")
+        .fetch_all(&mut *conn)
+        .await
+        .unwrap();
+    assert_eq!(by_name, by_position);
+}
+
+#[tokio::test]
+#[ignore = "requires DATABASE_URL"]
+async fn ltree_order() {
+    use fractional_index::ltree::{from_ltree, to_ltree};
+
+    let pool = connect().await;
+
+    let mut conn = pool.acquire().await.unwrap();
+    sqlx::query("create extension if not exists ltree")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    sqlx::query("create temporary table tree (id bigint primary key, path ltree not null)")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+
+    let mut keys = FractionalIndex::new_after_many(&FractionalIndex::default(), 300);
+    for i in 0..20 {
+        keys.push(FractionalIndex::new_between(&keys[i], &keys[i + 1]).unwrap());
+    }
+    for (id, key) in keys.iter().enumerate() {
+        sqlx::query("insert into tree (id, path) values ($1, $2::ltree)")
+            .bind(id as i64)
+            .bind(to_ltree(key))
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+    }
+
+    let rows: Vec<(i64, String)> = sqlx::query_as("select id, path::text from tree order by path")
+        .fetch_all(&mut *conn)
+        .await
+        .unwrap();
+    let mut expected: Vec<(&FractionalIndex, usize)> = keys.iter().zip(0..).collect();
+    expected.sort();
+    assert_eq!(rows.len(), keys.len());
+    for ((id, path), (key, expected_id)) in rows.iter().zip(expected) {
+        assert_eq!(*id as usize, expected_id);
+        assert_eq!(from_ltree(path).as_ref(), Ok(key));
+    }
+}
+
+#[tokio::test]
+#[ignore = "requires DATABASE_URL"]
+async fn domain() {
+    use fractional_index::sql::domain_ddl;
+
+    let pool = connect().await;
+
+    // The domain is created in a transaction that is rolled back.
+    let mut tx = pool.begin().await.unwrap();
+    sqlx::query(&domain_ddl("fractional_index_test"))
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    sqlx::query(
+        "create temporary table ordered (id integer primary key, position fractional_index_test, snapshot fractional_index_test[])",
+    )
+    .execute(&mut *tx)
+    .await
+    .unwrap();
+
+    let keys = keys();
+    sqlx::query("insert into ordered (id, position, snapshot) values (1, $1, $2)")
+        .bind(&keys[1])
+        .bind(&keys)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    let (position, snapshot): (FractionalIndex, Vec<FractionalIndex>) =
+        sqlx::query_as("select position, snapshot from ordered where id = 1")
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+    assert_eq!(position, keys[1]);
+    assert_eq!(snapshot, keys);
+
+    for malformed in [vec![], vec![129u8]] {
+        // A failed statement aborts the transaction, so run it in a
+        // savepoint.
+        let mut savepoint = sqlx::Connection::begin(&mut *tx).await.unwrap();
+        let result = sqlx::query("select $1::fractional_index_test")
+            .bind(malformed)
+            .execute(&mut *savepoint)
+            .await;
+        assert!(result.is_err());
+        savepoint.rollback().await.unwrap();
+    }
+}
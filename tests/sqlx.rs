@@ -1,4 +1,4 @@
-use fractional_index::FractionalIndex;
+use fractional_index::{FractionalIndex, OrDefault};
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::FromRow;
 
@@ -10,6 +10,8 @@ const CREATE_TABLE_QUERY: &str = r#"
         nullable_fractional_index blob
     )"#;
 
+// Decodes the nullable column with the deprecated
+// `TryFrom<Option<Vec<u8>>>` impl of `FractionalIndex`.
 #[derive(FromRow, Debug)]
 struct Item {
     #[allow(unused)]
@@ -21,6 +23,23 @@ struct Item {
     nullable_fractional_index: FractionalIndex,
 }
 
+#[derive(FromRow, Debug)]
+struct OrDefaultItem {
+    name: String,
+    #[sqlx(try_from = "Vec<u8>")]
+    fractional_index: FractionalIndex,
+    #[sqlx(try_from = "Option<Vec<u8>>")]
+    nullable_fractional_index: OrDefault,
+}
+
+#[cfg(feature = "sqlx")]
+#[derive(FromRow, Debug)]
+struct NativeItem {
+    name: String,
+    fractional_index: FractionalIndex,
+    nullable_fractional_index: Option<FractionalIndex>,
+}
+
 #[tokio::test]
 async fn sqlx_insert_select() {
     let pool = SqlitePoolOptions::new()
@@ -117,3 +136,276 @@ async fn sqlx_insert_select_nullable() {
 
     assert!(items.next().is_none());
 }
+
+#[tokio::test]
+async fn sqlx_insert_select_or_default() {
+    let pool = SqlitePoolOptions::new()
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    // Create table.
+    sqlx::query(CREATE_TABLE_QUERY)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let idx2 = FractionalIndex::new_after(&FractionalIndex::default());
+    let idx3 = FractionalIndex::new_after(&idx2);
+
+    // Insert an item.
+    sqlx::query(
+        "insert into item (name, fractional_index, nullable_fractional_index) values (?, ?, ?)",
+    )
+    .bind("item1")
+    .bind(&*idx2)
+    .bind(&*idx3)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "insert into item (name, fractional_index, nullable_fractional_index) values (?, ?, NULL)",
+    )
+    .bind("item2")
+    .bind(&*idx3)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // Fetch all items
+    let items: Vec<OrDefaultItem> = sqlx::query_as("select * from item order by id asc")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+    let mut items = items.into_iter();
+
+    {
+        let item = items.next().unwrap();
+        assert_eq!(item.name, "item1");
+        assert_eq!(item.fractional_index, idx2);
+        assert_eq!(*item.nullable_fractional_index, idx3);
+    }
+
+    {
+        let item = items.next().unwrap();
+        assert_eq!(item.name, "item2");
+        assert_eq!(item.fractional_index, idx3);
+        assert_eq!(*item.nullable_fractional_index, FractionalIndex::default());
+    }
+
+    assert!(items.next().is_none());
+}
+
+#[cfg(feature = "sqlx")]
+#[tokio::test]
+async fn sqlx_native_option() {
+    let pool = SqlitePoolOptions::new()
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+
+    // Create table.
+    sqlx::query(CREATE_TABLE_QUERY)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let idx2 = FractionalIndex::new_after(&FractionalIndex::default());
+    let idx3 = FractionalIndex::new_after(&idx2);
+
+    sqlx::query(
+        "insert into item (name, fractional_index, nullable_fractional_index) values (?, ?, ?)",
+    )
+    .bind("item1")
+    .bind(&idx2)
+    .bind(Some(&idx3))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "insert into item (name, fractional_index, nullable_fractional_index) values (?, ?, ?)",
+    )
+    .bind("item2")
+    .bind(&idx3)
+    .bind(None::<FractionalIndex>)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // Fetch all items
+    let items: Vec<NativeItem> = sqlx::query_as("select * from item order by id asc")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].name, "item1");
+    assert_eq!(items[0].fractional_index, idx2);
+    assert_eq!(items[0].nullable_fractional_index, Some(idx3.clone()));
+    assert_eq!(items[1].name, "item2");
+    assert_eq!(items[1].fractional_index, idx3);
+    assert_eq!(items[1].nullable_fractional_index, None);
+
+    // OrDefault can also be decoded directly.
+    let (value,): (OrDefault,) =
+        sqlx::query_as("select nullable_fractional_index from item where name = 'item2'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(*value, FractionalIndex::default());
+
+    // Corrupt values are rejected rather than decoded.
+    let result: Result<(FractionalIndex,), _> =
+        sqlx::query_as("select x'0102'").fetch_one(&pool).await;
+    assert!(result.is_err());
+}
+
+#[cfg(feature = "sqlx")]
+#[tokio::test]
+async fn sqlx_add_order_column() {
+    use fractional_index::sql::{backfill, order_column_ddl, Dialect};
+
+    let pool = SqlitePoolOptions::new()
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+
+    sqlx::query("create table legacy (id integer primary key, name text not null)")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    for name in ["c", "a", "b"] {
+        sqlx::query("insert into legacy (name) values (?)")
+            .bind(name)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+    }
+
+    for statement in order_column_ddl(Dialect::Sqlite, "legacy", "position") {
+        sqlx::query(&statement).execute(&mut *conn).await.unwrap();
+    }
+    let updated = backfill::<sqlx::Sqlite>(
+        &mut conn,
+        Dialect::Sqlite,
+        "legacy",
+        "id",
+        "position",
+        "name",
+    )
+    .await
+    .unwrap();
+    assert_eq!(updated, 3);
+
+    // Rows added later are placed after the existing ones.
+    sqlx::query("insert into legacy (name) values ('0')")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    let updated = backfill::<sqlx::Sqlite>(
+        &mut conn,
+        Dialect::Sqlite,
+        "legacy",
+        "id",
+        "position",
+        "name",
+    )
+    .await
+    .unwrap();
+    assert_eq!(updated, 1);
+
+    let rows: Vec<(String, FractionalIndex)> =
+        sqlx::query_as("select name, position from legacy order by position")
+            .fetch_all(&mut *conn)
+            .await
+            .unwrap();
+    let names: Vec<&str> = rows.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["a", "b", "c", "0"]);
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn sqlx_hex_collation() {
+    use fractional_index::sql::{with_hex_collation, HEX_COLLATION};
+    use sqlx::sqlite::SqliteConnectOptions;
+    use std::str::FromStr;
+
+    let options = with_hex_collation(SqliteConnectOptions::from_str("sqlite::memory:").unwrap());
+    let pool = SqlitePoolOptions::new()
+        .connect_with(options)
+        .await
+        .unwrap();
+
+    sqlx::query("create table item (id integer primary key, position text not null)")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let mut keys = vec![FractionalIndex::default()];
+    for _ in 0..10 {
+        keys.push(FractionalIndex::new_after(keys.last().unwrap()));
+    }
+    for (i, key) in keys.iter().enumerate() {
+        // Mix lower and uppercase, which the BINARY collation would missort.
+        let s = if i % 2 == 0 {
+            key.to_string()
+        } else {
+            key.to_string().to_uppercase()
+        };
+        sqlx::query("insert into item (id, position) values (?, ?)")
+            .bind(i as i64)
+            .bind(s)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let ids: Vec<(i64,)> = sqlx::query_as(&format!(
+        "select id from item order by position collate {}",
+        HEX_COLLATION
+    ))
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    let ids: Vec<i64> = ids.into_iter().map(|(id,)| id).collect();
+    assert_eq!(ids, (0..keys.len() as i64).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn sqlx_check_constraint() {
+    use fractional_index::sql::{check_constraint, Dialect};
+
+    let pool = SqlitePoolOptions::new()
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::query(&format!(
+        "create table item (id integer primary key, position blob, {})",
+        check_constraint(Dialect::Sqlite, "item", "position", 4)
+    ))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let valid = FractionalIndex::new_after(&FractionalIndex::default());
+    let candidates: Vec<(Option<Vec<u8>>, bool)> = vec![
+        (Some(valid.as_bytes().to_vec()), true),
+        (None, true),
+        (Some(vec![128, 128, 128, 128]), true),
+        (Some(vec![]), false),
+        (Some(vec![129]), false),
+        (Some(vec![128, 1]), false),
+        (Some(vec![128, 128, 128, 128, 128]), false),
+    ];
+    for (position, accepted) in candidates {
+        let result = sqlx::query("insert into item (position) values (?)")
+            .bind(position.clone())
+            .execute(&pool)
+            .await;
+        assert_eq!(result.is_ok(), accepted, "{:?}", position);
+    }
+}
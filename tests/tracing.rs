@@ -0,0 +1,105 @@
+//! Checks the events emitted with the `tracing` feature, using a minimal
+//! subscriber that records their fields.
+use fractional_index::{fixed::FixedFractionalIndex, FractionalIndex};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+/// The `operation`, `len` and `grew` fields of a recorded event.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Generated {
+    operation: String,
+    len: u64,
+    grew: bool,
+}
+
+impl Visit for Generated {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "operation" {
+            self.operation = value.to_string();
+        }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "len" {
+            self.len = value;
+        }
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        if field.name() == "grew" {
+            self.grew = value;
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn Debug) {}
+}
+
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Generated>>>);
+
+impl Subscriber for Recorder {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() == "fractional_index"
+    }
+
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut generated = Generated::default();
+        event.record(&mut generated);
+        self.0.lock().unwrap().push(generated);
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+fn generated(operation: &str, len: u64, grew: bool) -> Generated {
+    Generated {
+        operation: operation.to_string(),
+        len,
+        grew,
+    }
+}
+
+#[test]
+fn generation_events() {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let a = FractionalIndex::default();
+        let b = FractionalIndex::new_after(&a);
+        FractionalIndex::new_before(&a);
+        FractionalIndex::new_between(&a, &b).unwrap();
+        let c = FractionalIndex::from_bytes(vec![100, 128]).unwrap();
+        let d = FractionalIndex::from_bytes(vec![110, 128]).unwrap();
+        FractionalIndex::new_between(&c, &d).unwrap();
+        // No event is emitted if no key is generated.
+        assert_eq!(FractionalIndex::new_between(&b, &a), None);
+
+        let fixed = FixedFractionalIndex::<4>::default();
+        FixedFractionalIndex::new_after(&fixed).unwrap();
+    });
+
+    let events = recorder.0.lock().unwrap();
+    assert_eq!(
+        *events,
+        vec![
+            generated("new_after", 2, true),
+            generated("new_before", 2, true),
+            generated("new_between", 3, true),
+            generated("new_between", 2, false),
+            generated("new_after", 2, true),
+        ]
+    );
+}
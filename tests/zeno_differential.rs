@@ -0,0 +1,118 @@
+//! Differential tests that run the same operations through [ZenoIndex] and
+//! [FractionalIndex] and check that both produce the same order.
+//!
+//! Run more cases with e.g. `PROPTEST_CASES=100000 cargo test --test
+//! zeno_differential`.
+#![allow(deprecated)]
+
+use fractional_index::{FractionalIndex, ZenoIndex};
+use proptest::prelude::*;
+
+/// An insertion relative to the element at a position of the list (taken
+/// modulo its length).
+#[derive(Clone, Debug)]
+enum Op {
+    Before(usize),
+    After(usize),
+    Between(usize),
+    Front,
+    Back,
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        any::<usize>().prop_map(Op::Before),
+        any::<usize>().prop_map(Op::After),
+        any::<usize>().prop_map(Op::Between),
+        Just(Op::Front),
+        Just(Op::Back),
+    ]
+}
+
+/// Elements as pairs of keys, in the order of their [ZenoIndex].
+struct List(Vec<(ZenoIndex, FractionalIndex)>);
+
+impl List {
+    fn new() -> Self {
+        List(vec![(ZenoIndex::default(), FractionalIndex::default())])
+    }
+
+    fn insert_at(&mut self, position: usize) {
+        let lower = position.checked_sub(1).and_then(|i| self.0.get(i));
+        let upper = self.0.get(position);
+        let zeno = match (lower, upper) {
+            (Some((lower, _)), Some((upper, _))) => ZenoIndex::new_between(lower, upper).unwrap(),
+            (Some((lower, _)), None) => ZenoIndex::new_after(lower),
+            (None, Some((upper, _))) => ZenoIndex::new_before(upper),
+            (None, None) => ZenoIndex::default(),
+        };
+        let fractional =
+            FractionalIndex::new(lower.map(|(_, key)| key), upper.map(|(_, key)| key)).unwrap();
+        self.0.insert(position, (zeno, fractional));
+    }
+
+    fn apply(&mut self, op: &Op) {
+        let len = self.0.len();
+        match *op {
+            Op::Before(i) => self.insert_at(i % len),
+            Op::After(i) => self.insert_at(i % len + 1),
+            Op::Between(i) => self.insert_at(i % (len + 1)),
+            Op::Front => self.insert_at(0),
+            Op::Back => self.insert_at(len),
+        }
+    }
+
+    /// Checks that both kinds of keys are strictly ascending, i.e. that
+    /// they order the elements in the same way.
+    fn check(&self) -> Result<(), TestCaseError> {
+        for pair in self.0.windows(2) {
+            prop_assert!(pair[0].0 < pair[1].0, "{:?}", pair);
+            prop_assert!(pair[0].1 < pair[1].1, "{:?}", pair);
+        }
+        Ok(())
+    }
+}
+
+/// Converts a [ZenoIndex] by appending the terminator, which is the
+/// migration path from [ZenoIndex] to [FractionalIndex].
+fn migrate(zeno: &ZenoIndex) -> FractionalIndex {
+    let mut bytes = zeno.as_bytes().to_vec();
+    bytes.push(128);
+    FractionalIndex::from_bytes(bytes).unwrap()
+}
+
+proptest! {
+    #[test]
+    fn same_order(ops in prop::collection::vec(op(), 0..200)) {
+        let mut list = List::new();
+        for op in &ops {
+            list.apply(op);
+            list.check()?;
+        }
+    }
+
+    #[test]
+    fn migration_preserves_order(ops in prop::collection::vec(op(), 0..200)) {
+        let mut list = List::new();
+        for op in &ops {
+            list.apply(op);
+        }
+        let migrated: Vec<FractionalIndex> = list.0.iter().map(|(zeno, _)| migrate(zeno)).collect();
+        for pair in migrated.windows(2) {
+            prop_assert!(pair[0] < pair[1]);
+        }
+    }
+}
+
+#[test]
+fn dense_regions() {
+    // Repeated insertions at the same place, which proptest rarely hits
+    // by chance.
+    let mut list = List::new();
+    for i in 0..300 {
+        list.apply(&Op::Between(1 + i % 2));
+        list.apply(&Op::Front);
+        list.apply(&Op::Back);
+    }
+    list.check().unwrap();
+}
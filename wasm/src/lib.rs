@@ -0,0 +1,140 @@
+//! A WebAssembly component exposing [fractional_index] through the
+//! interface defined in `wit/world.wit`.
+//!
+//! Build it with a component-model target, e.g.
+//! `cargo build -p fractional_index_wasm --target wasm32-wasip2 --release`.
+use exports::jamsocket::fractional_index::keys::{
+    ChecksumMismatch, DecodeError as WitDecodeError, Guest, InvalidChar, Key, Ordering,
+    UnexpectedByte,
+};
+use fractional_index::{DecodeError, FractionalIndex};
+use std::cmp;
+
+wit_bindgen::generate!({
+    world: "fractional-index",
+    path: "wit",
+});
+
+struct Component;
+
+// The exported symbol names are only valid when linking a wasm module.
+#[cfg(target_family = "wasm")]
+export!(Component);
+
+fn decode(key: Key) -> Result<FractionalIndex, WitDecodeError> {
+    FractionalIndex::from_bytes(key).map_err(to_wit)
+}
+
+fn to_wit(err: DecodeError) -> WitDecodeError {
+    match err {
+        DecodeError::EmptyString => WitDecodeError::EmptyString,
+        DecodeError::MissingTerminator { found } => WitDecodeError::MissingTerminator(found),
+        DecodeError::InvalidChars {
+            character,
+            position,
+        } => WitDecodeError::InvalidChar(InvalidChar {
+            character,
+            position: position as u64,
+        }),
+        DecodeError::UnexpectedByte { byte, position } => {
+            WitDecodeError::UnexpectedByte(UnexpectedByte {
+                byte,
+                position: position as u64,
+            })
+        }
+        DecodeError::Truncated => WitDecodeError::Truncated,
+        DecodeError::InvalidLength => WitDecodeError::InvalidLength,
+        DecodeError::DigitOutOfRange => WitDecodeError::DigitOutOfRange,
+        DecodeError::TooLong { max } => WitDecodeError::TooLong(max as u64),
+        DecodeError::BatchTooLarge { max } => WitDecodeError::BatchTooLarge(max as u64),
+        DecodeError::UnsupportedVersion { version } => WitDecodeError::UnsupportedVersion(version),
+        DecodeError::MissingChecksum => WitDecodeError::MissingChecksum,
+        DecodeError::ChecksumMismatch { expected, found } => {
+            WitDecodeError::ChecksumMismatch(ChecksumMismatch { expected, found })
+        }
+    }
+}
+
+impl Guest for Component {
+    fn default_key() -> Key {
+        FractionalIndex::default().into_bytes()
+    }
+
+    fn new_before(key: Key) -> Result<Key, WitDecodeError> {
+        Ok(FractionalIndex::new_before(&decode(key)?).into_bytes())
+    }
+
+    fn new_after(key: Key) -> Result<Key, WitDecodeError> {
+        Ok(FractionalIndex::new_after(&decode(key)?).into_bytes())
+    }
+
+    fn new_between(left: Key, right: Key) -> Result<Option<Key>, WitDecodeError> {
+        let (left, right) = (decode(left)?, decode(right)?);
+        Ok(FractionalIndex::new_between(&left, &right).map(FractionalIndex::into_bytes))
+    }
+
+    fn new_key(lower: Option<Key>, upper: Option<Key>) -> Result<Option<Key>, WitDecodeError> {
+        let lower = lower.map(decode).transpose()?;
+        let upper = upper.map(decode).transpose()?;
+        Ok(FractionalIndex::new(lower.as_ref(), upper.as_ref()).map(FractionalIndex::into_bytes))
+    }
+
+    fn compare(a: Key, b: Key) -> Result<Ordering, WitDecodeError> {
+        Ok(match decode(a)?.cmp(&decode(b)?) {
+            cmp::Ordering::Less => Ordering::Less,
+            cmp::Ordering::Equal => Ordering::Equal,
+            cmp::Ordering::Greater => Ordering::Greater,
+        })
+    }
+
+    fn to_hex(key: Key) -> Result<String, WitDecodeError> {
+        Ok(decode(key)?.to_string())
+    }
+
+    fn from_hex(s: String) -> Result<Key, WitDecodeError> {
+        FractionalIndex::from_string(&s)
+            .map(FractionalIndex::into_bytes)
+            .map_err(to_wit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        let a = Component::default_key();
+        let b = Component::new_after(a.clone()).unwrap();
+        let c = Component::new_between(a.clone(), b.clone())
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            Component::compare(a.clone(), c.clone()),
+            Ok(Ordering::Less)
+        ));
+        assert!(matches!(
+            Component::compare(c.clone(), b.clone()),
+            Ok(Ordering::Less)
+        ));
+        assert_eq!(Component::new_between(b.clone(), a.clone()).unwrap(), None);
+        assert_eq!(
+            Component::new_key(Some(a.clone()), None).unwrap(),
+            Some(b.clone())
+        );
+
+        let hex = Component::to_hex(c.clone()).unwrap();
+        assert_eq!(Component::from_hex(hex).unwrap(), c);
+        assert!(matches!(
+            Component::new_before(vec![1]),
+            Err(WitDecodeError::MissingTerminator(Some(1)))
+        ));
+        assert!(matches!(
+            Component::from_hex("zz".to_string()),
+            Err(WitDecodeError::InvalidChar(InvalidChar {
+                character: 'z',
+                position: 0
+            }))
+        ));
+    }
+}